@@ -0,0 +1,1415 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use futures_util::{Stream, StreamExt, TryStreamExt, stream};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::Instrument;
+use url::Url;
+
+use crate::client::ERROR_BODY_LIMIT;
+use crate::endpoint::{
+    DOCUMENT_LIST_FIELDS, Endpoint, TASK_POLL_INTERVAL, download_path, id_filters,
+};
+use crate::error::ApiError;
+use crate::retry::{self, RetryPolicy};
+use crate::trace;
+use crate::types::{
+    BulkEdit, Correspondent, CorrespondentRequest, CustomField, CustomFieldRequest, Document,
+    DocumentMetadata, DocumentRequest, DocumentType, DocumentTypeRequest, DocumentUpload,
+    DocumentVersion, Note, PaginatedResponse, StoragePath, StoragePathRequest, Suggestions, Tag,
+    TagRequest, Task, UiSettings,
+};
+use crate::version;
+
+/// An asynchronous client for the Paperless-ngx REST API.
+///
+/// Created with [`ClientBuilder::build_async`](crate::ClientBuilder::build_async).
+/// It offers the same methods as [`Client`](crate::Client), plus `stream_*`
+/// methods that fetch pages on demand. Cloning is cheap and shares the
+/// underlying connection pool.
+#[derive(Clone)]
+pub struct AsyncClient {
+    endpoint: Endpoint,
+    token: String,
+    http: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+impl fmt::Debug for AsyncClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncClient")
+            .field("base_url", &self.endpoint.base_url)
+            .field("token", &"[REDACTED]")
+            .field("page_size", &self.endpoint.page_size)
+            .field("api_version", &self.endpoint.versions.current())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncClient {
+    pub(crate) fn new(
+        endpoint: Endpoint,
+        token: String,
+        http: reqwest::Client,
+        retry: RetryPolicy,
+    ) -> Self {
+        Self {
+            endpoint,
+            token,
+            http,
+            retry,
+        }
+    }
+
+    /// Exchanges a username and password for the user's API token. See
+    /// [`Client::obtain_token`](crate::Client::obtain_token).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Validation`] if the credentials are wrong, or
+    /// another error on network failure or if the client cannot be built.
+    pub async fn obtain_token(
+        builder: crate::ClientBuilder,
+        username: &str,
+        password: &str,
+    ) -> Result<String, ApiError> {
+        #[derive(serde::Deserialize)]
+        struct TokenResponse {
+            token: String,
+        }
+
+        let client = builder.without_token().build_async()?;
+        let url = client.endpoint.url("api/token/")?;
+        let body = serde_json::json!({ "username": username, "password": password });
+        let resp: TokenResponse = client.send_json(client.http.post(url), &body).await?;
+        Ok(resp.token)
+    }
+
+    /// Returns the running Paperless-ngx server version, as reported by an
+    /// earlier response or else fetched from the UI settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn server_version(&self) -> Result<String, ApiError> {
+        if let Some(version) = self.endpoint.versions.server() {
+            return Ok(version);
+        }
+        let settings = self.ui_settings().await?;
+        Ok(settings.settings.version)
+    }
+
+    /// Returns the API version sent with requests. See
+    /// [`Client::api_version`](crate::Client::api_version).
+    #[must_use]
+    pub fn api_version(&self) -> u32 {
+        self.endpoint.versions.current()
+    }
+
+    /// Returns the newest API version the server supports, once a response
+    /// has reported it.
+    #[must_use]
+    pub fn server_api_version(&self) -> Option<u32> {
+        self.endpoint.versions.server_api()
+    }
+
+    /// Fetches UI settings including user info and server version.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn ui_settings(&self) -> Result<UiSettings, ApiError> {
+        let url = self.endpoint.url("api/ui_settings/")?;
+        self.get(&url).await
+    }
+
+    /// Fetches the first page of documents.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure, authentication issues, or
+    /// deserialization problems.
+    pub async fn documents(&self) -> Result<PaginatedResponse<Document>, ApiError> {
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("fields", DOCUMENT_LIST_FIELDS)])?;
+        self.get(&url).await
+    }
+
+    /// Fetches a single document by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist.
+    pub async fn document(&self, id: u64) -> Result<Document, ApiError> {
+        let url = self.endpoint.url(&format!("api/documents/{id}/"))?;
+        self.get(&url).await
+    }
+
+    /// Fetches the extracted text content of a document.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist.
+    pub async fn document_content(&self, id: u64) -> Result<String, ApiError> {
+        let doc = self.document(id).await?;
+        Ok(doc.content.unwrap_or_default())
+    }
+
+    /// Downloads a document file and streams it into `dest`.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist, or
+    /// [`ApiError::Io`] if writing to `dest` fails.
+    pub async fn download_document<W: AsyncWrite + Unpin>(
+        &self,
+        id: u64,
+        version: DocumentVersion,
+        dest: &mut W,
+    ) -> Result<u64, ApiError> {
+        let url = self.endpoint.url(&download_path(id, version))?;
+        let mut resp = self.send(self.authorized(self.http.get(url))).await?;
+        let mut bytes = 0;
+        while let Some(chunk) = resp.chunk().await? {
+            dest.write_all(&chunk).await?;
+            bytes += chunk.len() as u64;
+        }
+        dest.flush().await?;
+        trace::streamed(resp.url(), bytes);
+        Ok(bytes)
+    }
+
+    /// Fetches the file metadata of a document, including checksums.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist.
+    pub async fn document_metadata(&self, id: u64) -> Result<DocumentMetadata, ApiError> {
+        let url = self
+            .endpoint
+            .url(&format!("api/documents/{id}/metadata/"))?;
+        self.get(&url).await
+    }
+
+    /// Fetches the correspondents, tags, document types, storage paths, and
+    /// dates the server suggests for a document.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist.
+    pub async fn suggestions(&self, id: u64) -> Result<Suggestions, ApiError> {
+        let url = self
+            .endpoint
+            .url(&format!("api/documents/{id}/suggestions/"))?;
+        self.get(&url).await
+    }
+
+    /// Downloads the thumbnail of a document and streams it into `dest`.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist, or
+    /// [`ApiError::Io`] if writing to `dest` fails.
+    pub async fn download_thumbnail<W: AsyncWrite + Unpin>(
+        &self,
+        id: u64,
+        dest: &mut W,
+    ) -> Result<u64, ApiError> {
+        let url = self.endpoint.url(&format!("api/documents/{id}/thumb/"))?;
+        let mut resp = self.send(self.authorized(self.http.get(url))).await?;
+        let mut bytes = 0;
+        while let Some(chunk) = resp.chunk().await? {
+            dest.write_all(&chunk).await?;
+            bytes += chunk.len() as u64;
+        }
+        dest.flush().await?;
+        trace::streamed(resp.url(), bytes);
+        Ok(bytes)
+    }
+
+    /// Updates the fields of a document that are set in `document`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist, or
+    /// [`ApiError::Validation`] if the server rejects a field.
+    pub async fn update_document(
+        &self,
+        id: u64,
+        document: &DocumentRequest,
+    ) -> Result<Document, ApiError> {
+        let url = self.endpoint.url(&format!("api/documents/{id}/"))?;
+        self.send_json(self.http.patch(url), document).await
+    }
+
+    /// Uploads a file as a new document with the given metadata.
+    ///
+    /// The server consumes the file in the background. Returns the ID of
+    /// the consumption task, to pass to [`task`](Self::task) or
+    /// [`wait_for_task`](Self::wait_for_task).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Validation`] if the server rejects the upload.
+    pub async fn upload_document(
+        &self,
+        file_name: &str,
+        data: &[u8],
+        metadata: &DocumentUpload,
+    ) -> Result<String, ApiError> {
+        let url = self.endpoint.url("api/documents/post_document/")?;
+        let form = metadata.form(file_name, data);
+        let request = self
+            .authorized(self.http.post(url))
+            .header("Content-Type", form.content_type())
+            .body(form.finish());
+        Self::read_json(self.send(request).await?).await
+    }
+
+    /// Fetches a background task by its UUID, or `None` if the server
+    /// doesn't know it (yet).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn task(&self, task_id: &str) -> Result<Option<Task>, ApiError> {
+        let mut url = self.endpoint.url("api/tasks/")?;
+        url.query_pairs_mut().append_pair("task_id", task_id);
+        let tasks: Vec<Task> = self.get(&url).await?;
+        Ok(tasks.into_iter().next())
+    }
+
+    /// Polls a background task until it is done or `timeout` has passed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Timeout`] if the task isn't done in time.
+    pub async fn wait_for_task(&self, task_id: &str, timeout: Duration) -> Result<Task, ApiError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(task) = self.task(task_id).await?
+                && task.status.is_done()
+            {
+                return Ok(task);
+            }
+            if Instant::now() >= deadline {
+                return Err(ApiError::Timeout);
+            }
+            tokio::time::sleep(TASK_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Adds a note to a document and returns all of its notes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist.
+    pub async fn add_note(&self, document: u64, note: &str) -> Result<Vec<Note>, ApiError> {
+        let url = self
+            .endpoint
+            .url(&format!("api/documents/{document}/notes/"))?;
+        self.send_json(self.http.post(url), &serde_json::json!({ "note": note }))
+            .await
+    }
+
+    /// Creates a storage path.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Validation`] if the server rejects a field, e.g.
+    /// because the name is taken.
+    pub async fn create_storage_path(
+        &self,
+        storage_path: &StoragePathRequest,
+    ) -> Result<StoragePath, ApiError> {
+        let url = self.endpoint.url("api/storage_paths/")?;
+        self.send_json(self.http.post(url), storage_path).await
+    }
+
+    /// Creates a custom field.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Validation`] if the server rejects a field, e.g.
+    /// because the name is taken.
+    pub async fn create_custom_field(
+        &self,
+        custom_field: &CustomFieldRequest,
+    ) -> Result<CustomField, ApiError> {
+        let url = self.endpoint.url("api/custom_fields/")?;
+        self.send_json(self.http.post(url), custom_field).await
+    }
+
+    /// Fetches the first page of tags.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn tags(&self) -> Result<PaginatedResponse<Tag>, ApiError> {
+        let url = self.endpoint.list_url("api/tags/", &[])?;
+        self.get(&url).await
+    }
+
+    /// Fetches a single tag by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the tag does not exist.
+    pub async fn tag(&self, id: u64) -> Result<Tag, ApiError> {
+        let url = self.endpoint.url(&format!("api/tags/{id}/"))?;
+        self.get(&url).await
+    }
+
+    /// Creates a new tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure, authentication issues, or if the
+    /// server rejects the request.
+    pub async fn create_tag(&self, tag: &TagRequest) -> Result<Tag, ApiError> {
+        let url = self.endpoint.url("api/tags/")?;
+        self.send_json(self.http.post(url), tag).await
+    }
+
+    /// Updates the fields of an existing tag that are set in `tag`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the tag does not exist.
+    pub async fn update_tag(&self, id: u64, tag: &TagRequest) -> Result<Tag, ApiError> {
+        let url = self.endpoint.url(&format!("api/tags/{id}/"))?;
+        self.send_json(self.http.patch(url), tag).await
+    }
+
+    /// Deletes a tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the tag does not exist.
+    pub async fn delete_tag(&self, id: u64) -> Result<(), ApiError> {
+        let url = self.endpoint.url(&format!("api/tags/{id}/"))?;
+        self.delete(url).await
+    }
+
+    /// Fetches the first page of correspondents.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn correspondents(&self) -> Result<PaginatedResponse<Correspondent>, ApiError> {
+        let url = self.endpoint.list_url("api/correspondents/", &[])?;
+        self.get(&url).await
+    }
+
+    /// Fetches a single correspondent by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the correspondent does not exist.
+    pub async fn correspondent(&self, id: u64) -> Result<Correspondent, ApiError> {
+        let url = self.endpoint.url(&format!("api/correspondents/{id}/"))?;
+        self.get(&url).await
+    }
+
+    /// Creates a new correspondent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure, authentication issues, or if the
+    /// server rejects the request.
+    pub async fn create_correspondent(
+        &self,
+        correspondent: &CorrespondentRequest,
+    ) -> Result<Correspondent, ApiError> {
+        let url = self.endpoint.url("api/correspondents/")?;
+        self.send_json(self.http.post(url), correspondent).await
+    }
+
+    /// Updates the fields of an existing correspondent that are set in
+    /// `correspondent`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the correspondent does not exist.
+    pub async fn update_correspondent(
+        &self,
+        id: u64,
+        correspondent: &CorrespondentRequest,
+    ) -> Result<Correspondent, ApiError> {
+        let url = self.endpoint.url(&format!("api/correspondents/{id}/"))?;
+        self.send_json(self.http.patch(url), correspondent).await
+    }
+
+    /// Deletes a correspondent. Documents assigned to it lose their
+    /// correspondent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the correspondent does not exist.
+    pub async fn delete_correspondent(&self, id: u64) -> Result<(), ApiError> {
+        let url = self.endpoint.url(&format!("api/correspondents/{id}/"))?;
+        self.delete(url).await
+    }
+
+    /// Fetches the first page of document types.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn document_types(&self) -> Result<PaginatedResponse<DocumentType>, ApiError> {
+        let url = self.endpoint.list_url("api/document_types/", &[])?;
+        self.get(&url).await
+    }
+
+    /// Fetches a single document type by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document type does not exist.
+    pub async fn document_type(&self, id: u64) -> Result<DocumentType, ApiError> {
+        let url = self.endpoint.url(&format!("api/document_types/{id}/"))?;
+        self.get(&url).await
+    }
+
+    /// Creates a new document type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure, authentication issues, or if the
+    /// server rejects the request.
+    pub async fn create_document_type(
+        &self,
+        document_type: &DocumentTypeRequest,
+    ) -> Result<DocumentType, ApiError> {
+        let url = self.endpoint.url("api/document_types/")?;
+        self.send_json(self.http.post(url), document_type).await
+    }
+
+    /// Updates the fields of an existing document type that are set in
+    /// `document_type`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document type does not exist.
+    pub async fn update_document_type(
+        &self,
+        id: u64,
+        document_type: &DocumentTypeRequest,
+    ) -> Result<DocumentType, ApiError> {
+        let url = self.endpoint.url(&format!("api/document_types/{id}/"))?;
+        self.send_json(self.http.patch(url), document_type).await
+    }
+
+    /// Deletes a document type. Documents of this type lose their type.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document type does not exist.
+    pub async fn delete_document_type(&self, id: u64) -> Result<(), ApiError> {
+        let url = self.endpoint.url(&format!("api/document_types/{id}/"))?;
+        self.delete(url).await
+    }
+
+    /// Applies `edit` to all `documents` in a single request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure, authentication issues, or if the
+    /// server rejects the request.
+    pub async fn bulk_edit(&self, documents: &[u64], edit: &BulkEdit) -> Result<(), ApiError> {
+        let url = self.endpoint.url("api/documents/bulk_edit/")?;
+        let (method, parameters) = edit.method_and_parameters();
+        let body = serde_json::json!({
+            "documents": documents,
+            "method": method,
+            "parameters": parameters,
+        });
+        let _: serde_json::Value = self.send_json(self.http.post(url), &body).await?;
+        Ok(())
+    }
+
+    /// Fetches the IDs of all documents matching the given query filters,
+    /// e.g. `[("correspondent__id__in", "1,2")]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_document_ids(
+        &self,
+        filters: &[(&str, &str)],
+    ) -> Result<Vec<u64>, ApiError> {
+        #[derive(serde::Deserialize)]
+        struct DocumentId {
+            id: u64,
+        }
+
+        let mut query = filters.to_vec();
+        query.push(("fields", "id"));
+        let url = self.endpoint.list_url("api/documents/", &query)?;
+        let (ids, _) = self.paginate::<DocumentId>(url, None).await?;
+        Ok(ids.into_iter().map(|d| d.id).collect())
+    }
+
+    /// Fetches the first page of inbox documents.
+    ///
+    /// Inbox documents are those tagged with an inbox tag
+    /// (`is_in_inbox=true`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn inbox_documents(&self) -> Result<PaginatedResponse<Document>, ApiError> {
+        let url = self.inbox_url()?;
+        self.get(&url).await
+    }
+
+    /// Fetches inbox documents across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all inbox documents. Returns the collected items
+    /// and the total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_inbox_documents(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<(Vec<Document>, u64), ApiError> {
+        let url = self.inbox_url()?;
+        self.paginate(url, limit).await
+    }
+
+    /// Streams all inbox documents, fetching pages on demand.
+    pub fn stream_inbox_documents(
+        &self,
+    ) -> impl Stream<Item = Result<Document, ApiError>> + Send + '_ {
+        self.items(self.inbox_url())
+    }
+
+    /// Searches documents matching `query`, returning the first page.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn search(&self, query: &str) -> Result<PaginatedResponse<Document>, ApiError> {
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("query", query)])?;
+        self.get(&url).await
+    }
+
+    /// Fetches documents across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all documents. Returns the collected items and
+    /// the total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_documents(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<(Vec<Document>, u64), ApiError> {
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("fields", DOCUMENT_LIST_FIELDS)])?;
+        self.paginate(url, limit).await
+    }
+
+    /// Fetches documents matching the given query filters across pages up
+    /// to `limit`, e.g. `[("modified__gt", "2024-01-01T00:00:00Z")]`.
+    ///
+    /// Unlike [`collect_documents`](Self::collect_documents), the documents
+    /// include their content and every other field the server returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_matching_documents(
+        &self,
+        filters: &[(&str, &str)],
+        limit: Option<usize>,
+    ) -> Result<(Vec<Document>, u64), ApiError> {
+        let url = self.endpoint.list_url("api/documents/", filters)?;
+        self.paginate(url, limit).await
+    }
+
+    /// Streams all documents, fetching pages on demand.
+    pub fn stream_documents(&self) -> impl Stream<Item = Result<Document, ApiError>> + Send + '_ {
+        self.items(
+            self.endpoint
+                .list_url("api/documents/", &[("fields", DOCUMENT_LIST_FIELDS)]),
+        )
+    }
+
+    /// Fetches tags across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all tags. Returns the collected items and the
+    /// total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_tags(&self, limit: Option<usize>) -> Result<(Vec<Tag>, u64), ApiError> {
+        let url = self.endpoint.list_url("api/tags/", &[])?;
+        self.paginate(url, limit).await
+    }
+
+    /// Streams all tags, fetching pages on demand.
+    pub fn stream_tags(&self) -> impl Stream<Item = Result<Tag, ApiError>> + Send + '_ {
+        self.items(self.endpoint.list_url("api/tags/", &[]))
+    }
+
+    /// Fetches correspondents across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all correspondents. Returns the collected items
+    /// and the total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_correspondents(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<(Vec<Correspondent>, u64), ApiError> {
+        let url = self.endpoint.list_url("api/correspondents/", &[])?;
+        self.paginate(url, limit).await
+    }
+
+    /// Streams all correspondents, fetching pages on demand.
+    pub fn stream_correspondents(
+        &self,
+    ) -> impl Stream<Item = Result<Correspondent, ApiError>> + Send + '_ {
+        self.items(self.endpoint.list_url("api/correspondents/", &[]))
+    }
+
+    /// Fetches document types across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all document types. Returns the collected items
+    /// and the total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_document_types(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<(Vec<DocumentType>, u64), ApiError> {
+        let url = self.endpoint.list_url("api/document_types/", &[])?;
+        self.paginate(url, limit).await
+    }
+
+    /// Streams all document types, fetching pages on demand.
+    pub fn stream_document_types(
+        &self,
+    ) -> impl Stream<Item = Result<DocumentType, ApiError>> + Send + '_ {
+        self.items(self.endpoint.list_url("api/document_types/", &[]))
+    }
+
+    /// Fetches storage paths across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all storage paths. Returns the collected items
+    /// and the total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_storage_paths(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<(Vec<StoragePath>, u64), ApiError> {
+        let url = self.endpoint.list_url("api/storage_paths/", &[])?;
+        self.paginate(url, limit).await
+    }
+
+    /// Fetches custom fields across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all custom fields. Returns the collected items
+    /// and the total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_custom_fields(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<(Vec<CustomField>, u64), ApiError> {
+        let url = self.endpoint.list_url("api/custom_fields/", &[])?;
+        self.paginate(url, limit).await
+    }
+
+    /// Fetches the tags with the given IDs. IDs that don't exist are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn tags_by_id(&self, ids: &[u64]) -> Result<Vec<Tag>, ApiError> {
+        self.collect_by_id("api/tags/", ids).await
+    }
+
+    /// Fetches the correspondents with the given IDs. IDs that don't exist
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn correspondents_by_id(&self, ids: &[u64]) -> Result<Vec<Correspondent>, ApiError> {
+        self.collect_by_id("api/correspondents/", ids).await
+    }
+
+    /// Fetches the document types with the given IDs. IDs that don't exist
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn document_types_by_id(&self, ids: &[u64]) -> Result<Vec<DocumentType>, ApiError> {
+        self.collect_by_id("api/document_types/", ids).await
+    }
+
+    /// Searches documents matching `query` across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all matching documents. Returns the collected
+    /// items and the total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub async fn collect_search(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+    ) -> Result<(Vec<Document>, u64), ApiError> {
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("query", query)])?;
+        self.paginate(url, limit).await
+    }
+
+    /// Streams all documents matching `query`, fetching pages on demand.
+    pub fn stream_search(
+        &self,
+        query: &str,
+    ) -> impl Stream<Item = Result<Document, ApiError>> + Send + '_ {
+        self.items(
+            self.endpoint
+                .list_url("api/documents/", &[("query", query)]),
+        )
+    }
+
+    fn inbox_url(&self) -> Result<Url, ApiError> {
+        self.endpoint.list_url(
+            "api/documents/",
+            &[("is_in_inbox", "true"), ("fields", DOCUMENT_LIST_FIELDS)],
+        )
+    }
+
+    /// Streams pages starting at `url`, following `next` links lazily.
+    fn pages<T: serde::de::DeserializeOwned + Send>(
+        &self,
+        url: Result<Url, ApiError>,
+    ) -> impl Stream<Item = Result<PaginatedResponse<T>, ApiError>> + Send + '_ {
+        stream::try_unfold(Some(url), move |next| async move {
+            let Some(url) = next else {
+                return Ok(None);
+            };
+            let page: PaginatedResponse<T> = self.get(&url?).await?;
+            let next = page.next.as_deref().map(|n| self.endpoint.next_url(n));
+            Ok(Some((page, next)))
+        })
+    }
+
+    fn items<T: serde::de::DeserializeOwned + Send + 'static>(
+        &self,
+        url: Result<Url, ApiError>,
+    ) -> impl Stream<Item = Result<T, ApiError>> + Send + '_ {
+        self.pages(url)
+            .map_ok(|page| stream::iter(page.results.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    async fn collect_by_id<T: serde::de::DeserializeOwned + Send>(
+        &self,
+        path: &str,
+        ids: &[u64],
+    ) -> Result<Vec<T>, ApiError> {
+        let mut items = Vec::with_capacity(ids.len());
+        for filter in id_filters(ids) {
+            let url = self.endpoint.list_url(path, &[("id__in", &filter)])?;
+            items.extend(self.paginate(url, None).await?.0);
+        }
+        Ok(items)
+    }
+
+    async fn paginate<T: serde::de::DeserializeOwned + Send>(
+        &self,
+        url: Url,
+        limit: Option<usize>,
+    ) -> Result<(Vec<T>, u64), ApiError> {
+        let max = limit.unwrap_or(usize::MAX);
+        let mut pages = self.pages::<T>(Ok(url)).boxed();
+        let mut total = None;
+        let mut results = Vec::new();
+        while let Some(page) = pages.try_next().await? {
+            total.get_or_insert(page.count);
+            results.extend(page.results);
+            if results.len() >= max {
+                break;
+            }
+        }
+        results.truncate(max);
+        Ok((results, total.unwrap_or_default()))
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.token.is_empty() {
+            return request;
+        }
+        request.header("Authorization", Endpoint::auth_header(&self.token))
+    }
+
+    /// Sends a request, retrying idempotent ones according to the retry
+    /// policy, and maps unsuccessful responses to errors.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, ApiError> {
+        let request = request.build()?;
+        let span = trace::request_span(request.method().as_str(), request.url());
+        self.send_with_retries(request).instrument(span).await
+    }
+
+    async fn send_with_retries(
+        &self,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, ApiError> {
+        let mut retry = 0;
+        loop {
+            let version = self.endpoint.versions.current();
+            // Streamed bodies cannot be cloned, so such requests are sent
+            // once, without retries.
+            let Some(mut attempt) = request.try_clone() else {
+                let mut request = request;
+                Self::accept_version(&mut request, version)?;
+                let resp = self.http.execute(request).await?;
+                if resp.status().is_success() {
+                    return Ok(resp);
+                }
+                return Err(error_from_response(resp).await);
+            };
+            Self::accept_version(&mut attempt, version)?;
+            trace::request_headers(attempt.headers());
+            let started = Instant::now();
+            let result = self.http.execute(attempt).await;
+            if let Ok(resp) = &result {
+                trace::response(resp.status().as_u16(), started.elapsed(), resp.headers());
+                self.observe_versions(resp.headers());
+            }
+
+            let (error, retry_after) = match result {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp)
+                    if resp.status() == reqwest::StatusCode::NOT_ACCEPTABLE
+                        && self.negotiate_version(version).await? =>
+                {
+                    continue;
+                }
+                Ok(resp) => {
+                    let status = resp.status().as_u16();
+                    let retry_after = resp
+                        .headers()
+                        .get("Retry-After")
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(retry::parse_retry_after);
+                    let error = error_from_response(resp).await;
+                    if !retry::is_retryable_status(status) {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                Err(err) if err.is_connect() || err.is_timeout() => (ApiError::from(err), None),
+                Err(err) => return Err(err.into()),
+            };
+
+            let delay = if request.method().is_idempotent() {
+                self.retry.delay(retry, retry_after)
+            } else {
+                None
+            };
+            let Some(delay) = delay else {
+                return Err(error);
+            };
+            retry += 1;
+            tracing::warn!(
+                method = %request.method(),
+                url = %request.url(),
+                retry,
+                ?delay,
+                %error,
+                "retrying request"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn accept_version(request: &mut reqwest::Request, version: u32) -> Result<(), ApiError> {
+        request.headers_mut().insert(
+            reqwest::header::ACCEPT,
+            reqwest::header::HeaderValue::from_str(&version::accept_header(version))
+                .map_err(|err| ApiError::Network(Box::new(err)))?,
+        );
+        Ok(())
+    }
+
+    fn observe_versions(&self, headers: &reqwest::header::HeaderMap) {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        self.endpoint
+            .versions
+            .observe(header("X-Api-Version"), header("X-Version"));
+    }
+
+    /// After the server rejected API version `sent`, learns the versions it
+    /// supports if needed and returns whether to resend with a lower one.
+    async fn negotiate_version(&self, sent: u32) -> Result<bool, ApiError> {
+        if self.endpoint.versions.needs_probe() {
+            // Without a version in `Accept` the server uses its default,
+            // and authenticated responses report the supported versions.
+            let url = self.endpoint.url("api/ui_settings/")?;
+            let resp = self
+                .authorized(self.http.get(url))
+                .header("Accept", "application/json")
+                .send()
+                .await?;
+            self.observe_versions(resp.headers());
+        }
+        let resend = self.endpoint.versions.resend_after_not_acceptable(sent)?;
+        if resend {
+            tracing::debug!(
+                from = sent,
+                to = self.endpoint.versions.current(),
+                "negotiated lower API version"
+            );
+        }
+        Ok(resend)
+    }
+
+    async fn read_json<T: serde::de::DeserializeOwned>(
+        resp: reqwest::Response,
+    ) -> Result<T, ApiError> {
+        let url = resp.url().clone();
+        let bytes = resp.bytes().await?;
+        trace::body(&url, &bytes);
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, url: &Url) -> Result<T, ApiError> {
+        let request = self.authorized(self.http.get(url.as_str()));
+        Self::read_json(self.send(request).await?).await
+    }
+
+    async fn send_json<B: serde::Serialize + ?Sized, T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+        body: &B,
+    ) -> Result<T, ApiError> {
+        let body = serde_json::to_vec(body)?;
+        let request = self
+            .authorized(request)
+            .header("Content-Type", "application/json")
+            .body(body);
+        Self::read_json(self.send(request).await?).await
+    }
+
+    async fn delete(&self, url: Url) -> Result<(), ApiError> {
+        let request = self.authorized(self.http.delete(url));
+        self.send(request).await?;
+        Ok(())
+    }
+}
+
+/// Maps an unsuccessful response to an error, reading at most
+/// `ERROR_BODY_LIMIT` bytes of its body.
+async fn error_from_response(mut resp: reqwest::Response) -> ApiError {
+    let status = resp.status().as_u16();
+    let url = resp.url().clone();
+    let limit = usize::try_from(ERROR_BODY_LIMIT).unwrap_or(usize::MAX);
+    let mut body = Vec::new();
+    while let Ok(Some(chunk)) = resp.chunk().await {
+        body.extend_from_slice(&chunk[..chunk.len().min(limit - body.len())]);
+        if body.len() == limit {
+            break;
+        }
+    }
+    trace::body(&url, &body);
+    ApiError::from_response(status, &body)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use wiremock::matchers::{body_json, body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::Client;
+
+    async fn setup() -> (MockServer, AsyncClient) {
+        let server = MockServer::start().await;
+        let client = Client::builder(&server.uri(), "test-token")
+            .build_async()
+            .expect("client creation should succeed");
+        (server, client)
+    }
+
+    fn tag_page(ids: &[u64], next: Option<&str>) -> serde_json::Value {
+        let results: Vec<_> = ids
+            .iter()
+            .map(|id| serde_json::json!({"id": id, "name": format!("Tag {id}"), "slug": format!("tag-{id}"), "color": null, "is_inbox_tag": false, "document_count": 0}))
+            .collect();
+        serde_json::json!({"count": 3, "next": next, "previous": null, "results": results})
+    }
+
+    #[tokio::test]
+    async fn test_document_by_id() {
+        let (server, client) = setup().await;
+
+        let body = serde_json::json!({
+            "id": 42,
+            "title": "Specific Document",
+            "content": "Full content here",
+            "correspondent": null,
+            "document_type": null,
+            "tags": [],
+            "created": null,
+            "added": null,
+            "archive_serial_number": null,
+            "original_file_name": null
+        });
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/42/"))
+            .and(header("Accept", "application/json; version=9"))
+            .and(header("Authorization", "Token test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let doc = client
+            .document(42)
+            .await
+            .expect("document request should succeed");
+        assert_eq!(doc.title, "Specific Document");
+    }
+
+    #[tokio::test]
+    async fn test_stream_follows_next_pages() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(&[3], None)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(
+                &[1, 2],
+                Some(&format!("{}/api/tags/?page=2", server.uri())),
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tags: Vec<Tag> = client
+            .stream_tags()
+            .try_collect()
+            .await
+            .expect("stream should succeed");
+        assert_eq!(tags.iter().map(|t| t.id).collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_collect_stops_at_limit() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(
+                &[1, 2],
+                Some(&format!("{}/api/tags/?page=2", server.uri())),
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let (tags, total) = client
+            .collect_tags(Some(1))
+            .await
+            .expect("collect should succeed");
+        assert_eq!(tags.len(), 1);
+        assert_eq!(total, 3);
+    }
+
+    #[tokio::test]
+    async fn test_stream_scheme_mismatch() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(
+                &[1],
+                Some("https://paperless.example.com/api/tags/?page=2"),
+            )))
+            .mount(&server)
+            .await;
+
+        let err = client
+            .stream_tags()
+            .try_collect::<Vec<_>>()
+            .await
+            .expect_err("should return scheme mismatch");
+        assert!(matches!(err, ApiError::SchemeMismatch { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_download_document() {
+        let (server, client) = setup().await;
+
+        let pdf_bytes = b"%PDF-fake-content";
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/10/download/"))
+            .and(header("Authorization", "Token test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(pdf_bytes.as_slice()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut buf = Vec::new();
+        let bytes = client
+            .download_document(10, DocumentVersion::Original, &mut buf)
+            .await
+            .expect("download should succeed");
+        assert_eq!(buf, pdf_bytes);
+        assert_eq!(bytes, pdf_bytes.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_upload_document_and_add_note() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/documents/post_document/"))
+            .and(body_string_contains("name=\"title\"\r\n\r\nScan\r\n"))
+            .respond_with(ResponseTemplate::new(200).set_body_json("3f7c-task"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/documents/12/notes/"))
+            .and(body_json(serde_json::json!({ "note": "Checked" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "id": 1, "note": "Checked", "created": "2024-03-01T10:00:00Z" }
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let upload = DocumentUpload {
+            title: Some("Scan".to_string()),
+            ..DocumentUpload::default()
+        };
+        let task_id = client
+            .upload_document("scan.pdf", b"%PDF", &upload)
+            .await
+            .expect("upload should succeed");
+        assert_eq!(task_id, "3f7c-task");
+        let notes = client
+            .add_note(12, "Checked")
+            .await
+            .expect("note should be added");
+        assert_eq!(notes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_not_found_error() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/999/"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client
+            .document(999)
+            .await
+            .expect_err("should return not found error");
+        assert!(matches!(err, ApiError::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_validation_error() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/api/tags/1/"))
+            .respond_with(
+                ResponseTemplate::new(400)
+                    .set_body_json(serde_json::json!({"color": ["Enter a valid color."]})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = crate::TagRequest {
+            color: Some("red".to_string()),
+            ..crate::TagRequest::default()
+        };
+        let err = client
+            .update_tag(1, &request)
+            .await
+            .expect_err("should return validation error");
+        assert!(matches!(
+            err,
+            ApiError::Validation { ref field_errors, .. } if field_errors["color"] == ["Enter a valid color."]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let server = MockServer::start().await;
+        let client = Client::builder(&server.uri(), "test-token")
+            .retry_policy(RetryPolicy::new(3).initial_backoff(std::time::Duration::from_millis(1)))
+            .build_async()
+            .expect("client creation should succeed");
+
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(502))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(&[1], None)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let page = client.tags().await.expect("retried request should succeed");
+        assert_eq!(page.results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_static_headers_and_proxy() {
+        let proxy = MockServer::start().await;
+        let client = Client::builder("http://paperless.invalid", "test-token")
+            .header("CF-Access-Client-Id", "abc")
+            .proxy(proxy.uri())
+            .build_async()
+            .expect("client creation should succeed");
+
+        Mock::given(method("GET"))
+            .and(path("/api/tags/1/"))
+            .and(header("CF-Access-Client-Id", "abc"))
+            .and(header("Authorization", "Token test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 1,
+                "name": "invoice",
+                "slug": "invoice",
+                "color": null,
+                "is_inbox_tag": false,
+                "document_count": 0
+            })))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+
+        let tag = client.tag(1).await.expect("proxied request should succeed");
+        assert_eq!(tag.name, "invoice");
+    }
+
+    #[tokio::test]
+    async fn test_obtain_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/token/"))
+            .and(body_json(
+                serde_json::json!({"username": "alice", "password": "secret"}),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"token": "abc123"})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/token/"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+                "non_field_errors": ["Unable to log in with provided credentials."]
+            })))
+            .mount(&server)
+            .await;
+
+        let token =
+            AsyncClient::obtain_token(Client::builder(&server.uri(), ""), "alice", "secret")
+                .await
+                .expect("token request should succeed");
+        assert_eq!(token, "abc123");
+
+        let err = AsyncClient::obtain_token(Client::builder(&server.uri(), ""), "alice", "wrong")
+            .await
+            .expect_err("wrong password should fail");
+        assert!(matches!(err, ApiError::Validation { .. }));
+
+        let requests = server
+            .received_requests()
+            .await
+            .expect("requests are recorded");
+        assert!(
+            requests
+                .iter()
+                .all(|r| !r.headers.contains_key("Authorization"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_body_is_capped() {
+        let (server, client) = setup().await;
+        let detail = "x".repeat(usize::try_from(ERROR_BODY_LIMIT).unwrap());
+        Mock::given(method("GET"))
+            .and(path("/api/tags/1/"))
+            .respond_with(
+                ResponseTemplate::new(409).set_body_json(serde_json::json!({"detail": detail})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags/2/"))
+            .respond_with(
+                ResponseTemplate::new(409)
+                    .set_body_json(serde_json::json!({"detail": "Tag is locked."})),
+            )
+            .mount(&server)
+            .await;
+
+        let err = client.tag(1).await.expect_err("should fail");
+        assert!(
+            matches!(&err, ApiError::Server { status: 409, message } if message == "unexpected status code"),
+            "{err:?}"
+        );
+        let err = client.tag(2).await.expect_err("should fail");
+        assert!(
+            matches!(&err, ApiError::Server { status: 409, message } if message == "Tag is locked."),
+            "{err:?}"
+        );
+    }
+}
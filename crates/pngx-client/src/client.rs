@@ -1,32 +1,48 @@
 use std::fmt;
 use std::io::{self, Write};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use ureq::http::{self, Method};
 use url::Url;
 
+use crate::endpoint::{
+    DOCUMENT_LIST_FIELDS, Endpoint, TASK_POLL_INTERVAL, download_path, id_filters,
+};
 use crate::error::ApiError;
+use crate::pages::Pages;
+use crate::retry::{self, RetryPolicy};
+use crate::trace;
+use crate::transport::Transport;
 use crate::types::{
-    Correspondent, Document, DocumentType, DocumentVersion, PaginatedResponse, Tag, UiSettings,
+    BulkEdit, Correspondent, CorrespondentRequest, CustomField, CustomFieldRequest, Document,
+    DocumentMetadata, DocumentRequest, DocumentType, DocumentTypeRequest, DocumentUpload,
+    DocumentVersion, Note, PaginatedResponse, StoragePath, StoragePathRequest, Suggestions, Tag,
+    TagRequest, Task, UiSettings,
 };
+use crate::version::{self, ApiVersions};
 
-const DEFAULT_PAGE_SIZE: u32 = 100;
+pub(crate) const DEFAULT_PAGE_SIZE: u32 = 100;
 
-const DOCUMENT_LIST_FIELDS: &str = "id,title,correspondent,document_type,tags,created,added,archive_serial_number,original_file_name";
+/// Maximum size of an error response body read for its message.
+pub(crate) const ERROR_BODY_LIMIT: u64 = 64 * 1024;
 
 /// A synchronous client for the Paperless-ngx REST API.
 pub struct Client {
-    base_url: Url,
+    pub(crate) endpoint: Endpoint,
     token: String,
     agent: ureq::Agent,
-    page_size: u32,
+    headers: http::HeaderMap,
+    retry: RetryPolicy,
 }
 
 impl fmt::Debug for Client {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Client")
-            .field("base_url", &self.base_url)
+            .field("base_url", &self.endpoint.base_url)
             .field("token", &"[REDACTED]")
-            .field("page_size", &self.page_size)
+            .field("page_size", &self.endpoint.page_size)
+            .field("api_version", &self.endpoint.versions.current())
             .finish_non_exhaustive()
     }
 }
@@ -41,6 +57,32 @@ impl Client {
         Self::builder(base_url, token).build()
     }
 
+    /// Exchanges a username and password for the user's API token, creating
+    /// the token if the user has none yet. The request is sent with the
+    /// options of `builder`, such as certificates and proxy; its token is
+    /// not sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Validation`] if the credentials are wrong, or
+    /// another error on network failure or if the client cannot be built.
+    pub fn obtain_token(
+        builder: ClientBuilder,
+        username: &str,
+        password: &str,
+    ) -> Result<String, ApiError> {
+        #[derive(serde::Deserialize)]
+        struct TokenResponse {
+            token: String,
+        }
+
+        let client = builder.without_token().build()?;
+        let url = client.endpoint.url("api/token/")?;
+        let body = serde_json::json!({ "username": username, "password": password });
+        let resp: TokenResponse = client.post(&url, &body)?;
+        Ok(resp.token)
+    }
+
     /// Returns a [`ClientBuilder`] for configuring a new client.
     #[must_use]
     pub fn builder(base_url: &str, token: &str) -> ClientBuilder {
@@ -49,26 +91,51 @@ impl Client {
             token: token.to_string(),
             timeout: None,
             page_size: DEFAULT_PAGE_SIZE,
+            retry: RetryPolicy::default(),
+            api_version: None,
+            rewrite_next_urls: false,
+            transport: Transport::default(),
         }
     }
 
-    /// Returns the running Paperless-ngx server version.
+    /// Returns the running Paperless-ngx server version, as reported by an
+    /// earlier response or else fetched from the UI settings.
     ///
     /// # Errors
     ///
     /// Returns an error on network failure or authentication issues.
     pub fn server_version(&self) -> Result<String, ApiError> {
+        if let Some(version) = self.endpoint.versions.server() {
+            return Ok(version);
+        }
         let settings = self.ui_settings()?;
         Ok(settings.settings.version)
     }
 
+    /// Returns the API version sent with requests. Unless pinned with
+    /// [`ClientBuilder::api_version`], this starts at [`MAX_API_VERSION`]
+    /// and is lowered once a response shows the server supports less.
+    ///
+    /// [`MAX_API_VERSION`]: crate::MAX_API_VERSION
+    #[must_use]
+    pub fn api_version(&self) -> u32 {
+        self.endpoint.versions.current()
+    }
+
+    /// Returns the newest API version the server supports, once a response
+    /// has reported it.
+    #[must_use]
+    pub fn server_api_version(&self) -> Option<u32> {
+        self.endpoint.versions.server_api()
+    }
+
     /// Fetches UI settings including user info and server version.
     ///
     /// # Errors
     ///
     /// Returns an error on network failure or authentication issues.
     pub fn ui_settings(&self) -> Result<UiSettings, ApiError> {
-        let url = self.url("api/ui_settings/")?;
+        let url = self.endpoint.url("api/ui_settings/")?;
         self.get(&url)
     }
 
@@ -79,10 +146,9 @@ impl Client {
     /// Returns an error on network failure, authentication issues, or
     /// deserialization problems.
     pub fn documents(&self) -> Result<PaginatedResponse<Document>, ApiError> {
-        let mut url = self.url("api/documents/")?;
-        url.query_pairs_mut()
-            .append_pair("fields", DOCUMENT_LIST_FIELDS)
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("fields", DOCUMENT_LIST_FIELDS)])?;
         self.get(&url)
     }
 
@@ -92,7 +158,7 @@ impl Client {
     ///
     /// Returns [`ApiError::NotFound`] if the document does not exist.
     pub fn document(&self, id: u64) -> Result<Document, ApiError> {
-        let url = self.url(&format!("api/documents/{id}/"))?;
+        let url = self.endpoint.url(&format!("api/documents/{id}/"))?;
         self.get(&url)
     }
 
@@ -102,7 +168,7 @@ impl Client {
     ///
     /// Returns [`ApiError::NotFound`] if the document does not exist.
     pub fn document_content(&self, id: u64) -> Result<String, ApiError> {
-        let url = self.url(&format!("api/documents/{id}/"))?;
+        let url = self.endpoint.url(&format!("api/documents/{id}/"))?;
         let doc: Document = self.get(&url)?;
         Ok(doc.content.unwrap_or_default())
     }
@@ -121,56 +187,378 @@ impl Client {
         version: DocumentVersion,
         dest: &mut W,
     ) -> Result<u64, ApiError> {
-        let path = match version {
-            DocumentVersion::Original => format!("api/documents/{id}/download/"),
-            DocumentVersion::Archived => format!("api/documents/{id}/preview/"),
-        };
-        let url = self.url(&path)?;
-        let mut resp = self
-            .agent
-            .get(url.as_str())
-            .header("Authorization", &format!("Token {}", self.token))
-            .call()?;
+        let url = self.endpoint.url(&download_path(id, version))?;
+        let mut resp = self.send(&Method::GET, &url, None)?;
+        let bytes = io::copy(&mut resp.body_mut().as_reader(), dest)?;
+        trace::streamed(&url, bytes);
+        Ok(bytes)
+    }
+
+    /// Fetches the file metadata of a document, including checksums.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist.
+    pub fn document_metadata(&self, id: u64) -> Result<DocumentMetadata, ApiError> {
+        let url = self
+            .endpoint
+            .url(&format!("api/documents/{id}/metadata/"))?;
+        self.get(&url)
+    }
+
+    /// Fetches the correspondents, tags, document types, storage paths, and
+    /// dates the server suggests for a document.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist.
+    pub fn suggestions(&self, id: u64) -> Result<Suggestions, ApiError> {
+        let url = self
+            .endpoint
+            .url(&format!("api/documents/{id}/suggestions/"))?;
+        self.get(&url)
+    }
+
+    /// Downloads the thumbnail of a document and streams it into `dest`.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist, or
+    /// [`ApiError::Io`] if writing to `dest` fails.
+    pub fn download_thumbnail<W: Write>(&self, id: u64, dest: &mut W) -> Result<u64, ApiError> {
+        let url = self.endpoint.url(&format!("api/documents/{id}/thumb/"))?;
+        let mut resp = self.send(&Method::GET, &url, None)?;
         let bytes = io::copy(&mut resp.body_mut().as_reader(), dest)?;
+        trace::streamed(&url, bytes);
         Ok(bytes)
     }
 
+    /// Updates the fields of a document that are set in `document`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist, or
+    /// [`ApiError::Validation`] if the server rejects a field.
+    pub fn update_document(
+        &self,
+        id: u64,
+        document: &DocumentRequest,
+    ) -> Result<Document, ApiError> {
+        let url = self.endpoint.url(&format!("api/documents/{id}/"))?;
+        self.patch(&url, document)
+    }
+
+    /// Uploads a file as a new document with the given metadata.
+    ///
+    /// The server consumes the file in the background. Returns the ID of
+    /// the consumption task, to pass to [`task`](Self::task) or
+    /// [`wait_for_task`](Self::wait_for_task).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Validation`] if the server rejects the upload.
+    pub fn upload_document(
+        &self,
+        file_name: &str,
+        data: &[u8],
+        metadata: &DocumentUpload,
+    ) -> Result<String, ApiError> {
+        let url = self.endpoint.url("api/documents/post_document/")?;
+        let form = metadata.form(file_name, data);
+        let content_type = form.content_type();
+        let body = form.finish();
+        self.send_json(
+            &Method::POST,
+            &url,
+            Some(Body {
+                content_type: &content_type,
+                data: &body,
+            }),
+        )
+    }
+
+    /// Fetches a background task by its UUID, or `None` if the server
+    /// doesn't know it (yet).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub fn task(&self, task_id: &str) -> Result<Option<Task>, ApiError> {
+        let mut url = self.endpoint.url("api/tasks/")?;
+        url.query_pairs_mut().append_pair("task_id", task_id);
+        let tasks: Vec<Task> = self.get(&url)?;
+        Ok(tasks.into_iter().next())
+    }
+
+    /// Polls a background task until it is done or `timeout` has passed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Timeout`] if the task isn't done in time.
+    pub fn wait_for_task(&self, task_id: &str, timeout: Duration) -> Result<Task, ApiError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(task) = self.task(task_id)?
+                && task.status.is_done()
+            {
+                return Ok(task);
+            }
+            if Instant::now() >= deadline {
+                return Err(ApiError::Timeout);
+            }
+            std::thread::sleep(TASK_POLL_INTERVAL);
+        }
+    }
+
+    /// Adds a note to a document and returns all of its notes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document does not exist.
+    pub fn add_note(&self, document: u64, note: &str) -> Result<Vec<Note>, ApiError> {
+        let url = self
+            .endpoint
+            .url(&format!("api/documents/{document}/notes/"))?;
+        self.post(&url, &serde_json::json!({ "note": note }))
+    }
+
+    /// Creates a storage path.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Validation`] if the server rejects a field, e.g.
+    /// because the name is taken.
+    pub fn create_storage_path(
+        &self,
+        storage_path: &StoragePathRequest,
+    ) -> Result<StoragePath, ApiError> {
+        let url = self.endpoint.url("api/storage_paths/")?;
+        self.post(&url, storage_path)
+    }
+
+    /// Creates a custom field.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::Validation`] if the server rejects a field, e.g.
+    /// because the name is taken.
+    pub fn create_custom_field(
+        &self,
+        custom_field: &CustomFieldRequest,
+    ) -> Result<CustomField, ApiError> {
+        let url = self.endpoint.url("api/custom_fields/")?;
+        self.post(&url, custom_field)
+    }
+
     /// Fetches the first page of tags.
     ///
     /// # Errors
     ///
     /// Returns an error on network failure or authentication issues.
     pub fn tags(&self) -> Result<PaginatedResponse<Tag>, ApiError> {
-        let mut url = self.url("api/tags/")?;
-        url.query_pairs_mut()
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self.endpoint.list_url("api/tags/", &[])?;
+        self.get(&url)
+    }
+
+    /// Fetches a single tag by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the tag does not exist.
+    pub fn tag(&self, id: u64) -> Result<Tag, ApiError> {
+        let url = self.endpoint.url(&format!("api/tags/{id}/"))?;
         self.get(&url)
     }
 
+    /// Creates a new tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure, authentication issues, or if the
+    /// server rejects the request.
+    pub fn create_tag(&self, tag: &TagRequest) -> Result<Tag, ApiError> {
+        let url = self.endpoint.url("api/tags/")?;
+        self.post(&url, tag)
+    }
+
+    /// Updates the fields of an existing tag that are set in `tag`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the tag does not exist.
+    pub fn update_tag(&self, id: u64, tag: &TagRequest) -> Result<Tag, ApiError> {
+        let url = self.endpoint.url(&format!("api/tags/{id}/"))?;
+        self.patch(&url, tag)
+    }
+
+    /// Deletes a tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the tag does not exist.
+    pub fn delete_tag(&self, id: u64) -> Result<(), ApiError> {
+        let url = self.endpoint.url(&format!("api/tags/{id}/"))?;
+        self.delete(&url)
+    }
+
     /// Fetches the first page of correspondents.
     ///
     /// # Errors
     ///
     /// Returns an error on network failure or authentication issues.
     pub fn correspondents(&self) -> Result<PaginatedResponse<Correspondent>, ApiError> {
-        let mut url = self.url("api/correspondents/")?;
-        url.query_pairs_mut()
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self.endpoint.list_url("api/correspondents/", &[])?;
+        self.get(&url)
+    }
+
+    /// Fetches a single correspondent by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the correspondent does not exist.
+    pub fn correspondent(&self, id: u64) -> Result<Correspondent, ApiError> {
+        let url = self.endpoint.url(&format!("api/correspondents/{id}/"))?;
         self.get(&url)
     }
 
+    /// Creates a new correspondent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure, authentication issues, or if the
+    /// server rejects the request.
+    pub fn create_correspondent(
+        &self,
+        correspondent: &CorrespondentRequest,
+    ) -> Result<Correspondent, ApiError> {
+        let url = self.endpoint.url("api/correspondents/")?;
+        self.post(&url, correspondent)
+    }
+
+    /// Updates the fields of an existing correspondent that are set in
+    /// `correspondent`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the correspondent does not exist.
+    pub fn update_correspondent(
+        &self,
+        id: u64,
+        correspondent: &CorrespondentRequest,
+    ) -> Result<Correspondent, ApiError> {
+        let url = self.endpoint.url(&format!("api/correspondents/{id}/"))?;
+        self.patch(&url, correspondent)
+    }
+
+    /// Deletes a correspondent. Documents assigned to it lose their
+    /// correspondent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the correspondent does not exist.
+    pub fn delete_correspondent(&self, id: u64) -> Result<(), ApiError> {
+        let url = self.endpoint.url(&format!("api/correspondents/{id}/"))?;
+        self.delete(&url)
+    }
+
     /// Fetches the first page of document types.
     ///
     /// # Errors
     ///
     /// Returns an error on network failure or authentication issues.
     pub fn document_types(&self) -> Result<PaginatedResponse<DocumentType>, ApiError> {
-        let mut url = self.url("api/document_types/")?;
-        url.query_pairs_mut()
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self.endpoint.list_url("api/document_types/", &[])?;
         self.get(&url)
     }
 
+    /// Fetches a single document type by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document type does not exist.
+    pub fn document_type(&self, id: u64) -> Result<DocumentType, ApiError> {
+        let url = self.endpoint.url(&format!("api/document_types/{id}/"))?;
+        self.get(&url)
+    }
+
+    /// Creates a new document type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure, authentication issues, or if the
+    /// server rejects the request.
+    pub fn create_document_type(
+        &self,
+        document_type: &DocumentTypeRequest,
+    ) -> Result<DocumentType, ApiError> {
+        let url = self.endpoint.url("api/document_types/")?;
+        self.post(&url, document_type)
+    }
+
+    /// Updates the fields of an existing document type that are set in
+    /// `document_type`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document type does not exist.
+    pub fn update_document_type(
+        &self,
+        id: u64,
+        document_type: &DocumentTypeRequest,
+    ) -> Result<DocumentType, ApiError> {
+        let url = self.endpoint.url(&format!("api/document_types/{id}/"))?;
+        self.patch(&url, document_type)
+    }
+
+    /// Deletes a document type. Documents of this type lose their type.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::NotFound`] if the document type does not exist.
+    pub fn delete_document_type(&self, id: u64) -> Result<(), ApiError> {
+        let url = self.endpoint.url(&format!("api/document_types/{id}/"))?;
+        self.delete(&url)
+    }
+
+    /// Applies `edit` to all `documents` in a single request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure, authentication issues, or if the
+    /// server rejects the request.
+    pub fn bulk_edit(&self, documents: &[u64], edit: &BulkEdit) -> Result<(), ApiError> {
+        let url = self.endpoint.url("api/documents/bulk_edit/")?;
+        let (method, parameters) = edit.method_and_parameters();
+        let body = serde_json::json!({
+            "documents": documents,
+            "method": method,
+            "parameters": parameters,
+        });
+        let _: serde_json::Value = self.post(&url, &body)?;
+        Ok(())
+    }
+
+    /// Fetches the IDs of all documents matching the given query filters,
+    /// e.g. `[("correspondent__id__in", "1,2")]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub fn collect_document_ids(&self, filters: &[(&str, &str)]) -> Result<Vec<u64>, ApiError> {
+        #[derive(serde::Deserialize)]
+        struct DocumentId {
+            id: u64,
+        }
+
+        let mut query = filters.to_vec();
+        query.push(("fields", "id"));
+        let url = self.endpoint.list_url("api/documents/", &query)?;
+        let (ids, _) = self.paginate::<DocumentId>(&url, None)?;
+        Ok(ids.into_iter().map(|d| d.id).collect())
+    }
+
     /// Fetches the first page of inbox documents.
     ///
     /// Inbox documents are those tagged with an inbox tag
@@ -180,11 +568,10 @@ impl Client {
     ///
     /// Returns an error on network failure or authentication issues.
     pub fn inbox_documents(&self) -> Result<PaginatedResponse<Document>, ApiError> {
-        let mut url = self.url("api/documents/")?;
-        url.query_pairs_mut()
-            .append_pair("is_in_inbox", "true")
-            .append_pair("fields", DOCUMENT_LIST_FIELDS)
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self.endpoint.list_url(
+            "api/documents/",
+            &[("is_in_inbox", "true"), ("fields", DOCUMENT_LIST_FIELDS)],
+        )?;
         self.get(&url)
     }
 
@@ -200,11 +587,10 @@ impl Client {
         &self,
         limit: Option<usize>,
     ) -> Result<(Vec<Document>, u64), ApiError> {
-        let mut url = self.url("api/documents/")?;
-        url.query_pairs_mut()
-            .append_pair("is_in_inbox", "true")
-            .append_pair("fields", DOCUMENT_LIST_FIELDS)
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self.endpoint.list_url(
+            "api/documents/",
+            &[("is_in_inbox", "true"), ("fields", DOCUMENT_LIST_FIELDS)],
+        )?;
         self.paginate(&url, limit)
     }
 
@@ -214,10 +600,9 @@ impl Client {
     ///
     /// Returns an error on network failure or authentication issues.
     pub fn search(&self, query: &str) -> Result<PaginatedResponse<Document>, ApiError> {
-        let mut url = self.url("api/documents/")?;
-        url.query_pairs_mut()
-            .append_pair("query", query)
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("query", query)])?;
         self.get(&url)
     }
 
@@ -233,10 +618,27 @@ impl Client {
         &self,
         limit: Option<usize>,
     ) -> Result<(Vec<Document>, u64), ApiError> {
-        let mut url = self.url("api/documents/")?;
-        url.query_pairs_mut()
-            .append_pair("fields", DOCUMENT_LIST_FIELDS)
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("fields", DOCUMENT_LIST_FIELDS)])?;
+        self.paginate(&url, limit)
+    }
+
+    /// Fetches documents matching the given query filters across pages up
+    /// to `limit`, e.g. `[("modified__gt", "2024-01-01T00:00:00Z")]`.
+    ///
+    /// Unlike [`collect_documents`](Self::collect_documents), the documents
+    /// include their content and every other field the server returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub fn collect_matching_documents(
+        &self,
+        filters: &[(&str, &str)],
+        limit: Option<usize>,
+    ) -> Result<(Vec<Document>, u64), ApiError> {
+        let url = self.endpoint.list_url("api/documents/", filters)?;
         self.paginate(&url, limit)
     }
 
@@ -249,9 +651,7 @@ impl Client {
     ///
     /// Returns an error on network failure or authentication issues.
     pub fn collect_tags(&self, limit: Option<usize>) -> Result<(Vec<Tag>, u64), ApiError> {
-        let mut url = self.url("api/tags/")?;
-        url.query_pairs_mut()
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self.endpoint.list_url("api/tags/", &[])?;
         self.paginate(&url, limit)
     }
 
@@ -267,9 +667,7 @@ impl Client {
         &self,
         limit: Option<usize>,
     ) -> Result<(Vec<Correspondent>, u64), ApiError> {
-        let mut url = self.url("api/correspondents/")?;
-        url.query_pairs_mut()
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self.endpoint.list_url("api/correspondents/", &[])?;
         self.paginate(&url, limit)
     }
 
@@ -285,12 +683,72 @@ impl Client {
         &self,
         limit: Option<usize>,
     ) -> Result<(Vec<DocumentType>, u64), ApiError> {
-        let mut url = self.url("api/document_types/")?;
-        url.query_pairs_mut()
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self.endpoint.list_url("api/document_types/", &[])?;
         self.paginate(&url, limit)
     }
 
+    /// Fetches storage paths across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all storage paths. Returns the collected items
+    /// and the total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub fn collect_storage_paths(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<(Vec<StoragePath>, u64), ApiError> {
+        let url = self.endpoint.list_url("api/storage_paths/", &[])?;
+        self.paginate(&url, limit)
+    }
+
+    /// Fetches custom fields across pages up to `limit`.
+    ///
+    /// Pass `None` to fetch all custom fields. Returns the collected items
+    /// and the total count reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub fn collect_custom_fields(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<(Vec<CustomField>, u64), ApiError> {
+        let url = self.endpoint.list_url("api/custom_fields/", &[])?;
+        self.paginate(&url, limit)
+    }
+
+    /// Fetches the tags with the given IDs. IDs that don't exist are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub fn tags_by_id(&self, ids: &[u64]) -> Result<Vec<Tag>, ApiError> {
+        self.collect_by_id("api/tags/", ids)
+    }
+
+    /// Fetches the correspondents with the given IDs. IDs that don't exist
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub fn correspondents_by_id(&self, ids: &[u64]) -> Result<Vec<Correspondent>, ApiError> {
+        self.collect_by_id("api/correspondents/", ids)
+    }
+
+    /// Fetches the document types with the given IDs. IDs that don't exist
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on network failure or authentication issues.
+    pub fn document_types_by_id(&self, ids: &[u64]) -> Result<Vec<DocumentType>, ApiError> {
+        self.collect_by_id("api/document_types/", ids)
+    }
+
     /// Searches documents matching `query` across pages up to `limit`.
     ///
     /// Pass `None` to fetch all matching documents. Returns the collected
@@ -304,74 +762,318 @@ impl Client {
         query: &str,
         limit: Option<usize>,
     ) -> Result<(Vec<Document>, u64), ApiError> {
-        let mut url = self.url("api/documents/")?;
-        url.query_pairs_mut()
-            .append_pair("query", query)
-            .append_pair("page_size", &self.page_size.to_string());
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("query", query)])?;
         self.paginate(&url, limit)
     }
 
+    /// Returns an iterator that fetches document pages on demand.
+    #[must_use]
+    pub fn document_pages(&self) -> Pages<'_, Document> {
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("fields", DOCUMENT_LIST_FIELDS)]);
+        Pages::new(self, url)
+    }
+
+    /// Returns an iterator that fetches inbox document pages on demand.
+    #[must_use]
+    pub fn inbox_document_pages(&self) -> Pages<'_, Document> {
+        let url = self.endpoint.list_url(
+            "api/documents/",
+            &[("is_in_inbox", "true"), ("fields", DOCUMENT_LIST_FIELDS)],
+        );
+        Pages::new(self, url)
+    }
+
+    /// Returns an iterator that fetches pages of documents matching the
+    /// given query filters on demand. Like
+    /// [`collect_matching_documents`](Self::collect_matching_documents), the
+    /// documents include their content.
+    #[must_use]
+    pub fn matching_document_pages(&self, filters: &[(&str, &str)]) -> Pages<'_, Document> {
+        let url = self.endpoint.list_url("api/documents/", filters);
+        Pages::new(self, url)
+    }
+
+    /// Returns an iterator that fetches pages of documents matching `query`
+    /// on demand.
+    #[must_use]
+    pub fn search_pages(&self, query: &str) -> Pages<'_, Document> {
+        let url = self
+            .endpoint
+            .list_url("api/documents/", &[("query", query)]);
+        Pages::new(self, url)
+    }
+
+    /// Returns an iterator that fetches tag pages on demand.
+    #[must_use]
+    pub fn tag_pages(&self) -> Pages<'_, Tag> {
+        Pages::new(self, self.endpoint.list_url("api/tags/", &[]))
+    }
+
+    /// Returns an iterator that fetches correspondent pages on demand.
+    #[must_use]
+    pub fn correspondent_pages(&self) -> Pages<'_, Correspondent> {
+        Pages::new(self, self.endpoint.list_url("api/correspondents/", &[]))
+    }
+
+    /// Returns an iterator that fetches document type pages on demand.
+    #[must_use]
+    pub fn document_type_pages(&self) -> Pages<'_, DocumentType> {
+        Pages::new(self, self.endpoint.list_url("api/document_types/", &[]))
+    }
+
+    fn collect_by_id<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        ids: &[u64],
+    ) -> Result<Vec<T>, ApiError> {
+        let mut items = Vec::with_capacity(ids.len());
+        for filter in id_filters(ids) {
+            let url = self.endpoint.list_url(path, &[("id__in", &filter)])?;
+            items.extend(self.paginate(&url, None)?.0);
+        }
+        Ok(items)
+    }
+
     fn paginate<T: serde::de::DeserializeOwned>(
         &self,
         url: &Url,
         limit: Option<usize>,
     ) -> Result<(Vec<T>, u64), ApiError> {
-        let first: PaginatedResponse<T> = self.get(url)?;
-        let total = first.count;
-        let mut results = first.results;
-
         let max = limit.unwrap_or(usize::MAX);
-        if results.len() >= max {
-            results.truncate(max);
-            return Ok((results, total));
-        }
-
-        let mut next = first.next;
-        while let Some(next_url) = next {
+        let mut total = None;
+        let mut results = Vec::new();
+        for page in Pages::new(self, Ok(url.clone())) {
+            let page = page?;
+            total.get_or_insert(page.count);
+            results.extend(page.results);
             if results.len() >= max {
                 break;
             }
-            let parsed = Url::parse(&next_url)?;
-            if parsed.scheme() != self.base_url.scheme() {
-                return Err(ApiError::SchemeMismatch {
-                    expected: self.base_url.scheme().to_string(),
-                    returned: parsed.scheme().to_string(),
-                });
+        }
+        results.truncate(max);
+        Ok((results, total.unwrap_or_default()))
+    }
+
+    pub(crate) fn get<T: serde::de::DeserializeOwned>(&self, url: &Url) -> Result<T, ApiError> {
+        self.send_json(&Method::GET, url, None)
+    }
+
+    fn post<B: serde::Serialize, T: serde::de::DeserializeOwned>(
+        &self,
+        url: &Url,
+        body: &B,
+    ) -> Result<T, ApiError> {
+        let body = serde_json::to_vec(body)?;
+        self.send_json(&Method::POST, url, Some(Body::json(&body)))
+    }
+
+    fn patch<B: serde::Serialize, T: serde::de::DeserializeOwned>(
+        &self,
+        url: &Url,
+        body: &B,
+    ) -> Result<T, ApiError> {
+        let body = serde_json::to_vec(body)?;
+        self.send_json(&Method::PATCH, url, Some(Body::json(&body)))
+    }
+
+    fn send_json<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &Method,
+        url: &Url,
+        body: Option<Body<'_>>,
+    ) -> Result<T, ApiError> {
+        let mut resp = self.send(method, url, body)?;
+        let body = resp.body_mut().read_to_vec()?;
+        trace::body(url, &body);
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    fn delete(&self, url: &Url) -> Result<(), ApiError> {
+        self.send(&Method::DELETE, url, None)?;
+        Ok(())
+    }
+
+    /// Adds the configured static headers that the request does not set
+    /// itself.
+    fn with_static_headers(&self, mut request: http::request::Builder) -> http::request::Builder {
+        if let Some(headers) = request.headers_mut() {
+            let own: Vec<_> = headers.keys().cloned().collect();
+            for (name, value) in &self.headers {
+                if !own.contains(name) {
+                    headers.append(name, value.clone());
+                }
             }
-            let page: PaginatedResponse<T> = self.get(&parsed)?;
-            next = page.next;
-            results.extend(page.results);
         }
+        request
+    }
 
-        results.truncate(max);
-        Ok((results, total))
+    /// Sends a request, retrying idempotent ones according to the retry
+    /// policy, and maps unsuccessful responses to errors.
+    fn send(
+        &self,
+        method: &Method,
+        url: &Url,
+        body: Option<Body<'_>>,
+    ) -> Result<http::Response<ureq::Body>, ApiError> {
+        let span = trace::request_span(method.as_str(), url);
+        let _enter = span.enter();
+        let mut retry = 0;
+        loop {
+            let version = self.endpoint.versions.current();
+            let mut request = http::Request::builder()
+                .method(method.clone())
+                .uri(url.as_str())
+                .header("Accept", version::accept_header(version));
+            // Only the token endpoint is called without a token.
+            if !self.token.is_empty() {
+                request = request.header("Authorization", Endpoint::auth_header(&self.token));
+            }
+            request = self.with_static_headers(request);
+            if let Some(headers) = request.headers_ref() {
+                trace::request_headers(headers);
+            }
+            let started = Instant::now();
+            let result = match body {
+                Some(body) => request
+                    .header("Content-Type", body.content_type)
+                    .body(body.data)
+                    .map(|req| self.agent.run(req)),
+                None => request.body(()).map(|req| self.agent.run(req)),
+            }
+            .map_err(|err| ApiError::Network(Box::new(err)))?;
+            if let Ok(resp) = &result {
+                trace::response(resp.status().as_u16(), started.elapsed(), resp.headers());
+                self.observe_versions(resp.headers());
+            }
+
+            let (error, retry_after) = match result {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp)
+                    if resp.status() == http::StatusCode::NOT_ACCEPTABLE
+                        && self.negotiate_version(version)? =>
+                {
+                    continue;
+                }
+                Ok(mut resp) => {
+                    let status = resp.status().as_u16();
+                    let retry_after = resp
+                        .headers()
+                        .get("Retry-After")
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(retry::parse_retry_after);
+                    // Error bodies are small; anything unreadable or larger
+                    // falls back to the bare status.
+                    let body = resp
+                        .body_mut()
+                        .with_config()
+                        .limit(ERROR_BODY_LIMIT)
+                        .read_to_vec()
+                        .unwrap_or_default();
+                    trace::body(url, &body);
+                    let error = ApiError::from_response(status, &body);
+                    if !retry::is_retryable_status(status) {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                Err(
+                    err @ (ureq::Error::Timeout(_)
+                    | ureq::Error::Io(_)
+                    | ureq::Error::ConnectionFailed
+                    | ureq::Error::HostNotFound),
+                ) => (ApiError::from(err), None),
+                Err(err) => return Err(err.into()),
+            };
+
+            let delay = if method.is_idempotent() {
+                self.retry.delay(retry, retry_after)
+            } else {
+                None
+            };
+            let Some(delay) = delay else {
+                return Err(error);
+            };
+            retry += 1;
+            tracing::warn!(%method, %url, retry, ?delay, %error, "retrying request");
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn observe_versions(&self, headers: &http::HeaderMap) {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        self.endpoint
+            .versions
+            .observe(header("X-Api-Version"), header("X-Version"));
     }
 
-    fn url(&self, path: &str) -> Result<Url, ApiError> {
-        Ok(self.base_url.join(path)?)
+    /// After the server rejected API version `sent`, learns the versions it
+    /// supports if needed and returns whether to resend with a lower one.
+    fn negotiate_version(&self, sent: u32) -> Result<bool, ApiError> {
+        if self.endpoint.versions.needs_probe() {
+            // Without a version in `Accept` the server uses its default,
+            // and authenticated responses report the supported versions.
+            let url = self.endpoint.url("api/ui_settings/")?;
+            let request = http::Request::get(url.as_str())
+                .header("Accept", "application/json")
+                .header("Authorization", Endpoint::auth_header(&self.token));
+            let request = self
+                .with_static_headers(request)
+                .body(())
+                .map_err(|err| ApiError::Network(Box::new(err)))?;
+            let resp = self.agent.run(request)?;
+            self.observe_versions(resp.headers());
+        }
+        let resend = self.endpoint.versions.resend_after_not_acceptable(sent)?;
+        if resend {
+            tracing::debug!(
+                from = sent,
+                to = self.endpoint.versions.current(),
+                "negotiated lower API version"
+            );
+        }
+        Ok(resend)
     }
+}
 
-    fn get<T: serde::de::DeserializeOwned>(&self, url: &Url) -> Result<T, ApiError> {
-        let mut resp = self
-            .agent
-            .get(url.as_str())
-            .header("Accept", "application/json; version=9")
-            .header("Authorization", &format!("Token {}", self.token))
-            .call()?;
-        let body: T = resp.body_mut().read_json()?;
-        Ok(body)
+/// A request body and its content type.
+#[derive(Clone, Copy)]
+struct Body<'a> {
+    content_type: &'a str,
+    data: &'a [u8],
+}
+
+impl<'a> Body<'a> {
+    fn json(data: &'a [u8]) -> Self {
+        Self {
+            content_type: "application/json",
+            data,
+        }
     }
 }
 
-/// A builder for configuring a [`Client`].
+/// A builder for configuring a [`Client`], or with the `async` feature an
+/// `AsyncClient`.
 pub struct ClientBuilder {
     base_url: String,
     token: String,
     timeout: Option<Duration>,
     page_size: u32,
+    retry: RetryPolicy,
+    api_version: Option<u32>,
+    rewrite_next_urls: bool,
+    transport: Transport,
 }
 
 impl ClientBuilder {
+    pub(crate) fn without_token(mut self) -> Self {
+        self.token.clear();
+        self
+    }
+
     /// Sets the global request timeout.
     #[must_use]
     pub fn timeout(mut self, timeout: Duration) -> Self {
@@ -386,39 +1088,148 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the policy for retrying failed idempotent requests. By default,
+    /// requests are not retried.
+    #[must_use]
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Pins the API version sent with every request. By default, the
+    /// client requests [`MAX_API_VERSION`](crate::MAX_API_VERSION) and
+    /// falls back to the newest version the server supports.
+    #[must_use]
+    pub fn api_version(mut self, version: u32) -> Self {
+        self.api_version = Some(version);
+        self
+    }
+
+    /// Moves the `next` URLs of paginated responses onto the base URL,
+    /// keeping only their API path and query. Use this when a reverse proxy
+    /// makes the server generate URLs with the wrong scheme, host, or path
+    /// prefix and the server cannot be configured to trust forwarded
+    /// headers. By default, `next` URLs are followed as returned.
+    #[must_use]
+    pub fn rewrite_next_urls(mut self, rewrite: bool) -> Self {
+        self.rewrite_next_urls = rewrite;
+        self
+    }
+
+    /// Trusts the PEM-encoded CA certificates, in addition to the bundled
+    /// Mozilla roots. May be called repeatedly, and each call may pass a
+    /// bundle of several certificates.
+    #[must_use]
+    pub fn add_root_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.transport.root_certs.push(pem.into());
+        self
+    }
+
+    /// Presents the PEM-encoded certificate chain and private key to servers
+    /// that require client certificates (mutual TLS).
+    #[must_use]
+    pub fn client_certificate(mut self, cert: impl Into<Vec<u8>>, key: impl Into<Vec<u8>>) -> Self {
+        self.transport.client_cert = Some((cert.into(), key.into()));
+        self
+    }
+
+    /// Sends a header with every request, e.g. the service token of an
+    /// authenticating proxy. Headers set by the client itself take
+    /// precedence.
+    #[must_use]
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.transport.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Routes all requests through the given HTTP(S) proxy, e.g.
+    /// `http://proxy.internal:3128`. By default, the proxy is read from the
+    /// `HTTPS_PROXY`, `HTTP_PROXY`, and `ALL_PROXY` environment variables.
+    #[must_use]
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.transport.proxy = Some(url.into());
+        self
+    }
+
+    fn endpoint(&self) -> Result<Endpoint, ApiError> {
+        let versions = match self.api_version {
+            Some(version) => ApiVersions::pinned(version)?,
+            None => ApiVersions::negotiate(),
+        };
+        Ok(Endpoint {
+            base_url: Endpoint::base_url(&self.base_url)?,
+            page_size: self.page_size,
+            versions: Arc::new(versions),
+            rewrite_next_urls: self.rewrite_next_urls,
+        })
+    }
+
     /// Builds the [`Client`].
     ///
     /// # Errors
     ///
-    /// Returns [`ApiError::InvalidUrl`] if the base URL cannot be parsed.
+    /// Returns [`ApiError::InvalidUrl`] if the base URL cannot be parsed,
+    /// [`ApiError::UnsupportedApiVersion`] if the pinned API version is not
+    /// supported, or [`ApiError::InvalidConfig`] if a certificate, header,
+    /// or the proxy URL is invalid.
     pub fn build(self) -> Result<Client, ApiError> {
-        let base_url = Url::parse(&self.base_url)?;
-
-        let agent = if let Some(timeout) = self.timeout {
-            let config = ureq::Agent::config_builder()
-                .timeout_global(Some(timeout))
-                .build();
-            ureq::Agent::new_with_config(config)
-        } else {
-            ureq::Agent::new_with_defaults()
-        };
+        let endpoint = self.endpoint()?;
+
+        // Status codes are handled by `Client::send`, which needs the
+        // response headers to honor `Retry-After`.
+        let mut config = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(self.timeout)
+            .tls_config(self.transport.tls_config()?);
+        if let Some(proxy) = self.transport.ureq_proxy()? {
+            config = config.proxy(Some(proxy));
+        }
+        let agent = ureq::Agent::new_with_config(config.build());
 
         Ok(Client {
-            base_url,
+            endpoint,
             token: self.token,
             agent,
-            page_size: self.page_size,
+            headers: self.transport.header_map()?,
+            retry: self.retry,
         })
     }
+
+    /// Builds an [`AsyncClient`](crate::AsyncClient).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::InvalidUrl`] if the base URL cannot be parsed,
+    /// [`ApiError::UnsupportedApiVersion`] if the pinned API version is not
+    /// supported, [`ApiError::InvalidConfig`] if a certificate, header, or
+    /// the proxy URL is invalid, or [`ApiError::Network`] if the HTTP client
+    /// cannot be initialized.
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> Result<crate::AsyncClient, ApiError> {
+        let endpoint = self.endpoint()?;
+
+        let mut http = self.transport.apply_reqwest(reqwest::Client::builder())?;
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        let http = http.build()?;
+
+        Ok(crate::AsyncClient::new(
+            endpoint, self.token, http, self.retry,
+        ))
+    }
 }
 
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 mod tests {
-    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::matchers::{
+        body_json, body_string_contains, header, header_regex, method, path, query_param,
+    };
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     use super::*;
+    use crate::types::MatchingAlgorithm;
 
     async fn setup() -> (MockServer, Client) {
         let server = MockServer::start().await;
@@ -503,127 +1314,415 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_document_content() {
+    async fn test_document_full_model() {
+        let (server, client) = setup().await;
+
+        let body = serde_json::json!({
+            "id": 7,
+            "title": "Lease",
+            "content": "",
+            "correspondent": 1,
+            "document_type": 2,
+            "storage_path": 3,
+            "tags": [4],
+            "created": "2024-05-01",
+            "modified": "2024-05-03T10:00:00Z",
+            "added": "2024-05-02T09:00:00Z",
+            "archive_serial_number": 12,
+            "original_file_name": "lease.pdf",
+            "archived_file_name": "2024-05-01 Lease.pdf",
+            "owner": 5,
+            "user_can_change": true,
+            "is_shared_by_requester": false,
+            "notes": [{
+                "id": 9,
+                "note": "Renew in May",
+                "created": "2024-05-04T08:00:00Z",
+                "user": {"id": 5, "username": "alice", "first_name": "", "last_name": ""}
+            }],
+            "custom_fields": [{"field": 1, "value": "2026-05-01"}, {"field": 2, "value": null}],
+            "page_count": 4,
+            "mime_type": "application/pdf",
+            "deleted_at": null,
+            "future_field": {"nested": [1, 2]}
+        });
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/7/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .mount(&server)
+            .await;
+
+        let doc = client.document(7).expect("document request should succeed");
+        assert_eq!(doc.storage_path, Some(3));
+        assert_eq!(doc.owner, Some(5));
+        assert_eq!(doc.page_count, Some(4));
+        assert_eq!(doc.mime_type.as_deref(), Some("application/pdf"));
+        assert_eq!(doc.notes[0].note, "Renew in May");
+        assert_eq!(
+            doc.notes[0]
+                .user
+                .as_ref()
+                .and_then(|u| u.username.as_deref()),
+            Some("alice")
+        );
+        assert_eq!(doc.custom_fields[0].value, "2026-05-01");
+        assert!(doc.custom_fields[1].value.is_null());
+        assert_eq!(
+            doc.extra.keys().collect::<Vec<_>>(),
+            ["deleted_at", "future_field"]
+        );
+
+        // Unknown fields survive a round-trip.
+        let value = serde_json::to_value(&doc).expect("document should serialize");
+        assert_eq!(value["future_field"], body["future_field"]);
+        assert_eq!(value["modified"], body["modified"]);
+    }
+
+    #[tokio::test]
+    async fn test_document_content() {
+        let (server, client) = setup().await;
+
+        let body = serde_json::json!({
+            "id": 1,
+            "title": "Doc",
+            "content": "The full text content",
+            "correspondent": null,
+            "document_type": null,
+            "tags": [],
+            "created": null,
+            "added": null,
+            "archive_serial_number": null,
+            "original_file_name": null
+        });
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/1/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .mount(&server)
+            .await;
+
+        let content = client
+            .document_content(1)
+            .expect("content request should succeed");
+        assert_eq!(content, "The full text content");
+    }
+
+    #[tokio::test]
+    async fn test_download_document() {
+        let (server, client) = setup().await;
+
+        let pdf_bytes = b"%PDF-fake-content";
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/10/download/"))
+            .and(header("Authorization", "Token test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(pdf_bytes.as_slice()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut buf = Vec::new();
+        let bytes = client
+            .download_document(10, DocumentVersion::Original, &mut buf)
+            .expect("download should succeed");
+        assert_eq!(buf, pdf_bytes);
+        assert_eq!(bytes, pdf_bytes.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_download_preview() {
+        let (server, client) = setup().await;
+
+        let preview_bytes = b"preview-data";
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/10/preview/"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(preview_bytes.as_slice()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut buf = Vec::new();
+        let bytes = client
+            .download_document(10, DocumentVersion::Archived, &mut buf)
+            .expect("preview download should succeed");
+        assert_eq!(buf, preview_bytes);
+        assert_eq!(bytes, preview_bytes.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_tags() {
+        let (server, client) = setup().await;
+
+        let body = serde_json::json!({
+            "count": 2,
+            "next": null,
+            "previous": null,
+            "results": [
+                {"id": 1, "name": "Invoice", "slug": "invoice", "color": "#ff0000", "is_inbox_tag": false, "document_count": 10},
+                {"id": 2, "name": "Receipt", "slug": "receipt", "color": null, "is_inbox_tag": true, "document_count": 5}
+            ]
+        });
+
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let result = client.tags().expect("tags request should succeed");
+        assert_eq!(result.count, 2);
+        assert_eq!(result.results[0].name, "Invoice");
+        assert_eq!(result.results[1].name, "Receipt");
+    }
+
+    #[tokio::test]
+    async fn test_create_tag() {
+        let (server, client) = setup().await;
+
+        let body = serde_json::json!({
+            "id": 3,
+            "name": "Taxes",
+            "slug": "taxes",
+            "color": "#a6cee3",
+            "text_color": "#000000",
+            "is_inbox_tag": false,
+            "match": "tax",
+            "matching_algorithm": 1,
+            "is_insensitive": true,
+            "parent": 1,
+            "document_count": 0
+        });
+
+        Mock::given(method("POST"))
+            .and(path("/api/tags/"))
+            .and(header("Authorization", "Token test-token"))
+            .and(body_json(serde_json::json!({
+                "name": "Taxes",
+                "match": "tax",
+                "matching_algorithm": 1,
+                "is_insensitive": true,
+                "parent": 1
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(&body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = TagRequest {
+            name: Some("Taxes".to_string()),
+            r#match: Some("tax".to_string()),
+            matching_algorithm: Some(MatchingAlgorithm::Any),
+            is_insensitive: Some(true),
+            parent: Some(Some(1)),
+            ..TagRequest::default()
+        };
+        let tag = client.create_tag(&request).expect("create should succeed");
+        assert_eq!(tag.id, 3);
+        assert_eq!(tag.matching_algorithm, Some(MatchingAlgorithm::Any));
+        assert_eq!(tag.text_color.as_deref(), Some("#000000"));
+        assert_eq!(tag.parent, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_upload_document_and_wait_for_task() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/documents/post_document/"))
+            .and(header_regex(
+                "Content-Type",
+                "^multipart/form-data; boundary=",
+            ))
+            .and(body_string_contains("filename=\"scan.pdf\""))
+            .and(body_string_contains("name=\"tags\"\r\n\r\n2\r\n"))
+            .and(body_string_contains("name=\"tags\"\r\n\r\n5\r\n"))
+            .respond_with(ResponseTemplate::new(200).set_body_json("3f7c-task"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/tasks/"))
+            .and(query_param("task_id", "3f7c-task"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                    "id": 1,
+                    "task_id": "3f7c-task",
+                    "task_file_name": "scan.pdf",
+                    "status": "SUCCESS",
+                    "result": "Success. New document id 12 created",
+                    "related_document": "12"
+                }])),
+            )
+            .mount(&server)
+            .await;
+
+        let upload = DocumentUpload {
+            title: Some("Scan".to_string()),
+            tags: vec![2, 5],
+            ..DocumentUpload::default()
+        };
+        let task_id = client
+            .upload_document("scan.pdf", b"%PDF", &upload)
+            .expect("upload should succeed");
+        assert_eq!(task_id, "3f7c-task");
+        let task = client
+            .wait_for_task(&task_id, Duration::from_secs(5))
+            .expect("task should finish");
+        assert_eq!(task.status, crate::TaskStatus::Success);
+        assert_eq!(task.related_document, Some(12));
+    }
+
+    #[tokio::test]
+    async fn test_update_tag_clears_parent() {
+        let (server, client) = setup().await;
+
+        let body = serde_json::json!({
+            "id": 3,
+            "name": "Taxes",
+            "slug": "taxes",
+            "color": null,
+            "is_inbox_tag": false,
+            "parent": null,
+            "document_count": 0
+        });
+
+        Mock::given(method("PATCH"))
+            .and(path("/api/tags/3/"))
+            .and(body_json(serde_json::json!({"parent": null})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = TagRequest {
+            parent: Some(None),
+            ..TagRequest::default()
+        };
+        let tag = client
+            .update_tag(3, &request)
+            .expect("update should succeed");
+        assert_eq!(tag.parent, None);
+    }
+
+    #[tokio::test]
+    async fn test_delete_tag() {
         let (server, client) = setup().await;
 
-        let body = serde_json::json!({
-            "id": 1,
-            "title": "Doc",
-            "content": "The full text content",
-            "correspondent": null,
-            "document_type": null,
-            "tags": [],
-            "created": null,
-            "added": null,
-            "archive_serial_number": null,
-            "original_file_name": null
-        });
-
-        Mock::given(method("GET"))
-            .and(path("/api/documents/1/"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+        Mock::given(method("DELETE"))
+            .and(path("/api/tags/3/"))
+            .and(header("Authorization", "Token test-token"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
             .mount(&server)
             .await;
 
-        let content = client
-            .document_content(1)
-            .expect("content request should succeed");
-        assert_eq!(content, "The full text content");
+        client.delete_tag(3).expect("delete should succeed");
     }
 
     #[tokio::test]
-    async fn test_download_document() {
+    async fn test_correspondents() {
         let (server, client) = setup().await;
 
-        let pdf_bytes = b"%PDF-fake-content";
+        let body = serde_json::json!({
+            "count": 1,
+            "next": null,
+            "previous": null,
+            "results": [{"id": 1, "name": "ACME Corp", "slug": "acme-corp", "document_count": 3}]
+        });
 
         Mock::given(method("GET"))
-            .and(path("/api/documents/10/download/"))
-            .and(header("Authorization", "Token test-token"))
-            .respond_with(ResponseTemplate::new(200).set_body_bytes(pdf_bytes.as_slice()))
+            .and(path("/api/correspondents/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&server)
             .await;
 
-        let mut buf = Vec::new();
-        let bytes = client
-            .download_document(10, DocumentVersion::Original, &mut buf)
-            .expect("download should succeed");
-        assert_eq!(buf, pdf_bytes);
-        assert_eq!(bytes, pdf_bytes.len() as u64);
+        let result = client
+            .correspondents()
+            .expect("correspondents request should succeed");
+        assert_eq!(result.count, 1);
+        assert_eq!(result.results[0].name, "ACME Corp");
     }
 
     #[tokio::test]
-    async fn test_download_preview() {
+    async fn test_bulk_edit_set_correspondent() {
         let (server, client) = setup().await;
 
-        let preview_bytes = b"preview-data";
-
-        Mock::given(method("GET"))
-            .and(path("/api/documents/10/preview/"))
-            .respond_with(ResponseTemplate::new(200).set_body_bytes(preview_bytes.as_slice()))
+        Mock::given(method("POST"))
+            .and(path("/api/documents/bulk_edit/"))
+            .and(body_json(serde_json::json!({
+                "documents": [1, 2],
+                "method": "set_correspondent",
+                "parameters": {"correspondent": 7}
+            })))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"result": "OK"})),
+            )
             .expect(1)
             .mount(&server)
             .await;
 
-        let mut buf = Vec::new();
-        let bytes = client
-            .download_document(10, DocumentVersion::Archived, &mut buf)
-            .expect("preview download should succeed");
-        assert_eq!(buf, preview_bytes);
-        assert_eq!(bytes, preview_bytes.len() as u64);
+        client
+            .bulk_edit(&[1, 2], &BulkEdit::SetCorrespondent(Some(7)))
+            .expect("bulk edit should succeed");
     }
 
     #[tokio::test]
-    async fn test_tags() {
+    async fn test_collect_document_ids() {
         let (server, client) = setup().await;
 
         let body = serde_json::json!({
             "count": 2,
             "next": null,
             "previous": null,
-            "results": [
-                {"id": 1, "name": "Invoice", "slug": "invoice", "color": "#ff0000", "is_inbox_tag": false, "document_count": 10},
-                {"id": 2, "name": "Receipt", "slug": "receipt", "color": null, "is_inbox_tag": true, "document_count": 5}
-            ]
+            "results": [{"id": 4}, {"id": 9}]
         });
 
         Mock::given(method("GET"))
-            .and(path("/api/tags/"))
+            .and(path("/api/documents/"))
+            .and(query_param("correspondent__id__in", "1,2"))
+            .and(query_param("fields", "id"))
             .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&server)
             .await;
 
-        let result = client.tags().expect("tags request should succeed");
-        assert_eq!(result.count, 2);
-        assert_eq!(result.results[0].name, "Invoice");
-        assert_eq!(result.results[1].name, "Receipt");
+        let ids = client
+            .collect_document_ids(&[("correspondent__id__in", "1,2")])
+            .expect("request should succeed");
+        assert_eq!(ids, vec![4, 9]);
     }
 
     #[tokio::test]
-    async fn test_correspondents() {
+    async fn test_tags_by_id() {
         let (server, client) = setup().await;
 
         let body = serde_json::json!({
-            "count": 1,
+            "count": 2,
             "next": null,
             "previous": null,
-            "results": [{"id": 1, "name": "ACME Corp", "slug": "acme-corp", "document_count": 3}]
+            "results": [
+                {"id": 3, "name": "Finance", "slug": "finance", "color": null, "is_inbox_tag": false, "document_count": 1},
+                {"id": 7, "name": "Taxes", "slug": "taxes", "color": null, "is_inbox_tag": false, "document_count": 2}
+            ]
         });
 
         Mock::given(method("GET"))
-            .and(path("/api/correspondents/"))
+            .and(path("/api/tags/"))
+            .and(query_param("id__in", "3,7,12"))
             .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&server)
             .await;
 
-        let result = client
-            .correspondents()
-            .expect("correspondents request should succeed");
-        assert_eq!(result.count, 1);
-        assert_eq!(result.results[0].name, "ACME Corp");
+        let tags = client
+            .tags_by_id(&[3, 7, 12])
+            .expect("request should succeed");
+        let names: Vec<_> = tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["Finance", "Taxes"]);
     }
 
     #[tokio::test]
@@ -694,6 +1793,30 @@ mod tests {
         assert_eq!(result.results[0].id, 7);
     }
 
+    #[tokio::test]
+    async fn test_suggestions() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/7/suggestions/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "correspondents": [2],
+                "tags": [1, 4],
+                "document_types": [],
+                "storage_paths": [],
+                "dates": ["2024-06-15"]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let suggestions = client.suggestions(7).expect("suggestions should load");
+        assert_eq!(suggestions.correspondents, [2]);
+        assert_eq!(suggestions.tags, [1, 4]);
+        assert!(suggestions.document_types.is_empty());
+        assert_eq!(suggestions.dates, [jiff::civil::date(2024, 6, 15)]);
+    }
+
     #[tokio::test]
     async fn test_search() {
         let (server, client) = setup().await;
@@ -743,7 +1866,7 @@ mod tests {
         let err = client
             .documents()
             .expect_err("should return unauthorized error");
-        assert!(matches!(err, ApiError::Unauthorized));
+        assert!(matches!(err, ApiError::Unauthorized { .. }));
     }
 
     #[tokio::test]
@@ -760,7 +1883,7 @@ mod tests {
         let err = client
             .documents()
             .expect_err("should return unauthorized error");
-        assert!(matches!(err, ApiError::Unauthorized));
+        assert!(matches!(err, ApiError::Unauthorized { .. }));
     }
 
     #[tokio::test]
@@ -777,7 +1900,50 @@ mod tests {
         let err = client
             .document(999)
             .expect_err("should return not found error");
-        assert!(matches!(err, ApiError::NotFound));
+        assert!(matches!(err, ApiError::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_error_detail_for_each_status() {
+        let (server, client) = setup().await;
+        for (status, id, detail) in [
+            (401, 1, "Invalid token."),
+            (403, 2, "You do not have permission to perform this action."),
+            (404, 3, "No Document matches the given query."),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(format!("/api/documents/{id}/")))
+                .respond_with(
+                    ResponseTemplate::new(status)
+                        .set_body_json(serde_json::json!({"detail": detail})),
+                )
+                .mount(&server)
+                .await;
+        }
+
+        let err = client.document(1).expect_err("401 should fail");
+        assert!(
+            matches!(&err, ApiError::Unauthorized { detail: Some(d) } if d == "Invalid token."),
+            "{err:?}"
+        );
+        assert_eq!(err.to_string(), "unauthorized: Invalid token.");
+
+        let err = client.document(2).expect_err("403 should fail");
+        assert!(
+            matches!(&err, ApiError::Unauthorized { detail: Some(d) } if d.starts_with("You do not have permission")),
+            "{err:?}"
+        );
+
+        let err = client.document(3).expect_err("404 should fail");
+        assert!(
+            matches!(&err, ApiError::NotFound { detail: Some(d) } if d == "No Document matches the given query."),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.to_string(),
+            "not found: No Document matches the given query."
+        );
+        assert_eq!(ApiError::NotFound { detail: None }.to_string(), "not found");
     }
 
     #[tokio::test]
@@ -795,6 +1961,157 @@ mod tests {
         assert!(matches!(err, ApiError::Server { status: 500, .. }));
     }
 
+    #[tokio::test]
+    async fn test_validation_error() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+                "name": ["Tag with this name already exists."],
+                "matching_algorithm": "Invalid choice.",
+                "non_field_errors": ["Permission denied."],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = TagRequest {
+            name: Some("dup".to_string()),
+            ..TagRequest::default()
+        };
+        let err = client
+            .create_tag(&request)
+            .expect_err("should return validation error");
+        let ApiError::Validation {
+            field_errors,
+            non_field_errors,
+        } = &err
+        else {
+            panic!("expected validation error, got {err:?}");
+        };
+        assert_eq!(field_errors["name"], ["Tag with this name already exists."]);
+        assert_eq!(field_errors["matching_algorithm"], ["Invalid choice."]);
+        assert_eq!(non_field_errors, &["Permission denied."]);
+        assert_eq!(
+            err.to_string(),
+            "validation failed: Permission denied.; matching_algorithm: Invalid choice.; \
+             name: Tag with this name already exists."
+        );
+    }
+
+    #[test]
+    fn test_validation_error_nested_fields() {
+        let body = serde_json::json!({
+            "custom_fields": [{}, {"value": ["Enter a valid date."]}],
+        });
+        let err = ApiError::from_response(400, body.to_string().as_bytes());
+        let ApiError::Validation { field_errors, .. } = err else {
+            panic!("expected validation error, got {err:?}");
+        };
+        assert_eq!(
+            field_errors["custom_fields.1.value"],
+            ["Enter a valid date."]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_error_detail() {
+        let (server, client) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/"))
+            .respond_with(
+                ResponseTemplate::new(500)
+                    .set_body_json(serde_json::json!({"detail": "Index is corrupt."})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client.documents().expect_err("should return server error");
+        assert_eq!(err.to_string(), "server error (500): Index is corrupt.");
+    }
+
+    #[tokio::test]
+    async fn test_retries_with_retry_after() {
+        let (server, _) = setup().await;
+        let client = Client::builder(&server.uri(), "test-token")
+            .retry_policy(RetryPolicy::new(2))
+            .build()
+            .expect("client builder should succeed");
+
+        Mock::given(method("GET"))
+            .and(path("/api/tags/1/"))
+            .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags/1/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 1,
+                "name": "invoice",
+                "slug": "invoice",
+                "color": null,
+                "is_inbox_tag": false,
+                "document_count": 0
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tag = client.tag(1).expect("retried request should succeed");
+        assert_eq!(tag.name, "invoice");
+    }
+
+    #[tokio::test]
+    async fn test_retries_exhausted() {
+        let (server, _) = setup().await;
+        let client = Client::builder(&server.uri(), "test-token")
+            .retry_policy(RetryPolicy::new(2).initial_backoff(Duration::from_millis(1)))
+            .build()
+            .expect("client builder should succeed");
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/"))
+            .respond_with(ResponseTemplate::new(429))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let err = client
+            .documents()
+            .expect_err("should give up after retries");
+        assert!(matches!(err, ApiError::Server { status: 429, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_post_is_not_retried() {
+        let (server, _) = setup().await;
+        let client = Client::builder(&server.uri(), "test-token")
+            .retry_policy(RetryPolicy::new(2).initial_backoff(Duration::from_millis(1)))
+            .build()
+            .expect("client builder should succeed");
+
+        Mock::given(method("POST"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = TagRequest {
+            name: Some("new".to_string()),
+            ..TagRequest::default()
+        };
+        let err = client
+            .create_tag(&request)
+            .expect_err("should not retry POST");
+        assert!(matches!(err, ApiError::Server { status: 503, .. }));
+    }
+
     #[tokio::test]
     async fn test_custom_page_size() {
         let (server, _) = setup().await;
@@ -832,7 +2149,7 @@ mod tests {
             .build()
             .expect("builder should succeed");
 
-        assert_eq!(client.page_size, 50);
+        assert_eq!(client.endpoint.page_size, 50);
     }
 
     #[test]
@@ -881,6 +2198,219 @@ mod tests {
         let err = client
             .server_version()
             .expect_err("should return unauthorized error");
-        assert!(matches!(err, ApiError::Unauthorized));
+        assert!(matches!(err, ApiError::Unauthorized { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_obtain_token() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/token/"))
+            .and(body_json(
+                serde_json::json!({"username": "alice", "password": "secret"}),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"token": "abc123"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/token/"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+                "non_field_errors": ["Unable to log in with provided credentials."]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let token = Client::obtain_token(Client::builder(&server.uri(), ""), "alice", "secret")
+            .expect("token request should succeed");
+        assert_eq!(token, "abc123");
+
+        let err = Client::obtain_token(
+            Client::builder(&server.uri(), "stale-token").header("X-Proxy-User", "alice"),
+            "alice",
+            "wrong",
+        )
+        .expect_err("wrong password should fail");
+        assert!(matches!(err, ApiError::Validation { .. }));
+
+        let requests = server
+            .received_requests()
+            .await
+            .expect("requests are recorded");
+        assert!(
+            requests
+                .iter()
+                .all(|r| !r.headers.contains_key("Authorization"))
+        );
+        assert_eq!(requests[1].headers["X-Proxy-User"], "alice");
+    }
+
+    #[tokio::test]
+    async fn test_api_version_negotiation() {
+        let (server, client) = setup().await;
+        assert_eq!(client.api_version(), crate::MAX_API_VERSION);
+
+        Mock::given(method("GET"))
+            .and(header("Accept", "application/json; version=9"))
+            .respond_with(ResponseTemplate::new(406).set_body_json(
+                serde_json::json!({"detail": "Invalid version in \"Accept\" header."}),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/ui_settings/"))
+            .and(header("Accept", "application/json"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("X-Api-Version", "5")
+                    .insert_header("X-Version", "2.1.3")
+                    .set_body_json(serde_json::json!({"settings": {"version": "2.1.3"}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/documents/1/"))
+            .and(header("Accept", "application/json; version=5"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("X-Api-Version", "5")
+                    .set_body_json(serde_json::json!({
+                        "id": 1,
+                        "title": "Old",
+                        "content": null,
+                        "correspondent": null,
+                        "document_type": null,
+                        "tags": [],
+                        "created": "2023-04-01T23:30:00+02:00",
+                        "added": "2023-04-02T08:00:00Z",
+                        "archive_serial_number": null,
+                        "original_file_name": null
+                    })),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let doc = client.document(1).expect("request should succeed");
+        assert_eq!(doc.created, Some(jiff::civil::date(2023, 4, 1)));
+        assert_eq!(client.api_version(), 5);
+        assert_eq!(client.server_api_version(), Some(5));
+        assert_eq!(
+            client.server_version().expect("version should be known"),
+            "2.1.3"
+        );
+
+        // Later requests use the negotiated version right away.
+        client.document(1).expect("request should succeed");
+    }
+
+    #[tokio::test]
+    async fn test_pinned_api_version() {
+        let (server, _) = setup().await;
+        let client = Client::builder(&server.uri(), "test-token")
+            .api_version(7)
+            .build()
+            .expect("client builder should succeed");
+
+        Mock::given(method("GET"))
+            .and(path("/api/documents/"))
+            .and(header("Accept", "application/json; version=7"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("X-Api-Version", "9")
+                    .set_body_json(serde_json::json!({
+                        "count": 0,
+                        "next": null,
+                        "previous": null,
+                        "results": []
+                    })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client.documents().expect("request should succeed");
+        assert_eq!(client.api_version(), 7);
+        assert_eq!(client.server_api_version(), Some(9));
+
+        let err = Client::builder(&server.uri(), "test-token")
+            .api_version(42)
+            .build()
+            .expect_err("version should be rejected");
+        assert!(matches!(
+            err,
+            ApiError::UnsupportedApiVersion { version: 42 }
+        ));
+    }
+
+    fn tag_body() -> serde_json::Value {
+        serde_json::json!({
+            "id": 1,
+            "name": "invoice",
+            "slug": "invoice",
+            "color": null,
+            "is_inbox_tag": false,
+            "document_count": 0
+        })
+    }
+
+    #[tokio::test]
+    async fn test_static_headers() {
+        let server = MockServer::start().await;
+        let client = Client::builder(&server.uri(), "test-token")
+            .header("CF-Access-Client-Id", "abc")
+            .header("Authorization", "Basic ignored")
+            .build()
+            .expect("client creation should succeed");
+
+        Mock::given(method("GET"))
+            .and(path("/api/tags/1/"))
+            .and(header("CF-Access-Client-Id", "abc"))
+            .and(header("Authorization", "Token test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tag = client.tag(1).expect("tag request should succeed");
+        assert_eq!(tag.name, "invoice");
+    }
+
+    #[tokio::test]
+    async fn test_proxy() {
+        let proxy = MockServer::start().await;
+        let client = Client::builder("http://paperless.invalid", "test-token")
+            .proxy(proxy.uri())
+            .build()
+            .expect("client creation should succeed");
+
+        // ureq tunnels every request through the proxy with CONNECT.
+        Mock::given(method("CONNECT"))
+            .respond_with(ResponseTemplate::new(403))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+
+        let err = client.tag(1).expect_err("proxy should refuse the tunnel");
+        assert!(matches!(err, ApiError::Network(_)));
+    }
+
+    #[test]
+    fn test_invalid_client_options() {
+        let result = Client::builder("http://localhost", "test-token")
+            .client_certificate("not a certificate", "not a key")
+            .build();
+        assert!(matches!(result, Err(ApiError::InvalidConfig(_))));
+
+        let result = Client::builder("http://localhost", "test-token")
+            .proxy("::invalid")
+            .build();
+        assert!(matches!(result, Err(ApiError::InvalidConfig(_))));
     }
 }
@@ -0,0 +1,116 @@
+//! Request construction shared by the blocking and async clients.
+
+use std::sync::Arc;
+
+use url::Url;
+
+use crate::error::ApiError;
+use crate::version::ApiVersions;
+
+/// Every modeled document field except the potentially large `content`.
+pub(crate) const DOCUMENT_LIST_FIELDS: &str = "id,title,correspondent,document_type,storage_path,tags,created,modified,added,archive_serial_number,original_file_name,archived_file_name,owner,notes,custom_fields,page_count,mime_type,is_shared_by_requester,user_can_change";
+
+/// Base URL, paging settings, and API version used to build requests.
+#[derive(Debug, Clone)]
+pub(crate) struct Endpoint {
+    pub(crate) base_url: Url,
+    pub(crate) page_size: u32,
+    pub(crate) versions: Arc<ApiVersions>,
+    /// Move `next` URLs onto the base URL instead of following them as
+    /// returned.
+    pub(crate) rewrite_next_urls: bool,
+}
+
+impl Endpoint {
+    /// Parses the base URL. A path without a trailing slash is treated as a
+    /// directory, so `https://host/paperless` serves the API under
+    /// `/paperless/api/`.
+    pub(crate) fn base_url(base_url: &str) -> Result<Url, ApiError> {
+        let mut url = Url::parse(base_url)?;
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        Ok(url)
+    }
+
+    /// Resolves `path` against the base URL.
+    pub(crate) fn url(&self, path: &str) -> Result<Url, ApiError> {
+        Ok(self.base_url.join(path)?)
+    }
+
+    /// Resolves a list endpoint with the given query pairs and the
+    /// configured page size.
+    pub(crate) fn list_url(&self, path: &str, query: &[(&str, &str)]) -> Result<Url, ApiError> {
+        let mut url = self.url(path)?;
+        url.query_pairs_mut()
+            .extend_pairs(query)
+            .append_pair("page_size", &self.page_size.to_string());
+        Ok(url)
+    }
+
+    /// Parses the `next` URL of a paginated response, rejecting URLs whose
+    /// scheme differs from the base URL unless they are rewritten.
+    pub(crate) fn next_url(&self, next: &str) -> Result<Url, ApiError> {
+        let parsed = Url::parse(next)?;
+        if self.rewrite_next_urls {
+            return self.rebase(&parsed);
+        }
+        if parsed.scheme() != self.base_url.scheme() {
+            return Err(ApiError::SchemeMismatch {
+                expected: self.base_url.scheme().to_string(),
+                returned: parsed.scheme().to_string(),
+            });
+        }
+        Ok(parsed)
+    }
+
+    /// Moves a URL generated by the server onto the base URL, keeping the
+    /// path from `api/` on and the query. A reverse proxy may have changed
+    /// the scheme, host, port, and path prefix the server sees. The base
+    /// URL's path is stripped if the server sees the same prefix, so the
+    /// prefix may itself contain `/api/`; otherwise the path is kept from
+    /// the last `/api/` on.
+    fn rebase(&self, url: &Url) -> Result<Url, ApiError> {
+        let path = url.path();
+        let api_path = match path.strip_prefix(self.base_url.path()) {
+            Some(rest) if rest.starts_with("api/") => rest,
+            _ => path
+                .rfind("/api/")
+                .map_or(path.trim_start_matches('/'), |start| &path[start + 1..]),
+        };
+        let mut rebased = self.url(api_path)?;
+        rebased.set_query(url.query());
+        Ok(rebased)
+    }
+
+    pub(crate) fn auth_header(token: &str) -> String {
+        format!("Token {token}")
+    }
+}
+
+/// Delay between polls of a background task.
+pub(crate) const TASK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Maximum number of IDs sent in a single `id__in` filter, to keep request
+/// URLs short.
+const ID_FILTER_CHUNK: usize = 100;
+
+/// Splits `ids` into comma-separated values for `id__in` filters.
+pub(crate) fn id_filters(ids: &[u64]) -> impl Iterator<Item = String> + '_ {
+    ids.chunks(ID_FILTER_CHUNK).map(|chunk| {
+        chunk
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    })
+}
+
+/// Path of the file endpoint for a document version.
+pub(crate) fn download_path(id: u64, version: crate::DocumentVersion) -> String {
+    match version {
+        crate::DocumentVersion::Original => format!("api/documents/{id}/download/"),
+        crate::DocumentVersion::Archived => format!("api/documents/{id}/preview/"),
+    }
+}
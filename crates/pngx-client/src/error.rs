@@ -1,15 +1,27 @@
+use std::collections::BTreeMap;
+
 use thiserror::Error;
 
 /// Errors returned by the Paperless-ngx API client.
 #[derive(Debug, Error)]
 pub enum ApiError {
-    /// The API token is invalid or missing.
-    #[error("unauthorized: invalid or missing API token")]
-    Unauthorized,
+    /// The API token is invalid or missing, or the user lacks permission
+    /// (HTTP 401 or 403).
+    #[error(
+        "unauthorized: {}",
+        detail.as_deref().unwrap_or("invalid or missing API token")
+    )]
+    Unauthorized {
+        /// Message from the `detail` field of the response body, if any.
+        detail: Option<String>,
+    },
 
-    /// The requested resource was not found.
-    #[error("not found")]
-    NotFound,
+    /// The requested resource was not found (HTTP 404).
+    #[error("not found{}", detail.as_deref().map(|d| format!(": {d}")).unwrap_or_default())]
+    NotFound {
+        /// Message from the `detail` field of the response body, if any.
+        detail: Option<String>,
+    },
 
     /// The base URL could not be parsed.
     #[error("invalid URL: {0}")]
@@ -21,7 +33,7 @@ pub enum ApiError {
 
     /// A network-level error occurred.
     #[error("network error")]
-    Network(#[source] Box<ureq::Error>),
+    Network(#[source] Box<dyn std::error::Error + Send + Sync>),
 
     /// The request timed out.
     #[error("request timed out")]
@@ -35,10 +47,13 @@ pub enum ApiError {
     /// the configured base URL, typically `http` instead of `https`. This
     /// usually means the server is behind a reverse proxy that terminates TLS
     /// but the server is not configured to trust forwarded headers (e.g.
-    /// `X-Forwarded-Proto`).
+    /// `X-Forwarded-Proto`). Enable
+    /// [`ClientBuilder::rewrite_next_urls`](crate::ClientBuilder::rewrite_next_urls)
+    /// if the server cannot be reconfigured.
     #[error(
         "server returned pagination URL with scheme \"{returned}\" but client uses \"{expected}\"; \
-        configure your server to trust proxy headers (e.g. PAPERLESS_PROXY_SSL_HEADER)"
+        configure your server to trust proxy headers (e.g. PAPERLESS_PROXY_SSL_HEADER) \
+        or enable rewrite_next_urls"
     )]
     SchemeMismatch {
         /// Scheme the client is configured with.
@@ -47,29 +62,192 @@ pub enum ApiError {
         returned: String,
     },
 
+    /// A client option such as a certificate, header, or proxy URL is
+    /// invalid.
+    #[error("invalid client configuration: {0}")]
+    InvalidConfig(String),
+
+    /// The requested API version, or the newest version the server
+    /// supports, is outside the range this client supports.
+    #[error(
+        "API version {version} is not supported; pngx-client supports versions {} to {}",
+        crate::MIN_API_VERSION,
+        crate::MAX_API_VERSION
+    )]
+    UnsupportedApiVersion {
+        /// The unsupported version.
+        version: u32,
+    },
+
+    /// The server rejected the submitted data (HTTP 400).
+    #[error(
+        "validation failed: {}",
+        format_validation(field_errors, non_field_errors)
+    )]
+    Validation {
+        /// Messages per field. Errors on nested values use dotted keys,
+        /// e.g. `custom_fields.0.value`.
+        field_errors: BTreeMap<String, Vec<String>>,
+        /// Messages that don't belong to a single field.
+        non_field_errors: Vec<String>,
+    },
+
     /// The server returned an unexpected status code.
     #[error("server error ({status}): {message}")]
     Server {
         /// HTTP status code.
         status: u16,
-        /// Error message from the server.
+        /// Error message from the server, taken from the `detail` field of
+        /// the response body when present.
         message: String,
     },
 }
 
+impl ApiError {
+    /// Maps an unsuccessful HTTP status code to an error.
+    pub(crate) fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ApiError::Unauthorized { detail: None },
+            404 => ApiError::NotFound { detail: None },
+            _ => ApiError::Server {
+                status,
+                message: "unexpected status code".to_string(),
+            },
+        }
+    }
+
+    /// Maps an unsuccessful response to an error, using the JSON error body
+    /// the server sends where possible.
+    pub(crate) fn from_response(status: u16, body: &[u8]) -> Self {
+        let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) else {
+            return Self::from_status(status);
+        };
+        let detail = value.get("detail").and_then(|d| d.as_str());
+
+        match (status, detail) {
+            (401 | 403, detail) => ApiError::Unauthorized {
+                detail: detail.map(str::to_string),
+            },
+            (404, detail) => ApiError::NotFound {
+                detail: detail.map(str::to_string),
+            },
+            (_, Some(detail)) => ApiError::Server {
+                status,
+                message: detail.to_string(),
+            },
+            (400, None) => validation_error(value),
+            _ => Self::from_status(status),
+        }
+    }
+}
+
+/// Builds a validation error from a DRF error body: an object mapping field
+/// names to messages, or a plain list of messages.
+fn validation_error(value: serde_json::Value) -> ApiError {
+    let mut field_errors = BTreeMap::new();
+    let mut non_field_errors = Vec::new();
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                if key == "non_field_errors" {
+                    collect_messages(&value, &key, &mut field_errors, &mut non_field_errors);
+                } else {
+                    let mut messages = Vec::new();
+                    collect_messages(&value, &key, &mut field_errors, &mut messages);
+                    if !messages.is_empty() {
+                        field_errors.entry(key).or_default().extend(messages);
+                    }
+                }
+            }
+        }
+        other => collect_messages(&other, "", &mut field_errors, &mut non_field_errors),
+    }
+    ApiError::Validation {
+        field_errors,
+        non_field_errors,
+    }
+}
+
+/// Collects the messages in `value` into `messages`. Errors on nested
+/// objects, such as items of a list field, go into `fields` under dotted keys
+/// below `prefix`.
+fn collect_messages(
+    value: &serde_json::Value,
+    prefix: &str,
+    fields: &mut BTreeMap<String, Vec<String>>,
+    messages: &mut Vec<String>,
+) {
+    match value {
+        serde_json::Value::String(message) => messages.push(message.clone()),
+        serde_json::Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                if item.is_object() {
+                    collect_nested(item, &format!("{prefix}.{i}"), fields);
+                } else {
+                    collect_messages(item, prefix, fields, messages);
+                }
+            }
+        }
+        serde_json::Value::Object(_) => collect_nested(value, prefix, fields),
+        serde_json::Value::Null => {}
+        other => messages.push(other.to_string()),
+    }
+}
+
+fn collect_nested(
+    value: &serde_json::Value,
+    prefix: &str,
+    fields: &mut BTreeMap<String, Vec<String>>,
+) {
+    let serde_json::Value::Object(map) = value else {
+        return;
+    };
+    for (key, value) in map {
+        let key = format!("{prefix}.{key}");
+        let mut messages = Vec::new();
+        collect_messages(value, &key, fields, &mut messages);
+        if !messages.is_empty() {
+            fields.entry(key).or_default().extend(messages);
+        }
+    }
+}
+
+fn format_validation(
+    field_errors: &BTreeMap<String, Vec<String>>,
+    non_field_errors: &[String],
+) -> String {
+    let mut parts: Vec<String> = non_field_errors.to_vec();
+    parts.extend(
+        field_errors
+            .iter()
+            .map(|(field, messages)| format!("{field}: {}", messages.join(" "))),
+    );
+    if parts.is_empty() {
+        "invalid request".to_string()
+    } else {
+        parts.join("; ")
+    }
+}
+
 impl From<ureq::Error> for ApiError {
     fn from(err: ureq::Error) -> Self {
         match err {
-            ureq::Error::StatusCode(status) => match status {
-                401 | 403 => ApiError::Unauthorized,
-                404 => ApiError::NotFound,
-                _ => ApiError::Server {
-                    status,
-                    message: "unexpected status code".to_string(),
-                },
-            },
+            ureq::Error::StatusCode(status) => ApiError::from_status(status),
             ureq::Error::Timeout(_) => ApiError::Timeout,
             other => ApiError::Network(Box::new(other)),
         }
     }
 }
+
+#[cfg(feature = "async")]
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        if let Some(status) = err.status() {
+            ApiError::from_status(status.as_u16())
+        } else if err.is_timeout() {
+            ApiError::Timeout
+        } else {
+            ApiError::Network(Box::new(err))
+        }
+    }
+}
@@ -12,17 +12,41 @@
 //! }
 //! # Ok::<(), pngx_client::ApiError>(())
 //! ```
+//!
+//! # Features
+//!
+//! - `async`: adds `AsyncClient`, a non-blocking client built with
+//!   [`ClientBuilder::build_async`] that offers the same methods plus
+//!   `Stream`-based pagination.
 
 #![warn(missing_docs)]
 
+#[cfg(feature = "async")]
+mod async_client;
 mod client;
+mod endpoint;
 mod error;
+mod multipart;
+mod pages;
+mod retry;
+mod trace;
+mod transport;
 mod types;
+mod version;
 
 pub use jiff;
 
+#[cfg(feature = "async")]
+pub use async_client::AsyncClient;
 pub use client::{Client, ClientBuilder};
 pub use error::ApiError;
+pub use pages::{Items, Pages};
+pub use retry::RetryPolicy;
 pub use types::{
-    Correspondent, Document, DocumentType, DocumentVersion, PaginatedResponse, Tag, UiSettings,
+    BulkEdit, Correspondent, CorrespondentRequest, CustomField, CustomFieldInstance,
+    CustomFieldRequest, Document, DocumentMetadata, DocumentRequest, DocumentType,
+    DocumentTypeRequest, DocumentUpload, DocumentVersion, MatchingAlgorithm, Note, NoteUser,
+    PaginatedResponse, StoragePath, StoragePathRequest, Suggestions, Tag, TagRequest, Task,
+    TaskStatus, UiSettings,
 };
+pub use version::{MAX_API_VERSION, MIN_API_VERSION};
@@ -0,0 +1,93 @@
+//! Encoding of `multipart/form-data` bodies for document uploads.
+
+/// A `multipart/form-data` body built in memory.
+pub(crate) struct Form {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Form {
+    pub(crate) fn new() -> Self {
+        let suffix: String = std::iter::repeat_with(fastrand::alphanumeric)
+            .take(24)
+            .collect();
+        Self {
+            boundary: format!("pngx-{suffix}"),
+            body: Vec::new(),
+        }
+    }
+
+    /// Adds a text field. Repeated names send several values.
+    pub(crate) fn text(&mut self, name: &str, value: &str) {
+        self.part_header(name, None, None);
+        self.body.extend_from_slice(value.as_bytes());
+        self.body.extend_from_slice(b"\r\n");
+    }
+
+    /// Adds a file field.
+    pub(crate) fn file(&mut self, name: &str, file_name: &str, data: &[u8]) {
+        self.part_header(name, Some(file_name), Some("application/octet-stream"));
+        self.body.extend_from_slice(data);
+        self.body.extend_from_slice(b"\r\n");
+    }
+
+    fn part_header(&mut self, name: &str, file_name: Option<&str>, content_type: Option<&str>) {
+        let mut header = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            escape(name)
+        );
+        if let Some(file_name) = file_name {
+            header.push_str(&format!("; filename=\"{}\"", escape(file_name)));
+        }
+        header.push_str("\r\n");
+        if let Some(content_type) = content_type {
+            header.push_str(&format!("Content-Type: {content_type}\r\n"));
+        }
+        header.push_str("\r\n");
+        self.body.extend_from_slice(header.as_bytes());
+    }
+
+    /// The `Content-Type` header value, including the boundary.
+    pub(crate) fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Completes the body.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        self.body
+    }
+}
+
+/// Escapes a name for a quoted header parameter, replacing line breaks
+/// that would end the header.
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodes_fields_and_files() {
+        let mut form = Form::new();
+        form.text("tags", "1");
+        form.file("document", "a \"b\".pdf", b"%PDF");
+        let boundary = form.boundary.clone();
+        let body = String::from_utf8(form.finish()).unwrap_or_default();
+        assert_eq!(
+            body,
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"tags\"\r\n\r\n1\r\n\
+                 --{boundary}\r\nContent-Disposition: form-data; name=\"document\"; \
+                 filename=\"a \\\"b\\\".pdf\"\r\nContent-Type: application/octet-stream\r\n\r\n\
+                 %PDF\r\n--{boundary}--\r\n"
+            )
+        );
+    }
+}
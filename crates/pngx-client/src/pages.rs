@@ -0,0 +1,316 @@
+use std::marker::PhantomData;
+
+use url::Url;
+
+use crate::client::Client;
+use crate::error::ApiError;
+use crate::types::PaginatedResponse;
+
+/// An iterator over the pages of a list endpoint, fetched on demand.
+///
+/// Created with methods such as [`Client::document_pages`]. Each call to
+/// [`next`](Iterator::next) issues at most one request. Iteration ends after
+/// the last page or the first error, including a `next` URL whose scheme
+/// differs from the base URL ([`ApiError::SchemeMismatch`]).
+pub struct Pages<'a, T> {
+    client: &'a Client,
+    next: Option<Result<Url, ApiError>>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> Pages<'a, T> {
+    pub(crate) fn new(client: &'a Client, first: Result<Url, ApiError>) -> Self {
+        Self {
+            client,
+            next: Some(first),
+            marker: PhantomData,
+        }
+    }
+
+    /// Requests every field the server returns, including document content
+    /// and fields this crate doesn't model, instead of the trimmed field
+    /// list of endpoints such as [`Client::document_pages`].
+    #[must_use]
+    pub fn all_fields(mut self) -> Self {
+        if let Some(Ok(url)) = &mut self.next {
+            let pairs: Vec<(String, String)> = url
+                .query_pairs()
+                .filter(|(key, _)| key != "fields")
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+        self
+    }
+
+    /// Flattens the pages into an iterator over individual items.
+    #[must_use]
+    pub fn items(self) -> Items<'a, T> {
+        Items {
+            pages: self,
+            current: Vec::new().into_iter(),
+            total: None,
+        }
+    }
+}
+
+impl<T: serde::de::DeserializeOwned> Iterator for Pages<'_, T> {
+    type Item = Result<PaginatedResponse<T>, ApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let url = match self.next.take()? {
+            Ok(url) => url,
+            Err(err) => return Some(Err(err)),
+        };
+        let page: PaginatedResponse<T> = match self.client.get(&url) {
+            Ok(page) => page,
+            Err(err) => return Some(Err(err)),
+        };
+        self.next = page
+            .next
+            .as_deref()
+            .map(|next| self.client.endpoint.next_url(next));
+        Some(Ok(page))
+    }
+}
+
+/// An iterator over the items of a list endpoint, fetching pages on demand.
+///
+/// Created with [`Pages::items`].
+pub struct Items<'a, T> {
+    pages: Pages<'a, T>,
+    current: std::vec::IntoIter<T>,
+    total: Option<u64>,
+}
+
+impl<T> Items<'_, T> {
+    /// Total number of items reported by the server, known once the first
+    /// page has been fetched.
+    #[must_use]
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+}
+
+impl<T: serde::de::DeserializeOwned> Iterator for Items<'_, T> {
+    type Item = Result<T, ApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.current.next() {
+                return Some(Ok(item));
+            }
+            let page = match self.pages.next()? {
+                Ok(page) => page,
+                Err(err) => return Some(Err(err)),
+            };
+            self.total.get_or_insert(page.count);
+            self.current = page.results.into_iter();
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn tag_page(ids: &[u64], next: Option<&str>) -> serde_json::Value {
+        let results: Vec<_> = ids
+            .iter()
+            .map(|id| serde_json::json!({"id": id, "name": format!("Tag {id}"), "slug": format!("tag-{id}"), "color": null, "is_inbox_tag": false, "document_count": 0}))
+            .collect();
+        serde_json::json!({"count": 3, "next": next, "previous": null, "results": results})
+    }
+
+    async fn mount_pages(server: &MockServer, second_page: u64) {
+        let next = format!("{}/api/tags/?page=2", server.uri());
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(&[3], None)))
+            .expect(second_page)
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(&[1, 2], Some(&next))))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_pages_are_fetched_on_demand() {
+        let server = MockServer::start().await;
+        mount_pages(&server, 0).await;
+        let client =
+            Client::new(&server.uri(), "test-token").expect("client creation should succeed");
+
+        let mut pages = client.tag_pages();
+        let first = pages
+            .next()
+            .expect("should have a page")
+            .expect("request should succeed");
+        assert_eq!(first.results.len(), 2);
+        assert_eq!(first.count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_items_span_pages() {
+        let server = MockServer::start().await;
+        mount_pages(&server, 1).await;
+        let client =
+            Client::new(&server.uri(), "test-token").expect("client creation should succeed");
+
+        let mut items = client.tag_pages().items();
+        assert_eq!(items.total(), None);
+        let ids: Vec<u64> = items
+            .by_ref()
+            .map(|tag| tag.expect("request should succeed").id)
+            .collect();
+        assert_eq!(ids, [1, 2, 3]);
+        assert_eq!(items.total(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_pages_scheme_mismatch() {
+        let server = MockServer::start().await;
+        let next = server.uri().replace("http://", "https://") + "/api/tags/?page=2";
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(&[1], Some(&next))))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client =
+            Client::new(&server.uri(), "test-token").expect("client creation should succeed");
+
+        let results: Vec<_> = client.tag_pages().collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(ApiError::SchemeMismatch { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_pages_rewrite_next_urls_under_subpath() {
+        let server = MockServer::start().await;
+        // The server sees neither the proxy's scheme, host, nor path prefix.
+        let next = "https://paperless:8000/api/tags/?page=2";
+        Mock::given(method("GET"))
+            .and(path("/paperless/api/tags/"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(&[3], None)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/paperless/api/tags/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(&[1, 2], Some(next))))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = Client::builder(&format!("{}/paperless", server.uri()), "test-token")
+            .rewrite_next_urls(true)
+            .build()
+            .expect("client creation should succeed");
+
+        let ids: Vec<u64> = client
+            .tag_pages()
+            .items()
+            .map(|tag| tag.expect("request should succeed").id)
+            .collect();
+        assert_eq!(ids, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_pages_rewrite_next_urls_under_api_subpath() {
+        let server = MockServer::start().await;
+        // The prefix contains `/api/`. The server sees it on the first page
+        // and a different one on the second.
+        let prefix = "/tools/api/paperless";
+        let api_path = format!("{prefix}/api/tags/");
+        let pages = [
+            ("3", tag_page(&[3], None)),
+            (
+                "2",
+                tag_page(
+                    &[2],
+                    Some("https://paperless:8000/api/internal/api/tags/?page=3"),
+                ),
+            ),
+        ];
+        for (page, body) in pages {
+            Mock::given(method("GET"))
+                .and(path(api_path.as_str()))
+                .and(query_param("page", page))
+                .respond_with(ResponseTemplate::new(200).set_body_json(body))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        let next = format!("https://paperless:8000{prefix}/api/tags/?page=2");
+        Mock::given(method("GET"))
+            .and(path(api_path.as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tag_page(&[1], Some(&next))))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = Client::builder(&format!("{}{prefix}", server.uri()), "test-token")
+            .rewrite_next_urls(true)
+            .build()
+            .expect("client creation should succeed");
+
+        let ids: Vec<u64> = client
+            .tag_pages()
+            .items()
+            .map(|tag| tag.expect("request should succeed").id)
+            .collect();
+        assert_eq!(ids, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_pages_all_fields() {
+        let server = MockServer::start().await;
+        let next = format!("{}/api/documents/?is_in_inbox=true&page=2", server.uri());
+        Mock::given(method("GET"))
+            .and(path("/api/documents/"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "count": 2,
+                "next": null,
+                "previous": null,
+                "results": [{"id": 2, "title": "B", "tags": [], "future_field": 2}],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/documents/"))
+            .and(query_param("is_in_inbox", "true"))
+            .and(query_param_is_missing("fields"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "count": 2,
+                "next": next,
+                "previous": null,
+                "results": [{"id": 1, "title": "A", "tags": [], "future_field": 1}],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client =
+            Client::new(&server.uri(), "test-token").expect("client creation should succeed");
+
+        let documents: Vec<_> = client
+            .inbox_document_pages()
+            .all_fields()
+            .items()
+            .map(|doc| doc.expect("request should succeed"))
+            .collect();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[1].extra["future_field"], 2);
+    }
+}
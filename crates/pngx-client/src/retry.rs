@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Controls how requests are retried after transient failures.
+///
+/// Only idempotent requests (`GET`, `PUT`, `DELETE`, ...) are retried. A
+/// request is retried when the server answers `429`, `502`, `503` or `504`,
+/// or when the connection fails or times out. The delay between attempts
+/// grows exponentially from [`initial_backoff`](Self::initial_backoff) up to
+/// [`max_backoff`](Self::max_backoff), with random jitter. A `Retry-After`
+/// header takes precedence over the computed delay, capped at `max_backoff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// No retries.
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy that retries a failed request up to `max_retries`
+    /// times, for at most `max_retries + 1` attempts in total.
+    #[must_use]
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+
+    /// Sets the delay before the first retry.
+    #[must_use]
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the upper bound for the delay between retries.
+    #[must_use]
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Returns the delay before retry number `retry` (starting at 0), or
+    /// `None` if no further retry should be made.
+    pub(crate) fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if retry >= self.max_retries {
+            return None;
+        }
+        if let Some(retry_after) = retry_after {
+            return Some(retry_after.min(self.max_backoff));
+        }
+
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        // Equal jitter: wait at least half the backoff, plus a random share
+        // of the other half, so concurrent clients don't retry in lockstep.
+        let half = exponential / 2;
+        let jitter = Duration::from_millis(fastrand::u64(0..=half.as_millis() as u64));
+        Some(half + jitter)
+    }
+}
+
+/// Whether a response status indicates a transient server-side condition.
+pub(crate) fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 429 | 502 | 503 | 504)
+}
+
+/// Parses a `Retry-After` header given either in seconds or as an HTTP date.
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = jiff::fmt::rfc2822::parse(value).ok()?;
+    let wait = date.timestamp().duration_since(jiff::Timestamp::now());
+    Some(Duration::try_from(wait).unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_retries_by_default() {
+        assert_eq!(RetryPolicy::default().delay(0, None), None);
+    }
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::new(10)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(400));
+
+        let first = policy.delay(0, None).expect("should retry");
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+
+        let later = policy.delay(8, None).expect("should retry");
+        assert!(later >= Duration::from_millis(200) && later <= Duration::from_millis(400));
+
+        assert_eq!(policy.delay(10, None), None);
+    }
+
+    #[test]
+    fn test_retry_after_takes_precedence() {
+        let policy = RetryPolicy::new(3).max_backoff(Duration::from_secs(10));
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(7))),
+            Some(Duration::from_secs(7))
+        );
+    }
+
+    #[test]
+    fn test_long_retry_after_is_capped() {
+        let policy = RetryPolicy::new(2).max_backoff(Duration::from_secs(10));
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(45))),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(500))),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(policy.delay(2, Some(Duration::from_secs(45))), None);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("90"), Some(Duration::from_secs(90)));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
@@ -0,0 +1,137 @@
+//! Request tracing shared by the blocking and async clients.
+//!
+//! Each request is an `http_request` span on the `pngx_client::http` target,
+//! with events for every attempt's response. Request headers are logged at
+//! trace level with credentials redacted. Response bodies, truncated, go to
+//! the separate `pngx_client::http::body` target so they can be enabled on
+//! their own.
+
+use std::fmt;
+use std::time::Duration;
+
+use ureq::http::{HeaderMap, HeaderName, HeaderValue};
+use url::Url;
+
+/// Target of request spans and events.
+pub(crate) const TARGET: &str = "pngx_client::http";
+
+/// Target of response body events.
+pub(crate) const BODY_TARGET: &str = "pngx_client::http::body";
+
+/// Longest prefix of a response body that is logged.
+const BODY_LIMIT: usize = 2048;
+
+/// Headers whose values are never logged.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+pub(crate) fn request_span(method: &str, url: &Url) -> tracing::Span {
+    tracing::debug_span!(target: TARGET, "http_request", method, url = %url)
+}
+
+pub(crate) fn request_headers(headers: &HeaderMap) {
+    tracing::trace!(target: TARGET, headers = %Redacted(headers), "sending request");
+}
+
+pub(crate) fn response(status: u16, elapsed: Duration, headers: &HeaderMap) {
+    let content_length = headers
+        .get("Content-Length")
+        .and_then(|value| value.to_str().ok());
+    tracing::debug!(
+        target: TARGET,
+        status,
+        elapsed_ms = elapsed.as_millis(),
+        content_length,
+        "received response"
+    );
+    tracing::trace!(target: TARGET, headers = %Redacted(headers), "response headers");
+}
+
+/// Logs the size of a response body read in full and, on the body target, a
+/// truncated copy. Bodies of the token endpoint carry the token and are
+/// never logged.
+pub(crate) fn body(url: &Url, body: &[u8]) {
+    tracing::debug!(target: TARGET, %url, bytes = body.len(), "read response body");
+    if url.path().ends_with("/api/token/") {
+        tracing::trace!(target: BODY_TARGET, %url, body = "[REDACTED]");
+    } else {
+        tracing::trace!(target: BODY_TARGET, %url, body = %Preview(body));
+    }
+}
+
+/// Logs the size of a response body streamed to a writer.
+pub(crate) fn streamed(url: &Url, bytes: u64) {
+    tracing::debug!(target: TARGET, %url, bytes, "streamed response body");
+}
+
+fn is_secret(name: &HeaderName, value: &HeaderValue) -> bool {
+    value.is_sensitive() || SECRET_HEADERS.contains(&name.as_str())
+}
+
+/// Displays headers as `name: value` pairs with credentials redacted.
+struct Redacted<'a>(&'a HeaderMap);
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            if is_secret(name, value) {
+                write!(f, "{name}: [REDACTED]")?;
+            } else {
+                write!(f, "{name}: {}", String::from_utf8_lossy(value.as_bytes()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Displays a body as text, cut off after [`BODY_LIMIT`] bytes.
+struct Preview<'a>(&'a [u8]);
+
+impl fmt::Display for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown = &self.0[..self.0.len().min(BODY_LIMIT)];
+        f.write_str(&String::from_utf8_lossy(shown))?;
+        if self.0.len() > shown.len() {
+            write!(f, "... ({} more bytes)", self.0.len() - shown.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", HeaderValue::from_static("Token secret"));
+        headers.insert("Accept", HeaderValue::from_static("application/json"));
+        let mut proxy_token = HeaderValue::from_static("hunter2");
+        proxy_token.set_sensitive(true);
+        headers.insert("X-Proxy-Token", proxy_token);
+
+        let shown = Redacted(&headers).to_string();
+        assert!(shown.contains("authorization: [REDACTED]"));
+        assert!(shown.contains("x-proxy-token: [REDACTED]"));
+        assert!(shown.contains("accept: application/json"));
+        assert!(!shown.contains("secret"));
+        assert!(!shown.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_truncates_body() {
+        let body = "x".repeat(BODY_LIMIT + 10);
+        let shown = Preview(body.as_bytes()).to_string();
+        assert!(shown.ends_with("... (10 more bytes)"));
+        assert_eq!(Preview(b"{}").to_string(), "{}");
+    }
+}
@@ -0,0 +1,349 @@
+//! TLS, proxy, and static header options shared by the blocking and async
+//! clients.
+
+use ureq::http::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::error::ApiError;
+
+/// Connection options collected by [`ClientBuilder`](crate::ClientBuilder).
+#[derive(Default)]
+pub(crate) struct Transport {
+    /// PEM-encoded CA certificates trusted in addition to the bundled roots.
+    pub(crate) root_certs: Vec<Vec<u8>>,
+    /// PEM-encoded client certificate chain and private key.
+    pub(crate) client_cert: Option<(Vec<u8>, Vec<u8>)>,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) proxy: Option<String>,
+}
+
+impl Transport {
+    /// Validates the static headers.
+    pub(crate) fn header_map(&self) -> Result<HeaderMap, ApiError> {
+        let mut map = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .map_err(|_| ApiError::InvalidConfig(format!("invalid header name: {name}")))?;
+            let mut value = HeaderValue::try_from(value.as_str())
+                .map_err(|_| ApiError::InvalidConfig(format!("invalid value for header {name}")))?;
+            // Static headers often carry proxy credentials.
+            value.set_sensitive(true);
+            map.append(name, value);
+        }
+        Ok(map)
+    }
+
+    /// TLS settings for the blocking client. Custom CA certificates are
+    /// trusted alongside the Mozilla roots that ureq uses by default.
+    pub(crate) fn tls_config(&self) -> Result<ureq::tls::TlsConfig, ApiError> {
+        use ureq::tls::{Certificate, ClientCert, RootCerts, TlsConfig};
+
+        let mut builder = TlsConfig::builder();
+        if !self.root_certs.is_empty() {
+            let mut roots: Vec<Certificate<'static>> = webpki_root_certs::TLS_SERVER_ROOT_CERTS
+                .iter()
+                .map(|cert| Certificate::from_der(cert.as_ref()))
+                .collect();
+            for pem in &self.root_certs {
+                let (certs, _) = parse_pem(pem, "CA certificate")?;
+                if certs.is_empty() {
+                    return Err(ApiError::InvalidConfig(
+                        "no certificate found in CA certificate".to_string(),
+                    ));
+                }
+                roots.extend(certs);
+            }
+            builder = builder.root_certs(RootCerts::new_with_certs(&roots));
+        }
+        if let Some((cert, key)) = &self.client_cert {
+            let (chain, _) = parse_pem(cert, "client certificate")?;
+            let (_, key) = parse_pem(key, "client key")?;
+            let (false, Some(key)) = (chain.is_empty(), key) else {
+                return Err(ApiError::InvalidConfig(
+                    "client certificate and key must both be PEM-encoded".to_string(),
+                ));
+            };
+            builder = builder.client_cert(Some(ClientCert::new_with_certs(&chain, key)));
+        }
+        Ok(builder.build())
+    }
+
+    /// The explicit proxy for the blocking client. Without one, ureq reads
+    /// the usual `HTTPS_PROXY`, `HTTP_PROXY`, and `ALL_PROXY` variables.
+    pub(crate) fn ureq_proxy(&self) -> Result<Option<ureq::Proxy>, ApiError> {
+        self.proxy
+            .as_deref()
+            .map(|proxy| {
+                ureq::Proxy::new(proxy)
+                    .map_err(|err| ApiError::InvalidConfig(format!("invalid proxy {proxy}: {err}")))
+            })
+            .transpose()
+    }
+
+    /// Applies the TLS and proxy settings to the async client.
+    #[cfg(feature = "async")]
+    pub(crate) fn apply_reqwest(
+        &self,
+        mut http: reqwest::ClientBuilder,
+    ) -> Result<reqwest::ClientBuilder, ApiError> {
+        for pem in &self.root_certs {
+            let certs = reqwest::Certificate::from_pem_bundle(pem)
+                .map_err(|err| ApiError::InvalidConfig(format!("invalid CA certificate: {err}")))?;
+            if certs.is_empty() {
+                return Err(ApiError::InvalidConfig(
+                    "no certificate found in CA certificate".to_string(),
+                ));
+            }
+            for cert in certs {
+                http = http.add_root_certificate(cert);
+            }
+        }
+        if let Some((cert, key)) = &self.client_cert {
+            let mut pem = cert.clone();
+            pem.push(b'\n');
+            pem.extend_from_slice(key);
+            let identity = reqwest::Identity::from_pem(&pem).map_err(|err| {
+                ApiError::InvalidConfig(format!("invalid client certificate: {err}"))
+            })?;
+            http = http.identity(identity);
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy.as_str())
+                .map_err(|err| ApiError::InvalidConfig(format!("invalid proxy {proxy}: {err}")))?;
+            http = http.proxy(proxy);
+        }
+        Ok(http.default_headers(self.header_map()?))
+    }
+}
+
+/// Splits a PEM file into its certificates and first private key.
+fn parse_pem(
+    pem: &[u8],
+    what: &str,
+) -> Result<
+    (
+        Vec<ureq::tls::Certificate<'static>>,
+        Option<ureq::tls::PrivateKey<'static>>,
+    ),
+    ApiError,
+> {
+    use ureq::tls::PemItem;
+
+    let mut certs = Vec::new();
+    let mut key = None;
+    for item in ureq::tls::parse_pem(pem) {
+        match item.map_err(|err| ApiError::InvalidConfig(format!("invalid {what}: {err}")))? {
+            PemItem::Certificate(cert) => certs.push(cert),
+            PemItem::PrivateKey(k) if key.is_none() => key = Some(k),
+            _ => {}
+        }
+    }
+    Ok((certs, key))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rustls::pki_types::PrivateKeyDer;
+
+    use super::*;
+    use crate::Client;
+
+    /// A certificate and its PEM-encoded key.
+    struct Issued {
+        cert: rcgen::Certificate,
+        key: KeyPair,
+    }
+
+    impl Issued {
+        fn key_pem(&self) -> Vec<u8> {
+            self.key.serialize_pem().into_bytes()
+        }
+
+        fn cert_pem(&self) -> Vec<u8> {
+            self.cert.pem().into_bytes()
+        }
+    }
+
+    /// A self-signed CA with a server certificate for `localhost` and a
+    /// client certificate.
+    struct Pki {
+        ca: Issued,
+        server: Issued,
+        client: Issued,
+    }
+
+    impl Pki {
+        fn new() -> Self {
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let key = KeyPair::generate().unwrap();
+            let ca = Issued {
+                cert: params.self_signed(&key).unwrap(),
+                key,
+            };
+            let issue = |name: &str| {
+                let key = KeyPair::generate().unwrap();
+                let cert = CertificateParams::new(vec![name.to_string()])
+                    .unwrap()
+                    .signed_by(&key, &ca.cert, &ca.key)
+                    .unwrap();
+                Issued { cert, key }
+            };
+            let server = issue("localhost");
+            let client = issue("client");
+            Self { ca, server, client }
+        }
+
+        /// Serves the UI settings over HTTPS on a local port, requiring a
+        /// client certificate signed by the CA if `mutual` is set. Returns
+        /// the base URL.
+        fn serve(&self, mutual: bool) -> String {
+            let provider = Arc::new(rustls::crypto::ring::default_provider());
+            let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+                .with_safe_default_protocol_versions()
+                .unwrap();
+            let builder = if mutual {
+                let mut roots = rustls::RootCertStore::empty();
+                roots.add(self.ca.cert.der().clone()).unwrap();
+                let verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(
+                    roots.into(),
+                    provider,
+                )
+                .build()
+                .unwrap();
+                builder.with_client_cert_verifier(verifier)
+            } else {
+                builder.with_no_client_auth()
+            };
+            let config = Arc::new(
+                builder
+                    .with_single_cert(
+                        vec![self.server.cert.der().clone()],
+                        PrivateKeyDer::Pkcs8(self.server.key.serialize_der().into()),
+                    )
+                    .unwrap(),
+            );
+
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let conn = rustls::ServerConnection::new(config.clone()).unwrap();
+                    let mut tls = rustls::StreamOwned::new(conn, stream.unwrap());
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    // Failed handshakes end up here as read errors.
+                    while !request.ends_with(b"\r\n\r\n") {
+                        match tls.read(&mut buf) {
+                            Ok(0) | Err(_) => break,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    if !request.ends_with(b"\r\n\r\n") {
+                        continue;
+                    }
+                    let body = r#"{"user":{"id":1,"username":"admin"},"settings":{"version":"2.14.7"},"permissions":[]}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let _ = tls.write_all(response.as_bytes());
+                    tls.conn.send_close_notify();
+                    let _ = tls.flush();
+                }
+            });
+            format!("https://localhost:{port}")
+        }
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let transport = Transport {
+            headers: vec![("Bad Header".to_string(), "x".to_string())],
+            ..Transport::default()
+        };
+        assert!(matches!(
+            transport.header_map(),
+            Err(ApiError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_ca_certificate_without_certificate() {
+        let transport = Transport {
+            root_certs: vec![b"not a certificate".to_vec()],
+            ..Transport::default()
+        };
+        assert!(matches!(
+            transport.tls_config(),
+            Err(ApiError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_custom_ca_certificate() {
+        let pki = Pki::new();
+        let url = pki.serve(false);
+
+        let client = Client::builder(&url, "test-token").build().unwrap();
+        let err = client
+            .ui_settings()
+            .expect_err("server should be untrusted");
+        assert!(matches!(err, ApiError::Network(_)), "{err:?}");
+
+        let client = Client::builder(&url, "test-token")
+            .add_root_certificate(pki.ca.cert_pem())
+            .build()
+            .unwrap();
+        let settings = client.ui_settings().expect("CA should be trusted");
+        assert_eq!(settings.settings.version, "2.14.7");
+    }
+
+    #[test]
+    fn test_client_certificate() {
+        let pki = Pki::new();
+        let url = pki.serve(true);
+
+        let client = Client::builder(&url, "test-token")
+            .add_root_certificate(pki.ca.cert_pem())
+            .build()
+            .unwrap();
+        let err = client
+            .ui_settings()
+            .expect_err("server should require a client certificate");
+        assert!(matches!(err, ApiError::Network(_)), "{err:?}");
+
+        let client = Client::builder(&url, "test-token")
+            .add_root_certificate(pki.ca.cert_pem())
+            .client_certificate(pki.client.cert_pem(), pki.client.key_pem())
+            .build()
+            .unwrap();
+        let settings = client
+            .ui_settings()
+            .expect("client certificate should be accepted");
+        assert_eq!(settings.settings.version, "2.14.7");
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_client_certificate() {
+        let pki = Pki::new();
+        let url = pki.serve(true);
+
+        let client = Client::builder(&url, "test-token")
+            .add_root_certificate(pki.ca.cert_pem())
+            .client_certificate(pki.client.cert_pem(), pki.client.key_pem())
+            .build_async()
+            .unwrap();
+        let settings = client
+            .ui_settings()
+            .await
+            .expect("client certificate should be accepted");
+        assert_eq!(settings.settings.version, "2.14.7");
+    }
+}
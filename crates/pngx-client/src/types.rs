@@ -1,5 +1,9 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
+use crate::multipart::Form;
+
 /// Selects which version of a document to download.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DocumentVersion {
@@ -86,7 +90,9 @@ pub struct Document {
     pub document_type: Option<u64>,
     /// IDs of assigned tags.
     pub tags: Vec<u64>,
-    /// Date the document was created.
+    /// Date the document was created. API versions before 9 return a
+    /// datetime, of which only the date is kept.
+    #[serde(default, deserialize_with = "deserialize_created")]
     pub created: Option<jiff::civil::Date>,
     /// Timestamp when the document was added to Paperless-ngx.
     pub added: Option<jiff::Timestamp>,
@@ -94,6 +100,177 @@ pub struct Document {
     pub archive_serial_number: Option<u64>,
     /// Original file name at time of upload.
     pub original_file_name: Option<String>,
+    /// Timestamp of the last change.
+    #[serde(default)]
+    pub modified: Option<jiff::Timestamp>,
+    /// ID of the owning user.
+    #[serde(default)]
+    pub owner: Option<u64>,
+    /// Notes attached to the document.
+    #[serde(default)]
+    pub notes: Vec<Note>,
+    /// ID of the assigned storage path.
+    #[serde(default)]
+    pub storage_path: Option<u64>,
+    /// Values of the custom fields set on the document.
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldInstance>,
+    /// Number of pages, if known.
+    #[serde(default)]
+    pub page_count: Option<u64>,
+    /// MIME type of the original file.
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// File name of the archived version, if there is one.
+    #[serde(default)]
+    pub archived_file_name: Option<String>,
+    /// Whether the requesting user shares the document with others.
+    #[serde(default)]
+    pub is_shared_by_requester: Option<bool>,
+    /// Whether the requesting user may change the document.
+    #[serde(default)]
+    pub user_can_change: Option<bool>,
+    /// Fields not modeled above, kept so they survive a round-trip.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A note attached to a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Note {
+    /// Unique identifier.
+    pub id: u64,
+    /// Note text.
+    pub note: String,
+    /// Timestamp when the note was written.
+    #[serde(default)]
+    pub created: Option<jiff::Timestamp>,
+    /// The user who wrote the note.
+    #[serde(default)]
+    pub user: Option<NoteUser>,
+}
+
+/// Author of a [`Note`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct NoteUser {
+    /// User ID.
+    pub id: u64,
+    /// The username.
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+/// The value of a custom field on a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CustomFieldInstance {
+    /// ID of the custom field.
+    pub field: u64,
+    /// The value, whose type depends on the field's data type.
+    #[serde(default)]
+    pub value: serde_json::Value,
+}
+
+impl CustomFieldInstance {
+    /// Creates a value for the custom field with ID `field`.
+    #[must_use]
+    pub fn new(field: u64, value: serde_json::Value) -> Self {
+        Self { field, value }
+    }
+}
+
+/// Deserializes a creation date given either as a date (API version 9) or
+/// as an RFC 3339 datetime (older versions). The date is taken as written,
+/// in the offset the server reported.
+fn deserialize_created<'de, D>(deserializer: D) -> Result<Option<jiff::civil::Date>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let Some(value) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let date = match value.split_once(['T', ' ']) {
+        Some((date, _)) => date,
+        None => &value,
+    };
+    date.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Algorithm Paperless-ngx uses to automatically assign an object to new
+/// documents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum MatchingAlgorithm {
+    /// Never match automatically.
+    None,
+    /// Match if any of the words in `match` occur.
+    Any,
+    /// Match if all of the words in `match` occur.
+    All,
+    /// Match if `match` occurs as an exact string.
+    Literal,
+    /// Match if `match` is a regular expression that matches.
+    Regex,
+    /// Match if `match` fuzzy-matches the content.
+    Fuzzy,
+    /// Let the server learn the assignment from existing documents.
+    #[default]
+    Auto,
+}
+
+impl MatchingAlgorithm {
+    /// Returns the lowercase name of the algorithm.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Any => "any",
+            Self::All => "all",
+            Self::Literal => "literal",
+            Self::Regex => "regex",
+            Self::Fuzzy => "fuzzy",
+            Self::Auto => "auto",
+        }
+    }
+}
+
+impl fmt::Display for MatchingAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<MatchingAlgorithm> for u8 {
+    fn from(algorithm: MatchingAlgorithm) -> Self {
+        match algorithm {
+            MatchingAlgorithm::None => 0,
+            MatchingAlgorithm::Any => 1,
+            MatchingAlgorithm::All => 2,
+            MatchingAlgorithm::Literal => 3,
+            MatchingAlgorithm::Regex => 4,
+            MatchingAlgorithm::Fuzzy => 5,
+            MatchingAlgorithm::Auto => 6,
+        }
+    }
+}
+
+impl TryFrom<u8> for MatchingAlgorithm {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Any),
+            2 => Ok(Self::All),
+            3 => Ok(Self::Literal),
+            4 => Ok(Self::Regex),
+            5 => Ok(Self::Fuzzy),
+            6 => Ok(Self::Auto),
+            other => Err(format!("unknown matching algorithm: {other}")),
+        }
+    }
 }
 
 /// A tag used to categorize documents.
@@ -108,12 +285,57 @@ pub struct Tag {
     pub slug: String,
     /// Hex color code (e.g. `#ff0000`).
     pub color: Option<String>,
+    /// Hex color code for text on top of `color`, computed by the server.
+    #[serde(default)]
+    pub text_color: Option<String>,
     /// Whether this tag marks documents as inbox items.
     pub is_inbox_tag: Option<bool>,
+    /// Pattern used by the matching algorithm.
+    #[serde(default)]
+    pub r#match: Option<String>,
+    /// Algorithm used to automatically assign this tag.
+    #[serde(default)]
+    pub matching_algorithm: Option<MatchingAlgorithm>,
+    /// Whether matching ignores case.
+    #[serde(default)]
+    pub is_insensitive: Option<bool>,
+    /// ID of the parent tag (Paperless-ngx 2.x tag hierarchy).
+    #[serde(default)]
+    pub parent: Option<u64>,
     /// Number of documents with this tag.
     pub document_count: Option<u64>,
 }
 
+/// Fields for creating or updating a [`Tag`].
+///
+/// Unset fields are omitted from the request body, so the same type serves
+/// for creating a tag (where `name` is required by the server) and for
+/// partially updating one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TagRequest {
+    /// Display name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Hex color code (e.g. `#ff0000`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Whether this tag marks documents as inbox items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_inbox_tag: Option<bool>,
+    /// Pattern used by the matching algorithm.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#match: Option<String>,
+    /// Algorithm used to automatically assign this tag.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matching_algorithm: Option<MatchingAlgorithm>,
+    /// Whether matching ignores case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_insensitive: Option<bool>,
+    /// ID of the parent tag. `Some(None)` removes the tag from its parent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Option<u64>>,
+}
+
 /// A correspondent (sender/recipient) associated with documents.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -124,10 +346,38 @@ pub struct Correspondent {
     pub name: String,
     /// URL-safe slug.
     pub slug: String,
+    /// Pattern used by the matching algorithm.
+    #[serde(default)]
+    pub r#match: Option<String>,
+    /// Algorithm used to automatically assign this correspondent.
+    #[serde(default)]
+    pub matching_algorithm: Option<MatchingAlgorithm>,
+    /// Whether matching ignores case.
+    #[serde(default)]
+    pub is_insensitive: Option<bool>,
     /// Number of documents from this correspondent.
     pub document_count: Option<u64>,
 }
 
+/// Fields for creating or updating a [`Correspondent`].
+///
+/// Unset fields are omitted from the request body.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CorrespondentRequest {
+    /// Display name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Pattern used by the matching algorithm.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#match: Option<String>,
+    /// Algorithm used to automatically assign this correspondent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matching_algorithm: Option<MatchingAlgorithm>,
+    /// Whether matching ignores case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_insensitive: Option<bool>,
+}
+
 /// A document type used to classify documents.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -138,6 +388,376 @@ pub struct DocumentType {
     pub name: String,
     /// URL-safe slug.
     pub slug: String,
+    /// Pattern used by the matching algorithm.
+    #[serde(default)]
+    pub r#match: Option<String>,
+    /// Algorithm used to automatically assign this document type.
+    #[serde(default)]
+    pub matching_algorithm: Option<MatchingAlgorithm>,
+    /// Whether matching ignores case.
+    #[serde(default)]
+    pub is_insensitive: Option<bool>,
     /// Number of documents with this type.
     pub document_count: Option<u64>,
 }
+
+/// Fields for creating or updating a [`DocumentType`].
+///
+/// Unset fields are omitted from the request body.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DocumentTypeRequest {
+    /// Display name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Pattern used by the matching algorithm.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#match: Option<String>,
+    /// Algorithm used to automatically assign this document type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matching_algorithm: Option<MatchingAlgorithm>,
+    /// Whether matching ignores case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_insensitive: Option<bool>,
+}
+
+/// A storage path that determines where Paperless-ngx files documents.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct StoragePath {
+    /// Unique identifier.
+    pub id: u64,
+    /// Display name.
+    pub name: String,
+    /// URL-safe slug.
+    pub slug: String,
+    /// File name template relative to the media directory.
+    pub path: String,
+    /// Pattern used by the matching algorithm.
+    #[serde(default)]
+    pub r#match: Option<String>,
+    /// Algorithm used to automatically assign this storage path.
+    #[serde(default)]
+    pub matching_algorithm: Option<MatchingAlgorithm>,
+    /// Whether matching ignores case.
+    #[serde(default)]
+    pub is_insensitive: Option<bool>,
+    /// Number of documents stored under this path.
+    pub document_count: Option<u64>,
+}
+
+/// A custom field that documents can carry values for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CustomField {
+    /// Unique identifier.
+    pub id: u64,
+    /// Display name.
+    pub name: String,
+    /// Type of the values, e.g. `string`, `date`, or `monetary`.
+    pub data_type: String,
+    /// Type-specific settings, such as the options of a `select` field.
+    #[serde(default)]
+    pub extra_data: serde_json::Value,
+    /// Number of documents with a value for this field.
+    #[serde(default)]
+    pub document_count: Option<u64>,
+}
+
+/// File metadata of a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DocumentMetadata {
+    /// MD5 checksum of the original file.
+    pub original_checksum: String,
+    /// Size of the original file in bytes.
+    pub original_size: u64,
+    /// MIME type of the original file.
+    pub original_mime_type: String,
+    /// Path of the original file relative to the media directory.
+    pub media_filename: String,
+    /// Whether the document has an archived version.
+    pub has_archive_version: bool,
+    /// MD5 checksum of the archived file.
+    #[serde(default)]
+    pub archive_checksum: Option<String>,
+    /// Size of the archived file in bytes.
+    #[serde(default)]
+    pub archive_size: Option<u64>,
+    /// Path of the archived file relative to the media directory.
+    #[serde(default)]
+    pub archive_media_filename: Option<String>,
+    /// Detected language of the content.
+    #[serde(default)]
+    pub lang: Option<String>,
+}
+
+/// Metadata the server suggests for a document, from its matching rules
+/// and its classifier.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Suggestions {
+    /// IDs of suggested correspondents.
+    #[serde(default)]
+    pub correspondents: Vec<u64>,
+    /// IDs of suggested tags.
+    #[serde(default)]
+    pub tags: Vec<u64>,
+    /// IDs of suggested document types.
+    #[serde(default)]
+    pub document_types: Vec<u64>,
+    /// IDs of suggested storage paths.
+    #[serde(default)]
+    pub storage_paths: Vec<u64>,
+    /// Dates found in the content.
+    #[serde(default)]
+    pub dates: Vec<jiff::civil::Date>,
+}
+
+/// Fields for creating a [`StoragePath`].
+///
+/// Unset fields are omitted from the request body.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StoragePathRequest {
+    /// Display name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// File name template relative to the media directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Pattern used by the matching algorithm.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#match: Option<String>,
+    /// Algorithm used to automatically assign this storage path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matching_algorithm: Option<MatchingAlgorithm>,
+    /// Whether matching ignores case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_insensitive: Option<bool>,
+}
+
+/// Fields for creating a [`CustomField`].
+///
+/// Unset fields are omitted from the request body.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CustomFieldRequest {
+    /// Display name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Type of the values, e.g. `string`, `date`, or `monetary`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+    /// Type-specific settings, such as the options of a `select` field.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_data: Option<serde_json::Value>,
+}
+
+/// Fields for updating a [`Document`].
+///
+/// Unset fields are omitted from the request body. For the references,
+/// `Some(None)` clears the value.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DocumentRequest {
+    /// Document title.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// ID of the correspondent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correspondent: Option<Option<u64>>,
+    /// ID of the document type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_type: Option<Option<u64>>,
+    /// ID of the storage path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_path: Option<Option<u64>>,
+    /// IDs of the tags, replacing the current ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<u64>>,
+    /// Date the document was created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<jiff::civil::Date>,
+    /// Archive serial number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_serial_number: Option<Option<u64>>,
+    /// Custom field values, replacing the current ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_fields: Option<Vec<CustomFieldInstance>>,
+}
+
+/// Metadata to set on a document uploaded with
+/// [`Client::upload_document`](crate::Client::upload_document).
+///
+/// Unset fields are left to the server, which may assign them with its
+/// matching rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentUpload {
+    /// Document title. Defaults to the file name.
+    pub title: Option<String>,
+    /// Date the document was created.
+    pub created: Option<jiff::civil::Date>,
+    /// ID of the correspondent.
+    pub correspondent: Option<u64>,
+    /// ID of the document type.
+    pub document_type: Option<u64>,
+    /// ID of the storage path.
+    pub storage_path: Option<u64>,
+    /// IDs of the tags.
+    pub tags: Vec<u64>,
+    /// Archive serial number.
+    pub archive_serial_number: Option<u64>,
+    /// IDs of custom fields to add without a value.
+    pub custom_fields: Vec<u64>,
+}
+
+impl DocumentUpload {
+    /// Encodes the file and metadata as the form `post_document` expects.
+    pub(crate) fn form(&self, file_name: &str, data: &[u8]) -> Form {
+        let mut form = Form::new();
+        form.file("document", file_name, data);
+        if let Some(title) = &self.title {
+            form.text("title", title);
+        }
+        if let Some(created) = self.created {
+            form.text("created", &created.to_string());
+        }
+        if let Some(id) = self.correspondent {
+            form.text("correspondent", &id.to_string());
+        }
+        if let Some(id) = self.document_type {
+            form.text("document_type", &id.to_string());
+        }
+        if let Some(id) = self.storage_path {
+            form.text("storage_path", &id.to_string());
+        }
+        for id in &self.tags {
+            form.text("tags", &id.to_string());
+        }
+        if let Some(asn) = self.archive_serial_number {
+            form.text("archive_serial_number", &asn.to_string());
+        }
+        for id in &self.custom_fields {
+            form.text("custom_fields", &id.to_string());
+        }
+        form
+    }
+}
+
+/// State of a background [`Task`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TaskStatus {
+    /// Waiting to be picked up by a worker.
+    Pending,
+    /// Being processed.
+    Started,
+    /// Finished successfully.
+    Success,
+    /// Failed; the task's `result` holds the error.
+    Failure,
+    /// Failed and scheduled to run again.
+    Retry,
+    /// Cancelled.
+    Revoked,
+    /// A state this library doesn't know.
+    #[serde(other)]
+    Unknown,
+}
+
+impl TaskStatus {
+    /// Whether the task has finished, successfully or not.
+    #[must_use]
+    pub fn is_done(self) -> bool {
+        matches!(self, Self::Success | Self::Failure | Self::Revoked)
+    }
+}
+
+/// A background task, such as consuming an uploaded document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Task {
+    /// Unique identifier.
+    pub id: u64,
+    /// The task UUID returned when the task was queued.
+    pub task_id: String,
+    /// Name of the file the task processes.
+    #[serde(default)]
+    pub task_file_name: Option<String>,
+    /// Current state.
+    pub status: TaskStatus,
+    /// Result message, or the error of a failed task.
+    #[serde(default)]
+    pub result: Option<String>,
+    /// ID of the document the task created.
+    #[serde(default, deserialize_with = "deserialize_lenient_id")]
+    pub related_document: Option<u64>,
+    /// Timestamp when the task was queued.
+    #[serde(default)]
+    pub date_created: Option<jiff::Timestamp>,
+    /// Timestamp when the task finished.
+    #[serde(default)]
+    pub date_done: Option<jiff::Timestamp>,
+}
+
+/// Deserializes an ID sent as a number or, by some server versions, as a
+/// string.
+fn deserialize_lenient_id<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(u64),
+        Text(String),
+    }
+
+    match Option::<Id>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Id::Number(id)) => Ok(Some(id)),
+        Some(Id::Text(id)) => id.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// An operation applied to many documents at once via
+/// [`Client::bulk_edit`](crate::Client::bulk_edit).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BulkEdit {
+    /// Set (or with `None`, clear) the correspondent.
+    SetCorrespondent(Option<u64>),
+    /// Set (or with `None`, clear) the document type.
+    SetDocumentType(Option<u64>),
+    /// Add a tag.
+    AddTag(u64),
+    /// Remove a tag.
+    RemoveTag(u64),
+    /// Add and remove several tags in one operation.
+    ModifyTags {
+        /// Tag IDs to add.
+        add: Vec<u64>,
+        /// Tag IDs to remove.
+        remove: Vec<u64>,
+    },
+}
+
+impl BulkEdit {
+    /// Returns the `method` name and `parameters` object expected by the
+    /// `bulk_edit` endpoint.
+    pub(crate) fn method_and_parameters(&self) -> (&'static str, serde_json::Value) {
+        match self {
+            Self::SetCorrespondent(id) => (
+                "set_correspondent",
+                serde_json::json!({ "correspondent": id }),
+            ),
+            Self::SetDocumentType(id) => (
+                "set_document_type",
+                serde_json::json!({ "document_type": id }),
+            ),
+            Self::AddTag(id) => ("add_tag", serde_json::json!({ "tag": id })),
+            Self::RemoveTag(id) => ("remove_tag", serde_json::json!({ "tag": id })),
+            Self::ModifyTags { add, remove } => (
+                "modify_tags",
+                serde_json::json!({ "add_tags": add, "remove_tags": remove }),
+            ),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! REST API version negotiation.
+//!
+//! Paperless-ngx selects the API version from the `Accept` header and reports
+//! the highest version it supports in the `X-Api-Version` response header,
+//! along with its own release in `X-Version`. Both headers are only sent on
+//! authenticated responses.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::error::ApiError;
+
+/// Oldest API version this client supports. Version 1 reports tag colors as
+/// palette indices instead of hex codes.
+pub const MIN_API_VERSION: u32 = 2;
+
+/// Newest API version this client supports, and the one it requests first.
+pub const MAX_API_VERSION: u32 = 9;
+
+/// The API version in use and the versions reported by the server, shared by
+/// all requests of a client.
+#[derive(Debug)]
+pub(crate) struct ApiVersions {
+    pinned: bool,
+    current: AtomicU32,
+    /// Highest version supported by the server, or 0 if not yet known.
+    server_api: AtomicU32,
+    server: Mutex<Option<String>>,
+}
+
+impl ApiVersions {
+    /// Starts at [`MAX_API_VERSION`] and lowers the version to what the
+    /// server supports.
+    pub(crate) fn negotiate() -> Self {
+        Self::new(MAX_API_VERSION, false)
+    }
+
+    /// Always requests `version`.
+    pub(crate) fn pinned(version: u32) -> Result<Self, ApiError> {
+        if !(MIN_API_VERSION..=MAX_API_VERSION).contains(&version) {
+            return Err(ApiError::UnsupportedApiVersion { version });
+        }
+        Ok(Self::new(version, true))
+    }
+
+    fn new(version: u32, pinned: bool) -> Self {
+        Self {
+            pinned,
+            current: AtomicU32::new(version),
+            server_api: AtomicU32::new(0),
+            server: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn current(&self) -> u32 {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn server_api(&self) -> Option<u32> {
+        Some(self.server_api.load(Ordering::Relaxed)).filter(|&v| v != 0)
+    }
+
+    pub(crate) fn server(&self) -> Option<String> {
+        self.server.lock().ok().and_then(|server| server.clone())
+    }
+
+    /// Records the `X-Api-Version` and `X-Version` headers of a response.
+    /// Unless pinned, a lower supported server version becomes the version
+    /// for subsequent requests.
+    pub(crate) fn observe(&self, api_version: Option<&str>, server_version: Option<&str>) {
+        if let Some(version) = api_version.and_then(|v| v.trim().parse::<u32>().ok()) {
+            self.server_api.store(version, Ordering::Relaxed);
+            if !self.pinned && version >= MIN_API_VERSION {
+                self.current.fetch_min(version, Ordering::Relaxed);
+            }
+        }
+        if let Some(server_version) = server_version
+            && let Ok(mut server) = self.server.lock()
+        {
+            *server = Some(server_version.to_string());
+        }
+    }
+
+    /// Whether the server's supported version should be probed after it
+    /// rejected a request's version with 406 Not Acceptable.
+    pub(crate) fn needs_probe(&self) -> bool {
+        !self.pinned && self.server_api().is_none()
+    }
+
+    /// Decides whether a request rejected with 406 Not Acceptable should be
+    /// resent, because a lower version the server supports is now known.
+    pub(crate) fn resend_after_not_acceptable(&self, sent: u32) -> Result<bool, ApiError> {
+        if self.pinned {
+            return Ok(false);
+        }
+        match self.server_api() {
+            Some(version) if version < MIN_API_VERSION => {
+                Err(ApiError::UnsupportedApiVersion { version })
+            }
+            Some(_) => Ok(self.current() < sent),
+            None => Ok(false),
+        }
+    }
+}
+
+/// The `Accept` header requesting JSON in the given API version.
+pub(crate) fn accept_header(version: u32) -> String {
+    format!("application/json; version={version}")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_lowers_to_server_version() {
+        let versions = ApiVersions::negotiate();
+        assert_eq!(versions.current(), MAX_API_VERSION);
+
+        versions.observe(Some("7"), Some("2.3.0"));
+        assert_eq!(versions.current(), 7);
+        assert_eq!(versions.server_api(), Some(7));
+        assert_eq!(versions.server().as_deref(), Some("2.3.0"));
+
+        // A newer server never raises the version above what is supported.
+        versions.observe(Some("12"), None);
+        assert_eq!(versions.current(), 7);
+    }
+
+    #[test]
+    fn test_pinned_version() {
+        let versions = ApiVersions::pinned(5).expect("version should be supported");
+        versions.observe(Some("3"), None);
+        assert_eq!(versions.current(), 5);
+        assert!(!versions.needs_probe());
+        assert!(
+            !versions
+                .resend_after_not_acceptable(5)
+                .expect("should not fail")
+        );
+
+        assert!(matches!(
+            ApiVersions::pinned(1),
+            Err(ApiError::UnsupportedApiVersion { version: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_resend_after_not_acceptable() {
+        let versions = ApiVersions::negotiate();
+        assert!(versions.needs_probe());
+        assert!(
+            !versions
+                .resend_after_not_acceptable(9)
+                .expect("should not fail")
+        );
+
+        versions.observe(Some("6"), None);
+        assert!(
+            versions
+                .resend_after_not_acceptable(9)
+                .expect("should not fail")
+        );
+        assert!(
+            !versions
+                .resend_after_not_acceptable(6)
+                .expect("should not fail")
+        );
+
+        let old = ApiVersions::negotiate();
+        old.observe(Some("1"), None);
+        assert!(matches!(
+            old.resend_after_not_acceptable(9),
+            Err(ApiError::UnsupportedApiVersion { version: 1 })
+        ));
+    }
+}
@@ -0,0 +1,975 @@
+//! An in-memory fake [Paperless-ngx](https://docs.paperless-ngx.com) server
+//! for testing code built on `pngx-client`.
+//!
+//! [`FakePaperless`] serves documents, tags, correspondents, document types,
+//! storage paths, and custom fields from a store seeded with
+//! [`FakePaperlessBuilder`]. It paginates lists with `next` URLs, filters and
+//! searches documents, serves downloads, thumbnails, and file metadata, and
+//! applies creates, updates, deletes, and bulk edits, which tests can then
+//! inspect.
+//!
+//! # Example
+//!
+//! ```
+//! use pngx_client::Client;
+//! use pngx_testing::{FakePaperless, seed};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), pngx_client::ApiError> {
+//! let server = FakePaperless::builder()
+//!     .tag(seed::Tag::new("inbox").inbox())
+//!     .document(seed::Document::new("Invoice 42").content("Total: 42 EUR").tag(1))
+//!     .start()
+//!     .await;
+//!
+//! let client = Client::new(&server.uri(), server.token())?;
+//! let (documents, total) = client.collect_inbox_documents(None)?;
+//! assert_eq!(total, 1);
+//! assert_eq!(documents[0].title, "Invoice 42");
+//! # Ok(())
+//! # }
+//! ```
+
+#![warn(missing_docs)]
+
+mod multipart;
+pub mod seed;
+mod store;
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use pngx_client::{Correspondent, CustomField, Document, DocumentType, StoragePath, Tag, Task};
+use serde::de::DeserializeOwned;
+use wiremock::matchers::any;
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+use crate::store::{Kind, Store};
+
+/// A fake Paperless-ngx server listening on a random local port.
+///
+/// The server stops when this value is dropped.
+pub struct FakePaperless {
+    server: MockServer,
+    store: Arc<Mutex<Store>>,
+    token: String,
+}
+
+impl FakePaperless {
+    /// Starts configuring a server.
+    #[must_use]
+    pub fn builder() -> FakePaperlessBuilder {
+        FakePaperlessBuilder {
+            store: Store::default(),
+        }
+    }
+
+    /// Starts an empty server.
+    pub async fn start() -> Self {
+        Self::builder().start().await
+    }
+
+    /// The base URL of the server, e.g. `http://127.0.0.1:39123`.
+    #[must_use]
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// The API token the server accepts.
+    #[must_use]
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// The underlying wiremock server, e.g. to inspect received requests.
+    #[must_use]
+    pub fn mock_server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// Returns a document as currently stored.
+    #[must_use]
+    pub fn document(&self, id: u64) -> Option<Document> {
+        self.documents().into_iter().find(|doc| doc.id == id)
+    }
+
+    /// Returns all documents, ordered by ID.
+    #[must_use]
+    pub fn documents(&self) -> Vec<Document> {
+        parse(self.store().document_values())
+    }
+
+    /// Returns all tags, ordered by ID.
+    #[must_use]
+    pub fn tags(&self) -> Vec<Tag> {
+        parse(self.store().values(Kind::Tag))
+    }
+
+    /// Returns all correspondents, ordered by ID.
+    #[must_use]
+    pub fn correspondents(&self) -> Vec<Correspondent> {
+        parse(self.store().values(Kind::Correspondent))
+    }
+
+    /// Returns all document types, ordered by ID.
+    #[must_use]
+    pub fn document_types(&self) -> Vec<DocumentType> {
+        parse(self.store().values(Kind::DocumentType))
+    }
+
+    /// Returns all storage paths, ordered by ID.
+    #[must_use]
+    pub fn storage_paths(&self) -> Vec<StoragePath> {
+        parse(self.store().values(Kind::StoragePath))
+    }
+
+    /// Returns all custom fields, ordered by ID.
+    #[must_use]
+    pub fn custom_fields(&self) -> Vec<CustomField> {
+        parse(self.store().values(Kind::CustomField))
+    }
+
+    /// Returns all tasks, such as the consumption of uploaded documents,
+    /// in the order they were queued.
+    #[must_use]
+    pub fn tasks(&self) -> Vec<Task> {
+        parse(self.store().task_values())
+    }
+
+    /// Adds a document while the server is running and returns its ID.
+    #[must_use]
+    pub fn add_document(&self, document: seed::Document) -> u64 {
+        self.store().add_document(document)
+    }
+
+    /// Deletes a document and its files while the server is running.
+    /// Returns whether it existed.
+    #[must_use]
+    pub fn remove_document(&self, id: u64) -> bool {
+        self.store().remove_document(id)
+    }
+
+    fn store(&self) -> MutexGuard<'_, Store> {
+        lock(&self.store)
+    }
+}
+
+/// Configures and seeds a [`FakePaperless`].
+pub struct FakePaperlessBuilder {
+    store: Store,
+}
+
+impl FakePaperlessBuilder {
+    /// Sets the API token the server accepts. Defaults to `test-token`.
+    #[must_use]
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.store.token = token.into();
+        self
+    }
+
+    /// Sets the credentials `api/token/` exchanges for the token. Defaults
+    /// to `admin` and `admin`.
+    #[must_use]
+    pub fn user(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.store.username = username.into();
+        self.store.password = password.into();
+        self
+    }
+
+    /// Sets the Paperless-ngx version reported in `X-Version` and the UI
+    /// settings.
+    #[must_use]
+    pub fn server_version(mut self, version: impl Into<String>) -> Self {
+        self.store.server_version = version.into();
+        self
+    }
+
+    /// Sets the newest REST API version the server supports. Requests for
+    /// newer versions are rejected with 406 Not Acceptable. Defaults to
+    /// [`pngx_client::MAX_API_VERSION`].
+    #[must_use]
+    pub fn api_version(mut self, version: u32) -> Self {
+        self.store.api_version = version;
+        self
+    }
+
+    /// Sets the page size used when a request doesn't set `page_size`.
+    /// Defaults to 25, like Paperless-ngx.
+    #[must_use]
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.store.page_size = page_size.max(1);
+        self
+    }
+
+    /// Adds a document.
+    #[must_use]
+    pub fn document(mut self, document: seed::Document) -> Self {
+        self.store.add_document(document);
+        self
+    }
+
+    /// Adds a tag.
+    #[must_use]
+    pub fn tag(mut self, tag: seed::Tag) -> Self {
+        self.store.add_tag(tag);
+        self
+    }
+
+    /// Adds a correspondent.
+    #[must_use]
+    pub fn correspondent(mut self, correspondent: seed::Correspondent) -> Self {
+        self.store.add_correspondent(correspondent);
+        self
+    }
+
+    /// Adds a document type.
+    #[must_use]
+    pub fn document_type(mut self, document_type: seed::DocumentType) -> Self {
+        self.store.add_document_type(document_type);
+        self
+    }
+
+    /// Adds a storage path.
+    #[must_use]
+    pub fn storage_path(mut self, storage_path: seed::StoragePath) -> Self {
+        self.store.add_storage_path(storage_path);
+        self
+    }
+
+    /// Adds a custom field.
+    #[must_use]
+    pub fn custom_field(mut self, custom_field: seed::CustomField) -> Self {
+        self.store.add_custom_field(custom_field);
+        self
+    }
+
+    /// Starts the server.
+    pub async fn start(self) -> FakePaperless {
+        let server = MockServer::start().await;
+        let token = self.store.token.clone();
+        let store = Arc::new(Mutex::new(self.store));
+        Mock::given(any())
+            .respond_with(Handler(Arc::clone(&store)))
+            .mount(&server)
+            .await;
+        FakePaperless {
+            server,
+            store,
+            token,
+        }
+    }
+}
+
+struct Handler(Arc<Mutex<Store>>);
+
+impl wiremock::Respond for Handler {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        lock(&self.0).handle(request)
+    }
+}
+
+/// Locks the store. A test that panicked while holding the lock leaves the
+/// store usable.
+fn lock(store: &Mutex<Store>) -> MutexGuard<'_, Store> {
+    store
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn parse<T: DeserializeOwned>(values: Vec<serde_json::Value>) -> Vec<T> {
+    values
+        .into_iter()
+        .filter_map(|value| serde_json::from_value(value).ok())
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use std::time::Duration;
+
+    use pngx_client::{
+        ApiError, BulkEdit, Client, CorrespondentRequest, DocumentRequest, DocumentTypeRequest,
+        DocumentUpload, DocumentVersion, MatchingAlgorithm, TagRequest, TaskStatus,
+    };
+
+    use super::*;
+
+    async fn seeded() -> FakePaperless {
+        FakePaperless::builder()
+            .page_size(2)
+            .tag(seed::Tag::new("Inbox").inbox())
+            .tag(seed::Tag::new("Tax").color("#000000"))
+            .correspondent(seed::Correspondent::new("ACME"))
+            .document_type(seed::DocumentType::new("Invoice"))
+            .document(
+                seed::Document::new("Invoice 42")
+                    .content("Total amount: 42 EUR")
+                    .correspondent(1)
+                    .document_type(1)
+                    .tag(1)
+                    .original_file_name("invoice-42.pdf")
+                    .file(b"%PDF-original".to_vec())
+                    .archived_file(b"%PDF-archived".to_vec()),
+            )
+            .document(seed::Document::new("Tax return 2024").tag(2))
+            .document(seed::Document::new("Letter").content("Dear customer"))
+            .start()
+            .await
+    }
+
+    fn client(server: &FakePaperless) -> Client {
+        Client::new(&server.uri(), server.token()).expect("client creation should succeed")
+    }
+
+    #[tokio::test]
+    async fn test_pagination_follows_next_urls() {
+        let server = seeded().await;
+        let client = Client::builder(&server.uri(), server.token())
+            .page_size(2)
+            .build()
+            .expect("client creation should succeed");
+
+        let first = client.documents().expect("request should succeed");
+        assert_eq!(first.count, 3);
+        assert_eq!(first.results.len(), 2);
+        assert!(first.next.is_some());
+
+        let (documents, total) = client
+            .collect_documents(None)
+            .expect("request should succeed");
+        assert_eq!(total, 3);
+        let titles: Vec<_> = documents.iter().map(|doc| doc.title.as_str()).collect();
+        assert_eq!(titles, ["Invoice 42", "Tax return 2024", "Letter"]);
+    }
+
+    /// A client that asks for pages of `page_size` items.
+    fn paged_client(server: &FakePaperless, page_size: u32) -> Client {
+        Client::builder(&server.uri(), server.token())
+            .page_size(page_size)
+            .build()
+            .expect("client creation should succeed")
+    }
+
+    /// The query of every request the server received for `path`.
+    async fn queries(server: &FakePaperless, path: &str) -> Vec<String> {
+        server
+            .mock_server()
+            .received_requests()
+            .await
+            .expect("requests should be recorded")
+            .into_iter()
+            .filter(|request| request.url.path() == path)
+            .map(|request| request.url.query().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_taxonomy_pagination() {
+        let mut builder = FakePaperless::builder();
+        for name in ["A", "B", "C", "D", "E"] {
+            builder = builder
+                .tag(seed::Tag::new(name))
+                .correspondent(seed::Correspondent::new(name))
+                .document_type(seed::DocumentType::new(name));
+        }
+        let server = builder.start().await;
+        let client = paged_client(&server, 2);
+
+        let first = client.tags().expect("request should succeed");
+        assert_eq!(first.count, 5);
+        let next = first.next.expect("first page should link the next");
+        assert!(next.starts_with(&format!("{}/api/tags/?", server.uri())));
+        assert!(next.contains("page=2") && next.contains("page_size=2"));
+        assert!(first.previous.is_none());
+
+        let sizes: Vec<usize> = client
+            .tag_pages()
+            .map(|page| page.expect("request should succeed").results.len())
+            .collect();
+        assert_eq!(sizes, [2, 2, 1]);
+        let (correspondents, total) = client
+            .collect_correspondents(None)
+            .expect("request should succeed");
+        assert_eq!(total, 5);
+        assert_eq!(correspondents[4].name, "E");
+        let (document_types, _) = client
+            .collect_document_types(Some(3))
+            .expect("request should succeed");
+        assert_eq!(document_types.len(), 3);
+
+        let tags = client
+            .tags_by_id(&[5, 1, 3])
+            .expect("request should succeed");
+        let ids: Vec<_> = tags.iter().map(|tag| tag.id).collect();
+        assert_eq!(ids, [1, 3, 5]);
+
+        let err = client
+            .collect_matching_documents(&[("page", "2")], None)
+            .expect_err("page past the end should not exist");
+        assert!(matches!(err, ApiError::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_next_urls_keep_filters() {
+        let server = seeded().await;
+        let client = paged_client(&server, 1);
+
+        let (documents, total) = client
+            .collect_matching_documents(&[("title_content", "e"), ("ordering", "-title")], None)
+            .expect("request should succeed");
+        assert_eq!(total, 3);
+        let titles: Vec<_> = documents.iter().map(|doc| doc.title.as_str()).collect();
+        assert_eq!(titles, ["Tax return 2024", "Letter", "Invoice 42"]);
+
+        let queries = queries(&server, "/api/documents/").await;
+        assert_eq!(queries.len(), 3);
+        for (page, query) in queries.iter().enumerate() {
+            assert!(query.contains("title_content=e"), "{query}");
+            assert!(query.contains("ordering=-title"), "{query}");
+            assert_eq!(query.contains(&format!("page={}", page + 1)), page > 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_document_filters() {
+        let server = FakePaperless::builder()
+            .tag(seed::Tag::new("Inbox").inbox())
+            .tag(seed::Tag::new("Tax"))
+            .document_type(seed::DocumentType::new("Invoice"))
+            .document(
+                seed::Document::new("Invoice 42")
+                    .tag(1)
+                    .tag(2)
+                    .document_type(1)
+                    .file(b"%PDF-invoice".to_vec()),
+            )
+            .document(seed::Document::new("Tax return").tag(2))
+            .document(seed::Document::new("Letter").content("Invoice attached"))
+            .start()
+            .await;
+        let client = client(&server);
+        let ids = |filters: &[(&str, &str)]| {
+            client
+                .collect_document_ids(filters)
+                .expect("request should succeed")
+        };
+
+        assert_eq!(ids(&[("tags__id__all", "1,2")]), [1]);
+        assert_eq!(ids(&[("tags__id__in", "1,2")]), [1, 2]);
+        assert_eq!(ids(&[("document_type__id__in", "1")]), [1]);
+        assert_eq!(ids(&[("id__in", "3,1,9")]), [1, 3]);
+        assert_eq!(ids(&[("is_in_inbox", "true")]), [1]);
+        assert_eq!(ids(&[("is_in_inbox", "false")]), [2, 3]);
+        assert_eq!(ids(&[("title__icontains", "INVOICE")]), [1]);
+        assert_eq!(ids(&[("title_content", "invoice")]), [1, 3]);
+        assert_eq!(
+            ids(&[("tags__id__in", "2"), ("title__icontains", "tax")]),
+            [2]
+        );
+        // Unknown filters are ignored, like in Paperless-ngx.
+        assert_eq!(ids(&[("owner__id", "7")]), [1, 2, 3]);
+
+        let document = server.document(1).expect("document should exist");
+        let checksum = client
+            .document_metadata(document.id)
+            .expect("request should succeed")
+            .original_checksum
+            .to_uppercase();
+        assert_eq!(ids(&[("checksum__iexact", &checksum)]), [1]);
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_all_words() {
+        let server = seeded().await;
+        let client = paged_client(&server, 1);
+
+        let results = client.search("42 TOTAL").expect("request should succeed");
+        assert_eq!(results.count, 1);
+        assert_eq!(results.results[0].title, "Invoice 42");
+        let results = client
+            .search("amount customer")
+            .expect("request should succeed");
+        assert_eq!(results.count, 0);
+        assert!(results.results.is_empty());
+
+        let (documents, total) = client
+            .collect_search("e", None)
+            .expect("request should succeed");
+        assert_eq!(total, 3);
+        assert_eq!(documents.len(), 3);
+        let pages = client.search_pages("e").count();
+        assert_eq!(pages, 3);
+        // Every page of both searches keeps the query.
+        let searches = queries(&server, "/api/documents/")
+            .await
+            .into_iter()
+            .filter(|query| query.contains("query=e&"))
+            .count();
+        assert_eq!(searches, 6);
+    }
+
+    #[tokio::test]
+    async fn test_document_reads() {
+        let server = seeded().await;
+        let client = client(&server);
+
+        let document = client.document(1).expect("request should succeed");
+        assert_eq!(document.content.as_deref(), Some("Total amount: 42 EUR"));
+        assert_eq!(document.tags, [1]);
+        assert_eq!(
+            client.document_content(3).expect("request should succeed"),
+            "Dear customer"
+        );
+        assert!(matches!(client.document(9), Err(ApiError::NotFound { .. })));
+
+        // Lists leave out the content.
+        let (documents, _) = client
+            .collect_documents(None)
+            .expect("request should succeed");
+        assert!(documents.iter().all(|doc| doc.content.is_none()));
+        assert_eq!(
+            documents[0].original_file_name.as_deref(),
+            Some("invoice-42.pdf")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_correspondent_and_document_type_writes() {
+        let server = seeded().await;
+        let client = client(&server);
+
+        let created = client
+            .create_correspondent(&CorrespondentRequest {
+                name: Some("Tax Office".to_string()),
+                ..CorrespondentRequest::default()
+            })
+            .expect("create should succeed");
+        assert_eq!(created.id, 2);
+        let updated = client
+            .update_correspondent(
+                2,
+                &CorrespondentRequest {
+                    r#match: Some("finanzamt".to_string()),
+                    matching_algorithm: Some(MatchingAlgorithm::Any),
+                    ..CorrespondentRequest::default()
+                },
+            )
+            .expect("update should succeed");
+        assert_eq!(updated.name, "Tax Office");
+        assert_eq!(updated.r#match.as_deref(), Some("finanzamt"));
+        assert_eq!(
+            client
+                .correspondent(1)
+                .expect("request should succeed")
+                .name,
+            "ACME"
+        );
+
+        let err = client
+            .create_document_type(&DocumentTypeRequest {
+                name: Some("INVOICE".to_string()),
+                ..DocumentTypeRequest::default()
+            })
+            .expect_err("duplicate name should be rejected");
+        assert!(matches!(
+            err,
+            ApiError::Validation { ref field_errors, .. } if field_errors.contains_key("name")
+        ));
+
+        client
+            .delete_correspondent(1)
+            .expect("delete should succeed");
+        client
+            .delete_document_type(1)
+            .expect("delete should succeed");
+        assert!(matches!(
+            client.correspondent(1),
+            Err(ApiError::NotFound { .. })
+        ));
+        assert!(matches!(
+            client.delete_document_type(1),
+            Err(ApiError::NotFound { .. })
+        ));
+        let document = server.document(1).expect("document should exist");
+        assert_eq!(document.correspondent, None);
+        assert_eq!(document.document_type, None);
+    }
+
+    #[tokio::test]
+    async fn test_filters_and_search() {
+        let server = seeded().await;
+        let client = client(&server);
+
+        let (inbox, _) = client
+            .collect_inbox_documents(None)
+            .expect("request should succeed");
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].id, 1);
+
+        let results = client.search("dear").expect("request should succeed");
+        assert_eq!(results.count, 1);
+        assert_eq!(results.results[0].title, "Letter");
+
+        let ids = client
+            .collect_document_ids(&[("correspondent__id__in", "1")])
+            .expect("request should succeed");
+        assert_eq!(ids, [1]);
+    }
+
+    #[tokio::test]
+    async fn test_download() {
+        let server = seeded().await;
+        let client = client(&server);
+
+        let mut original = Vec::new();
+        client
+            .download_document(1, DocumentVersion::Original, &mut original)
+            .expect("download should succeed");
+        assert_eq!(original, b"%PDF-original");
+
+        let mut archived = Vec::new();
+        client
+            .download_document(1, DocumentVersion::Archived, &mut archived)
+            .expect("download should succeed");
+        assert_eq!(archived, b"%PDF-archived");
+
+        let err = client
+            .download_document(99, DocumentVersion::Original, &mut Vec::new())
+            .expect_err("document should not exist");
+        assert!(matches!(err, ApiError::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_tag_writes() {
+        let server = seeded().await;
+        let client = client(&server);
+
+        let created = client
+            .create_tag(&TagRequest {
+                name: Some("Receipts".to_string()),
+                matching_algorithm: Some(MatchingAlgorithm::Literal),
+                ..TagRequest::default()
+            })
+            .expect("create should succeed");
+        assert_eq!(created.id, 3);
+        assert_eq!(created.slug, "receipts");
+
+        let err = client
+            .create_tag(&TagRequest {
+                name: Some("tax".to_string()),
+                ..TagRequest::default()
+            })
+            .expect_err("duplicate name should be rejected");
+        assert!(matches!(
+            err,
+            ApiError::Validation { ref field_errors, .. } if field_errors.contains_key("name")
+        ));
+
+        let updated = client
+            .update_tag(
+                3,
+                &TagRequest {
+                    parent: Some(Some(2)),
+                    ..TagRequest::default()
+                },
+            )
+            .expect("update should succeed");
+        assert_eq!(updated.parent, Some(2));
+
+        client.delete_tag(2).expect("delete should succeed");
+        assert_eq!(server.tags().len(), 2);
+        assert!(
+            server
+                .document(2)
+                .expect("document should exist")
+                .tags
+                .is_empty()
+        );
+        assert_eq!(server.tags()[1].parent, None);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_edit() {
+        let server = seeded().await;
+        let client = client(&server);
+
+        client
+            .bulk_edit(&[2, 3], &BulkEdit::AddTag(1))
+            .expect("bulk edit should succeed");
+        client
+            .bulk_edit(&[3], &BulkEdit::SetCorrespondent(Some(1)))
+            .expect("bulk edit should succeed");
+
+        assert_eq!(
+            server.document(2).expect("document should exist").tags,
+            [2, 1]
+        );
+        let letter = server.document(3).expect("document should exist");
+        assert_eq!(letter.tags, [1]);
+        assert_eq!(letter.correspondent, Some(1));
+        assert_eq!(server.tags()[0].document_count, Some(3));
+
+        let err = client
+            .bulk_edit(&[3], &BulkEdit::AddTag(99))
+            .expect_err("unknown tag should be rejected");
+        assert!(matches!(err, ApiError::Validation { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_modified_since_and_removal() {
+        let server = seeded().await;
+        let client = client(&server);
+        let before = server
+            .document(3)
+            .and_then(|doc| doc.modified)
+            .expect("document should have a modification time");
+
+        client
+            .bulk_edit(&[2], &BulkEdit::AddTag(1))
+            .expect("bulk edit should succeed");
+        let since = before.to_string();
+        let (changed, _) = client
+            .collect_matching_documents(&[("modified__gt", &since)], None)
+            .expect("request should succeed");
+        let ids: Vec<_> = changed.iter().map(|doc| doc.id).collect();
+        assert_eq!(ids, [2]);
+
+        assert!(server.remove_document(1));
+        assert!(!server.remove_document(1));
+        let ids = client
+            .collect_document_ids(&[])
+            .expect("request should succeed");
+        assert_eq!(ids, [2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_export_endpoints() {
+        let server = FakePaperless::builder()
+            .storage_path(seed::StoragePath::new("Archive", "{{ title }}"))
+            .custom_field(seed::CustomField::new("Amount", "monetary"))
+            .document(
+                seed::Document::new("Invoice 42")
+                    .original_file_name("invoice-42.pdf")
+                    .file(b"%PDF-original".to_vec())
+                    .thumbnail(b"thumb".to_vec())
+                    .storage_path(1)
+                    .custom_field(1, "EUR42.00")
+                    .note("Paid"),
+            )
+            .start()
+            .await;
+        let client = client(&server);
+
+        let metadata = client.document_metadata(1).expect("request should succeed");
+        assert_eq!(
+            metadata.original_checksum,
+            "0497c4bd8fc29f86da69f6716b6bd72f"
+        );
+        assert_eq!(metadata.original_size, 13);
+        assert_eq!(metadata.media_filename, "0000001.pdf");
+        assert!(!metadata.has_archive_version);
+
+        let mut thumbnail = Vec::new();
+        client
+            .download_thumbnail(1, &mut thumbnail)
+            .expect("download should succeed");
+        assert_eq!(thumbnail, b"thumb");
+
+        let (paths, _) = client
+            .collect_storage_paths(None)
+            .expect("request should succeed");
+        assert_eq!(paths[0].path, "{{ title }}");
+        assert_eq!(paths[0].document_count, Some(1));
+        let (fields, _) = client
+            .collect_custom_fields(None)
+            .expect("request should succeed");
+        assert_eq!(fields[0].data_type, "monetary");
+        assert_eq!(fields[0].document_count, Some(1));
+
+        let document = server.document(1).expect("document should exist");
+        assert_eq!(document.notes[0].note, "Paid");
+        assert_eq!(document.custom_fields[0].value, "EUR42.00");
+    }
+
+    #[tokio::test]
+    async fn test_suggestions() {
+        let server = FakePaperless::builder()
+            .tag(seed::Tag::new("Tax").matching(MatchingAlgorithm::Any, "vat tax"))
+            .tag(seed::Tag::new("Paid").matching(MatchingAlgorithm::None, ""))
+            .correspondent(seed::Correspondent::new("ACME").matching(MatchingAlgorithm::Auto, ""))
+            .document_type(
+                seed::DocumentType::new("Invoice")
+                    .matching(MatchingAlgorithm::Literal, "invoice no"),
+            )
+            .document(seed::Document::new("Scan").content("ACME Corp\nVAT 19%\nDue 2024-07-01"))
+            .start()
+            .await;
+        let client = client(&server);
+
+        let suggestions = client.suggestions(1).expect("request should succeed");
+        assert_eq!(suggestions.correspondents, [1]);
+        assert_eq!(suggestions.tags, [1]);
+        assert!(suggestions.document_types.is_empty());
+        assert_eq!(
+            suggestions.dates,
+            [pngx_client::jiff::civil::date(2024, 7, 1)]
+        );
+        assert!(matches!(
+            client.suggestions(9),
+            Err(ApiError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_upload_tasks_and_notes() {
+        let server = seeded().await;
+        let client = client(&server);
+        let upload = DocumentUpload {
+            title: Some("Receipt".to_string()),
+            tags: vec![2],
+            correspondent: Some(1),
+            ..DocumentUpload::default()
+        };
+
+        let task_id = client
+            .upload_document("receipt.pdf", b"%PDF-receipt", &upload)
+            .expect("upload should succeed");
+        let task = client
+            .wait_for_task(&task_id, Duration::from_secs(1))
+            .expect("task should finish");
+        assert_eq!(task.status, TaskStatus::Success);
+        let id = task
+            .related_document
+            .expect("task should create a document");
+        let document = server.document(id).expect("document should exist");
+        assert_eq!(document.title, "Receipt");
+        assert_eq!(document.tags, [2]);
+        assert_eq!(document.original_file_name.as_deref(), Some("receipt.pdf"));
+
+        let duplicate = client
+            .upload_document("copy.pdf", b"%PDF-receipt", &DocumentUpload::default())
+            .expect("upload should succeed");
+        let task = client
+            .task(&duplicate)
+            .expect("request should succeed")
+            .expect("task should exist");
+        assert_eq!(task.status, TaskStatus::Failure);
+        assert!(
+            task.result
+                .is_some_and(|result| result.contains("duplicate of Receipt"))
+        );
+        assert_eq!(server.tasks().len(), 2);
+
+        let notes = client.add_note(id, "Paid").expect("note should be added");
+        assert_eq!(notes[0].note, "Paid");
+        let request = DocumentRequest {
+            custom_fields: Some(vec![]),
+            title: Some("Receipt 7".to_string()),
+            ..DocumentRequest::default()
+        };
+        let updated = client
+            .update_document(id, &request)
+            .expect("update should succeed");
+        assert_eq!(updated.title, "Receipt 7");
+    }
+
+    #[tokio::test]
+    async fn test_upload_form_fields() {
+        let server = FakePaperless::builder()
+            .tag(seed::Tag::new("Inbox"))
+            .tag(seed::Tag::new("Tax"))
+            .document_type(seed::DocumentType::new("Invoice"))
+            .custom_field(seed::CustomField::new("Amount", "monetary"))
+            .start()
+            .await;
+        let client = client(&server);
+        // Line breaks and dashes in the file must not end the part early.
+        let file = b"%PDF\r\n--boundary\r\n\r\nend";
+        let upload = DocumentUpload {
+            created: Some(pngx_client::jiff::civil::date(2024, 3, 1)),
+            document_type: Some(1),
+            tags: vec![1, 2],
+            archive_serial_number: Some(17),
+            custom_fields: vec![1],
+            ..DocumentUpload::default()
+        };
+
+        let task_id = client
+            .upload_document("scan \"March\".pdf", file, &upload)
+            .expect("upload should succeed");
+        let task = client
+            .wait_for_task(&task_id, Duration::from_secs(1))
+            .expect("task should finish");
+        let id = task
+            .related_document
+            .expect("task should create a document");
+        let document = server.document(id).expect("document should exist");
+        assert_eq!(document.title, "scan \"March\"");
+        assert_eq!(
+            document.original_file_name.as_deref(),
+            Some("scan \"March\".pdf")
+        );
+        assert_eq!(
+            document.created,
+            Some(pngx_client::jiff::civil::date(2024, 3, 1))
+        );
+        assert_eq!(document.document_type, Some(1));
+        assert_eq!(document.tags, [1, 2]);
+        assert_eq!(document.archive_serial_number, Some(17));
+        assert_eq!(document.custom_fields[0].field, 1);
+        let mut original = Vec::new();
+        client
+            .download_document(id, DocumentVersion::Original, &mut original)
+            .expect("download should succeed");
+        assert_eq!(original, file);
+
+        let upload = DocumentUpload {
+            tags: vec![1, 9],
+            ..DocumentUpload::default()
+        };
+        let err = client
+            .upload_document("other.pdf", b"%PDF-other", &upload)
+            .expect_err("unknown tag should be rejected");
+        assert!(matches!(
+            err,
+            ApiError::Validation { ref field_errors, .. } if field_errors.contains_key("tags")
+        ));
+        assert_eq!(server.tasks().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_authentication() {
+        let server = FakePaperless::builder()
+            .token("secret")
+            .user("alice", "hunter2")
+            .start()
+            .await;
+
+        let err = Client::new(&server.uri(), "wrong")
+            .expect("client creation should succeed")
+            .tags()
+            .expect_err("wrong token should be rejected");
+        assert!(matches!(err, ApiError::Unauthorized { .. }));
+
+        let token = Client::obtain_token(Client::builder(&server.uri(), ""), "alice", "hunter2")
+            .expect("login should succeed");
+        assert_eq!(token, "secret");
+    }
+
+    #[tokio::test]
+    async fn test_api_version_negotiation() {
+        let server = FakePaperless::builder()
+            .api_version(7)
+            .server_version("2.3.0")
+            .start()
+            .await;
+        let client = client(&server);
+
+        client.tags().expect("request should succeed");
+        assert_eq!(client.api_version(), 7);
+        assert_eq!(
+            client.server_version().expect("version should be known"),
+            "2.3.0"
+        );
+    }
+}
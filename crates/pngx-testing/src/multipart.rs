@@ -0,0 +1,106 @@
+//! Parsing of the `multipart/form-data` bodies of document uploads.
+
+use wiremock::Request;
+
+/// One field of a form.
+pub(crate) struct Part {
+    pub(crate) name: String,
+    pub(crate) file_name: Option<String>,
+    pub(crate) data: Vec<u8>,
+}
+
+/// The parts of a form, in the order they were sent.
+pub(crate) struct Form(Vec<Part>);
+
+impl Form {
+    /// The first file field called `name`.
+    pub(crate) fn file(&self, name: &str) -> Option<&Part> {
+        self.0
+            .iter()
+            .find(|part| part.name == name && part.file_name.is_some())
+    }
+
+    /// All text values of the fields called `name`.
+    pub(crate) fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.0
+            .iter()
+            .filter(move |part| part.name == name && part.file_name.is_none())
+            .filter_map(|part| std::str::from_utf8(&part.data).ok())
+    }
+
+    /// The first text value of the field called `name`.
+    pub(crate) fn value(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|part| part.name == name && part.file_name.is_none())
+            .and_then(|part| std::str::from_utf8(&part.data).ok())
+    }
+}
+
+/// Splits a `multipart/form-data` request body into its parts, or returns
+/// `None` if it isn't one.
+pub(crate) fn parse(request: &Request) -> Option<Form> {
+    let content_type = request.headers.get("Content-Type")?.to_str().ok()?;
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    let boundary = params
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("--{boundary}");
+
+    let mut parts = Vec::new();
+    let mut rest = request.body.strip_prefix(delimiter.as_bytes())?;
+    loop {
+        if rest.starts_with(b"--") {
+            return Some(Form(parts));
+        }
+        rest = rest.strip_prefix(b"\r\n")?;
+        let end = find(rest, format!("\r\n{delimiter}").as_bytes())?;
+        parts.push(part(&rest[..end])?);
+        rest = &rest[end + 2 + delimiter.len()..];
+    }
+}
+
+fn part(bytes: &[u8]) -> Option<Part> {
+    let split = find(bytes, b"\r\n\r\n")?;
+    let headers = std::str::from_utf8(&bytes[..split]).ok()?;
+    let disposition = headers.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.eq_ignore_ascii_case("Content-Disposition")
+            .then_some(value)
+    })?;
+    Some(Part {
+        name: parameter(disposition, "name")?,
+        file_name: parameter(disposition, "filename"),
+        data: bytes[split + 4..].to_vec(),
+    })
+}
+
+/// Reads a quoted header parameter, undoing backslash escapes.
+fn parameter(header: &str, key: &str) -> Option<String> {
+    let start = header
+        .match_indices(&format!("{key}=\""))
+        .find(|(i, _)| header[..*i].trim_end().ends_with(';'))?
+        .0
+        + key.len()
+        + 2;
+    let mut value = String::new();
+    let mut chars = header[start..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => value.push(chars.next()?),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
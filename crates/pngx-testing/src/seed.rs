@@ -0,0 +1,402 @@
+//! Objects to seed a [`FakePaperless`](crate::FakePaperless) with.
+//!
+//! Objects get consecutive IDs per kind, starting at 1, unless one is set
+//! explicitly with `id`.
+
+use pngx_client::MatchingAlgorithm;
+use pngx_client::jiff::Timestamp;
+use pngx_client::jiff::civil::Date;
+
+/// A document and its files.
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_field_names)]
+pub struct Document {
+    pub(crate) id: Option<u64>,
+    pub(crate) title: String,
+    pub(crate) content: String,
+    pub(crate) correspondent: Option<u64>,
+    pub(crate) document_type: Option<u64>,
+    pub(crate) tags: Vec<u64>,
+    pub(crate) storage_path: Option<u64>,
+    pub(crate) custom_fields: Vec<(u64, serde_json::Value)>,
+    pub(crate) notes: Vec<String>,
+    pub(crate) created: Option<Date>,
+    pub(crate) added: Option<Timestamp>,
+    pub(crate) archive_serial_number: Option<u64>,
+    pub(crate) original_file_name: Option<String>,
+    pub(crate) original: Vec<u8>,
+    pub(crate) archived: Option<Vec<u8>>,
+    pub(crate) thumbnail: Vec<u8>,
+    pub(crate) extra: Vec<(String, serde_json::Value)>,
+}
+
+impl Document {
+    /// A document with the given title, no content, and an empty original
+    /// file.
+    #[must_use]
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            title: title.into(),
+            content: String::new(),
+            correspondent: None,
+            document_type: None,
+            tags: Vec::new(),
+            storage_path: None,
+            custom_fields: Vec::new(),
+            notes: Vec::new(),
+            created: None,
+            added: None,
+            archive_serial_number: None,
+            original_file_name: None,
+            original: Vec::new(),
+            archived: None,
+            thumbnail: Vec::new(),
+            extra: Vec::new(),
+        }
+    }
+
+    /// Sets the document ID.
+    #[must_use]
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the extracted text, which search matches against.
+    #[must_use]
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = content.into();
+        self
+    }
+
+    /// Assigns a correspondent by ID.
+    #[must_use]
+    pub fn correspondent(mut self, id: u64) -> Self {
+        self.correspondent = Some(id);
+        self
+    }
+
+    /// Assigns a document type by ID.
+    #[must_use]
+    pub fn document_type(mut self, id: u64) -> Self {
+        self.document_type = Some(id);
+        self
+    }
+
+    /// Adds a tag by ID.
+    #[must_use]
+    pub fn tag(mut self, id: u64) -> Self {
+        self.tags.push(id);
+        self
+    }
+
+    /// Assigns a storage path by ID.
+    #[must_use]
+    pub fn storage_path(mut self, id: u64) -> Self {
+        self.storage_path = Some(id);
+        self
+    }
+
+    /// Sets the value of a custom field by the field's ID.
+    #[must_use]
+    pub fn custom_field(mut self, id: u64, value: impl Into<serde_json::Value>) -> Self {
+        self.custom_fields.push((id, value.into()));
+        self
+    }
+
+    /// Adds a note written by the server's user.
+    #[must_use]
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Sets the creation date.
+    #[must_use]
+    pub fn created(mut self, date: Date) -> Self {
+        self.created = Some(date);
+        self
+    }
+
+    /// Sets the time the document was added. Defaults to the time it is
+    /// seeded.
+    #[must_use]
+    pub fn added(mut self, added: Timestamp) -> Self {
+        self.added = Some(added);
+        self
+    }
+
+    /// Sets the archive serial number.
+    #[must_use]
+    pub fn archive_serial_number(mut self, asn: u64) -> Self {
+        self.archive_serial_number = Some(asn);
+        self
+    }
+
+    /// Sets the original file name, also sent when the file is downloaded.
+    #[must_use]
+    pub fn original_file_name(mut self, name: impl Into<String>) -> Self {
+        self.original_file_name = Some(name.into());
+        self
+    }
+
+    /// Sets the original file.
+    #[must_use]
+    pub fn file(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.original = bytes.into();
+        self
+    }
+
+    /// Sets the archived version. Without one, the original is served
+    /// instead.
+    #[must_use]
+    pub fn archived_file(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.archived = Some(bytes.into());
+        self
+    }
+
+    /// Sets the thumbnail. Defaults to an empty file.
+    #[must_use]
+    pub fn thumbnail(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.thumbnail = bytes.into();
+        self
+    }
+
+    /// Sets a field the server doesn't otherwise model, e.g. one added in a
+    /// newer Paperless-ngx version.
+    #[must_use]
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.extra.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// A tag.
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_field_names)]
+pub struct Tag {
+    pub(crate) id: Option<u64>,
+    pub(crate) name: String,
+    pub(crate) color: String,
+    pub(crate) is_inbox_tag: bool,
+    pub(crate) matching: Matching,
+    pub(crate) parent: Option<u64>,
+}
+
+impl Tag {
+    /// A tag with the given name.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            name: name.into(),
+            color: "#a6cee3".to_string(),
+            is_inbox_tag: false,
+            matching: Matching::default(),
+            parent: None,
+        }
+    }
+
+    /// Sets the tag ID.
+    #[must_use]
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the hex color, e.g. `#ff0000`.
+    #[must_use]
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    /// Marks the tag as an inbox tag.
+    #[must_use]
+    pub fn inbox(mut self) -> Self {
+        self.is_inbox_tag = true;
+        self
+    }
+
+    /// Sets the matching rule.
+    #[must_use]
+    pub fn matching(mut self, algorithm: MatchingAlgorithm, pattern: impl Into<String>) -> Self {
+        self.matching = Matching::new(algorithm, pattern);
+        self
+    }
+
+    /// Nests the tag under a parent tag.
+    #[must_use]
+    pub fn parent(mut self, id: u64) -> Self {
+        self.parent = Some(id);
+        self
+    }
+}
+
+/// A correspondent.
+#[derive(Debug, Clone)]
+pub struct Correspondent {
+    pub(crate) id: Option<u64>,
+    pub(crate) name: String,
+    pub(crate) matching: Matching,
+}
+
+impl Correspondent {
+    /// A correspondent with the given name.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            name: name.into(),
+            matching: Matching::default(),
+        }
+    }
+
+    /// Sets the correspondent ID.
+    #[must_use]
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the matching rule.
+    #[must_use]
+    pub fn matching(mut self, algorithm: MatchingAlgorithm, pattern: impl Into<String>) -> Self {
+        self.matching = Matching::new(algorithm, pattern);
+        self
+    }
+}
+
+/// A document type.
+#[derive(Debug, Clone)]
+pub struct DocumentType {
+    pub(crate) id: Option<u64>,
+    pub(crate) name: String,
+    pub(crate) matching: Matching,
+}
+
+impl DocumentType {
+    /// A document type with the given name.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            name: name.into(),
+            matching: Matching::default(),
+        }
+    }
+
+    /// Sets the document type ID.
+    #[must_use]
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the matching rule.
+    #[must_use]
+    pub fn matching(mut self, algorithm: MatchingAlgorithm, pattern: impl Into<String>) -> Self {
+        self.matching = Matching::new(algorithm, pattern);
+        self
+    }
+}
+
+/// A storage path.
+#[derive(Debug, Clone)]
+pub struct StoragePath {
+    pub(crate) id: Option<u64>,
+    pub(crate) name: String,
+    pub(crate) path: String,
+    pub(crate) matching: Matching,
+}
+
+impl StoragePath {
+    /// A storage path with the given name and file name template, e.g.
+    /// `{{ correspondent }}/{{ title }}`.
+    #[must_use]
+    pub fn new(name: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            name: name.into(),
+            path: path.into(),
+            matching: Matching::default(),
+        }
+    }
+
+    /// Sets the storage path ID.
+    #[must_use]
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the matching rule.
+    #[must_use]
+    pub fn matching(mut self, algorithm: MatchingAlgorithm, pattern: impl Into<String>) -> Self {
+        self.matching = Matching::new(algorithm, pattern);
+        self
+    }
+}
+
+/// A custom field.
+#[derive(Debug, Clone)]
+pub struct CustomField {
+    pub(crate) id: Option<u64>,
+    pub(crate) name: String,
+    pub(crate) data_type: String,
+    pub(crate) extra_data: serde_json::Value,
+}
+
+impl CustomField {
+    /// A custom field with the given name and data type, e.g. `string`,
+    /// `date`, or `monetary`.
+    #[must_use]
+    pub fn new(name: impl Into<String>, data_type: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            name: name.into(),
+            data_type: data_type.into(),
+            extra_data: serde_json::Value::Null,
+        }
+    }
+
+    /// Sets the custom field ID.
+    #[must_use]
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the type-specific settings, such as the options of a `select`
+    /// field.
+    #[must_use]
+    pub fn extra_data(mut self, extra_data: serde_json::Value) -> Self {
+        self.extra_data = extra_data;
+        self
+    }
+}
+
+/// Matching rule shared by tags, correspondents, document types, and
+/// storage paths.
+#[derive(Debug, Clone)]
+pub(crate) struct Matching {
+    pub(crate) algorithm: MatchingAlgorithm,
+    pub(crate) pattern: String,
+}
+
+impl Matching {
+    fn new(algorithm: MatchingAlgorithm, pattern: impl Into<String>) -> Self {
+        Self {
+            algorithm,
+            pattern: pattern.into(),
+        }
+    }
+}
+
+impl Default for Matching {
+    /// Paperless-ngx defaults to matching any word of an empty pattern.
+    fn default() -> Self {
+        Self::new(MatchingAlgorithm::Any, "")
+    }
+}
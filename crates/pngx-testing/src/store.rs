@@ -0,0 +1,1289 @@
+//! The fake server's state and request handling.
+//!
+//! Objects are kept as JSON in the shape the REST API returns them, so
+//! handlers only add computed fields such as `document_count`.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use md5::{Digest, Md5};
+use pngx_client::jiff::Timestamp;
+use pngx_client::jiff::civil::Date;
+use pngx_client::jiff::tz::TimeZone;
+use serde_json::{Map, Value, json};
+use wiremock::http::{Method, Url};
+use wiremock::{Request, ResponseTemplate};
+
+use crate::{multipart, seed};
+
+type Object = Map<String, Value>;
+type Query = HashMap<String, String>;
+
+/// Page size Paperless-ngx uses when a request doesn't set one.
+pub(crate) const DEFAULT_PAGE_SIZE: usize = 25;
+
+/// Data types a custom field can have.
+const DATA_TYPES: &[&str] = &[
+    "string",
+    "longtext",
+    "url",
+    "date",
+    "boolean",
+    "integer",
+    "float",
+    "monetary",
+    "documentlink",
+    "select",
+];
+
+/// Tags, correspondents, document types, storage paths, and custom fields
+/// share their endpoints' shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Tag,
+    Correspondent,
+    DocumentType,
+    StoragePath,
+    CustomField,
+}
+
+impl Kind {
+    fn from_path(segment: &str) -> Option<Self> {
+        match segment {
+            "tags" => Some(Self::Tag),
+            "correspondents" => Some(Self::Correspondent),
+            "document_types" => Some(Self::DocumentType),
+            "storage_paths" => Some(Self::StoragePath),
+            "custom_fields" => Some(Self::CustomField),
+            _ => None,
+        }
+    }
+
+    /// Name used in error messages.
+    fn label(self) -> &'static str {
+        match self {
+            Self::Tag => "Tag",
+            Self::Correspondent => "Correspondent",
+            Self::DocumentType => "Document type",
+            Self::StoragePath => "Storage path",
+            Self::CustomField => "Custom field",
+        }
+    }
+
+    /// Document field that references objects of this kind.
+    fn document_field(self) -> &'static str {
+        match self {
+            Self::Tag => "tags",
+            Self::Correspondent => "correspondent",
+            Self::DocumentType => "document_type",
+            Self::StoragePath => "storage_path",
+            Self::CustomField => "custom_fields",
+        }
+    }
+
+    /// Whether objects of this kind have a matching rule.
+    fn has_matching(self) -> bool {
+        self != Self::CustomField
+    }
+}
+
+struct Files {
+    original: Vec<u8>,
+    archived: Option<Vec<u8>>,
+    thumbnail: Vec<u8>,
+}
+
+pub(crate) struct Store {
+    pub(crate) token: String,
+    pub(crate) username: String,
+    pub(crate) password: String,
+    pub(crate) server_version: String,
+    pub(crate) api_version: u32,
+    pub(crate) page_size: usize,
+    documents: BTreeMap<u64, Object>,
+    files: HashMap<u64, Files>,
+    tags: BTreeMap<u64, Object>,
+    correspondents: BTreeMap<u64, Object>,
+    document_types: BTreeMap<u64, Object>,
+    storage_paths: BTreeMap<u64, Object>,
+    custom_fields: BTreeMap<u64, Object>,
+    tasks: Vec<Object>,
+    next_note: u64,
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self {
+            token: "test-token".to_string(),
+            username: "admin".to_string(),
+            password: "admin".to_string(),
+            server_version: "2.15.0".to_string(),
+            api_version: pngx_client::MAX_API_VERSION,
+            page_size: DEFAULT_PAGE_SIZE,
+            documents: BTreeMap::new(),
+            files: HashMap::new(),
+            tags: BTreeMap::new(),
+            correspondents: BTreeMap::new(),
+            document_types: BTreeMap::new(),
+            storage_paths: BTreeMap::new(),
+            custom_fields: BTreeMap::new(),
+            tasks: Vec::new(),
+            next_note: 1,
+        }
+    }
+}
+
+impl Store {
+    pub(crate) fn add_document(&mut self, doc: seed::Document) -> u64 {
+        let id = doc.id.unwrap_or_else(|| next_id(&self.documents));
+        let added = doc.added.unwrap_or_else(Timestamp::now);
+        let created = doc
+            .created
+            .unwrap_or_else(|| added.to_zoned(TimeZone::UTC).date());
+        let notes: Vec<Value> = doc
+            .notes
+            .iter()
+            .map(|note| self.note(note, added))
+            .collect();
+        let custom_fields: Vec<Value> = doc
+            .custom_fields
+            .iter()
+            .map(|(field, value)| json!({"field": field, "value": value}))
+            .collect();
+        let object = json!({
+            "id": id,
+            "title": doc.title,
+            "content": doc.content,
+            "correspondent": doc.correspondent,
+            "document_type": doc.document_type,
+            "tags": doc.tags,
+            "created": created.to_string(),
+            "added": added.to_string(),
+            "modified": added.to_string(),
+            "archive_serial_number": doc.archive_serial_number,
+            "original_file_name": doc.original_file_name,
+            "archived_file_name": doc.archived.as_ref().map(|_| format!("{id:07}.pdf")),
+            "mime_type": doc.original_file_name.as_deref().map_or("application/pdf", mime_type),
+            "page_count": null,
+            "storage_path": doc.storage_path,
+            "owner": null,
+            "user_can_change": true,
+            "is_shared_by_requester": false,
+            "notes": notes,
+            "custom_fields": custom_fields,
+        });
+        let mut object = into_object(object);
+        object.extend(doc.extra);
+        self.documents.insert(id, object);
+        self.files.insert(
+            id,
+            Files {
+                original: doc.original,
+                archived: doc.archived,
+                thumbnail: doc.thumbnail,
+            },
+        );
+        id
+    }
+
+    pub(crate) fn add_tag(&mut self, tag: seed::Tag) -> u64 {
+        let seed::Tag {
+            id,
+            name,
+            color,
+            is_inbox_tag,
+            matching,
+            parent,
+        } = tag;
+        let mut object = taxonomy_object(&name, &matching);
+        object.insert("text_color".to_string(), json!(text_color(&color)));
+        object.insert("color".to_string(), json!(color));
+        object.insert("is_inbox_tag".to_string(), json!(is_inbox_tag));
+        object.insert("parent".to_string(), json!(parent));
+        self.insert(Kind::Tag, id, object)
+    }
+
+    pub(crate) fn add_correspondent(&mut self, correspondent: seed::Correspondent) -> u64 {
+        let seed::Correspondent { id, name, matching } = correspondent;
+        self.insert(Kind::Correspondent, id, taxonomy_object(&name, &matching))
+    }
+
+    pub(crate) fn add_document_type(&mut self, document_type: seed::DocumentType) -> u64 {
+        let seed::DocumentType { id, name, matching } = document_type;
+        self.insert(Kind::DocumentType, id, taxonomy_object(&name, &matching))
+    }
+
+    pub(crate) fn add_storage_path(&mut self, storage_path: seed::StoragePath) -> u64 {
+        let seed::StoragePath {
+            id,
+            name,
+            path,
+            matching,
+        } = storage_path;
+        let mut object = taxonomy_object(&name, &matching);
+        object.insert("path".to_string(), json!(path));
+        self.insert(Kind::StoragePath, id, object)
+    }
+
+    pub(crate) fn add_custom_field(&mut self, custom_field: seed::CustomField) -> u64 {
+        let seed::CustomField {
+            id,
+            name,
+            data_type,
+            extra_data,
+        } = custom_field;
+        let object = into_object(json!({
+            "name": name,
+            "data_type": data_type,
+            "extra_data": extra_data,
+        }));
+        self.insert(Kind::CustomField, id, object)
+    }
+
+    /// A note with the next free ID, written by the server's user.
+    fn note(&mut self, text: &str, created: Timestamp) -> Value {
+        let id = self.next_note;
+        self.next_note += 1;
+        json!({
+            "id": id,
+            "note": text,
+            "created": created.to_string(),
+            "user": {"id": 1, "username": self.username},
+        })
+    }
+
+    fn insert(&mut self, kind: Kind, id: Option<u64>, mut object: Object) -> u64 {
+        let objects = self.objects_mut(kind);
+        let id = id.unwrap_or_else(|| next_id(objects));
+        object.insert("id".to_string(), json!(id));
+        objects.insert(id, object);
+        id
+    }
+
+    fn objects(&self, kind: Kind) -> &BTreeMap<u64, Object> {
+        match kind {
+            Kind::Tag => &self.tags,
+            Kind::Correspondent => &self.correspondents,
+            Kind::DocumentType => &self.document_types,
+            Kind::StoragePath => &self.storage_paths,
+            Kind::CustomField => &self.custom_fields,
+        }
+    }
+
+    fn objects_mut(&mut self, kind: Kind) -> &mut BTreeMap<u64, Object> {
+        match kind {
+            Kind::Tag => &mut self.tags,
+            Kind::Correspondent => &mut self.correspondents,
+            Kind::DocumentType => &mut self.document_types,
+            Kind::StoragePath => &mut self.storage_paths,
+            Kind::CustomField => &mut self.custom_fields,
+        }
+    }
+
+    /// All documents as the API returns them.
+    pub(crate) fn document_values(&self) -> Vec<Value> {
+        self.documents
+            .values()
+            .cloned()
+            .map(Value::Object)
+            .collect()
+    }
+
+    /// All objects of `kind` as the API returns them.
+    pub(crate) fn task_values(&self) -> Vec<Value> {
+        self.tasks.iter().cloned().map(Value::Object).collect()
+    }
+
+    pub(crate) fn values(&self, kind: Kind) -> Vec<Value> {
+        self.objects(kind)
+            .values()
+            .map(|object| Value::Object(self.render(kind, object)))
+            .collect()
+    }
+
+    /// Adds the fields the server computes.
+    fn render(&self, kind: Kind, object: &Object) -> Object {
+        let id = object.get("id").cloned().unwrap_or(Value::Null);
+        let field = kind.document_field();
+        let count = self
+            .documents
+            .values()
+            .filter(|doc| match doc.get(field) {
+                Some(Value::Array(ids)) => ids
+                    .iter()
+                    .any(|other| *other == id || other.get("field") == Some(&id)),
+                Some(value) => *value == id,
+                None => false,
+            })
+            .count();
+        let mut object = object.clone();
+        object.insert("document_count".to_string(), json!(count));
+        object
+    }
+
+    pub(crate) fn handle(&mut self, request: &Request) -> ResponseTemplate {
+        let Some(route) = request.url.path().strip_prefix("/api/") else {
+            return not_found();
+        };
+        let segments: Vec<&str> = route.trim_end_matches('/').split('/').collect();
+
+        if segments == ["token"] {
+            return match request.method {
+                Method::POST => self.obtain_token(request),
+                _ => method_not_allowed(&request.method),
+            };
+        }
+        if !self.is_authorized(request) {
+            return error(401, "Invalid token.");
+        }
+        if !self.accepts_version(request) {
+            return error(406, "Invalid version in \"Accept\" header.");
+        }
+
+        self.route(request, &segments)
+            .insert_header("X-Api-Version", self.api_version.to_string())
+            .insert_header("X-Version", self.server_version.as_str())
+    }
+
+    fn route(&mut self, request: &Request, segments: &[&str]) -> ResponseTemplate {
+        let method = &request.method;
+        match *segments {
+            ["ui_settings"] if *method == Method::GET => json_response(
+                200,
+                &json!({
+                    "user": {"id": 1, "username": self.username, "first_name": "", "last_name": ""},
+                    "settings": {"version": self.server_version},
+                }),
+            ),
+            ["documents"] if *method == Method::GET => self.list_documents(request),
+            ["documents", "bulk_edit"] if *method == Method::POST => self.bulk_edit(request),
+            ["documents", "post_document"] if *method == Method::POST => {
+                self.post_document(request)
+            }
+            ["documents", id] => match (id.parse(), method) {
+                (Ok(id), &Method::GET) => self.get_document(id),
+                (Ok(id), &Method::PATCH | &Method::PUT) => self.update_document(id, request),
+                (Ok(id), &Method::DELETE) => self.delete_document(id),
+                (Ok(_), _) => method_not_allowed(method),
+                (Err(_), _) => not_found(),
+            },
+            ["documents", id, file @ ("download" | "preview")] if *method == Method::GET => {
+                match id.parse() {
+                    Ok(id) => self.download(id, file == "preview"),
+                    Err(_) => not_found(),
+                }
+            }
+            ["documents", id, "thumb"] if *method == Method::GET => match id.parse() {
+                Ok(id) => self.thumbnail(id),
+                Err(_) => not_found(),
+            },
+            ["documents", id, "metadata"] if *method == Method::GET => match id.parse() {
+                Ok(id) => self.metadata(id),
+                Err(_) => not_found(),
+            },
+            ["documents", id, "suggestions"] if *method == Method::GET => match id.parse() {
+                Ok(id) => self.suggestions(id),
+                Err(_) => not_found(),
+            },
+            ["documents", id, "notes"] if *method == Method::POST => match id.parse() {
+                Ok(id) => self.add_note(id, request),
+                Err(_) => not_found(),
+            },
+            ["tasks"] if *method == Method::GET => self.list_tasks(request),
+            [collection] => match (Kind::from_path(collection), method) {
+                (Some(kind), &Method::GET) => self.list(kind, request),
+                (Some(kind), &Method::POST) => self.create(kind, request),
+                (Some(_), _) => method_not_allowed(method),
+                (None, _) => not_found(),
+            },
+            [collection, id] => match (Kind::from_path(collection), id.parse(), method) {
+                (Some(kind), Ok(id), &Method::GET) => match self.objects(kind).get(&id) {
+                    Some(object) => json_response(200, &Value::Object(self.render(kind, object))),
+                    None => no_match(kind.label()),
+                },
+                (Some(kind), Ok(id), &Method::PATCH | &Method::PUT) => {
+                    self.update(kind, id, request)
+                }
+                (Some(kind), Ok(id), &Method::DELETE) => self.delete(kind, id),
+                (Some(_), Ok(_), _) => method_not_allowed(method),
+                _ => not_found(),
+            },
+            _ => not_found(),
+        }
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        request
+            .headers
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value == format!("Token {}", self.token))
+    }
+
+    /// Rejects API versions newer than the server's, like Paperless-ngx
+    /// does with 406 Not Acceptable.
+    fn accepts_version(&self, request: &Request) -> bool {
+        let requested = request
+            .headers
+            .get("Accept")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once("version="))
+            .and_then(|(_, version)| version.trim().parse::<u32>().ok());
+        requested.is_none_or(|version| (1..=self.api_version).contains(&version))
+    }
+
+    fn obtain_token(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = request.body_json().unwrap_or_default();
+        if body["username"] == self.username.as_str() && body["password"] == self.password.as_str()
+        {
+            json_response(200, &json!({ "token": self.token }))
+        } else {
+            json_response(
+                400,
+                &json!({"non_field_errors": ["Unable to log in with provided credentials."]}),
+            )
+        }
+    }
+
+    fn list_documents(&self, request: &Request) -> ResponseTemplate {
+        let query = query(&request.url);
+        let documents = self
+            .documents
+            .values()
+            .filter(|doc| self.document_matches(doc, &query))
+            .cloned()
+            .collect();
+        page(&absolute_url(request), &query, self.page_size, documents)
+    }
+
+    /// Applies the document filters this server understands. Unknown query
+    /// parameters are ignored, like unknown filters in Paperless-ngx.
+    fn document_matches(&self, doc: &Object, query: &Query) -> bool {
+        let tags = id_list(doc.get("tags"));
+        query.iter().all(|(key, value)| match key.as_str() {
+            "id__in" => ids(value).any(|id| doc.get("id") == Some(&json!(id))),
+            "correspondent__id__in" => ids(value).any(|id| doc["correspondent"] == json!(id)),
+            "document_type__id__in" => ids(value).any(|id| doc["document_type"] == json!(id)),
+            "tags__id__all" => ids(value).all(|id| tags.contains(&id)),
+            "tags__id__in" => ids(value).any(|id| tags.contains(&id)),
+            "is_in_inbox" => {
+                let in_inbox = tags.iter().any(|id| {
+                    self.tags
+                        .get(id)
+                        .is_some_and(|tag| tag["is_inbox_tag"] == true)
+                });
+                in_inbox == (value == "true" || value == "1")
+            }
+            "query" => {
+                let text =
+                    format!("{} {}", text(doc, "title"), text(doc, "content")).to_lowercase();
+                value
+                    .to_lowercase()
+                    .split_whitespace()
+                    .all(|word| text.contains(word))
+            }
+            "modified__gt" => match (timestamp(doc, "modified"), value.parse::<Timestamp>()) {
+                (Some(modified), Ok(after)) => modified > after,
+                _ => false,
+            },
+            "checksum__iexact" => doc["id"]
+                .as_u64()
+                .and_then(|id| self.files.get(&id))
+                .is_some_and(|files| checksum(&files.original).eq_ignore_ascii_case(value)),
+            "title__icontains" => text(doc, "title")
+                .to_lowercase()
+                .contains(&value.to_lowercase()),
+            "title_content" => {
+                let value = value.to_lowercase();
+                text(doc, "title").to_lowercase().contains(&value)
+                    || text(doc, "content").to_lowercase().contains(&value)
+            }
+            _ => true,
+        })
+    }
+
+    fn get_document(&self, id: u64) -> ResponseTemplate {
+        match self.documents.get(&id) {
+            Some(doc) => json_response(200, &Value::Object(doc.clone())),
+            None => no_match("Document"),
+        }
+    }
+
+    fn update_document(&mut self, id: u64, request: &Request) -> ResponseTemplate {
+        let Some(mut doc) = self.documents.get(&id).cloned() else {
+            return no_match("Document");
+        };
+        let Some(body) = body_object(request) else {
+            return invalid_body();
+        };
+
+        let mut errors = Object::new();
+        for (key, value) in body {
+            let valid = match key.as_str() {
+                "title" => value.as_str().is_some_and(|title| !title.is_empty()),
+                "content" => value.is_string(),
+                "created" => value
+                    .as_str()
+                    .is_some_and(|date| date.parse::<pngx_client::jiff::civil::Date>().is_ok()),
+                "archive_serial_number" => value.is_null() || value.is_u64(),
+                "correspondent" => self.is_reference(Kind::Correspondent, &value),
+                "document_type" => self.is_reference(Kind::DocumentType, &value),
+                "storage_path" => self.is_reference(Kind::StoragePath, &value),
+                "tags" => value.as_array().is_some_and(|ids| {
+                    ids.iter()
+                        .all(|id| !id.is_null() && self.is_reference(Kind::Tag, id))
+                }),
+                "custom_fields" => value.as_array().is_some_and(|fields| {
+                    fields.iter().all(|field| {
+                        field.get("field").is_some_and(|id| {
+                            !id.is_null() && self.is_reference(Kind::CustomField, id)
+                        })
+                    })
+                }),
+                // Read-only and unknown fields are ignored.
+                _ => continue,
+            };
+            if valid {
+                doc.insert(key, value);
+            } else {
+                errors.insert(key, json!(["Invalid value."]));
+            }
+        }
+        if !errors.is_empty() {
+            return json_response(400, &Value::Object(errors));
+        }
+        doc.insert("modified".to_string(), json!(Timestamp::now().to_string()));
+        self.documents.insert(id, doc.clone());
+        json_response(200, &Value::Object(doc))
+    }
+
+    /// Consumes an uploaded file right away and records the task, which
+    /// fails for a duplicate of an existing document like the real
+    /// consumer.
+    fn post_document(&mut self, request: &Request) -> ResponseTemplate {
+        let form = multipart::parse(request);
+        let Some(file) = form.as_ref().and_then(|form| form.file("document")) else {
+            return json_response(400, &json!({"document": ["No file was submitted."]}));
+        };
+        let file_name = file.file_name.clone().unwrap_or_default();
+        let doc = match form.as_ref().map(|form| self.upload(form, &file_name)) {
+            Some(Ok(doc)) => doc.file(file.data.clone()),
+            Some(Err(errors)) => return json_response(400, &errors),
+            None => return invalid_body(),
+        };
+
+        let sum = checksum(&doc.original);
+        let duplicate = self
+            .files
+            .iter()
+            .find(|(_, files)| checksum(&files.original) == sum);
+        let (status, result, document) = if let Some((&id, _)) = duplicate {
+            let message = format!(
+                "{file_name}: Not consuming {file_name}: It is a duplicate of {} (#{id}).",
+                text(&self.documents[&id], "title"),
+            );
+            ("FAILURE", message, None)
+        } else {
+            let id = self.add_document(doc);
+            let message = format!("Success. New document id {id} created");
+            ("SUCCESS", message, Some(id))
+        };
+
+        let number = self.tasks.len() + 1;
+        let task_id = format!("{number:08x}-0000-4000-8000-000000000000");
+        let now = Timestamp::now().to_string();
+        self.tasks.push(into_object(json!({
+            "id": number,
+            "task_id": task_id,
+            "task_file_name": file_name,
+            "date_created": now,
+            "date_done": now,
+            "type": "file",
+            "status": status,
+            "result": result,
+            "acknowledged": false,
+            "related_document": document.map(|id| id.to_string()),
+        })));
+        json_response(200, &json!(task_id))
+    }
+
+    /// Validates the metadata fields of an upload.
+    fn upload(&self, form: &multipart::Form, file_name: &str) -> Result<seed::Document, Value> {
+        let references = [
+            ("correspondent", Kind::Correspondent),
+            ("document_type", Kind::DocumentType),
+            ("storage_path", Kind::StoragePath),
+            ("tags", Kind::Tag),
+            ("custom_fields", Kind::CustomField),
+        ];
+        for (field, kind) in references {
+            let exists = |id: &str| {
+                id.parse()
+                    .is_ok_and(|id| self.objects(kind).contains_key(&id))
+            };
+            if !form.values(field).all(exists) {
+                return Err(json!({ field: ["Invalid pk - object does not exist."] }));
+            }
+        }
+        let number = |name: &str| form.value(name).and_then(|value| value.parse().ok());
+        let ids = |name| form.values(name).filter_map(|id| id.parse().ok());
+
+        let title = form.value("title").unwrap_or_else(|| {
+            file_name
+                .rsplit_once('.')
+                .map_or(file_name, |(stem, _)| stem)
+        });
+        let mut doc = seed::Document::new(title).original_file_name(file_name);
+        doc.created = match form.value("created").map(str::parse) {
+            Some(Err(_)) => return Err(json!({"created": ["Invalid date."]})),
+            created => created.and_then(Result::ok),
+        };
+        doc.correspondent = number("correspondent");
+        doc.document_type = number("document_type");
+        doc.storage_path = number("storage_path");
+        doc.archive_serial_number = number("archive_serial_number");
+        doc.tags = ids("tags").collect();
+        doc.custom_fields = ids("custom_fields").map(|id| (id, Value::Null)).collect();
+        Ok(doc)
+    }
+
+    /// Lists tasks as a plain array, like the real endpoint.
+    fn list_tasks(&self, request: &Request) -> ResponseTemplate {
+        let query = query(&request.url);
+        let tasks: Vec<&Object> = self
+            .tasks
+            .iter()
+            .filter(|task| {
+                query
+                    .get("task_id")
+                    .is_none_or(|task_id| text(task, "task_id") == task_id)
+            })
+            .collect();
+        json_response(200, &json!(tasks))
+    }
+
+    fn add_note(&mut self, id: u64, request: &Request) -> ResponseTemplate {
+        if !self.documents.contains_key(&id) {
+            return no_match("Document");
+        }
+        let body: Value = request.body_json().unwrap_or_default();
+        let Some(text) = body["note"].as_str().filter(|note| !note.is_empty()) else {
+            return json_response(400, &json!({"note": ["This field may not be blank."]}));
+        };
+        let note = self.note(text, Timestamp::now());
+        let Some(doc) = self.documents.get_mut(&id) else {
+            return no_match("Document");
+        };
+        let notes = doc.entry("notes").or_insert_with(|| json!([]));
+        if let Value::Array(notes) = notes {
+            notes.push(note);
+        }
+        json_response(200, notes)
+    }
+
+    pub(crate) fn remove_document(&mut self, id: u64) -> bool {
+        self.files.remove(&id);
+        self.documents.remove(&id).is_some()
+    }
+
+    fn delete_document(&mut self, id: u64) -> ResponseTemplate {
+        if !self.remove_document(id) {
+            return no_match("Document");
+        }
+        ResponseTemplate::new(204)
+    }
+
+    fn download(&self, id: u64, archived: bool) -> ResponseTemplate {
+        let (Some(doc), Some(files)) = (self.documents.get(&id), self.files.get(&id)) else {
+            return no_match("Document");
+        };
+        let name = doc["original_file_name"]
+            .as_str()
+            .map_or_else(|| format!("{id:07}.pdf"), str::to_string);
+        let bytes = match &files.archived {
+            Some(archived_file) if archived => archived_file.clone(),
+            _ => files.original.clone(),
+        };
+        ResponseTemplate::new(200)
+            .set_body_raw(bytes, mime_type(&name))
+            .insert_header(
+                "Content-Disposition",
+                format!("attachment; filename=\"{name}\"").as_str(),
+            )
+    }
+
+    fn thumbnail(&self, id: u64) -> ResponseTemplate {
+        match self.files.get(&id) {
+            Some(files) => {
+                ResponseTemplate::new(200).set_body_raw(files.thumbnail.clone(), "image/webp")
+            }
+            None => no_match("Document"),
+        }
+    }
+
+    fn metadata(&self, id: u64) -> ResponseTemplate {
+        let (Some(doc), Some(files)) = (self.documents.get(&id), self.files.get(&id)) else {
+            return no_match("Document");
+        };
+        let archive = files.archived.as_ref();
+        json_response(
+            200,
+            &json!({
+                "original_checksum": checksum(&files.original),
+                "original_size": files.original.len(),
+                "original_mime_type": doc["mime_type"],
+                "media_filename": format!("{id:07}{}", extension(text(doc, "original_file_name"))),
+                "original_filename": doc["original_file_name"],
+                "has_archive_version": archive.is_some(),
+                "original_metadata": [],
+                "archive_checksum": archive.map(|file| checksum(file)),
+                "archive_size": archive.map(Vec::len),
+                "archive_media_filename": doc["archived_file_name"],
+                "archive_metadata": archive.map(|_| json!([])),
+                "lang": "en",
+            }),
+        )
+    }
+
+    /// Suggests the objects whose matching rule matches the content, and the
+    /// ISO dates in it. Regular expressions, fuzzy, and automatic matching
+    /// fall back to looking for the object's name.
+    fn suggestions(&self, id: u64) -> ResponseTemplate {
+        let Some(doc) = self.documents.get(&id) else {
+            return no_match("Document");
+        };
+        let content = text(doc, "content").to_lowercase();
+        let matching = |kind: Kind| -> Vec<u64> {
+            self.objects(kind)
+                .iter()
+                .filter(|(_, object)| suggests(object, &content))
+                .map(|(id, _)| *id)
+                .collect()
+        };
+        let dates: Vec<String> = content
+            .split(|c: char| !(c.is_ascii_digit() || c == '-'))
+            .filter_map(|word| word.parse::<Date>().ok())
+            .map(|date| date.to_string())
+            .collect();
+        json_response(
+            200,
+            &json!({
+                "correspondents": matching(Kind::Correspondent),
+                "tags": matching(Kind::Tag),
+                "document_types": matching(Kind::DocumentType),
+                "storage_paths": matching(Kind::StoragePath),
+                "dates": dates,
+            }),
+        )
+    }
+
+    fn bulk_edit(&mut self, request: &Request) -> ResponseTemplate {
+        let body: Value = request.body_json().unwrap_or_default();
+        let documents = id_list(body.get("documents"));
+        if documents.is_empty() || !documents.iter().all(|id| self.documents.contains_key(id)) {
+            return json_response(
+                400,
+                &json!({"documents": ["Some documents don't exist or were specified twice."]}),
+            );
+        }
+        let parameters = &body["parameters"];
+        let method = body["method"].as_str().unwrap_or_default();
+
+        let (set, add, remove): (Option<(&str, Value)>, Vec<u64>, Vec<u64>) = match method {
+            "set_correspondent" | "set_document_type" => {
+                let (kind, field) = if method == "set_correspondent" {
+                    (Kind::Correspondent, "correspondent")
+                } else {
+                    (Kind::DocumentType, "document_type")
+                };
+                let value = parameters.get(field).cloned().unwrap_or(Value::Null);
+                if !self.is_reference(kind, &value) {
+                    return json_response(400, &json!({ field: ["Invalid value."] }));
+                }
+                (Some((field, value)), Vec::new(), Vec::new())
+            }
+            "add_tag" => (None, id_list(parameters.get("tag")), Vec::new()),
+            "remove_tag" => (None, Vec::new(), id_list(parameters.get("tag"))),
+            "modify_tags" => (
+                None,
+                id_list(parameters.get("add_tags")),
+                id_list(parameters.get("remove_tags")),
+            ),
+            "delete" => {
+                for id in &documents {
+                    self.documents.remove(id);
+                    self.files.remove(id);
+                }
+                return json_response(200, &json!({"result": "OK"}));
+            }
+            other => {
+                return json_response(
+                    400,
+                    &json!({"method": [format!("\"{other}\" is not a valid choice.")]}),
+                );
+            }
+        };
+        if !add.iter().all(|id| self.tags.contains_key(id)) {
+            return json_response(400, &json!({"tags": ["Invalid value."]}));
+        }
+
+        for id in documents {
+            let Some(doc) = self.documents.get_mut(&id) else {
+                continue;
+            };
+            if let Some((field, value)) = &set {
+                doc.insert((*field).to_string(), value.clone());
+            }
+            let mut tags = id_list(doc.get("tags"));
+            tags.retain(|tag| !remove.contains(tag));
+            for tag in &add {
+                if !tags.contains(tag) {
+                    tags.push(*tag);
+                }
+            }
+            doc.insert("tags".to_string(), json!(tags));
+            doc.insert("modified".to_string(), json!(Timestamp::now().to_string()));
+        }
+        json_response(200, &json!({"result": "OK"}))
+    }
+
+    fn list(&self, kind: Kind, request: &Request) -> ResponseTemplate {
+        let query = query(&request.url);
+        let objects = self
+            .objects(kind)
+            .values()
+            .filter(|object| {
+                let name = text(object, "name").to_lowercase();
+                query.iter().all(|(key, value)| match key.as_str() {
+                    "id__in" => ids(value).any(|id| object["id"] == json!(id)),
+                    "name__iexact" => name == value.to_lowercase(),
+                    "name__icontains" => name.contains(&value.to_lowercase()),
+                    "name__istartswith" => name.starts_with(&value.to_lowercase()),
+                    _ => true,
+                })
+            })
+            .map(|object| self.render(kind, object))
+            .collect();
+        page(&absolute_url(request), &query, self.page_size, objects)
+    }
+
+    fn create(&mut self, kind: Kind, request: &Request) -> ResponseTemplate {
+        let Some(body) = body_object(request) else {
+            return invalid_body();
+        };
+        let mut object = if kind.has_matching() {
+            taxonomy_object("", &seed::Matching::default())
+        } else {
+            into_object(json!({"name": "", "extra_data": null}))
+        };
+        if kind == Kind::Tag {
+            let tag = seed::Tag::new("");
+            object.insert("color".to_string(), json!(tag.color));
+            object.insert("is_inbox_tag".to_string(), json!(false));
+            object.insert("parent".to_string(), Value::Null);
+        }
+        let required: &[&str] = match kind {
+            Kind::StoragePath => &["name", "path"],
+            Kind::CustomField => &["name", "data_type"],
+            _ => &["name"],
+        };
+        if let Some(field) = required.iter().find(|field| !body.contains_key(**field)) {
+            return json_response(400, &json!({ *field: ["This field is required."] }));
+        }
+        match self.apply(kind, None, &mut object, body) {
+            Ok(()) => {
+                let id = self.insert(kind, None, object);
+                let object = &self.objects(kind)[&id];
+                json_response(201, &Value::Object(self.render(kind, object)))
+            }
+            Err(errors) => json_response(400, &Value::Object(errors)),
+        }
+    }
+
+    fn update(&mut self, kind: Kind, id: u64, request: &Request) -> ResponseTemplate {
+        let Some(mut object) = self.objects(kind).get(&id).cloned() else {
+            return no_match(kind.label());
+        };
+        let Some(body) = body_object(request) else {
+            return invalid_body();
+        };
+        match self.apply(kind, Some(id), &mut object, body) {
+            Ok(()) => {
+                let rendered = self.render(kind, &object);
+                self.objects_mut(kind).insert(id, object);
+                json_response(200, &Value::Object(rendered))
+            }
+            Err(errors) => json_response(400, &Value::Object(errors)),
+        }
+    }
+
+    /// Validates the writable fields of `body` and applies them to `object`.
+    fn apply(
+        &self,
+        kind: Kind,
+        id: Option<u64>,
+        object: &mut Object,
+        body: Object,
+    ) -> Result<(), Object> {
+        let mut errors = Object::new();
+        for (key, value) in body {
+            let error = match key.as_str() {
+                "name" => match value.as_str().map(str::trim) {
+                    None | Some("") => Some("This field may not be blank.".to_string()),
+                    Some(name) if self.name_taken(kind, id, name) => {
+                        Some(format!("{} with this name already exists.", kind.label()))
+                    }
+                    Some(name) => {
+                        object.insert("slug".to_string(), json!(slugify(name)));
+                        None
+                    }
+                },
+                "match" if kind.has_matching() => {
+                    (!value.is_string()).then(|| "Not a valid string.".to_string())
+                }
+                "matching_algorithm" if kind.has_matching() => value
+                    .as_u64()
+                    .and_then(|v| u8::try_from(v).ok())
+                    .and_then(|v| pngx_client::MatchingAlgorithm::try_from(v).ok())
+                    .is_none()
+                    .then(|| format!("\"{value}\" is not a valid choice.")),
+                "is_insensitive" if kind.has_matching() => {
+                    (!value.is_boolean()).then(|| "Must be a valid boolean.".to_string())
+                }
+                "path" if kind == Kind::StoragePath => {
+                    (!value.is_string()).then(|| "Not a valid string.".to_string())
+                }
+                "data_type" if kind == Kind::CustomField => match value.as_str() {
+                    Some(data_type) if DATA_TYPES.contains(&data_type) => None,
+                    _ => Some(format!("\"{value}\" is not a valid choice.")),
+                },
+                "extra_data" if kind == Kind::CustomField => (!value.is_object()
+                    && !value.is_null())
+                .then(|| "Must be a valid object.".to_string()),
+                "color" if kind == Kind::Tag => match value.as_str() {
+                    Some(color) => {
+                        object.insert("text_color".to_string(), json!(text_color(color)));
+                        None
+                    }
+                    None => Some("Not a valid string.".to_string()),
+                },
+                "is_inbox_tag" if kind == Kind::Tag => {
+                    (!value.is_boolean()).then(|| "Must be a valid boolean.".to_string())
+                }
+                "parent" if kind == Kind::Tag => (value.as_u64().is_some_and(|p| Some(p) == id)
+                    || !self.is_reference(Kind::Tag, &value))
+                .then(|| "Invalid parent tag.".to_string()),
+                // Read-only and unknown fields are ignored.
+                _ => continue,
+            };
+            if let Some(message) = error {
+                errors.insert(key, json!([message]));
+            } else {
+                let value = match &value {
+                    Value::String(name) if key == "name" => json!(name.trim()),
+                    _ => value,
+                };
+                object.insert(key, value);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn name_taken(&self, kind: Kind, id: Option<u64>, name: &str) -> bool {
+        self.objects(kind).iter().any(|(other, object)| {
+            Some(*other) != id && text(object, "name").eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// Whether `value` is null or the ID of an existing object of `kind`.
+    fn is_reference(&self, kind: Kind, value: &Value) -> bool {
+        value.is_null()
+            || value
+                .as_u64()
+                .is_some_and(|id| self.objects(kind).contains_key(&id))
+    }
+
+    /// Deletes an object and removes it from all documents, like the
+    /// database's `SET NULL` and many-to-many cleanup.
+    fn delete(&mut self, kind: Kind, id: u64) -> ResponseTemplate {
+        if self.objects_mut(kind).remove(&id).is_none() {
+            return no_match(kind.label());
+        }
+        let field = kind.document_field();
+        for doc in self.documents.values_mut() {
+            match doc.get_mut(field) {
+                Some(Value::Array(ids)) => ids
+                    .retain(|other| *other != json!(id) && other.get("field") != Some(&json!(id))),
+                Some(value) if *value == json!(id) => *value = Value::Null,
+                _ => {}
+            }
+        }
+        if kind == Kind::Tag {
+            for tag in self.tags.values_mut() {
+                if tag["parent"] == json!(id) {
+                    tag.insert("parent".to_string(), Value::Null);
+                }
+            }
+        }
+        ResponseTemplate::new(204)
+    }
+}
+
+/// Sorts, paginates, and trims `items` to the requested `fields`, in the
+/// shape of a Django REST Framework page.
+fn page(url: &Url, query: &Query, default_size: usize, mut items: Vec<Object>) -> ResponseTemplate {
+    if let Some(ordering) = query.get("ordering") {
+        let (descending, field) = match ordering.strip_prefix('-') {
+            Some(field) => (true, field),
+            None => (false, ordering.as_str()),
+        };
+        items.sort_by(|a, b| {
+            let order = compare(&a[field], &b[field]);
+            if descending { order.reverse() } else { order }
+        });
+    }
+
+    let size = query
+        .get("page_size")
+        .and_then(|size| size.parse().ok())
+        .filter(|&size| size > 0)
+        .unwrap_or(default_size);
+    let number = query
+        .get("page")
+        .map_or(Ok(1), |page| page.parse::<usize>())
+        .unwrap_or(0);
+    let count = items.len();
+    let start = number.saturating_sub(1) * size;
+    if number == 0 || (number > 1 && start >= count) {
+        return error(404, "Invalid page.");
+    }
+
+    let all: Vec<&Value> = items.iter().filter_map(|item| item.get("id")).collect();
+    let all = json!(all);
+    let fields: Option<Vec<&str>> = query.get("fields").map(|f| f.split(',').collect());
+    let results: Vec<Value> = items
+        .iter()
+        .skip(start)
+        .take(size)
+        .map(|item| match &fields {
+            Some(fields) => Value::Object(
+                item.iter()
+                    .filter(|(key, _)| fields.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            ),
+            None => Value::Object(item.clone()),
+        })
+        .collect();
+    let next = (start + size < count).then(|| page_url(url, number + 1));
+    let previous = (number > 1).then(|| page_url(url, number - 1));
+    json_response(
+        200,
+        &json!({
+            "count": count,
+            "next": next,
+            "previous": previous,
+            "all": all,
+            "results": results,
+        }),
+    )
+}
+
+/// The URL the client requested, with the host from the `Host` header like
+/// Django builds it for `next` links.
+fn absolute_url(request: &Request) -> Url {
+    let host = request
+        .headers
+        .get("Host")
+        .and_then(|host| host.to_str().ok());
+    let mut target = request.url.path().to_string();
+    if let Some(query) = request.url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    host.and_then(|host| Url::parse(&format!("http://{host}{target}")).ok())
+        .unwrap_or_else(|| request.url.clone())
+}
+
+/// `url` with its `page` parameter replaced.
+fn page_url(url: &Url, number: usize) -> String {
+    let mut url = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != "page")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("page", &number.to_string());
+    url.to_string()
+}
+
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(a), Value::String(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
+        _ => Ordering::Equal,
+    }
+}
+
+fn query(url: &Url) -> Query {
+    url.query_pairs()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect()
+}
+
+/// Parses a comma-separated ID list, skipping invalid entries.
+fn ids(value: &str) -> impl Iterator<Item = u64> + '_ {
+    value.split(',').filter_map(|id| id.trim().parse().ok())
+}
+
+/// Reads an ID or an array of IDs.
+fn id_list(value: Option<&Value>) -> Vec<u64> {
+    match value {
+        Some(Value::Array(ids)) => ids.iter().filter_map(Value::as_u64).collect(),
+        Some(value) => value.as_u64().into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+fn text<'a>(object: &'a Object, key: &str) -> &'a str {
+    object.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn timestamp(object: &Object, key: &str) -> Option<Timestamp> {
+    text(object, key).parse().ok()
+}
+
+fn next_id(objects: &BTreeMap<u64, Object>) -> u64 {
+    objects.keys().next_back().map_or(1, |id| id + 1)
+}
+
+fn into_object(value: Value) -> Object {
+    match value {
+        Value::Object(object) => object,
+        _ => Object::new(),
+    }
+}
+
+/// Whether an object's matching rule matches lowercased content.
+fn suggests(object: &Object, content: &str) -> bool {
+    let pattern = text(object, "match").to_lowercase();
+    let mut words = pattern.split_whitespace();
+    match object.get("matching_algorithm").and_then(Value::as_u64) {
+        Some(0) | None => false,
+        Some(1) => words.any(|word| content.contains(word)),
+        Some(2) => !pattern.trim().is_empty() && words.all(|word| content.contains(word)),
+        Some(3) => !pattern.is_empty() && content.contains(&pattern),
+        Some(_) => content.contains(&text(object, "name").to_lowercase()),
+    }
+}
+
+fn taxonomy_object(name: &str, matching: &seed::Matching) -> Object {
+    into_object(json!({
+        "slug": slugify(name),
+        "name": name,
+        "match": matching.pattern,
+        "matching_algorithm": u8::from(matching.algorithm),
+        "is_insensitive": true,
+    }))
+}
+
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+/// Returns the text color Paperless-ngx computes for a tag color: white on
+/// dark colors, black otherwise.
+fn text_color(color: &str) -> &'static str {
+    let hex = color.trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .map(u32::from)
+    };
+    match (channel(0), channel(2), channel(4)) {
+        (Some(r), Some(g), Some(b)) if 299 * r + 587 * g + 114 * b < 128_000 => "#ffffff",
+        _ => "#000000",
+    }
+}
+
+/// The hex MD5 digest Paperless-ngx stores as a file's checksum.
+fn checksum(bytes: &[u8]) -> String {
+    format!("{:x}", Md5::digest(bytes))
+}
+
+/// The extension of `name` including the dot, or `.pdf` if it has none.
+fn extension(name: &str) -> String {
+    name.rsplit_once('.').map_or_else(
+        || ".pdf".to_string(),
+        |(_, ext)| format!(".{}", ext.to_lowercase()),
+    )
+}
+
+fn mime_type(name: &str) -> &'static str {
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+fn body_object(request: &Request) -> Option<Object> {
+    match request.body_json::<Value>() {
+        Ok(Value::Object(object)) => Some(object),
+        _ => None,
+    }
+}
+
+fn invalid_body() -> ResponseTemplate {
+    json_response(
+        400,
+        &json!({"non_field_errors": ["Invalid data. Expected a dictionary."]}),
+    )
+}
+
+fn json_response(status: u16, body: &Value) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(body)
+}
+
+fn error(status: u16, detail: &str) -> ResponseTemplate {
+    json_response(status, &json!({ "detail": detail }))
+}
+
+fn not_found() -> ResponseTemplate {
+    error(404, "Not found.")
+}
+
+fn no_match(label: &str) -> ResponseTemplate {
+    error(404, &format!("No {label} matches the given query."))
+}
+
+fn method_not_allowed(method: &Method) -> ResponseTemplate {
+    error(405, &format!("Method \"{method}\" not allowed."))
+}
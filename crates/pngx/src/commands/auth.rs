@@ -1,35 +1,29 @@
 use std::fs;
 use std::io::{self, BufRead, IsTerminal, Write};
-use std::time::Duration;
 
 use anyhow::{Context, Result};
 use pngx_client::Client;
 
-use crate::config::{RawConfig, config_dir, config_file_path};
-
-pub fn login(url: Option<&str>, token: Option<&str>) -> Result<()> {
-    let (url, token) = match (url, token) {
-        (Some(u), Some(t)) => (u.to_string(), t.to_string()),
-        (None, None) => {
-            if !io::stdin().is_terminal() {
-                anyhow::bail!(
-                    "cannot run interactive login without a terminal.\n\
-                     Use: pngx auth login --url <URL> --token <TOKEN>"
-                );
-            }
-
-            let stdin = io::stdin();
-            let mut stdout = io::stdout();
-
-            print!("Paperless NGX URL: ");
-            stdout.flush()?;
-            let mut url = String::new();
-            stdin.lock().read_line(&mut url)?;
+use crate::config::{
+    ConfigError, ConfigFile, Overrides, RawConfig, TokenSource, ValidConfig, config_file_path,
+};
 
+pub fn login(
+    profile: Option<&str>,
+    url: Option<&str>,
+    token: Option<&str>,
+    username: Option<&str>,
+) -> Result<()> {
+    let (url, token) = match (url, token, username) {
+        (_, Some(_), Some(_)) => anyhow::bail!("--username cannot be used with --token"),
+        (url, None, Some(username)) => login_with_password(profile, url, username)?,
+        (Some(u), Some(t), None) => (u.to_string(), t.to_string()),
+        (None, None, None) => {
+            require_terminal()?;
+            let url = prompt("Paperless NGX URL: ")?;
             let token =
                 rpassword::prompt_password("API Token: ").context("failed to read token")?;
-
-            (url.trim().to_string(), token.trim().to_string())
+            (url, token.trim().to_string())
         }
         _ => {
             anyhow::bail!(
@@ -43,71 +37,162 @@ pub fn login(url: Option<&str>, token: Option<&str>) -> Result<()> {
         anyhow::bail!("URL and token must not be empty");
     }
 
-    let dir = config_dir();
-    fs::create_dir_all(&dir)
-        .with_context(|| format!("failed to create config directory: {}", dir.display()))?;
-
-    let content = format!("url = \"{url}\"\ntoken = \"{token}\"\n");
-    let path = config_file_path();
-    write_config_file(&path, content.as_bytes())
-        .with_context(|| format!("failed to write config file: {}", path.display()))?;
+    let mut file = ConfigFile::load()?;
+    file.set_credentials(profile, &url, &token)?;
+    // Make the first profile the default unless the top level already
+    // configures a server.
+    if let Some(name) = profile
+        && file.default_profile().is_none()
+        && !file.has_top_level_url()
+    {
+        file.set_default_profile(name);
+    }
+    file.save()?;
 
-    println!("Credentials saved to {}", path.display());
+    match profile {
+        Some(name) => println!(
+            "Credentials for profile '{name}' saved to {}",
+            file.path().display()
+        ),
+        None => println!("Credentials saved to {}", file.path().display()),
+    }
     Ok(())
 }
 
-#[cfg(unix)]
-fn write_config_file(path: &std::path::Path, content: &[u8]) -> io::Result<()> {
-    use std::fs::OpenOptions;
-    use std::os::unix::fs::OpenOptionsExt;
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .mode(0o600)
-        .open(path)?;
-    file.write_all(content)
+/// Prompts for the password, exchanges it for the user's API token, and
+/// checks that the token works before it is saved. Certificates, headers,
+/// and the proxy are taken from the config, as for other commands.
+fn login_with_password(
+    profile: Option<&str>,
+    url: Option<&str>,
+    username: &str,
+) -> Result<(String, String)> {
+    require_terminal()?;
+    let url = match url {
+        Some(url) => url.to_string(),
+        None => prompt("Paperless NGX URL: ")?,
+    };
+    if url.is_empty() {
+        anyhow::bail!("URL must not be empty");
+    }
+
+    let overrides = Overrides {
+        url: Some(&url),
+        ..Overrides::default()
+    };
+    let config = RawConfig::load_for_login(profile, &overrides)?.validate_without_token()?;
+
+    let password = rpassword::prompt_password(format!("Password for {username}: "))
+        .context("failed to read password")?;
+    let token = Client::obtain_token(crate::client_builder(&config), username, &password)
+        .context("failed to obtain API token")?;
+
+    let config = ValidConfig {
+        token: token.clone(),
+        ..config
+    };
+    let settings = crate::client_builder(&config)
+        .build()?
+        .ui_settings()
+        .context("failed to verify API token")?;
+    println!(
+        "Logged in as {} (paperless-ngx {})",
+        settings.user.display_name(),
+        settings.settings.version
+    );
+    Ok((url, token))
+}
+
+fn require_terminal() -> Result<()> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!(
+            "cannot run interactive login without a terminal.\n\
+             Use: pngx auth login --url <URL> --token <TOKEN>"
+        );
+    }
+    Ok(())
 }
 
-#[cfg(not(unix))]
-fn write_config_file(path: &std::path::Path, content: &[u8]) -> io::Result<()> {
-    fs::write(path, content)
+fn prompt(label: &str) -> Result<String> {
+    let mut stdout = io::stdout();
+    print!("{label}");
+    stdout.flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_string())
 }
 
-pub fn logout() -> Result<()> {
-    let path = config_file_path();
-    if path.exists() {
-        fs::remove_file(&path)
-            .with_context(|| format!("failed to remove config file: {}", path.display()))?;
-        println!("Logged out. Config removed from {}", path.display());
+/// Removes `profile`, else the default profile, else the top-level
+/// credentials. Other profiles and settings are kept.
+pub fn logout(profile: Option<&str>) -> Result<()> {
+    let mut file = ConfigFile::load()?;
+    let profile = profile
+        .or_else(|| file.default_profile())
+        .map(str::to_string);
+    if let Some(name) = profile {
+        if !file.remove_profile(&name) {
+            return Err(ConfigError::UnknownProfile(name).into());
+        }
+        file.save()?;
+        println!(
+            "Logged out. Profile '{name}' removed from {}",
+            file.path().display()
+        );
+        return Ok(());
+    }
+
+    if file.remove_credentials() {
+        file.save()?;
+        println!(
+            "Logged out. Credentials removed from {}",
+            file.path().display()
+        );
     } else {
-        println!("No config file found at {}", path.display());
+        println!("No credentials found in {}", file.path().display());
     }
     Ok(())
 }
 
-pub fn status(url_override: Option<&str>, token_override: Option<&str>) -> Result<()> {
-    let path = config_file_path();
-    if !path.exists() {
-        println!("Not configured. Run `pngx auth login` to set up.");
-        return Ok(());
+pub fn status(overrides: &Overrides, all: bool) -> Result<()> {
+    if all {
+        return status_all();
     }
 
-    let content = fs::read_to_string(&path)
-        .with_context(|| format!("failed to read config file: {}", path.display()))?;
+    let raw = RawConfig::load(overrides)?;
+    let path = config_file_path();
+    if path.exists() {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read config file: {}", path.display()))?;
 
-    println!("Config file: {}", path.display());
-    println!();
+        println!("Config file: {}", path.display());
+        println!();
 
-    for line in content.lines() {
-        if line.trim_start().starts_with("token") {
-            println!("token = \"***\"");
-        } else {
-            println!("{line}");
+        let mut in_headers = false;
+        for line in content.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('[') {
+                let table = trimmed.trim_start_matches('[').split(']').next();
+                in_headers = table
+                    .map(str::trim)
+                    .is_some_and(|name| name == "headers" || name.ends_with(".headers"));
+            }
+            match secret_key(line, in_headers) {
+                Some(key) => println!("{key} = \"***\""),
+                None => println!("{line}"),
+            }
         }
+        println!();
+    } else if raw.url.is_empty() && *raw.token_source() == TokenSource::Missing {
+        println!("Not configured. Run `pngx auth login` to set up.");
+        return Ok(());
+    }
+
+    if let Some(name) = raw.profile() {
+        println!("Profile: {name}");
     }
+    println!("Token: {}", raw.token_source());
 
-    match try_verify_server(url_override, token_override) {
+    match try_verify_server(raw) {
         Ok((user, version)) => {
             println!("\nUser: {user}");
             println!("Server: connected (paperless-ngx {version})");
@@ -118,18 +203,104 @@ pub fn status(url_override: Option<&str>, token_override: Option<&str>) -> Resul
     Ok(())
 }
 
-fn try_verify_server(
-    url_override: Option<&str>,
-    token_override: Option<&str>,
-) -> anyhow::Result<(String, String)> {
-    let raw = RawConfig::load(url_override, token_override)?;
+/// Verifies every profile, and the top-level settings if they configure a
+/// server.
+fn status_all() -> Result<()> {
+    let file = ConfigFile::load()?;
+    let mut profiles: Vec<Option<&str>> = file.profiles().into_iter().map(Some).collect();
+    if file.has_top_level_url() {
+        profiles.insert(0, None);
+    }
+    if profiles.is_empty() {
+        println!("Not configured. Run `pngx auth login` to set up.");
+        return Ok(());
+    }
+
+    println!("Config file: {}", file.path().display());
+    for profile in profiles {
+        let marker = if profile == file.default_profile() {
+            "*"
+        } else {
+            " "
+        };
+        println!();
+        println!("{marker} {}", profile.unwrap_or("(top level)"));
+
+        // Environment variables still apply, but the URL and token flags
+        // would make every profile look the same.
+        let raw = match RawConfig::load_profile(profile, &Overrides::default()) {
+            Ok(raw) => raw,
+            Err(err) => {
+                println!("  Error: {err:#}");
+                continue;
+            }
+        };
+        println!("  URL: {}", raw.url);
+        println!("  Token: {}", raw.token_source());
+        match try_verify_server(raw) {
+            Ok((user, version)) => {
+                println!("  User: {user}");
+                println!("  Server: connected (paperless-ngx {version})");
+            }
+            Err(err) => println!("  Server: could not verify: {err:#}"),
+        }
+    }
+    Ok(())
+}
+
+/// Returns the key of a line whose value must not be printed: the token and
+/// any header, since headers often carry proxy credentials. Headers may be
+/// an inline table, a `[headers]` table, or dotted `headers.*` keys.
+fn secret_key(line: &str, in_headers: bool) -> Option<&str> {
+    let (key, _) = line.split_once('=')?;
+    let key = key.trim();
+    let secret = key == "token"
+        || key == "headers"
+        || key.starts_with("headers.")
+        || (in_headers && !key.starts_with('#') && !key.starts_with('['));
+    secret.then_some(key)
+}
+
+fn try_verify_server(raw: RawConfig) -> anyhow::Result<(String, String)> {
     let config = raw.validate()?;
-    let client = Client::builder(config.url.as_str(), &config.token)
-        .timeout(Duration::from_secs(config.timeout))
-        .page_size(config.page_size)
-        .build()?;
+    let client = crate::client_builder(&config).build()?;
     let settings = client.ui_settings()?;
     let user = settings.user.display_name();
     let version = settings.settings.version;
     Ok((user, version))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_key() {
+        assert_eq!(secret_key("token = \"abc\"", false), Some("token"));
+        assert_eq!(secret_key("  token = \"abc\"", false), Some("token"));
+        assert_eq!(secret_key("url = \"https://x\"", false), None);
+        assert_eq!(secret_key("token_command = \"pass x\"", false), None);
+        assert_eq!(
+            secret_key("headers = { Authorization = \"Basic x\" }", false),
+            Some("headers")
+        );
+        assert_eq!(
+            secret_key("X-Proxy-Auth = \"x\"", true),
+            Some("X-Proxy-Auth")
+        );
+        assert_eq!(secret_key("X-Proxy-Auth = \"x\"", false), None);
+    }
+
+    #[test]
+    fn test_secret_key_dotted_headers() {
+        assert_eq!(
+            secret_key("headers.Authorization = \"Basic x\"", false),
+            Some("headers.Authorization")
+        );
+        // The same inside a `[profiles.x]` table.
+        assert_eq!(
+            secret_key("  headers.\"X-Proxy-Auth\" = \"x\"", false),
+            Some("headers.\"X-Proxy-Auth\"")
+        );
+    }
+}
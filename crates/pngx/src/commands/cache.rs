@@ -0,0 +1,13 @@
+use anyhow::{Context, Result};
+
+use crate::config::cache_dir;
+use crate::resolve::clear_cache;
+
+pub fn clear() -> Result<()> {
+    let dir = cache_dir();
+    let removed =
+        clear_cache(&dir).with_context(|| format!("failed to clear cache in {}", dir.display()))?;
+    let noun = if removed == 1 { "entry" } else { "entries" };
+    println!("Removed {removed} cache {noun} from {}", dir.display());
+    Ok(())
+}
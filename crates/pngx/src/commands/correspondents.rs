@@ -1,10 +1,61 @@
-use anyhow::Result;
-use pngx_client::Client;
+use anyhow::{Result, bail};
+use pngx_client::{BulkEdit, Client, CorrespondentRequest};
 
-use crate::output::{FieldFilter, OutputFormat};
+use super::ObjectKind;
+use crate::output::{FieldFilter, OutputOptions};
 
-pub fn list(client: &Client, format: OutputFormat, fields: Option<&FieldFilter>) -> Result<()> {
+pub fn list(
+    client: &Client,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
     let (correspondents, _) = client.collect_correspondents(None)?;
-    super::print_all(format, &correspondents, fields)?;
+    super::print_all(output, &correspondents, fields)?;
     Ok(())
 }
+
+pub fn create(
+    client: &Client,
+    request: &CorrespondentRequest,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
+    let correspondent = client.create_correspondent(request)?;
+    println!("{}", output.format_detail(&correspondent, fields)?);
+    Ok(())
+}
+
+pub fn edit(
+    client: &Client,
+    id: u64,
+    request: &CorrespondentRequest,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
+    if *request == CorrespondentRequest::default() {
+        bail!(
+            "nothing to change. Pass at least one option (see `pngx correspondents edit --help`)"
+        );
+    }
+    let correspondent = client.update_correspondent(id, request)?;
+    println!("{}", output.format_detail(&correspondent, fields)?);
+    Ok(())
+}
+
+const CORRESPONDENTS: ObjectKind = ObjectKind {
+    singular: "correspondent",
+    plural: "correspondents",
+    documents_filter: "correspondent__id__in",
+    name: |client, id| client.correspondent(id).map(|object| object.name),
+    delete: Client::delete_correspondent,
+    assign: |id| BulkEdit::SetCorrespondent(Some(id)),
+};
+
+pub fn delete(client: &Client, ids: &[u64], yes: bool) -> Result<()> {
+    super::delete_objects(client, &CORRESPONDENTS, ids, yes)
+}
+
+/// Reassign all documents of `sources` to `target`, then delete `sources`.
+pub fn merge(client: &Client, sources: &[u64], target: u64, yes: bool) -> Result<()> {
+    super::merge_objects(client, &CORRESPONDENTS, sources, target, yes)
+}
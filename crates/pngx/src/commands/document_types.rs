@@ -1,10 +1,61 @@
-use anyhow::Result;
-use pngx_client::Client;
+use anyhow::{Result, bail};
+use pngx_client::{BulkEdit, Client, DocumentTypeRequest};
 
-use crate::output::{FieldFilter, OutputFormat};
+use super::ObjectKind;
+use crate::output::{FieldFilter, OutputOptions};
 
-pub fn list(client: &Client, format: OutputFormat, fields: Option<&FieldFilter>) -> Result<()> {
+pub fn list(
+    client: &Client,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
     let (types, _) = client.collect_document_types(None)?;
-    super::print_all(format, &types, fields)?;
+    super::print_all(output, &types, fields)?;
     Ok(())
 }
+
+pub fn create(
+    client: &Client,
+    request: &DocumentTypeRequest,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
+    let document_type = client.create_document_type(request)?;
+    println!("{}", output.format_detail(&document_type, fields)?);
+    Ok(())
+}
+
+pub fn edit(
+    client: &Client,
+    id: u64,
+    request: &DocumentTypeRequest,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
+    if *request == DocumentTypeRequest::default() {
+        bail!(
+            "nothing to change. Pass at least one option (see `pngx document-types edit --help`)"
+        );
+    }
+    let document_type = client.update_document_type(id, request)?;
+    println!("{}", output.format_detail(&document_type, fields)?);
+    Ok(())
+}
+
+const DOCUMENT_TYPES: ObjectKind = ObjectKind {
+    singular: "document type",
+    plural: "document types",
+    documents_filter: "document_type__id__in",
+    name: |client, id| client.document_type(id).map(|object| object.name),
+    delete: Client::delete_document_type,
+    assign: |id| BulkEdit::SetDocumentType(Some(id)),
+};
+
+pub fn delete(client: &Client, ids: &[u64], yes: bool) -> Result<()> {
+    super::delete_objects(client, &DOCUMENT_TYPES, ids, yes)
+}
+
+/// Reassign all documents of `sources` to `target`, then delete `sources`.
+pub fn merge(client: &Client, sources: &[u64], target: u64, yes: bool) -> Result<()> {
+    super::merge_objects(client, &DOCUMENT_TYPES, sources, target, yes)
+}
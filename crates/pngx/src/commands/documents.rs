@@ -3,60 +3,84 @@ use std::path::PathBuf;
 
 use anyhow::{Context, Result, bail};
 use pngx_client::{Client, DocumentVersion};
+use serde::Serialize;
 use url::Url;
 
-use crate::output::{FieldFilter, OutputFormat, resolve_documents};
-use crate::resolve::NameResolver;
+use crate::output::{
+    DetailView, FieldFilter, OutputFormat, OutputOptions, Tabular, raw_pages, resolve_documents,
+    resolve_pages,
+};
+use crate::resolve::{NameCache, NameResolver};
 
 pub fn list(
     client: &Client,
-    format: OutputFormat,
+    cache: &NameCache,
+    output: OutputOptions<'_>,
     limit: Option<usize>,
     fields: Option<&FieldFilter>,
+    raw: bool,
 ) -> Result<()> {
-    let (docs, total) = client.collect_documents(limit)?;
-    let names = NameResolver::fetch(client, fields)?;
-    let docs = resolve_documents(&docs, &names);
-    super::print_results(format, &docs, total, fields)?;
-    Ok(())
+    let empty = "No documents found";
+    if raw {
+        let pages = raw_pages(client.document_pages().all_fields());
+        return super::print_results(output, pages, limit, fields, empty);
+    }
+    let names = NameResolver::cached(fields, cache);
+    let pages = resolve_pages(client, client.document_pages(), names, cache);
+    super::print_results(output, pages, limit, fields, empty)
 }
 
 pub fn get(
     client: &Client,
+    cache: &NameCache,
     ids: &[u64],
-    format: OutputFormat,
+    output: OutputOptions<'_>,
     fields: Option<&FieldFilter>,
+    raw: bool,
 ) -> Result<()> {
-    let names = NameResolver::fetch(client, fields)?;
-
     let mut docs = Vec::with_capacity(ids.len());
     for &id in ids {
         docs.push(client.document(id)?);
     }
-    let resolved = resolve_documents(&docs, &names);
+    if raw {
+        return print_documents(output, &docs, fields);
+    }
+    let names = NameResolver::fetch(client, &docs, fields, cache)?;
+    print_documents(output, &resolve_documents(&docs, &names), fields)
+}
 
-    if ids.len() == 1 {
-        println!("{}", format.format_detail(&resolved[0], fields)?);
-    } else {
-        match format {
-            OutputFormat::Json => {
-                let value = serde_json::to_value(&resolved)?;
-                let output = match fields {
-                    Some(f) => f.filter_json_array(value),
-                    None => value,
-                };
-                println!("{}", serde_json::to_string_pretty(&output)?);
-            }
-            OutputFormat::Ndjson => {
-                println!("{}", format.format_list(&resolved, fields)?);
-            }
-            OutputFormat::Markdown => {
-                for (i, doc) in resolved.iter().enumerate() {
-                    if i > 0 {
-                        println!();
-                    }
-                    println!("{}", format.format_detail(doc, fields)?);
+/// Print a single document as a detail view, or several as a list. CSV and
+/// TSV always use the list columns, however many documents there are.
+fn print_documents<T: Tabular + DetailView + Serialize>(
+    output: OutputOptions<'_>,
+    docs: &[T],
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
+    let delimited = matches!(output.format, OutputFormat::Csv | OutputFormat::Tsv);
+    if let [doc] = docs
+        && !delimited
+    {
+        println!("{}", output.format_detail(doc, fields)?);
+        return Ok(());
+    }
+    match output.format {
+        OutputFormat::Json => {
+            let value = serde_json::to_value(docs)?;
+            let output = match fields {
+                Some(f) => f.filter_json_array(value),
+                None => value,
+            };
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        OutputFormat::Ndjson | OutputFormat::Csv | OutputFormat::Tsv => {
+            println!("{}", output.format_list(docs, fields)?);
+        }
+        OutputFormat::Markdown => {
+            for (i, doc) in docs.iter().enumerate() {
+                if i > 0 {
+                    println!();
                 }
+                println!("{}", output.format_detail(doc, fields)?);
             }
         }
     }
@@ -119,13 +143,18 @@ pub fn download(
     Ok(())
 }
 
+/// The page of a document in the web UI.
+pub fn document_url(url: &Url, id: u64) -> String {
+    format!(
+        "{}/documents/{}/details",
+        url.as_str().trim_end_matches('/'),
+        id
+    )
+}
+
 pub fn open(url: &Url, ids: &[u64]) -> Result<()> {
     for &id in ids {
-        let doc_url = format!(
-            "{}/documents/{}/details",
-            url.as_str().trim_end_matches('/'),
-            id
-        );
+        let doc_url = document_url(url, id);
         open::that_detached(&doc_url)?;
         eprintln!("Opened {doc_url}");
     }
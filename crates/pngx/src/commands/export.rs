@@ -0,0 +1,518 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use jiff::Timestamp;
+use md5::{Digest, Md5};
+use pngx_client::{
+    Client, Correspondent, CustomField, Document, DocumentMetadata, DocumentType, DocumentVersion,
+    StoragePath, Tag,
+};
+use serde_json::{Value, json};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use super::sync::{component, create_parent, extension, part_path, remove_file, sanitize};
+
+/// The manifest `document_exporter` writes and `document_importer` reads.
+pub(super) const MANIFEST_FILE: &str = "manifest.json";
+
+/// Records the server version, which `document_importer` compares with its
+/// own.
+const METADATA_FILE: &str = "metadata.json";
+
+// Keys `document_exporter` adds to document records for their files.
+pub(super) const EXPORTED_FILE_NAME: &str = "__exported_file_name__";
+const EXPORTED_ARCHIVE_NAME: &str = "__exported_archive_name__";
+const EXPORTED_THUMBNAIL_NAME: &str = "__exported_thumbnail_name__";
+
+pub struct ExportOptions {
+    /// Write a zip archive instead of loose files.
+    pub zip: bool,
+    /// Compare MD5 checksums instead of size and modification time to find
+    /// files that are already exported.
+    pub compare_checksums: bool,
+    /// Only export documents matching this full-text query.
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Original,
+    Archive,
+    Thumbnail,
+}
+
+/// A file exported for a document.
+struct ExportFile {
+    kind: FileKind,
+    name: String,
+    size: Option<u64>,
+    checksum: Option<String>,
+}
+
+#[derive(Default)]
+struct Summary {
+    downloaded: usize,
+    unchanged: usize,
+    removed: usize,
+}
+
+/// Where the export is written.
+enum Target {
+    Dir(PathBuf),
+    Zip {
+        writer: Box<ZipWriter<fs::File>>,
+        tmp: PathBuf,
+        path: PathBuf,
+    },
+}
+
+/// The objects documents refer to. They are exported in full, even when
+/// only some documents are.
+struct Taxonomy {
+    correspondents: Vec<Correspondent>,
+    tags: Vec<Tag>,
+    document_types: Vec<DocumentType>,
+    storage_paths: Vec<StoragePath>,
+    custom_fields: Vec<CustomField>,
+}
+
+pub fn export(client: &Client, dir: &Path, options: &ExportOptions) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create directory: {}", dir.display()))?;
+    let mut target = if options.zip {
+        Target::zip(dir)?
+    } else {
+        Target::Dir(dir.to_path_buf())
+    };
+
+    let taxonomy = Taxonomy::fetch(client)?;
+    let mut manifest = taxonomy.records();
+    let mut filters = vec![("ordering", "id")];
+    if let Some(query) = &options.query {
+        filters.push(("query", query));
+    }
+    let (docs, _) = client.collect_matching_documents(&filters, None)?;
+    let correspondents: HashMap<u64, &str> = taxonomy
+        .correspondents
+        .iter()
+        .map(|c| (c.id, c.name.as_str()))
+        .collect();
+
+    let mut summary = Summary::default();
+    let mut names = HashSet::new();
+    let mut notes = Vec::new();
+    let mut instances = Vec::new();
+    for doc in &docs {
+        let metadata = client
+            .document_metadata(doc.id)
+            .with_context(|| format!("failed to fetch metadata of document {}", doc.id))?;
+        let base = base_name(doc, &correspondents, &mut names);
+        let files = export_files(doc, &metadata, &base);
+        for file in &files {
+            if target.is_current(file, doc.modified, options.compare_checksums)? {
+                summary.unchanged += 1;
+                continue;
+            }
+            target
+                .write_with(&file.name, doc.modified, |mut out| {
+                    match file.kind {
+                        FileKind::Original => {
+                            client.download_document(doc.id, DocumentVersion::Original, &mut out)
+                        }
+                        FileKind::Archive => {
+                            client.download_document(doc.id, DocumentVersion::Archived, &mut out)
+                        }
+                        FileKind::Thumbnail => client.download_thumbnail(doc.id, &mut out),
+                    }?;
+                    Ok(())
+                })
+                .with_context(|| format!("failed to export document {}", doc.id))?;
+            eprintln!("Downloaded {}", file.name);
+            summary.downloaded += 1;
+        }
+        manifest.push(document_record(doc, &metadata, &files));
+        notes.extend(note_records(doc));
+        for instance in &doc.custom_fields {
+            let Some(field) = taxonomy.custom_field(instance.field) else {
+                continue;
+            };
+            let mut fields = json!({
+                "document": doc.id,
+                "field": instance.field,
+            });
+            fields[value_column(&field.data_type)] = instance.value.clone();
+            let pk = instances.len() as u64 + 1;
+            instances.push(record("documents.customfieldinstance", pk, fields));
+        }
+    }
+    manifest.extend(notes);
+    manifest.extend(instances);
+
+    if let Target::Dir(dir) = &target {
+        summary.removed = remove_stale(dir, &manifest)?;
+    }
+    let version = client.server_version()?;
+    target.write_bytes(
+        METADATA_FILE,
+        &serde_json::to_vec_pretty(&json!({ "version": version }))?,
+    )?;
+    target.write_bytes(MANIFEST_FILE, &serde_json::to_vec_pretty(&manifest)?)?;
+    let location = target.finish()?;
+
+    eprintln!(
+        "Exported {} documents to {}: {} files downloaded, {} unchanged, {} removed",
+        docs.len(),
+        location.display(),
+        summary.downloaded,
+        summary.unchanged,
+        summary.removed,
+    );
+    Ok(())
+}
+
+impl Taxonomy {
+    fn fetch(client: &Client) -> Result<Self> {
+        Ok(Self {
+            correspondents: client.collect_correspondents(None)?.0,
+            tags: client.collect_tags(None)?.0,
+            document_types: client.collect_document_types(None)?.0,
+            storage_paths: client.collect_storage_paths(None)?.0,
+            custom_fields: client.collect_custom_fields(None)?.0,
+        })
+    }
+
+    fn custom_field(&self, id: u64) -> Option<&CustomField> {
+        self.custom_fields.iter().find(|field| field.id == id)
+    }
+
+    fn records(&self) -> Vec<Value> {
+        let mut records = Vec::new();
+        for c in &self.correspondents {
+            records.push(record(
+                "documents.correspondent",
+                c.id,
+                json!({
+                    "name": c.name,
+                    "match": c.r#match.as_deref().unwrap_or_default(),
+                    "matching_algorithm": c.matching_algorithm.map(u8::from),
+                    "is_insensitive": c.is_insensitive,
+                }),
+            ));
+        }
+        for tag in &self.tags {
+            records.push(record(
+                "documents.tag",
+                tag.id,
+                json!({
+                    "name": tag.name,
+                    "color": tag.color,
+                    "is_inbox_tag": tag.is_inbox_tag,
+                    "match": tag.r#match.as_deref().unwrap_or_default(),
+                    "matching_algorithm": tag.matching_algorithm.map(u8::from),
+                    "is_insensitive": tag.is_insensitive,
+                    "tn_parent": tag.parent,
+                }),
+            ));
+        }
+        for t in &self.document_types {
+            records.push(record(
+                "documents.documenttype",
+                t.id,
+                json!({
+                    "name": t.name,
+                    "match": t.r#match.as_deref().unwrap_or_default(),
+                    "matching_algorithm": t.matching_algorithm.map(u8::from),
+                    "is_insensitive": t.is_insensitive,
+                }),
+            ));
+        }
+        for path in &self.storage_paths {
+            records.push(record(
+                "documents.storagepath",
+                path.id,
+                json!({
+                    "name": path.name,
+                    "path": path.path,
+                    "match": path.r#match.as_deref().unwrap_or_default(),
+                    "matching_algorithm": path.matching_algorithm.map(u8::from),
+                    "is_insensitive": path.is_insensitive,
+                }),
+            ));
+        }
+        for field in &self.custom_fields {
+            records.push(record(
+                "documents.customfield",
+                field.id,
+                json!({
+                    "name": field.name,
+                    "data_type": field.data_type,
+                    "extra_data": field.extra_data,
+                }),
+            ));
+        }
+        records
+    }
+}
+
+/// A manifest record in the shape of Django's serializer.
+fn record(model: &str, pk: u64, fields: Value) -> Value {
+    let mut record = json!({
+        "model": model,
+        "pk": pk,
+    });
+    record["fields"] = fields;
+    record
+}
+
+fn note_records(doc: &Document) -> impl Iterator<Item = Value> + '_ {
+    doc.notes.iter().map(|note| {
+        record(
+            "documents.note",
+            note.id,
+            json!({
+                "note": note.note,
+                "created": note.created,
+                "document": doc.id,
+                "user": note.user.as_ref().map(|user| user.id),
+            }),
+        )
+    })
+}
+
+fn document_record(doc: &Document, metadata: &DocumentMetadata, files: &[ExportFile]) -> Value {
+    let mut value = record(
+        "documents.document",
+        doc.id,
+        json!({
+            "correspondent": doc.correspondent,
+            "storage_path": doc.storage_path,
+            "title": doc.title,
+            "content": doc.content.as_deref().unwrap_or_default(),
+            "document_type": doc.document_type,
+            "mime_type": metadata.original_mime_type,
+            "checksum": metadata.original_checksum,
+            "archive_checksum": metadata.archive_checksum,
+            "created": doc.created,
+            "modified": doc.modified,
+            "added": doc.added,
+            "storage_type": "unencrypted",
+            "filename": metadata.media_filename,
+            "archive_filename": metadata.archive_media_filename,
+            "original_filename": doc.original_file_name,
+            "archive_serial_number": doc.archive_serial_number,
+            "owner": doc.owner,
+            "tags": doc.tags,
+            "page_count": doc.page_count,
+        }),
+    );
+    for file in files {
+        let key = match file.kind {
+            FileKind::Original => EXPORTED_FILE_NAME,
+            FileKind::Archive => EXPORTED_ARCHIVE_NAME,
+            FileKind::Thumbnail => EXPORTED_THUMBNAIL_NAME,
+        };
+        value[key] = json!(file.name);
+    }
+    value
+}
+
+/// The column `document_exporter` stores a custom field value of
+/// `data_type` in.
+fn value_column(data_type: &str) -> &'static str {
+    match data_type {
+        "longtext" => "value_long_text",
+        "url" => "value_url",
+        "date" => "value_date",
+        "boolean" => "value_bool",
+        "integer" => "value_int",
+        "float" => "value_float",
+        "monetary" => "value_monetary",
+        "documentlink" => "value_document_ids",
+        "select" => "value_select",
+        _ => "value_text",
+    }
+}
+
+/// A unique file name stem like the one `document_exporter` uses:
+/// `<created> <correspondent> <title>`, with a counter for duplicates.
+fn base_name(
+    doc: &Document,
+    correspondents: &HashMap<u64, &str>,
+    taken: &mut HashSet<String>,
+) -> String {
+    let mut name = doc
+        .created
+        .map_or_else(|| "none".to_string(), |date| date.to_string());
+    if let Some(correspondent) = doc.correspondent.and_then(|id| correspondents.get(&id)) {
+        name.push(' ');
+        name.push_str(correspondent);
+    }
+    if !doc.title.is_empty() {
+        name.push(' ');
+        name.push_str(&doc.title);
+    }
+    let name = component(&sanitize(&name));
+    let mut unique = name.clone();
+    let mut counter = 0;
+    while !taken.insert(unique.to_lowercase()) {
+        counter += 1;
+        unique = format!("{name}_{counter:02}");
+    }
+    unique
+}
+
+fn export_files(doc: &Document, metadata: &DocumentMetadata, base: &str) -> Vec<ExportFile> {
+    let mut files = vec![ExportFile {
+        kind: FileKind::Original,
+        name: format!("{base}{}", extension(doc.original_file_name.as_deref())),
+        size: Some(metadata.original_size),
+        checksum: Some(metadata.original_checksum.clone()),
+    }];
+    if metadata.has_archive_version {
+        files.push(ExportFile {
+            kind: FileKind::Archive,
+            name: format!("{base}-archive.pdf"),
+            size: metadata.archive_size,
+            checksum: metadata.archive_checksum.clone(),
+        });
+    }
+    files.push(ExportFile {
+        kind: FileKind::Thumbnail,
+        name: format!("{base}-thumbnail.webp"),
+        size: None,
+        checksum: None,
+    });
+    files
+}
+
+/// Removes the files of the previous export that the new manifest no
+/// longer lists. Returns how many were removed.
+fn remove_stale(dir: &Path, manifest: &[Value]) -> Result<usize> {
+    let previous = match fs::read(dir.join(MANIFEST_FILE)) {
+        Ok(data) => serde_json::from_slice::<Vec<Value>>(&data).unwrap_or_default(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err).context("failed to read the previous manifest"),
+    };
+    let current: HashSet<&str> = manifest.iter().flat_map(exported_names).collect();
+    let mut removed = 0;
+    for name in previous.iter().flat_map(exported_names) {
+        if !current.contains(name) {
+            remove_file(dir, name)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// The names of the files a manifest record refers to.
+fn exported_names(record: &Value) -> impl Iterator<Item = &str> {
+    [
+        EXPORTED_FILE_NAME,
+        EXPORTED_ARCHIVE_NAME,
+        EXPORTED_THUMBNAIL_NAME,
+    ]
+    .into_iter()
+    .filter_map(|key| record.get(key).and_then(Value::as_str))
+}
+
+impl Target {
+    /// Starts `export-<date>.zip` in `dir`, like `document_exporter --zip`.
+    fn zip(dir: &Path) -> Result<Self> {
+        let date = jiff::Zoned::now().date();
+        let path = dir.join(format!("export-{date}.zip"));
+        let tmp = part_path(&path);
+        let file = fs::File::create(&tmp)
+            .with_context(|| format!("failed to create file: {}", tmp.display()))?;
+        Ok(Self::Zip {
+            writer: Box::new(ZipWriter::new(file)),
+            tmp,
+            path,
+        })
+    }
+
+    /// Whether `file` was already exported: its checksum matches, or its
+    /// size and modification time do. Zip archives are always rewritten.
+    fn is_current(
+        &self,
+        file: &ExportFile,
+        modified: Option<Timestamp>,
+        compare_checksums: bool,
+    ) -> Result<bool> {
+        let Self::Dir(dir) = self else {
+            return Ok(false);
+        };
+        let path = dir.join(&file.name);
+        let Ok(meta) = fs::metadata(&path) else {
+            return Ok(false);
+        };
+        if let (true, Some(checksum)) = (compare_checksums, &file.checksum) {
+            return Ok(md5_file(&path)? == *checksum);
+        }
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|time| Timestamp::try_from(time).ok());
+        Ok(modified.is_some()
+            && mtime == modified
+            && file.size.is_none_or(|size| size == meta.len()))
+    }
+
+    /// Writes a file produced by `write`, stamped with `modified` so the
+    /// next run can tell it is unchanged.
+    fn write_with(
+        &mut self,
+        name: &str,
+        modified: Option<Timestamp>,
+        write: impl FnOnce(&mut dyn Write) -> Result<()>,
+    ) -> Result<()> {
+        match self {
+            Self::Dir(dir) => {
+                let path = dir.join(name);
+                create_parent(&path)?;
+                let tmp = part_path(&path);
+                let mut file = fs::File::create(&tmp)
+                    .with_context(|| format!("failed to create file: {}", tmp.display()))?;
+                write(&mut file)?;
+                if let Some(modified) = modified {
+                    file.set_modified(SystemTime::from(modified))?;
+                }
+                fs::rename(&tmp, &path)?;
+            }
+            Self::Zip { writer, .. } => {
+                writer.start_file(name, SimpleFileOptions::default())?;
+                write(writer.as_mut())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.write_with(name, None, |out| Ok(out.write_all(data)?))
+    }
+
+    /// Completes the export and returns where it was written.
+    fn finish(self) -> Result<PathBuf> {
+        match self {
+            Self::Dir(dir) => Ok(dir),
+            Self::Zip { writer, tmp, path } => {
+                writer.finish()?;
+                fs::rename(&tmp, &path)?;
+                Ok(path)
+            }
+        }
+    }
+}
+
+/// The hex MD5 digest of a file, which Paperless-ngx uses as its checksum.
+fn md5_file(path: &Path) -> Result<String> {
+    let mut file =
+        fs::File::open(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let mut hasher = Md5::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,510 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use pngx_client::{
+    Client, CorrespondentRequest, CustomFieldInstance, CustomFieldRequest, DocumentRequest,
+    DocumentTypeRequest, DocumentUpload, MatchingAlgorithm, StoragePathRequest, TagRequest,
+    TaskStatus,
+};
+use serde::Serialize;
+use serde_json::Value;
+use zip::ZipArchive;
+
+use super::export::{EXPORTED_FILE_NAME, MANIFEST_FILE};
+
+pub struct ImportOptions {
+    /// Where to write the ID-mapping report.
+    pub report: PathBuf,
+    /// How long to wait for the server to consume each document.
+    pub timeout: Duration,
+}
+
+/// Where the export is read from.
+enum Source {
+    Dir(PathBuf),
+    Zip(Box<ZipArchive<fs::File>>),
+}
+
+/// A manifest record: `{"model": ..., "pk": ..., "fields": {...}}` plus
+/// the exported file names of documents.
+struct Record<'a> {
+    pk: u64,
+    fields: &'a Value,
+    value: &'a Value,
+}
+
+/// How an object of the export maps to the server.
+#[derive(Serialize)]
+struct Mapped {
+    /// Primary key in the export.
+    pk: u64,
+    /// ID on the server, if the object exists there now.
+    id: Option<u64>,
+    name: String,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    /// An object with the same name, or a document with the same checksum,
+    /// already existed.
+    Existing,
+    Created,
+    Failed,
+}
+
+/// The ID-mapping report, one list per kind of object.
+#[derive(Default, Serialize)]
+struct Report {
+    correspondents: Vec<Mapped>,
+    tags: Vec<Mapped>,
+    document_types: Vec<Mapped>,
+    storage_paths: Vec<Mapped>,
+    custom_fields: Vec<Mapped>,
+    documents: Vec<Mapped>,
+}
+
+pub fn import(client: &Client, source: &Path, options: &ImportOptions) -> Result<()> {
+    let mut source = Source::open(source)?;
+    let manifest: Vec<Value> = serde_json::from_slice(&source.read(MANIFEST_FILE)?)
+        .with_context(|| format!("failed to parse {MANIFEST_FILE}"))?;
+    let records = |model: &str| records(&manifest, model);
+
+    let mut report = Report {
+        correspondents: import_correspondents(client, &records("documents.correspondent"))?,
+        tags: import_tags(client, &records("documents.tag"))?,
+        document_types: import_document_types(client, &records("documents.documenttype"))?,
+        storage_paths: import_storage_paths(client, &records("documents.storagepath"))?,
+        custom_fields: import_custom_fields(client, &records("documents.customfield"))?,
+        documents: Vec::new(),
+    };
+    for doc in records("documents.document") {
+        let mapped = import_document(client, &mut source, &report, &doc, options.timeout);
+        match mapped.status {
+            Status::Existing => eprintln!("Skipped {} (already on the server)", mapped.name),
+            Status::Created => eprintln!("Imported {}", mapped.name),
+            Status::Failed => eprintln!(
+                "Failed to import {}: {}",
+                mapped.name,
+                mapped.error.as_deref().unwrap_or_default()
+            ),
+        }
+        report.documents.push(mapped);
+    }
+    apply_notes(client, &mut report, &records("documents.note"));
+    apply_custom_fields(
+        client,
+        &mut report,
+        &records("documents.customfieldinstance"),
+    );
+
+    fs::write(&options.report, serde_json::to_vec_pretty(&report)?)
+        .with_context(|| format!("failed to write report: {}", options.report.display()))?;
+    let count = |status| {
+        report
+            .documents
+            .iter()
+            .filter(|doc| doc.status == status)
+            .count()
+    };
+    let failed = count(Status::Failed);
+    let incomplete = report
+        .documents
+        .iter()
+        .filter(|doc| doc.status == Status::Created && doc.error.is_some())
+        .count();
+    eprintln!(
+        "Imported {} documents, {} already existed, {} failed. Report written to {}",
+        count(Status::Created),
+        count(Status::Existing),
+        failed,
+        options.report.display(),
+    );
+    if failed > 0 {
+        bail!("{failed} documents failed to import");
+    }
+    if incomplete > 0 {
+        bail!("{incomplete} imported documents lack notes or custom field values");
+    }
+    Ok(())
+}
+
+impl Report {
+    /// Records that updating the imported document `id` failed. The
+    /// document stays mapped, as it exists on the server.
+    fn fail(&mut self, id: u64, error: String) {
+        let Some(doc) = self.documents.iter_mut().find(|doc| doc.id == Some(id)) else {
+            return;
+        };
+        eprintln!("Failed to update {}: {error}", doc.name);
+        doc.error = Some(match doc.error.take() {
+            Some(previous) => format!("{previous}; {error}"),
+            None => error,
+        });
+    }
+}
+
+impl Source {
+    fn open(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            return Ok(Self::Dir(path.to_path_buf()));
+        }
+        let file =
+            fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let archive = ZipArchive::new(file)
+            .with_context(|| format!("not a directory or zip archive: {}", path.display()))?;
+        Ok(Self::Zip(Box::new(archive)))
+    }
+
+    fn read(&mut self, name: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Dir(dir) => {
+                let path = dir.join(name);
+                fs::read(&path).with_context(|| format!("failed to read {}", path.display()))
+            }
+            Self::Zip(archive) => {
+                let mut file = archive
+                    .by_name(name)
+                    .with_context(|| format!("{name} is missing from the archive"))?;
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                Ok(data)
+            }
+        }
+    }
+}
+
+fn records<'a>(manifest: &'a [Value], model: &str) -> Vec<Record<'a>> {
+    let mut records: Vec<Record> = manifest
+        .iter()
+        .filter(|value| value["model"] == model)
+        .filter_map(|value| {
+            Some(Record {
+                pk: value["pk"].as_u64()?,
+                fields: &value["fields"],
+                value,
+            })
+        })
+        .collect();
+    records.sort_by_key(|record| record.pk);
+    records
+}
+
+impl Record<'_> {
+    fn text(&self, key: &str) -> Option<&str> {
+        self.fields[key].as_str()
+    }
+
+    fn id(&self, key: &str) -> Option<u64> {
+        self.fields[key].as_u64()
+    }
+
+    fn name(&self) -> String {
+        self.text("name").unwrap_or_default().to_string()
+    }
+
+    fn matching(&self) -> (Option<String>, Option<MatchingAlgorithm>, Option<bool>) {
+        let algorithm = self
+            .id("matching_algorithm")
+            .and_then(|value| u8::try_from(value).ok())
+            .and_then(|value| MatchingAlgorithm::try_from(value).ok());
+        (
+            self.text("match").map(str::to_string),
+            algorithm,
+            self.fields["is_insensitive"].as_bool(),
+        )
+    }
+}
+
+/// Maps each record to the object of the same name, ignoring case, or
+/// creates it with `create`.
+fn map_by_name(
+    kind: &str,
+    records: &[Record],
+    existing: &[(u64, String)],
+    mut create: impl FnMut(&Record) -> Result<u64, pngx_client::ApiError>,
+) -> Result<Vec<Mapped>> {
+    let existing: HashMap<String, u64> = existing
+        .iter()
+        .map(|(id, name)| (name.to_lowercase(), *id))
+        .collect();
+    let mut mapped = Vec::new();
+    for record in records {
+        let name = record.name();
+        let (id, status) = if let Some(id) = existing.get(&name.to_lowercase()) {
+            (*id, Status::Existing)
+        } else {
+            let id = create(record).with_context(|| format!("failed to create {kind} {name:?}"))?;
+            eprintln!("Created {kind} {name}");
+            (id, Status::Created)
+        };
+        mapped.push(Mapped {
+            pk: record.pk,
+            id: Some(id),
+            name,
+            status,
+            error: None,
+        });
+    }
+    Ok(mapped)
+}
+
+fn import_correspondents(client: &Client, records: &[Record]) -> Result<Vec<Mapped>> {
+    let existing = client.collect_correspondents(None)?.0;
+    let existing: Vec<_> = existing.into_iter().map(|c| (c.id, c.name)).collect();
+    map_by_name("correspondent", records, &existing, |record| {
+        let (r#match, matching_algorithm, is_insensitive) = record.matching();
+        let request = CorrespondentRequest {
+            name: Some(record.name()),
+            r#match,
+            matching_algorithm,
+            is_insensitive,
+        };
+        Ok(client.create_correspondent(&request)?.id)
+    })
+}
+
+fn import_document_types(client: &Client, records: &[Record]) -> Result<Vec<Mapped>> {
+    let existing = client.collect_document_types(None)?.0;
+    let existing: Vec<_> = existing.into_iter().map(|t| (t.id, t.name)).collect();
+    map_by_name("document type", records, &existing, |record| {
+        let (r#match, matching_algorithm, is_insensitive) = record.matching();
+        let request = DocumentTypeRequest {
+            name: Some(record.name()),
+            r#match,
+            matching_algorithm,
+            is_insensitive,
+        };
+        Ok(client.create_document_type(&request)?.id)
+    })
+}
+
+fn import_storage_paths(client: &Client, records: &[Record]) -> Result<Vec<Mapped>> {
+    let existing = client.collect_storage_paths(None)?.0;
+    let existing: Vec<_> = existing.into_iter().map(|p| (p.id, p.name)).collect();
+    map_by_name("storage path", records, &existing, |record| {
+        let (r#match, matching_algorithm, is_insensitive) = record.matching();
+        let request = StoragePathRequest {
+            name: Some(record.name()),
+            path: record.text("path").map(str::to_string),
+            r#match,
+            matching_algorithm,
+            is_insensitive,
+        };
+        Ok(client.create_storage_path(&request)?.id)
+    })
+}
+
+fn import_custom_fields(client: &Client, records: &[Record]) -> Result<Vec<Mapped>> {
+    let existing = client.collect_custom_fields(None)?.0;
+    let existing: Vec<_> = existing.into_iter().map(|f| (f.id, f.name)).collect();
+    map_by_name("custom field", records, &existing, |record| {
+        let request = CustomFieldRequest {
+            name: Some(record.name()),
+            data_type: record.text("data_type").map(str::to_string),
+            extra_data: Some(record.fields["extra_data"].clone()).filter(|v| !v.is_null()),
+        };
+        Ok(client.create_custom_field(&request)?.id)
+    })
+}
+
+/// Creates missing tags, then links the created ones to their parents once
+/// all of them exist.
+fn import_tags(client: &Client, records: &[Record]) -> Result<Vec<Mapped>> {
+    let existing = client.collect_tags(None)?.0;
+    let existing: Vec<_> = existing.into_iter().map(|t| (t.id, t.name)).collect();
+    let mapped = map_by_name("tag", records, &existing, |record| {
+        let (r#match, matching_algorithm, is_insensitive) = record.matching();
+        let request = TagRequest {
+            name: Some(record.name()),
+            color: record.text("color").map(str::to_string),
+            is_inbox_tag: record.fields["is_inbox_tag"].as_bool(),
+            r#match,
+            matching_algorithm,
+            is_insensitive,
+            parent: None,
+        };
+        Ok(client.create_tag(&request)?.id)
+    })?;
+    for (record, tag) in records.iter().zip(&mapped) {
+        let parent = record.id("tn_parent").and_then(|pk| new_id(&mapped, pk));
+        if let (Status::Created, Some(id), Some(parent)) = (tag.status, tag.id, parent) {
+            let request = TagRequest {
+                parent: Some(Some(parent)),
+                ..TagRequest::default()
+            };
+            client
+                .update_tag(id, &request)
+                .with_context(|| format!("failed to set the parent of tag {:?}", tag.name))?;
+        }
+    }
+    Ok(mapped)
+}
+
+/// The server ID of the object with primary key `pk` in the export.
+fn new_id(mapped: &[Mapped], pk: u64) -> Option<u64> {
+    mapped
+        .iter()
+        .find(|object| object.pk == pk)
+        .and_then(|object| object.id)
+}
+
+/// Uploads a document unless one with the same checksum exists, and waits
+/// for the server to consume it.
+fn import_document(
+    client: &Client,
+    source: &mut Source,
+    report: &Report,
+    doc: &Record,
+    timeout: Duration,
+) -> Mapped {
+    let title = doc.text("title").unwrap_or_default().to_string();
+    let (id, status, error) = match upload_document(client, source, report, doc, timeout) {
+        Ok((id, status)) => (Some(id), status, None),
+        Err(err) => (None, Status::Failed, Some(format!("{err:#}"))),
+    };
+    Mapped {
+        pk: doc.pk,
+        id,
+        name: title,
+        status,
+        error,
+    }
+}
+
+fn upload_document(
+    client: &Client,
+    source: &mut Source,
+    report: &Report,
+    doc: &Record,
+    timeout: Duration,
+) -> Result<(u64, Status)> {
+    if let Some(checksum) = doc.text("checksum") {
+        let filters = [("checksum__iexact", checksum)];
+        if let Some(&id) = client.collect_document_ids(&filters)?.first() {
+            return Ok((id, Status::Existing));
+        }
+    }
+
+    let Some(file) = doc.value[EXPORTED_FILE_NAME].as_str() else {
+        bail!("the manifest names no file for document {}", doc.pk);
+    };
+    let data = source.read(file)?;
+    let file_name = doc.text("original_filename").unwrap_or(file);
+    let reference = |mapped: &[Mapped], key| doc.id(key).and_then(|pk| new_id(mapped, pk));
+    let tags = doc.fields["tags"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let upload = DocumentUpload {
+        title: doc.text("title").map(str::to_string),
+        created: doc.text("created").and_then(|created| {
+            // Older exports store a datetime.
+            created.get(..10).and_then(|date| date.parse().ok())
+        }),
+        correspondent: reference(&report.correspondents, "correspondent"),
+        document_type: reference(&report.document_types, "document_type"),
+        storage_path: reference(&report.storage_paths, "storage_path"),
+        tags: tags
+            .iter()
+            .filter_map(|pk| new_id(&report.tags, pk.as_u64()?))
+            .collect(),
+        archive_serial_number: doc.id("archive_serial_number"),
+        custom_fields: Vec::new(),
+    };
+    let task_id = client.upload_document(file_name, &data, &upload)?;
+    let task = client
+        .wait_for_task(&task_id, timeout)
+        .context("timed out waiting for the server to consume the document")?;
+    match (task.status, task.related_document) {
+        (TaskStatus::Success, Some(id)) => Ok((id, Status::Created)),
+        _ => bail!(
+            "{}",
+            task.result
+                .unwrap_or_else(|| "the server did not create a document".to_string())
+        ),
+    }
+}
+
+/// Adds the notes of the documents this run uploaded.
+fn apply_notes(client: &Client, report: &mut Report, notes: &[Record]) {
+    for note in notes {
+        let (Some(text), Some(id)) = (note.text("note"), created_document(report, note)) else {
+            continue;
+        };
+        if let Err(err) = client.add_note(id, text) {
+            report.fail(id, format!("failed to add a note: {err}"));
+        }
+    }
+}
+
+/// Sets the custom field values of the documents this run uploaded.
+/// Document links are mapped to the new document IDs.
+fn apply_custom_fields(client: &Client, report: &mut Report, instances: &[Record]) {
+    let mut values: BTreeMap<u64, Vec<CustomFieldInstance>> = BTreeMap::new();
+    for instance in instances {
+        let Some(id) = created_document(report, instance) else {
+            continue;
+        };
+        let Some(field) = report
+            .custom_fields
+            .iter()
+            .find(|field| Some(field.pk) == instance.id("field"))
+        else {
+            continue;
+        };
+        let Some(field_id) = field.id else {
+            continue;
+        };
+        let mut value = stored_value(instance.fields);
+        if let Value::Array(links) = &mut value {
+            *links = links
+                .iter()
+                .filter_map(|pk| new_id(&report.documents, pk.as_u64()?))
+                .map(Value::from)
+                .collect();
+        }
+        values
+            .entry(id)
+            .or_default()
+            .push(CustomFieldInstance::new(field_id, value));
+    }
+    for (id, custom_fields) in values {
+        let request = DocumentRequest {
+            custom_fields: Some(custom_fields),
+            ..DocumentRequest::default()
+        };
+        if let Err(err) = client.update_document(id, &request) {
+            report.fail(id, format!("failed to set custom fields: {err}"));
+        }
+    }
+}
+
+/// The document a note or custom field value belongs to, if this run
+/// uploaded it.
+fn created_document(report: &Report, record: &Record) -> Option<u64> {
+    let pk = record.id("document")?;
+    report
+        .documents
+        .iter()
+        .find(|doc| doc.pk == pk && doc.status == Status::Created)
+        .and_then(|doc| doc.id)
+}
+
+/// The value of a custom field instance. The exporter writes one column
+/// per data type, of which only the field's own is set.
+fn stored_value(fields: &Value) -> Value {
+    fields
+        .as_object()
+        .into_iter()
+        .flatten()
+        .find(|(key, value)| key.starts_with("value_") && !value.is_null())
+        .map_or(Value::Null, |(_, value)| value.clone())
+}
@@ -1,22 +1,23 @@
 use anyhow::Result;
 use pngx_client::Client;
 
-use crate::output::{FieldFilter, OutputFormat, resolve_documents};
-use crate::resolve::NameResolver;
+use crate::output::{FieldFilter, OutputOptions, raw_pages, resolve_pages};
+use crate::resolve::{NameCache, NameResolver};
 
 pub fn list(
     client: &Client,
-    format: OutputFormat,
+    cache: &NameCache,
+    output: OutputOptions<'_>,
     limit: Option<usize>,
     fields: Option<&FieldFilter>,
+    raw: bool,
 ) -> Result<()> {
-    let (results, total) = client.collect_inbox_documents(limit)?;
-    if results.is_empty() {
-        super::print_empty(format, "Inbox is empty")?;
-    } else {
-        let names = NameResolver::fetch(client, fields)?;
-        let results = resolve_documents(&results, &names);
-        super::print_results(format, &results, total, fields)?;
+    let empty = "Inbox is empty";
+    if raw {
+        let pages = raw_pages(client.inbox_document_pages().all_fields());
+        return super::print_results(output, pages, limit, fields, empty);
     }
-    Ok(())
+    let names = NameResolver::cached(fields, cache);
+    let pages = resolve_pages(client, client.inbox_document_pages(), names, cache);
+    super::print_results(output, pages, limit, fields, empty)
 }
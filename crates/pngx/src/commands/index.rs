@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, anyhow, bail};
+use pngx_client::Client;
+
+use crate::config::ValidConfig;
+use crate::index::LocalIndex;
+use crate::resolve::{NameCache, NameResolver};
+
+/// Most IDs per `id__in` filter when re-indexing documents by ID.
+const ID_CHUNK: usize = 100;
+
+/// Index every document, replacing the existing index.
+pub fn build(client: &Client, cache: &NameCache, config: &ValidConfig) -> Result<()> {
+    let names = all_names(client, cache)?;
+    let mut index = LocalIndex::create(&LocalIndex::path(config), config.url.as_str())?;
+    let indexed = add_pages(client, &mut index, &names, &[("ordering", "modified")])?;
+    index.set_names(names)?;
+    eprintln!("Indexed {indexed} documents in {}", index.dir().display());
+    Ok(())
+}
+
+/// Index documents modified since the last build or update, re-index those
+/// whose tag, correspondent, or document type names changed, and drop those
+/// deleted on the server. Builds the index if there is none.
+pub fn update(client: &Client, cache: &NameCache, config: &ValidConfig) -> Result<()> {
+    let Some(mut index) = LocalIndex::open(&LocalIndex::path(config))? else {
+        return build(client, cache, config);
+    };
+    if index.state.server != config.url.as_str() {
+        bail!(
+            "local index in {} belongs to {}, run `pngx index build`",
+            index.dir().display(),
+            index.state.server
+        );
+    }
+
+    let names = all_names(client, cache)?;
+    let stale = if index.state.names.same_names(&names) {
+        Vec::new()
+    } else {
+        index.find(|doc| index.state.names.is_stale(doc, &names))?
+    };
+
+    let since = index.state.last_modified.map(|ts| ts.to_string());
+    let mut filters = vec![("ordering", "modified")];
+    if let Some(since) = &since {
+        filters.push(("modified__gt", since));
+    }
+    let mut updated = add_pages(client, &mut index, &names, &filters)?;
+    for chunk in stale.chunks(ID_CHUNK) {
+        let ids = chunk
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        updated += add_pages(client, &mut index, &names, &[("id__in", &ids)])?;
+    }
+
+    let existing: HashSet<u64> = client.collect_document_ids(&[])?.into_iter().collect();
+    let removed: Vec<u64> = index
+        .state
+        .documents
+        .iter()
+        .copied()
+        .filter(|id| !existing.contains(id))
+        .collect();
+    index.apply(&[], &names, &removed)?;
+    index.set_names(names)?;
+    eprintln!(
+        "Updated {updated} documents and removed {} from {}",
+        removed.len(),
+        index.dir().display()
+    );
+    Ok(())
+}
+
+/// Every tag, correspondent, and document type name, which the index is
+/// resolved with. Refreshes the name cache on the way.
+fn all_names(client: &Client, cache: &NameCache) -> Result<NameResolver> {
+    let (tags, _) = client.collect_tags(None)?;
+    let (correspondents, _) = client.collect_correspondents(None)?;
+    let (document_types, _) = client.collect_document_types(None)?;
+    let names = NameResolver::from_lists(tags, correspondents, document_types);
+    cache.store(&names);
+    Ok(names)
+}
+
+/// Adds the documents matching `filters` to the index one page at a time.
+/// Returns how many were added.
+fn add_pages(
+    client: &Client,
+    index: &mut LocalIndex,
+    names: &NameResolver,
+    filters: &[(&str, &str)],
+) -> Result<usize> {
+    let mut added = 0;
+    for page in client.matching_document_pages(filters) {
+        let docs = page?.results;
+        index.apply(&docs, names, &[])?;
+        added += docs.len();
+    }
+    Ok(added)
+}
+
+pub fn stats(config: &ValidConfig) -> Result<()> {
+    let dir = LocalIndex::path(config);
+    let index = LocalIndex::open(&dir)?
+        .ok_or_else(|| anyhow!("no local index for {}, run `pngx index build`", config.url))?;
+    let time =
+        |ts: Option<jiff::Timestamp>| ts.map_or_else(|| "never".to_string(), |ts| ts.to_string());
+
+    println!("Path: {}", dir.display());
+    println!("Server: {}", index.state.server);
+    println!("Documents: {}", index.num_docs()?);
+    println!("Size: {} bytes", index.size()?);
+    println!("Built: {}", time(index.state.built));
+    println!("Updated: {}", time(index.state.updated));
+    println!("Last modified: {}", time(index.state.last_modified));
+    Ok(())
+}
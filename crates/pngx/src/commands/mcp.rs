@@ -1,6 +1,4 @@
-use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
 
 use rmcp::handler::server::tool::ToolRouter;
 use rmcp::handler::server::wrapper::Parameters;
@@ -10,39 +8,11 @@ use rmcp::model::{
 use rmcp::{ErrorData as McpError, ServiceExt, tool, tool_router};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, MutexGuard};
 
-use pngx_client::Client;
+use pngx_client::{AsyncClient, Document};
 
-const CACHE_TTL: Duration = Duration::from_secs(300);
-
-struct CachedResolver {
-    tags: HashMap<u64, String>,
-    correspondents: HashMap<u64, String>,
-    document_types: HashMap<u64, String>,
-    fetched_at: Instant,
-}
-
-impl CachedResolver {
-    fn is_expired(&self) -> bool {
-        self.fetched_at.elapsed() > CACHE_TTL
-    }
-
-    fn tag_name(&self, id: u64) -> String {
-        self.tags
-            .get(&id)
-            .cloned()
-            .unwrap_or_else(|| format!("#{id}"))
-    }
-
-    fn correspondent_name(&self, id: u64) -> Option<String> {
-        self.correspondents.get(&id).cloned()
-    }
-
-    fn document_type_name(&self, id: u64) -> Option<String> {
-        self.document_types.get(&id).cloned()
-    }
-}
+use crate::resolve::{NameCache, NameResolver};
 
 #[derive(Serialize)]
 struct ResolvedDoc {
@@ -57,7 +27,7 @@ struct ResolvedDoc {
     original_file_name: Option<String>,
 }
 
-fn resolve_doc(doc: &pngx_client::Document, resolver: &CachedResolver) -> ResolvedDoc {
+fn resolve_doc(doc: &Document, resolver: &NameResolver) -> ResolvedDoc {
     ResolvedDoc {
         id: doc.id,
         title: doc.title.clone(),
@@ -86,58 +56,45 @@ fn api_err(e: pngx_client::ApiError) -> McpError {
     McpError::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None)
 }
 
-#[allow(clippy::needless_pass_by_value)]
-fn spawn_err(e: tokio::task::JoinError) -> McpError {
-    McpError::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None)
-}
-
 #[derive(Clone)]
 pub struct PngxMcp {
-    client: Arc<Client>,
-    cache: Arc<RwLock<Option<Arc<CachedResolver>>>>,
+    client: AsyncClient,
+    cache: Arc<NameCache>,
+    names: Arc<Mutex<NameResolver>>,
     tool_router: ToolRouter<Self>,
 }
 
 impl PngxMcp {
-    pub fn new(client: Client) -> Self {
+    pub fn new(client: AsyncClient, cache: NameCache) -> Self {
         Self {
-            client: Arc::new(client),
-            cache: Arc::new(RwLock::new(None)),
+            client,
+            cache: Arc::new(cache),
+            names: Arc::new(Mutex::new(NameResolver::default())),
             tool_router: Self::tool_router(),
         }
     }
 
-    async fn resolver(&self) -> Result<Arc<CachedResolver>, McpError> {
-        {
-            let guard = self.cache.read().await;
-            if let Some(cached) = guard.as_ref()
-                && !cached.is_expired()
-            {
-                return Ok(Arc::clone(cached));
+    /// Returns the shared resolver with the names referenced by `docs`
+    /// loaded, fetching only what neither memory nor the disk cache has.
+    async fn names(&self, docs: &[Document]) -> Result<MutexGuard<'_, NameResolver>, McpError> {
+        let request = {
+            let mut names = self.names.lock().await;
+            if !names.is_fresh(self.cache.ttl()) {
+                *names = NameResolver::cached(None, &self.cache);
             }
-        }
-
-        let client = self.client.clone();
-        let resolver = tokio::task::spawn_blocking(move || {
-            let (tags, _) = client.collect_tags(None).map_err(api_err)?;
-            let (correspondents, _) = client.collect_correspondents(None).map_err(api_err)?;
-            let (document_types, _) = client.collect_document_types(None).map_err(api_err)?;
-            Ok::<_, McpError>(CachedResolver {
-                tags: tags.into_iter().map(|t| (t.id, t.name)).collect(),
-                correspondents: correspondents.into_iter().map(|c| (c.id, c.name)).collect(),
-                document_types: document_types
-                    .into_iter()
-                    .map(|dt| (dt.id, dt.name))
-                    .collect(),
-                fetched_at: Instant::now(),
-            })
-        })
-        .await
-        .map_err(spawn_err)??;
-
-        let resolver = Arc::new(resolver);
-        *self.cache.write().await = Some(Arc::clone(&resolver));
-        Ok(resolver)
+            match names.request(docs) {
+                Some(request) => request,
+                None => return Ok(names),
+            }
+        };
+
+        // The lock is released while fetching, so other calls don't wait
+        // behind a slow request.
+        let fetched = request.fetch_async(&self.client).await.map_err(api_err)?;
+        let mut names = self.names.lock().await;
+        names.apply(request, fetched);
+        self.cache.store(&names);
+        Ok(names)
     }
 }
 
@@ -177,18 +134,16 @@ impl PngxMcp {
     /// metadata (correspondent, type, tags resolved to names).
     #[tool(name = "search", annotations(read_only_hint = true))]
     async fn search(&self, params: Parameters<SearchParams>) -> Result<CallToolResult, McpError> {
-        let client = self.client.clone();
-        let query = params.0.query;
         let limit = Some(params.0.limit.unwrap_or(25));
 
-        let (docs, total) = tokio::task::spawn_blocking(move || {
-            client.collect_search(&query, limit).map_err(api_err)
-        })
-        .await
-        .map_err(spawn_err)??;
+        let (docs, total) = self
+            .client
+            .collect_search(&params.0.query, limit)
+            .await
+            .map_err(api_err)?;
 
-        let resolver = self.resolver().await?;
-        let resolved: Vec<ResolvedDoc> = docs.iter().map(|d| resolve_doc(d, &resolver)).collect();
+        let names = self.names(&docs).await?;
+        let resolved: Vec<ResolvedDoc> = docs.iter().map(|d| resolve_doc(d, &names)).collect();
 
         to_json_text(&serde_json::json!({
             "results": resolved,
@@ -202,17 +157,16 @@ impl PngxMcp {
     /// tag, with metadata resolved to names.
     #[tool(name = "inbox", annotations(read_only_hint = true))]
     async fn inbox(&self, params: Parameters<LimitParams>) -> Result<CallToolResult, McpError> {
-        let client = self.client.clone();
         let limit = Some(params.0.limit.unwrap_or(25));
 
-        let (docs, total) = tokio::task::spawn_blocking(move || {
-            client.collect_inbox_documents(limit).map_err(api_err)
-        })
-        .await
-        .map_err(spawn_err)??;
+        let (docs, total) = self
+            .client
+            .collect_inbox_documents(limit)
+            .await
+            .map_err(api_err)?;
 
-        let resolver = self.resolver().await?;
-        let resolved: Vec<ResolvedDoc> = docs.iter().map(|d| resolve_doc(d, &resolver)).collect();
+        let names = self.names(&docs).await?;
+        let resolved: Vec<ResolvedDoc> = docs.iter().map(|d| resolve_doc(d, &names)).collect();
 
         to_json_text(&serde_json::json!({
             "results": resolved,
@@ -228,16 +182,16 @@ impl PngxMcp {
         &self,
         params: Parameters<LimitParams>,
     ) -> Result<CallToolResult, McpError> {
-        let client = self.client.clone();
         let limit = Some(params.0.limit.unwrap_or(25));
 
-        let (docs, total) =
-            tokio::task::spawn_blocking(move || client.collect_documents(limit).map_err(api_err))
-                .await
-                .map_err(spawn_err)??;
+        let (docs, total) = self
+            .client
+            .collect_documents(limit)
+            .await
+            .map_err(api_err)?;
 
-        let resolver = self.resolver().await?;
-        let resolved: Vec<ResolvedDoc> = docs.iter().map(|d| resolve_doc(d, &resolver)).collect();
+        let names = self.names(&docs).await?;
+        let resolved: Vec<ResolvedDoc> = docs.iter().map(|d| resolve_doc(d, &names)).collect();
 
         to_json_text(&serde_json::json!({
             "results": resolved,
@@ -254,26 +208,26 @@ impl PngxMcp {
         &self,
         params: Parameters<DocumentIdsParams>,
     ) -> Result<CallToolResult, McpError> {
-        let client = self.client.clone();
-        let ids = params.0.ids;
-
-        let docs = tokio::task::spawn_blocking(move || {
-            ids.iter()
-                .map(|&id| match client.document(id) {
-                    Ok(doc) => Ok(doc),
-                    Err(e) => Err((id, e)),
-                })
-                .collect::<Vec<_>>()
-        })
-        .await
-        .map_err(spawn_err)?;
-
-        let resolver = self.resolver().await?;
+        let docs = futures_util::future::join_all(
+            params
+                .0
+                .ids
+                .iter()
+                .map(|&id| async move { self.client.document(id).await.map_err(|e| (id, e)) }),
+        )
+        .await;
+
+        let found: Vec<Document> = docs
+            .iter()
+            .filter_map(|r| r.as_ref().ok())
+            .cloned()
+            .collect();
+        let names = self.names(&found).await?;
 
         let results: Vec<serde_json::Value> = docs
             .into_iter()
             .map(|result| match result {
-                Ok(doc) => serde_json::to_value(resolve_doc(&doc, &resolver))
+                Ok(doc) => serde_json::to_value(resolve_doc(&doc, &names))
                     .unwrap_or_else(|e| serde_json::json!({"error": e.to_string()})),
                 Err((id, e)) => serde_json::json!({"id": id, "error": e.to_string()}),
             })
@@ -289,13 +243,9 @@ impl PngxMcp {
         &self,
         params: Parameters<DocumentIdParam>,
     ) -> Result<CallToolResult, McpError> {
-        let client = self.client.clone();
         let id = params.0.id;
 
-        let content =
-            tokio::task::spawn_blocking(move || client.document_content(id).map_err(api_err))
-                .await
-                .map_err(spawn_err)??;
+        let content = self.client.document_content(id).await.map_err(api_err)?;
 
         to_json_text(&serde_json::json!({ "id": id, "content": content }))
     }
@@ -303,12 +253,7 @@ impl PngxMcp {
     /// List all tags defined in Paperless-ngx.
     #[tool(name = "tags", annotations(read_only_hint = true))]
     async fn tags(&self) -> Result<CallToolResult, McpError> {
-        let client = self.client.clone();
-
-        let (tags, _) =
-            tokio::task::spawn_blocking(move || client.collect_tags(None).map_err(api_err))
-                .await
-                .map_err(spawn_err)??;
+        let (tags, _) = self.client.collect_tags(None).await.map_err(api_err)?;
 
         to_json_text(&tags)
     }
@@ -316,13 +261,11 @@ impl PngxMcp {
     /// List all correspondents defined in Paperless-ngx.
     #[tool(name = "correspondents", annotations(read_only_hint = true))]
     async fn correspondents(&self) -> Result<CallToolResult, McpError> {
-        let client = self.client.clone();
-
-        let (correspondents, _) = tokio::task::spawn_blocking(move || {
-            client.collect_correspondents(None).map_err(api_err)
-        })
-        .await
-        .map_err(spawn_err)??;
+        let (correspondents, _) = self
+            .client
+            .collect_correspondents(None)
+            .await
+            .map_err(api_err)?;
 
         to_json_text(&correspondents)
     }
@@ -330,13 +273,11 @@ impl PngxMcp {
     /// List all document types defined in Paperless-ngx.
     #[tool(name = "document_types", annotations(read_only_hint = true))]
     async fn document_types(&self) -> Result<CallToolResult, McpError> {
-        let client = self.client.clone();
-
-        let (document_types, _) = tokio::task::spawn_blocking(move || {
-            client.collect_document_types(None).map_err(api_err)
-        })
-        .await
-        .map_err(spawn_err)??;
+        let (document_types, _) = self
+            .client
+            .collect_document_types(None)
+            .await
+            .map_err(api_err)?;
 
         to_json_text(&document_types)
     }
@@ -344,11 +285,7 @@ impl PngxMcp {
     /// Get the Paperless-ngx server version.
     #[tool(name = "version", annotations(read_only_hint = true))]
     async fn version(&self) -> Result<CallToolResult, McpError> {
-        let client = self.client.clone();
-
-        let version = tokio::task::spawn_blocking(move || client.server_version().map_err(api_err))
-            .await
-            .map_err(spawn_err)??;
+        let version = self.client.server_version().await.map_err(api_err)?;
 
         to_json_text(&serde_json::json!({ "version": version }))
     }
@@ -366,8 +303,8 @@ impl rmcp::handler::server::ServerHandler for PngxMcp {
     }
 }
 
-pub async fn serve(client: Client) -> anyhow::Result<()> {
-    let server = PngxMcp::new(client);
+pub async fn serve(client: AsyncClient, cache: NameCache) -> anyhow::Result<()> {
+    let server = PngxMcp::new(client, cache);
     let transport = rmcp::transport::io::stdio();
     let server = server.serve(transport).await?;
     server.waiting().await?;
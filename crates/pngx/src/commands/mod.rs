@@ -1,60 +1,162 @@
 pub mod auth;
+pub mod cache;
 pub mod correspondents;
 pub mod document_types;
 pub mod documents;
+pub mod export;
+pub mod import;
 pub mod inbox;
+pub mod index;
 pub mod mcp;
+pub mod profile;
+pub mod rules;
 pub mod search;
+pub mod sync;
 pub mod tags;
+pub mod triage;
 pub mod version;
+pub mod watch;
 
-use crate::output::{FieldFilter, OutputFormat, Tabular};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, IsTerminal, Write};
 
-pub fn print_results<T: Tabular + serde::Serialize>(
-    format: OutputFormat,
+use pngx_client::{ApiError, BulkEdit, Client};
+
+use crate::output::{FieldFilter, OutputFormat, OutputOptions, Tabular};
+
+/// Print up to `limit` items from a paginated listing. Each page is a
+/// `(total_count, items)` pair. NDJSON is written page by page as results
+/// arrive; the other formats are rendered once all pages are fetched.
+pub fn print_results<T, I>(
+    output: OutputOptions<'_>,
+    pages: I,
+    limit: Option<usize>,
+    fields: Option<&FieldFilter>,
+    empty_message: &str,
+) -> anyhow::Result<()>
+where
+    T: Tabular + serde::Serialize,
+    I: IntoIterator<Item = anyhow::Result<(u64, Vec<T>)>>,
+{
+    let max = limit.unwrap_or(usize::MAX);
+    let (showing, total) = match output.format {
+        OutputFormat::Ndjson => stream_ndjson(pages, max, fields)?,
+        OutputFormat::Json | OutputFormat::Markdown | OutputFormat::Csv | OutputFormat::Tsv => {
+            let mut items = Vec::new();
+            let mut total = 0;
+            for (i, page) in pages.into_iter().enumerate() {
+                let (count, page) = page?;
+                if i == 0 {
+                    total = count;
+                }
+                items.extend(page);
+                if items.len() >= max {
+                    break;
+                }
+            }
+            items.truncate(max);
+            if items.is_empty() {
+                return print_empty::<T>(output, fields, empty_message);
+            }
+            print_collected(output, &items, total, fields)?;
+            (items.len(), total)
+        }
+    };
+    if (showing as u64) < total {
+        eprintln!(
+            "Showing {showing} of {total} results (use -n to change limit or --all to fetch all)",
+        );
+    }
+    Ok(())
+}
+
+fn print_collected<T: Tabular + serde::Serialize>(
+    output: OutputOptions<'_>,
     items: &[T],
     total: u64,
     fields: Option<&FieldFilter>,
 ) -> anyhow::Result<()> {
-    match format {
-        OutputFormat::Json => {
-            let value = serde_json::to_value(items)?;
-            let results = match fields {
-                Some(f) => f.filter_json_array(value),
-                None => value,
-            };
-            let wrapper = serde_json::json!({
-                "results": results,
-                "total_count": total,
-                "showing": items.len(),
-                "has_more": (items.len() as u64) < total,
-            });
-            println!("{}", serde_json::to_string_pretty(&wrapper)?);
-        }
-        OutputFormat::Ndjson => {
-            print_ndjson_meta(items.len(), total);
-            print_ndjson_items(items, fields)?;
+    if let OutputFormat::Json = output.format {
+        let value = serde_json::to_value(items)?;
+        let results = match fields {
+            Some(f) => f.filter_json_array(value),
+            None => value,
+        };
+        let wrapper = serde_json::json!({
+            "results": results,
+            "total_count": total,
+            "showing": items.len(),
+            "has_more": (items.len() as u64) < total,
+        });
+        println!("{}", serde_json::to_string_pretty(&wrapper)?);
+    } else {
+        println!("{}", output.format_list(items, fields)?);
+    }
+    Ok(())
+}
+
+/// Write NDJSON as pages arrive. Stops fetching once stdout is closed, e.g.
+/// when piped into `head`. Returns `(showing, total)`.
+fn stream_ndjson<T, I>(
+    pages: I,
+    max: usize,
+    fields: Option<&FieldFilter>,
+) -> anyhow::Result<(usize, u64)>
+where
+    T: serde::Serialize,
+    I: IntoIterator<Item = anyhow::Result<(u64, Vec<T>)>>,
+{
+    let mut showing = 0;
+    match write_ndjson_pages(pages, max, fields, &mut showing) {
+        Ok(total) => Ok((showing, total)),
+        Err(err) if is_broken_pipe(&err) => Ok((showing, showing as u64)),
+        Err(err) => Err(err),
+    }
+}
+
+/// The `_meta` line is derived from the first page's count, so it precedes
+/// any items. Returns the total count.
+fn write_ndjson_pages<T, I>(
+    pages: I,
+    max: usize,
+    fields: Option<&FieldFilter>,
+    showing: &mut usize,
+) -> anyhow::Result<u64>
+where
+    T: serde::Serialize,
+    I: IntoIterator<Item = anyhow::Result<(u64, Vec<T>)>>,
+{
+    let mut total = None;
+    for page in pages {
+        let (count, items) = page?;
+        if total.is_none() {
+            total = Some(count);
+            print_ndjson_meta(usize::try_from(count).unwrap_or(usize::MAX).min(max), count)?;
         }
-        OutputFormat::Markdown => {
-            println!("{}", format.format_list(items, fields)?);
+        let take = items.len().min(max - *showing);
+        print_ndjson_items(&items[..take], fields)?;
+        *showing += take;
+        if *showing >= max {
+            break;
         }
     }
-    if (items.len() as u64) < total {
-        eprintln!(
-            "Showing {} of {} results (use -n to change limit or --all to fetch all)",
-            items.len(),
-            total,
-        );
+    if total.is_none() {
+        print_ndjson_meta(0, 0)?;
     }
-    Ok(())
+    Ok(total.unwrap_or_default())
+}
+
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
 }
 
 pub fn print_all<T: Tabular + serde::Serialize>(
-    format: OutputFormat,
+    output: OutputOptions<'_>,
     items: &[T],
     fields: Option<&FieldFilter>,
 ) -> anyhow::Result<()> {
-    match format {
+    match output.format {
         OutputFormat::Json => {
             let value = serde_json::to_value(items)?;
             let output = match fields {
@@ -66,17 +168,21 @@ pub fn print_all<T: Tabular + serde::Serialize>(
         OutputFormat::Ndjson => {
             print_ndjson_items(items, fields)?;
         }
-        OutputFormat::Markdown => {
-            println!("{}", format.format_list(items, fields)?);
+        OutputFormat::Markdown | OutputFormat::Csv | OutputFormat::Tsv => {
+            println!("{}", output.format_list(items, fields)?);
         }
     }
     Ok(())
 }
 
-/// Handle empty results: emit structured output for JSON/NDJSON, human
-/// message for markdown.
-pub fn print_empty(format: OutputFormat, message: &str) -> anyhow::Result<()> {
-    match format {
+/// Handle empty results: emit structured output for JSON/NDJSON, a header
+/// row for CSV/TSV, and a human message for markdown.
+fn print_empty<T: Tabular + serde::Serialize>(
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+    message: &str,
+) -> anyhow::Result<()> {
+    match output.format {
         OutputFormat::Json => {
             let wrapper = serde_json::json!({
                 "results": [],
@@ -87,36 +193,158 @@ pub fn print_empty(format: OutputFormat, message: &str) -> anyhow::Result<()> {
             println!("{}", serde_json::to_string_pretty(&wrapper)?);
         }
         OutputFormat::Ndjson => {
-            print_ndjson_meta(0, 0);
+            print_ndjson_meta(0, 0)?;
         }
         OutputFormat::Markdown => {
             eprintln!("{message}");
         }
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            println!("{}", output.format_list::<T>(&[], fields)?);
+            eprintln!("{message}");
+        }
     }
     Ok(())
 }
 
-fn print_ndjson_meta(showing: usize, total: u64) {
+fn print_ndjson_meta(showing: usize, total: u64) -> io::Result<()> {
     let meta = serde_json::json!({
         "_meta": true,
         "total_count": total,
         "showing": showing,
         "has_more": (showing as u64) < total,
     });
-    println!("{meta}");
+    writeln!(io::stdout(), "{meta}")
 }
 
 fn print_ndjson_items<T: serde::Serialize>(
     items: &[T],
     fields: Option<&FieldFilter>,
 ) -> anyhow::Result<()> {
+    let mut out = io::stdout().lock();
     for item in items {
         let value = serde_json::to_value(item)?;
         let line = match fields {
             Some(f) => f.filter_json_object(value),
             None => value,
         };
-        println!("{}", serde_json::to_string(&line)?);
+        writeln!(out, "{}", serde_json::to_string(&line)?)?;
+    }
+    Ok(())
+}
+
+/// Ask for confirmation before a destructive operation. `yes` skips the
+/// prompt; without a terminal, `--yes` is required.
+pub fn confirm(prompt: &str, yes: bool) -> anyhow::Result<()> {
+    if yes {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        anyhow::bail!("refusing to continue without a terminal. Pass --yes to confirm");
+    }
+
+    eprint!("{prompt} [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if matches!(answer.trim(), "y" | "Y" | "yes") {
+        Ok(())
+    } else {
+        anyhow::bail!("aborted")
+    }
+}
+
+/// Join IDs into the comma-separated form used by `__id__in` filters.
+pub fn join_ids(ids: impl IntoIterator<Item = u64>) -> String {
+    ids.into_iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// A kind of object that documents are assigned to, such as correspondents,
+/// as far as `delete` and `merge` need to know it.
+pub struct ObjectKind {
+    /// Name of one object in messages, e.g. `correspondent`.
+    pub singular: &'static str,
+    /// Name of several objects in messages, e.g. `correspondents`.
+    pub plural: &'static str,
+    /// Document filter matching any of a comma-separated list of IDs.
+    pub documents_filter: &'static str,
+    pub name: fn(&Client, u64) -> Result<String, ApiError>,
+    pub delete: fn(&Client, u64) -> Result<(), ApiError>,
+    /// Bulk edit that assigns documents to the object with the given ID.
+    pub assign: fn(u64) -> BulkEdit,
+}
+
+impl ObjectKind {
+    /// Fetch the objects' names, labelled as `Name (#ID)`.
+    fn labels(&self, client: &Client, ids: &[u64]) -> anyhow::Result<Vec<(u64, String)>> {
+        ids.iter()
+            .map(|&id| Ok((id, format!("{} (#{id})", (self.name)(client, id)?))))
+            .collect()
+    }
+}
+
+pub fn delete_objects(
+    client: &Client,
+    kind: &ObjectKind,
+    ids: &[u64],
+    yes: bool,
+) -> anyhow::Result<()> {
+    let objects = kind.labels(client, ids)?;
+    let names: Vec<&str> = objects.iter().map(|(_, label)| label.as_str()).collect();
+    confirm(
+        &format!("Delete {} {}?", kind.plural, names.join(", ")),
+        yes,
+    )?;
+
+    for (id, label) in &objects {
+        (kind.delete)(client, *id)?;
+        eprintln!("Deleted {} {label}", kind.singular);
+    }
+    Ok(())
+}
+
+/// Reassign all documents of `sources` to `target`, then delete `sources`.
+pub fn merge_objects(
+    client: &Client,
+    kind: &ObjectKind,
+    sources: &[u64],
+    target: u64,
+    yes: bool,
+) -> anyhow::Result<()> {
+    if sources.contains(&target) {
+        anyhow::bail!("--into target #{target} must not be one of the sources");
+    }
+    let target_label = format!("{} (#{target})", (kind.name)(client, target)?);
+    // Each source is deleted once, however often it was given.
+    let sources: Vec<u64> = sources
+        .iter()
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let sources = kind.labels(client, &sources)?;
+    let names: Vec<&str> = sources.iter().map(|(_, label)| label.as_str()).collect();
+    confirm(
+        &format!(
+            "Merge {} {} into {target_label}?",
+            kind.plural,
+            names.join(", ")
+        ),
+        yes,
+    )?;
+
+    let source_ids = join_ids(sources.iter().map(|(id, _)| *id));
+    let documents = client.collect_document_ids(&[(kind.documents_filter, &source_ids)])?;
+    if !documents.is_empty() {
+        client.bulk_edit(&documents, &(kind.assign)(target))?;
+    }
+    eprintln!("Reassigned {} documents to {target_label}", documents.len());
+
+    for (id, label) in &sources {
+        (kind.delete)(client, *id)?;
+        eprintln!("Deleted {} {label}", kind.singular);
     }
     Ok(())
 }
@@ -0,0 +1,34 @@
+use anyhow::Result;
+
+use crate::config::{ConfigError, ConfigFile};
+
+pub fn list() -> Result<()> {
+    let file = ConfigFile::load()?;
+    let profiles = file.profiles();
+    if profiles.is_empty() {
+        println!("No profiles configured. Run `pngx auth login --profile <NAME>` to add one.");
+        return Ok(());
+    }
+
+    for name in profiles {
+        let marker = if file.default_profile() == Some(name) {
+            "*"
+        } else {
+            " "
+        };
+        let url = file.url(Some(name)).unwrap_or("(no URL)");
+        println!("{marker} {name}\t{url}");
+    }
+    Ok(())
+}
+
+pub fn use_profile(name: &str) -> Result<()> {
+    let mut file = ConfigFile::load()?;
+    if !file.has_profile(name) {
+        return Err(ConfigError::UnknownProfile(name.to_string()).into());
+    }
+    file.set_default_profile(name);
+    file.save()?;
+    println!("Default profile set to '{name}'");
+    Ok(())
+}
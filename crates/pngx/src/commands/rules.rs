@@ -0,0 +1,723 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use figment::Figment;
+use figment::providers::{Format, Toml};
+use pngx_client::{Client, Document, DocumentRequest, Suggestions};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::sync::Template;
+use crate::output::resolve_documents;
+use crate::resolve::NameResolver;
+
+/// Default rules file, in the config directory.
+pub const RULES_FILE: &str = "rules.toml";
+
+pub struct ProcessOptions {
+    /// TOML file with the rules.
+    pub rules: PathBuf,
+    /// Print the plan without changing documents.
+    pub dry_run: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RuleSpec>,
+}
+
+/// A rule as written in the rules file, with names instead of IDs.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    name: String,
+    #[serde(default)]
+    when: ConditionSpec,
+    #[serde(default)]
+    then: ActionSpec,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConditionSpec {
+    /// Regular expressions the fields must match.
+    content: Option<String>,
+    title: Option<String>,
+    original_file_name: Option<String>,
+    /// Names the server must suggest.
+    suggested_correspondent: Option<String>,
+    suggested_document_type: Option<String>,
+    #[serde(default)]
+    suggested_tags: Vec<String>,
+    /// Regular expressions the values of custom fields must match, by
+    /// field name.
+    #[serde(default)]
+    custom_fields: BTreeMap<String, String>,
+    /// Whether the document must have an archive serial number.
+    asn: Option<bool>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActionSpec {
+    correspondent: Option<String>,
+    document_type: Option<String>,
+    /// Tags to add.
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Template for the new title.
+    title: Option<String>,
+    #[serde(default)]
+    remove_inbox_tag: bool,
+}
+
+/// A rule with its patterns compiled and names resolved to IDs.
+struct Rule {
+    name: String,
+    content: Option<Regex>,
+    title: Option<Regex>,
+    original_file_name: Option<Regex>,
+    suggested_correspondent: Option<u64>,
+    suggested_document_type: Option<u64>,
+    suggested_tags: Vec<u64>,
+    custom_fields: Vec<(u64, Regex)>,
+    asn: Option<bool>,
+    set_correspondent: Option<u64>,
+    set_document_type: Option<u64>,
+    add_tags: Vec<u64>,
+    set_title: Option<Template>,
+    remove_inbox_tag: bool,
+}
+
+impl Rule {
+    fn needs_suggestions(&self) -> bool {
+        self.suggested_correspondent.is_some()
+            || self.suggested_document_type.is_some()
+            || !self.suggested_tags.is_empty()
+    }
+}
+
+/// IDs of the taxonomy by lowercase name.
+struct Taxonomy {
+    tags: HashMap<String, u64>,
+    correspondents: HashMap<String, u64>,
+    document_types: HashMap<String, u64>,
+    custom_fields: HashMap<String, u64>,
+    inbox_tags: Vec<u64>,
+    names: NameResolver,
+}
+
+impl Taxonomy {
+    fn fetch(client: &Client) -> Result<Self> {
+        let (tags, _) = client.collect_tags(None)?;
+        let (correspondents, _) = client.collect_correspondents(None)?;
+        let (document_types, _) = client.collect_document_types(None)?;
+        let (custom_fields, _) = client.collect_custom_fields(None)?;
+        let by_name = |objects: Vec<(u64, &str)>| -> HashMap<String, u64> {
+            objects
+                .into_iter()
+                .map(|(id, name)| (name.to_lowercase(), id))
+                .collect()
+        };
+        Ok(Self {
+            tags: by_name(tags.iter().map(|t| (t.id, t.name.as_str())).collect()),
+            correspondents: by_name(
+                correspondents
+                    .iter()
+                    .map(|c| (c.id, c.name.as_str()))
+                    .collect(),
+            ),
+            document_types: by_name(
+                document_types
+                    .iter()
+                    .map(|dt| (dt.id, dt.name.as_str()))
+                    .collect(),
+            ),
+            custom_fields: by_name(
+                custom_fields
+                    .iter()
+                    .map(|cf| (cf.id, cf.name.as_str()))
+                    .collect(),
+            ),
+            inbox_tags: tags
+                .iter()
+                .filter(|tag| tag.is_inbox_tag == Some(true))
+                .map(|tag| tag.id)
+                .collect(),
+            names: NameResolver::from_lists(tags, correspondents, document_types),
+        })
+    }
+
+    fn compile(&self, spec: RuleSpec) -> Result<Rule> {
+        let RuleSpec { name, when, then } = spec;
+        let context = || format!("rule \"{name}\"");
+        let regex = |pattern: Option<String>| -> Result<Option<Regex>> {
+            pattern
+                .map(|pattern| Regex::new(&pattern))
+                .transpose()
+                .with_context(context)
+        };
+        let id = |ids: &HashMap<String, u64>, kind: &str, name: &str| -> Result<u64> {
+            ids.get(&name.to_lowercase())
+                .copied()
+                .with_context(|| format!("unknown {kind} \"{name}\""))
+                .with_context(context)
+        };
+        let tag_ids = |names: &[String]| -> Result<Vec<u64>> {
+            names.iter().map(|tag| id(&self.tags, "tag", tag)).collect()
+        };
+        if then.correspondent.is_none()
+            && then.document_type.is_none()
+            && then.tags.is_empty()
+            && then.title.is_none()
+            && !then.remove_inbox_tag
+        {
+            bail!("rule \"{name}\" has no actions");
+        }
+        Ok(Rule {
+            content: regex(when.content)?,
+            title: regex(when.title)?,
+            original_file_name: regex(when.original_file_name)?,
+            suggested_correspondent: when
+                .suggested_correspondent
+                .map(|c| id(&self.correspondents, "correspondent", &c))
+                .transpose()?,
+            suggested_document_type: when
+                .suggested_document_type
+                .map(|dt| id(&self.document_types, "document type", &dt))
+                .transpose()?,
+            suggested_tags: tag_ids(&when.suggested_tags)?,
+            custom_fields: when
+                .custom_fields
+                .iter()
+                .map(|(field, pattern)| {
+                    let field = id(&self.custom_fields, "custom field", field)?;
+                    let pattern = Regex::new(pattern).with_context(context)?;
+                    Ok((field, pattern))
+                })
+                .collect::<Result<_>>()?,
+            asn: when.asn,
+            set_correspondent: then
+                .correspondent
+                .map(|c| id(&self.correspondents, "correspondent", &c))
+                .transpose()?,
+            set_document_type: then
+                .document_type
+                .map(|dt| id(&self.document_types, "document type", &dt))
+                .transpose()?,
+            add_tags: tag_ids(&then.tags)?,
+            set_title: then
+                .title
+                .map(|title| Template::parse(&title))
+                .transpose()
+                .map_err(anyhow::Error::msg)
+                .with_context(context)?,
+            remove_inbox_tag: then.remove_inbox_tag,
+            name,
+        })
+    }
+}
+
+/// Content and suggestions of a document, fetched once a rule needs them.
+struct Facts<'a> {
+    client: &'a Client,
+    doc: &'a Document,
+    content: Option<String>,
+    suggestions: Option<Suggestions>,
+}
+
+impl<'a> Facts<'a> {
+    fn new(client: &'a Client, doc: &'a Document) -> Self {
+        Self {
+            client,
+            doc,
+            content: None,
+            suggestions: None,
+        }
+    }
+
+    fn content(&mut self) -> Result<&str> {
+        if self.content.is_none() {
+            let content = match &self.doc.content {
+                Some(content) => content.clone(),
+                None => self.client.document_content(self.doc.id)?,
+            };
+            self.content = Some(content);
+        }
+        Ok(self.content.as_deref().unwrap_or_default())
+    }
+
+    fn suggestions(&mut self) -> Result<&Suggestions> {
+        if self.suggestions.is_none() {
+            self.suggestions = Some(self.client.suggestions(self.doc.id)?);
+        }
+        Ok(self.suggestions.get_or_insert_default())
+    }
+
+    /// Whether every condition of `rule` holds.
+    fn matches(&mut self, rule: &Rule) -> Result<bool> {
+        let doc = self.doc;
+        let text_matches = |pattern: &Option<Regex>, text: Option<&str>| {
+            pattern
+                .as_ref()
+                .is_none_or(|pattern| text.is_some_and(|text| pattern.is_match(text)))
+        };
+        if !text_matches(&rule.title, Some(&doc.title))
+            || !text_matches(&rule.original_file_name, doc.original_file_name.as_deref())
+            || rule
+                .asn
+                .is_some_and(|asn| asn != doc.archive_serial_number.is_some())
+        {
+            return Ok(false);
+        }
+        for (field, pattern) in &rule.custom_fields {
+            let value = doc.custom_fields.iter().find(|cf| cf.field == *field);
+            let matched = value.is_some_and(|cf| match cf.value.as_str() {
+                Some(text) => pattern.is_match(text),
+                None => !cf.value.is_null() && pattern.is_match(&cf.value.to_string()),
+            });
+            if !matched {
+                return Ok(false);
+            }
+        }
+        if let Some(pattern) = &rule.content
+            && !pattern.is_match(self.content()?)
+        {
+            return Ok(false);
+        }
+        if rule.needs_suggestions() {
+            let suggestions = self.suggestions()?;
+            let suggested = rule
+                .suggested_correspondent
+                .is_none_or(|id| suggestions.correspondents.contains(&id))
+                && rule
+                    .suggested_document_type
+                    .is_none_or(|id| suggestions.document_types.contains(&id))
+                && rule
+                    .suggested_tags
+                    .iter()
+                    .all(|id| suggestions.tags.contains(id));
+            if !suggested {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// The changes the matching rules make to one document.
+struct Plan<'a> {
+    doc: &'a Document,
+    rules: Vec<&'a str>,
+    request: DocumentRequest,
+}
+
+impl Plan<'_> {
+    /// Describes the changes, one field per line.
+    fn describe(&self, taxonomy: &Taxonomy) -> String {
+        let names = &taxonomy.names;
+        let doc = self.doc;
+        let correspondent = |id: Option<u64>| {
+            id.and_then(|id| names.correspondent_name(id))
+                .unwrap_or_else(|| "none".to_string())
+        };
+        let document_type = |id: Option<u64>| {
+            id.and_then(|id| names.document_type_name(id))
+                .unwrap_or_else(|| "none".to_string())
+        };
+        let mut lines = vec![
+            format!("#{} {}", doc.id, doc.title),
+            format!("  rules: {}", self.rules.join(", ")),
+        ];
+        if let Some(id) = self.request.correspondent {
+            lines.push(format!(
+                "  correspondent: {} -> {}",
+                correspondent(doc.correspondent),
+                correspondent(id)
+            ));
+        }
+        if let Some(id) = self.request.document_type {
+            lines.push(format!(
+                "  document type: {} -> {}",
+                document_type(doc.document_type),
+                document_type(id)
+            ));
+        }
+        if let Some(tags) = &self.request.tags {
+            let added = tags
+                .iter()
+                .filter(|id| !doc.tags.contains(id))
+                .map(|&id| format!("+{}", names.tag_name(id)));
+            let removed = doc
+                .tags
+                .iter()
+                .filter(|id| !tags.contains(id))
+                .map(|&id| format!("-{}", names.tag_name(id)));
+            lines.push(format!(
+                "  tags: {}",
+                added.chain(removed).collect::<Vec<_>>().join(" ")
+            ));
+        }
+        if let Some(title) = &self.request.title {
+            lines.push(format!("  title: {} -> {title}", doc.title));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Applies the rules to every inbox document, printing the plan for each
+/// changed document before updating any of them.
+pub fn process(client: &Client, options: &ProcessOptions) -> Result<()> {
+    let taxonomy = Taxonomy::fetch(client)?;
+    let rules = load_rules(&options.rules)?
+        .rule
+        .into_iter()
+        .map(|spec| taxonomy.compile(spec))
+        .collect::<Result<Vec<_>>>()?;
+    let (docs, _) = client.collect_inbox_documents(None)?;
+
+    let mut plans = Vec::new();
+    for doc in &docs {
+        if let Some(plan) = plan(client, &taxonomy, &rules, doc)? {
+            println!("{}", plan.describe(&taxonomy));
+            plans.push(plan);
+        }
+    }
+    if options.dry_run {
+        eprintln!(
+            "Dry run: {} of {} inbox documents would change",
+            plans.len(),
+            docs.len()
+        );
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for plan in &plans {
+        if let Err(err) = client.update_document(plan.doc.id, &plan.request) {
+            eprintln!("Could not update #{}: {err}", plan.doc.id);
+            failed += 1;
+        }
+    }
+    eprintln!(
+        "Updated {} of {} inbox documents",
+        plans.len() - failed,
+        docs.len()
+    );
+    if failed > 0 {
+        bail!("{failed} documents could not be updated");
+    }
+    Ok(())
+}
+
+/// Merges the actions of the matching rules, in order: later rules
+/// override the correspondent, document type, and title, tags add up.
+/// Returns `None` if no rule changes the document.
+fn plan<'a>(
+    client: &Client,
+    taxonomy: &Taxonomy,
+    rules: &'a [Rule],
+    doc: &'a Document,
+) -> Result<Option<Plan<'a>>> {
+    let mut facts = Facts::new(client, doc);
+    let mut planned = doc.clone();
+    let mut matched = Vec::new();
+    let mut title = None;
+    let mut remove_inbox_tag = false;
+    for rule in rules {
+        if !facts.matches(rule)? {
+            continue;
+        }
+        matched.push(rule.name.as_str());
+        if let Some(id) = rule.set_correspondent {
+            planned.correspondent = Some(id);
+        }
+        if let Some(id) = rule.set_document_type {
+            planned.document_type = Some(id);
+        }
+        for &id in &rule.add_tags {
+            if !planned.tags.contains(&id) {
+                planned.tags.push(id);
+            }
+        }
+        title = rule.set_title.as_ref().or(title);
+        remove_inbox_tag |= rule.remove_inbox_tag;
+    }
+    if remove_inbox_tag {
+        planned.tags.retain(|id| !taxonomy.inbox_tags.contains(id));
+    }
+    if let Some(template) = title {
+        let resolved = resolve_documents(std::slice::from_ref(&planned), &taxonomy.names);
+        if let Some(resolved) = resolved.first() {
+            planned.title = template.render_text(resolved);
+        }
+    }
+
+    let request = DocumentRequest {
+        title: (planned.title != doc.title).then_some(planned.title),
+        correspondent: (planned.correspondent != doc.correspondent)
+            .then_some(planned.correspondent),
+        document_type: (planned.document_type != doc.document_type)
+            .then_some(planned.document_type),
+        tags: (planned.tags != doc.tags).then_some(planned.tags),
+        ..DocumentRequest::default()
+    };
+    if request == DocumentRequest::default() {
+        return Ok(None);
+    }
+    Ok(Some(Plan {
+        doc,
+        rules: matched,
+        request,
+    }))
+}
+
+fn load_rules(path: &Path) -> Result<RulesFile> {
+    Figment::from(Toml::file_exact(path))
+        .extract()
+        .with_context(|| format!("failed to read rules from {}", path.display()))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use pngx_client::MatchingAlgorithm;
+    use pngx_testing::{FakePaperless, seed};
+
+    use super::*;
+
+    async fn server() -> FakePaperless {
+        FakePaperless::builder()
+            .tag(seed::Tag::new("Inbox").inbox())
+            .tag(seed::Tag::new("Tax"))
+            .tag(seed::Tag::new("Paid"))
+            .correspondent(
+                seed::Correspondent::new("ACME").matching(MatchingAlgorithm::Any, "acme"),
+            )
+            .correspondent(seed::Correspondent::new("Tax office"))
+            .document_type(seed::DocumentType::new("Invoice"))
+            .custom_field(seed::CustomField::new("Amount", "monetary"))
+            .custom_field(seed::CustomField::new("Count", "integer"))
+            .document(
+                seed::Document::new("Invoice 42")
+                    .content("ACME invoice, total 42")
+                    .custom_field(1, "EUR42.00")
+                    .custom_field(2, 3)
+                    .tag(1),
+            )
+            .document(seed::Document::new("Letter").content("Hello").tag(1))
+            .start()
+            .await
+    }
+
+    fn rules(taxonomy: &Taxonomy, toml: &str) -> Result<Vec<Rule>> {
+        let file: RulesFile = Figment::from(Toml::string(toml)).extract()?;
+        file.rule
+            .into_iter()
+            .map(|spec| taxonomy.compile(spec))
+            .collect()
+    }
+
+    /// Requests for the content or suggestions of single documents.
+    async fn detail_requests(server: &FakePaperless) -> Vec<String> {
+        server
+            .mock_server()
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| request.url.path().to_string())
+            .filter(|path| path != "/api/documents/" && path.starts_with("/api/documents/"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_compile_resolves_names_ignoring_case() {
+        let server = server().await;
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let taxonomy = Taxonomy::fetch(&client).unwrap();
+
+        let compiled = rules(
+            &taxonomy,
+            r#"
+            [[rule]]
+            name = "ACME"
+            when = { suggested_correspondent = "acme", custom_fields = { AMOUNT = "." } }
+            then = { document_type = "INVOICE", tags = ["tax", "PAID"] }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(compiled[0].suggested_correspondent, Some(1));
+        assert_eq!(compiled[0].custom_fields[0].0, 1);
+        assert_eq!(compiled[0].set_document_type, Some(1));
+        assert_eq!(compiled[0].add_tags, [2, 3]);
+
+        let err = rules(
+            &taxonomy,
+            "[[rule]]\nname = \"Typo\"\nthen = { tags = [\"Taxes\"] }\n",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(format!("{err:#}"), "rule \"Typo\": unknown tag \"Taxes\"");
+    }
+
+    #[tokio::test]
+    async fn test_compile_rejects_rules_without_actions() {
+        let server = server().await;
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let taxonomy = Taxonomy::fetch(&client).unwrap();
+
+        let err = rules(
+            &taxonomy,
+            "[[rule]]\nname = \"Idle\"\nwhen = { title = \"Invoice\" }\n",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "rule \"Idle\" has no actions");
+    }
+
+    #[tokio::test]
+    async fn test_plan_merges_matching_rules_in_order() {
+        let server = server().await;
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let taxonomy = Taxonomy::fetch(&client).unwrap();
+        let rules = rules(
+            &taxonomy,
+            r#"
+            [[rule]]
+            name = "First"
+            when = { title = "^Invoice" }
+            then = { correspondent = "ACME", document_type = "Invoice", tags = ["Tax"], title = "First" }
+
+            [[rule]]
+            name = "Letters"
+            when = { title = "^Letter" }
+            then = { tags = ["Paid"] }
+
+            [[rule]]
+            name = "Second"
+            when = { title = "^Invoice" }
+            then = { correspondent = "Tax office", tags = ["Paid"], title = "Second", remove_inbox_tag = true }
+            "#,
+        )
+        .unwrap();
+        let (docs, _) = client.collect_inbox_documents(None).unwrap();
+
+        let invoice = plan(&client, &taxonomy, &rules, &docs[0]).unwrap().unwrap();
+        assert_eq!(invoice.rules, ["First", "Second"]);
+        assert_eq!(invoice.request.correspondent, Some(Some(2)));
+        assert_eq!(invoice.request.document_type, Some(Some(1)));
+        assert_eq!(invoice.request.tags, Some(vec![2, 3]));
+        assert_eq!(invoice.request.title.as_deref(), Some("Second"));
+
+        let letter = plan(&client, &taxonomy, &rules, &docs[1]).unwrap().unwrap();
+        assert_eq!(letter.rules, ["Letters"]);
+        assert_eq!(letter.request.tags, Some(vec![1, 3]));
+        assert_eq!(letter.request.correspondent, None);
+    }
+
+    #[tokio::test]
+    async fn test_plan_skips_documents_rules_do_not_change() {
+        let server = server().await;
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let taxonomy = Taxonomy::fetch(&client).unwrap();
+        let rules = rules(
+            &taxonomy,
+            "[[rule]]\nname = \"Inbox\"\nthen = { tags = [\"Inbox\"] }\n",
+        )
+        .unwrap();
+        let (docs, _) = client.collect_inbox_documents(None).unwrap();
+
+        assert!(
+            plan(&client, &taxonomy, &rules, &docs[0])
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_field_values_match_as_text() {
+        let server = server().await;
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let taxonomy = Taxonomy::fetch(&client).unwrap();
+        let rules = rules(
+            &taxonomy,
+            r#"
+            [[rule]]
+            name = "Three"
+            when = { custom_fields = { Amount = "^EUR42", Count = "^3$" } }
+            then = { tags = ["Paid"] }
+
+            [[rule]]
+            name = "Four"
+            when = { custom_fields = { Count = "^4$" } }
+            then = { tags = ["Tax"] }
+            "#,
+        )
+        .unwrap();
+        let (docs, _) = client.collect_inbox_documents(None).unwrap();
+
+        let mut invoice = Facts::new(&client, &docs[0]);
+        assert!(invoice.matches(&rules[0]).unwrap());
+        assert!(!invoice.matches(&rules[1]).unwrap());
+        // The letter has no values at all.
+        assert!(!Facts::new(&client, &docs[1]).matches(&rules[0]).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_facts_fetch_content_and_suggestions_once_needed() {
+        let server = server().await;
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let taxonomy = Taxonomy::fetch(&client).unwrap();
+        let rules = rules(
+            &taxonomy,
+            r#"
+            [[rule]]
+            name = "Title"
+            when = { title = "^Invoice" }
+            then = { tags = ["Tax"] }
+
+            [[rule]]
+            name = "Content"
+            when = { content = "(?i)acme" }
+            then = { tags = ["Tax"] }
+
+            [[rule]]
+            name = "Content again"
+            when = { content = "total" }
+            then = { tags = ["Paid"] }
+
+            [[rule]]
+            name = "Suggested"
+            when = { suggested_correspondent = "ACME" }
+            then = { correspondent = "ACME" }
+
+            [[rule]]
+            name = "Suggested type"
+            when = { suggested_document_type = "Invoice" }
+            then = { document_type = "Invoice" }
+            "#,
+        )
+        .unwrap();
+        let (docs, _) = client.collect_inbox_documents(None).unwrap();
+        assert!(docs[0].content.is_none());
+        let mut facts = Facts::new(&client, &docs[0]);
+
+        assert!(facts.matches(&rules[0]).unwrap());
+        assert!(detail_requests(&server).await.is_empty());
+
+        assert!(facts.matches(&rules[1]).unwrap());
+        assert!(facts.matches(&rules[2]).unwrap());
+        assert_eq!(detail_requests(&server).await, ["/api/documents/1/"]);
+
+        assert!(facts.matches(&rules[3]).unwrap());
+        assert!(!facts.matches(&rules[4]).unwrap());
+        assert_eq!(
+            detail_requests(&server).await,
+            ["/api/documents/1/", "/api/documents/1/suggestions/"]
+        );
+    }
+}
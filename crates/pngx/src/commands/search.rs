@@ -1,23 +1,60 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use pngx_client::Client;
 
-use crate::output::{FieldFilter, OutputFormat, resolve_documents};
-use crate::resolve::NameResolver;
+use crate::config::ValidConfig;
+use crate::index::LocalIndex;
+use crate::output::{
+    FieldFilter, OutputOptions, SearchHit, raw_pages, resolve_documents, resolve_pages,
+};
+use crate::resolve::{NameCache, NameResolver};
 
 pub fn search(
     client: &Client,
+    cache: &NameCache,
     query: &str,
-    format: OutputFormat,
+    output: OutputOptions<'_>,
     limit: Option<usize>,
     fields: Option<&FieldFilter>,
+    raw: bool,
 ) -> Result<()> {
-    let (results, total) = client.collect_search(query, limit)?;
-    if results.is_empty() {
-        super::print_empty(format, &format!("No documents found for query: {query}"))?;
-    } else {
-        let names = NameResolver::fetch(client, fields)?;
-        let results = resolve_documents(&results, &names);
-        super::print_results(format, &results, total, fields)?;
+    let empty = format!("No documents found for query: {query}");
+    if raw {
+        let pages = raw_pages(client.search_pages(query));
+        return super::print_results(output, pages, limit, fields, &empty);
     }
-    Ok(())
+    let names = NameResolver::cached(fields, cache);
+    let pages = resolve_pages(client, client.search_pages(query), names, cache);
+    super::print_results(output, pages, limit, fields, &empty)
+}
+
+/// Search the local index, without contacting the server.
+pub fn search_local(
+    config: &ValidConfig,
+    query: &str,
+    output: OutputOptions<'_>,
+    limit: Option<usize>,
+    fields: Option<&FieldFilter>,
+    raw: bool,
+) -> Result<()> {
+    let index = LocalIndex::open(&LocalIndex::path(config))?
+        .ok_or_else(|| anyhow!("no local index for {}, run `pngx index build`", config.url))?;
+    let (total, hits) = index.search(query, limit)?;
+    let empty = format!("No documents found for query: {query}");
+    if raw {
+        let docs = hits.into_iter().map(|hit| hit.document).collect();
+        return super::print_results(output, [Ok((total, docs))], limit, fields, &empty);
+    }
+
+    let docs: Vec<_> = hits.iter().map(|hit| hit.document.clone()).collect();
+    let resolved = resolve_documents(&docs, &index.state.names);
+    let hits = resolved
+        .into_iter()
+        .zip(hits)
+        .map(|(document, hit)| SearchHit {
+            document,
+            score: hit.score,
+            snippet: hit.snippet,
+        })
+        .collect();
+    super::print_results(output, [Ok((total, hits))], limit, fields, &empty)
 }
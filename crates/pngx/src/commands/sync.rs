@@ -0,0 +1,578 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use jiff::Timestamp;
+use pngx_client::{Client, Document, DocumentVersion};
+use serde::{Deserialize, Serialize};
+
+use crate::output::{ResolvedDocument, resolve_documents};
+use crate::resolve::{NameCache, NameResolver};
+
+/// Sync progress, kept in the target directory.
+const STATE_FILE: &str = ".pngx-sync.json";
+
+pub const DEFAULT_TEMPLATE: &str = "{correspondent}/{created} {title}";
+
+/// How often the state is saved while documents are processed, so an
+/// interrupted sync doesn't download them again.
+const SAVE_EVERY: usize = 25;
+
+/// Longest file name component produced by a template, in bytes.
+const MAX_COMPONENT_LEN: usize = 150;
+
+/// Which files to mirror for each document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Files {
+    pub original: bool,
+    pub archived: bool,
+}
+
+pub struct SyncOptions {
+    pub files: Files,
+    pub template: Template,
+    /// Check every document instead of only those modified since the last
+    /// sync.
+    pub full: bool,
+    /// Delete local copies of documents deleted on the server.
+    pub prune: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SyncState {
+    server: String,
+    template: String,
+    files: Option<Files>,
+    /// Newest modification time of the synced documents, as reported by the
+    /// server.
+    last_modified: Option<Timestamp>,
+    documents: BTreeMap<u64, SyncedDocument>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SyncedDocument {
+    modified: Option<Timestamp>,
+    /// Path of the document relative to the sync directory, without an
+    /// extension.
+    stem: String,
+    /// Relative paths of the files written for the document.
+    files: BTreeMap<FileKind, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum FileKind {
+    Original,
+    Archived,
+    Metadata,
+}
+
+/// Metadata written next to the files of each document.
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    #[serde(flatten)]
+    document: &'a ResolvedDocument,
+    content: Option<&'a str>,
+}
+
+#[derive(Default)]
+struct Summary {
+    new: usize,
+    updated: usize,
+    unchanged: usize,
+    pruned: usize,
+}
+
+pub fn sync(
+    client: &Client,
+    cache: &NameCache,
+    server: &str,
+    dir: &Path,
+    options: &SyncOptions,
+) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create directory: {}", dir.display()))?;
+    let state_path = dir.join(STATE_FILE);
+    let mut state = load_state(&state_path)?;
+    if state.server.is_empty() {
+        state.server = server.to_string();
+    } else if state.server != server {
+        bail!(
+            "{} is a mirror of {}, not {server}",
+            dir.display(),
+            state.server
+        );
+    }
+
+    let template = options.template.to_string();
+    let settings_changed = state.files.is_some_and(|files| files != options.files)
+        || (!state.template.is_empty() && state.template != template);
+    if settings_changed && !options.full {
+        eprintln!("File settings changed since the last sync, checking all documents");
+    }
+    let full = options.full || settings_changed || state.last_modified.is_none();
+    state.template = template;
+    state.files = Some(options.files);
+
+    let since = state.last_modified.map(|ts| ts.to_string());
+    let mut filters = vec![("ordering", "modified")];
+    if let (false, Some(since)) = (full, &since) {
+        filters.push(("modified__gt", since));
+    }
+    let mut mirror = Mirror {
+        client,
+        dir,
+        state_path: &state_path,
+        options,
+        stems: state
+            .documents
+            .iter()
+            .map(|(&id, synced)| (synced.stem.clone(), id))
+            .collect(),
+        state,
+        summary: Summary::default(),
+        seen: HashSet::new(),
+    };
+    let mut names = NameResolver::cached(None, cache);
+    for page in client.matching_document_pages(&filters) {
+        let docs = page?.results;
+        names.fill(client, &docs, cache)?;
+        for (doc, meta) in docs.iter().zip(&resolve_documents(&docs, &names)) {
+            mirror.sync(doc, meta)?;
+        }
+    }
+    let Mirror {
+        mut state,
+        mut summary,
+        seen,
+        ..
+    } = mirror;
+
+    if options.prune {
+        let existing: HashSet<u64> = if full {
+            seen
+        } else {
+            client.collect_document_ids(&[])?.into_iter().collect()
+        };
+        let deleted: Vec<u64> = state
+            .documents
+            .keys()
+            .copied()
+            .filter(|id| !existing.contains(id))
+            .collect();
+        for id in deleted {
+            if let Some(synced) = state.documents.remove(&id) {
+                for path in synced.files.values() {
+                    remove_file(dir, path)?;
+                }
+                eprintln!("Removed {}", synced.stem);
+                summary.pruned += 1;
+            }
+        }
+    }
+    save_state(&state_path, &state)?;
+
+    eprintln!(
+        "Synced {} documents to {}: {} new, {} updated, {} unchanged, {} removed",
+        state.documents.len(),
+        dir.display(),
+        summary.new,
+        summary.updated,
+        summary.unchanged,
+        summary.pruned,
+    );
+    Ok(())
+}
+
+/// A sync in progress.
+struct Mirror<'a> {
+    client: &'a Client,
+    dir: &'a Path,
+    state_path: &'a Path,
+    options: &'a SyncOptions,
+    state: SyncState,
+    /// Owner of each stem in use, to keep file names unique.
+    stems: HashMap<String, u64>,
+    summary: Summary,
+    /// IDs of the documents synced so far.
+    seen: HashSet<u64>,
+}
+
+impl Mirror<'_> {
+    /// Syncs one document and records it in the state, which is saved
+    /// periodically.
+    fn sync(&mut self, doc: &Document, meta: &ResolvedDocument) -> Result<()> {
+        let previous = self.state.documents.get(&doc.id).cloned();
+        let mut stem = self.options.template.render(meta);
+        if self.stems.get(&stem).is_some_and(|&owner| owner != doc.id) {
+            stem = format!("{stem} ({})", doc.id);
+        }
+        let synced = sync_document(
+            self.client,
+            self.dir,
+            doc,
+            meta,
+            stem,
+            previous.as_ref(),
+            self.options.files,
+        )
+        .with_context(|| format!("failed to sync document {}", doc.id))?;
+
+        match &previous {
+            None => self.summary.new += 1,
+            Some(prev) if prev.modified != doc.modified => self.summary.updated += 1,
+            Some(_) => self.summary.unchanged += 1,
+        }
+        if let Some(prev) = &previous {
+            self.stems.remove(&prev.stem);
+        }
+        self.stems.insert(synced.stem.clone(), doc.id);
+        self.state.documents.insert(doc.id, synced);
+        self.state.last_modified = self.state.last_modified.max(doc.modified);
+
+        self.seen.insert(doc.id);
+        if self.seen.len().is_multiple_of(SAVE_EVERY) {
+            save_state(self.state_path, &self.state)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the files and sidecar of one document, reusing unchanged files
+/// from the previous sync and removing those no longer needed.
+fn sync_document(
+    client: &Client,
+    dir: &Path,
+    doc: &Document,
+    meta: &ResolvedDocument,
+    stem: String,
+    previous: Option<&SyncedDocument>,
+    files: Files,
+) -> Result<SyncedDocument> {
+    let unchanged = previous.is_some_and(|prev| prev.modified == doc.modified);
+    let mut written = BTreeMap::new();
+    for (kind, path) in file_paths(doc, &stem, files) {
+        let target = dir.join(&path);
+        let old = previous
+            .and_then(|prev| prev.files.get(&kind))
+            .map(|old| dir.join(old));
+        match old {
+            Some(old) if unchanged && old.is_file() => {
+                if old != target {
+                    create_parent(&target)?;
+                    fs::rename(&old, &target)?;
+                    remove_empty_parents(dir, &old);
+                }
+            }
+            _ => {
+                let version = if kind == FileKind::Original {
+                    DocumentVersion::Original
+                } else {
+                    DocumentVersion::Archived
+                };
+                download(client, doc.id, version, &target)?;
+                eprintln!("Downloaded {path}");
+            }
+        }
+        written.insert(kind, path);
+    }
+
+    let path = format!("{stem}.json");
+    let sidecar = Sidecar {
+        document: meta,
+        content: doc.content.as_deref(),
+    };
+    write_file(&dir.join(&path), &serde_json::to_vec_pretty(&sidecar)?)?;
+    written.insert(FileKind::Metadata, path);
+
+    if let Some(prev) = previous {
+        for old in prev.files.values() {
+            if !written.values().any(|path| path == old) {
+                remove_file(dir, old)?;
+            }
+        }
+    }
+    Ok(SyncedDocument {
+        modified: doc.modified,
+        stem,
+        files: written,
+    })
+}
+
+/// Relative paths of the files to download for `doc`. Documents without an
+/// archived version only get their original.
+fn file_paths(doc: &Document, stem: &str, files: Files) -> Vec<(FileKind, String)> {
+    let has_archive = doc.archived_file_name.is_some();
+    let archived = files.archived && (has_archive || !files.original);
+    let original_ext = extension(doc.original_file_name.as_deref());
+    let archived_ext = if has_archive {
+        extension(doc.archived_file_name.as_deref())
+    } else {
+        original_ext.clone()
+    };
+
+    let mut paths = Vec::new();
+    if archived {
+        paths.push((FileKind::Archived, format!("{stem}{archived_ext}")));
+    }
+    if files.original {
+        // Keep originals apart from the archived file and the sidecar.
+        let path = if archived || original_ext == ".json" {
+            format!("{stem}.original{original_ext}")
+        } else {
+            format!("{stem}{original_ext}")
+        };
+        paths.push((FileKind::Original, path));
+    }
+    paths
+}
+
+/// The extension of `name` including the dot, or an empty string.
+pub(super) fn extension(name: Option<&str>) -> String {
+    name.and_then(|name| Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .map(|ext| format!(".{}", ext.to_lowercase()))
+        .unwrap_or_default()
+}
+
+fn download(client: &Client, id: u64, version: DocumentVersion, path: &Path) -> Result<()> {
+    create_parent(path)?;
+    let tmp = part_path(path);
+    let mut file = fs::File::create(&tmp)
+        .with_context(|| format!("failed to create file: {}", tmp.display()))?;
+    client.download_document(id, version, &mut file)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+pub(super) fn write_file(path: &Path, data: &[u8]) -> Result<()> {
+    create_parent(path)?;
+    let tmp = part_path(path);
+    fs::write(&tmp, data).with_context(|| format!("failed to write file: {}", tmp.display()))?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// A hidden sibling of `path` that is renamed over it once complete.
+pub(super) fn part_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.part"))
+}
+
+pub(super) fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory: {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+pub(super) fn remove_file(dir: &Path, path: &str) -> Result<()> {
+    let path = dir.join(path);
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to remove {}", path.display()));
+        }
+    }
+    remove_empty_parents(dir, &path);
+    Ok(())
+}
+
+/// Removes the directories above `path` that became empty, up to `dir`.
+fn remove_empty_parents(dir: &Path, path: &Path) {
+    let mut parent = path.parent();
+    while let Some(current) = parent {
+        if current == dir || !current.starts_with(dir) || fs::remove_dir(current).is_err() {
+            break;
+        }
+        parent = current.parent();
+    }
+}
+
+fn load_state(path: &Path) -> Result<SyncState> {
+    match fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data)
+            .with_context(|| format!("malformed sync state: {}", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(SyncState::default()),
+        Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+fn save_state(path: &Path, state: &SyncState) -> Result<()> {
+    write_file(path, &serde_json::to_vec_pretty(state)?)
+}
+
+// --- Template ---
+
+/// A file name template such as `{correspondent}/{created} {title}`.
+/// Slashes separate directories.
+#[derive(Debug, Clone)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Field(Field),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Id,
+    Title,
+    Correspondent,
+    DocumentType,
+    Created,
+    CreatedYear,
+    CreatedMonth,
+    CreatedDay,
+    Asn,
+    OriginalName,
+}
+
+impl Field {
+    const ALL: &[(&str, Field)] = &[
+        ("id", Self::Id),
+        ("title", Self::Title),
+        ("correspondent", Self::Correspondent),
+        ("document_type", Self::DocumentType),
+        ("created", Self::Created),
+        ("created_year", Self::CreatedYear),
+        ("created_month", Self::CreatedMonth),
+        ("created_day", Self::CreatedDay),
+        ("asn", Self::Asn),
+        ("original_name", Self::OriginalName),
+    ];
+
+    /// The field's value, or `None` if the document has none.
+    fn value(self, doc: &ResolvedDocument) -> Option<String> {
+        match self {
+            Self::Id => Some(doc.id.to_string()),
+            Self::Title => Some(doc.title.clone()),
+            Self::Correspondent => doc.correspondent_name.clone(),
+            Self::DocumentType => doc.document_type_name.clone(),
+            Self::Created => doc.created.map(|date| date.to_string()),
+            Self::CreatedYear => doc.created.map(|date| format!("{:04}", date.year())),
+            Self::CreatedMonth => doc.created.map(|date| format!("{:02}", date.month())),
+            Self::CreatedDay => doc.created.map(|date| format!("{:02}", date.day())),
+            Self::Asn => doc.archive_serial_number.map(|asn| asn.to_string()),
+            Self::OriginalName => doc.original_file_name.as_deref().and_then(|name| {
+                Path::new(name)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+            }),
+        }
+    }
+}
+
+impl Template {
+    /// Parses a template, rejecting unknown placeholders.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let Some(len) = rest[start..].find('}') else {
+                return Err(format!("unclosed '{{' in template: {source}"));
+            };
+            let name = &rest[start + 1..start + len];
+            let Some(&(_, field)) = Field::ALL.iter().find(|(n, _)| *n == name) else {
+                let valid: Vec<&str> = Field::ALL.iter().map(|(n, _)| *n).collect();
+                return Err(format!(
+                    "unknown placeholder '{{{name}}}'. Valid placeholders: {}",
+                    valid.join(", ")
+                ));
+            };
+            parts.push(Part::Field(field));
+            rest = &rest[start + len + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+        if parts.is_empty() {
+            return Err("template is empty".to_string());
+        }
+        Ok(Self {
+            source: source.to_string(),
+            parts,
+        })
+    }
+
+    /// The relative path for `doc`, without an extension. Values are made
+    /// safe for file names and can't add directories. Missing values are
+    /// rendered as `none`, like Paperless-ngx does.
+    fn render(&self, doc: &ResolvedDocument) -> String {
+        let mut path = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => path.push_str(text),
+                Part::Field(field) => {
+                    let value = field.value(doc).unwrap_or_else(|| "none".to_string());
+                    path.push_str(&sanitize(&value));
+                }
+            }
+        }
+        path.split(['/', '\\'])
+            .map(component)
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// The text for `doc`, with values as they are, e.g. for a title.
+    /// Missing values are rendered as `none`.
+    pub fn render_text(&self, doc: &ResolvedDocument) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(text) => text.clone(),
+                Part::Field(field) => field.value(doc).unwrap_or_else(|| "none".to_string()),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Replaces characters that aren't allowed in file names on common
+/// systems.
+pub(super) fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// A safe, non-empty path component of bounded length.
+pub(super) fn component(name: &str) -> String {
+    let name = name.trim().trim_end_matches('.');
+    let name = match name {
+        "" => "none",
+        ".." => "_",
+        name => name,
+    };
+    let mut end = name.len().min(MAX_COMPONENT_LEN);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name[..end].trim_end().to_string()
+}
@@ -1,10 +1,306 @@
-use anyhow::Result;
-use pngx_client::Client;
+use std::collections::{HashMap, HashSet};
 
-use crate::output::{FieldFilter, OutputFormat};
+use anyhow::{Result, bail};
+use pngx_client::{Client, Tag, TagRequest};
+use serde::Serialize;
 
-pub fn list(client: &Client, format: OutputFormat, fields: Option<&FieldFilter>) -> Result<()> {
+use crate::output::{FieldFilter, OutputFormat, OutputOptions};
+
+pub fn list(
+    client: &Client,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
     let (tags, _) = client.collect_tags(None)?;
-    super::print_all(format, &tags, fields)?;
+    super::print_all(output, &tags, fields)?;
+    Ok(())
+}
+
+/// A tag together with its nested child tags.
+#[derive(Serialize)]
+struct TagNode {
+    #[serde(flatten)]
+    tag: Tag,
+    children: Vec<TagNode>,
+}
+
+pub fn tree(
+    client: &Client,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
+    let (tags, _) = client.collect_tags(None)?;
+    let roots = build_tree(tags);
+
+    match output.format {
+        OutputFormat::Json => {
+            let nodes = roots
+                .iter()
+                .map(|node| node_to_json(node, fields))
+                .collect::<Result<Vec<_>, _>>()?;
+            println!("{}", serde_json::to_string_pretty(&nodes)?);
+        }
+        OutputFormat::Ndjson => {
+            for node in &roots {
+                println!("{}", serde_json::to_string(&node_to_json(node, fields)?)?);
+            }
+        }
+        OutputFormat::Markdown => {
+            let mut out = String::new();
+            for node in &roots {
+                render_markdown(node, 0, &mut out);
+            }
+            print!("{out}");
+        }
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let mut tags = Vec::new();
+            for node in &roots {
+                flatten(node, &mut tags);
+            }
+            println!("{}", output.format_list(&tags, fields)?);
+        }
+    }
+    Ok(())
+}
+
+/// List the tags of a tree parents first, as the `parent` column of CSV and
+/// TSV output carries the nesting.
+fn flatten(node: &TagNode, out: &mut Vec<Tag>) {
+    out.push(node.tag.clone());
+    for child in &node.children {
+        flatten(child, out);
+    }
+}
+
+/// Arrange tags into a forest by `parent`. Tags whose parent is missing from
+/// the list (or that are part of a cycle) become roots.
+fn build_tree(tags: Vec<Tag>) -> Vec<TagNode> {
+    let ids: HashSet<u64> = tags.iter().map(|t| t.id).collect();
+    let mut children: HashMap<u64, Vec<Tag>> = HashMap::new();
+    let mut roots = Vec::new();
+    for tag in tags {
+        match tag.parent {
+            Some(parent) if parent != tag.id && ids.contains(&parent) => {
+                children.entry(parent).or_default().push(tag);
+            }
+            _ => roots.push(tag),
+        }
+    }
+
+    let mut nodes: Vec<TagNode> = roots
+        .into_iter()
+        .map(|tag| attach_children(tag, &mut children))
+        .collect();
+
+    // Whatever is left over is only reachable through a cycle.
+    let mut orphans: Vec<Tag> = children.drain().flat_map(|(_, tags)| tags).collect();
+    orphans.sort_by_key(|t| t.id);
+    for tag in orphans {
+        nodes.push(TagNode {
+            tag,
+            children: Vec::new(),
+        });
+    }
+    nodes
+}
+
+fn attach_children(tag: Tag, children: &mut HashMap<u64, Vec<Tag>>) -> TagNode {
+    let kids = children.remove(&tag.id).unwrap_or_default();
+    TagNode {
+        tag,
+        children: kids
+            .into_iter()
+            .map(|child| attach_children(child, children))
+            .collect(),
+    }
+}
+
+fn node_to_json(
+    node: &TagNode,
+    fields: Option<&FieldFilter>,
+) -> Result<serde_json::Value, serde_json::Error> {
+    let value = serde_json::to_value(&node.tag)?;
+    let mut value = match fields {
+        Some(f) => f.filter_json_object(value),
+        None => value,
+    };
+    let children = node
+        .children
+        .iter()
+        .map(|child| node_to_json(child, fields))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let serde_json::Value::Object(map) = &mut value {
+        map.insert("children".to_string(), serde_json::Value::Array(children));
+    }
+    Ok(value)
+}
+
+fn render_markdown(node: &TagNode, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    out.push_str(&format!("{indent}- {} (#{})", node.tag.name, node.tag.id));
+    if let Some(count) = node.tag.document_count {
+        let noun = if count == 1 { "document" } else { "documents" };
+        out.push_str(&format!(", {count} {noun}"));
+    }
+    out.push('\n');
+    for child in &node.children {
+        render_markdown(child, depth + 1, out);
+    }
+}
+
+pub fn create(
+    client: &Client,
+    request: &TagRequest,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
+    let tag = client.create_tag(request)?;
+    println!("{}", output.format_detail(&tag, fields)?);
+    Ok(())
+}
+
+pub fn edit(
+    client: &Client,
+    id: u64,
+    request: &TagRequest,
+    output: OutputOptions<'_>,
+    fields: Option<&FieldFilter>,
+) -> Result<()> {
+    if *request == TagRequest::default() {
+        bail!("nothing to change. Pass at least one option (see `pngx tags edit --help`)");
+    }
+    let tag = client.update_tag(id, request)?;
+    println!("{}", output.format_detail(&tag, fields)?);
     Ok(())
 }
+
+pub fn delete(client: &Client, ids: &[u64], yes: bool) -> Result<()> {
+    let tags = ids
+        .iter()
+        .map(|&id| client.tag(id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let names: Vec<String> = tags
+        .iter()
+        .map(|t| format!("{} (#{})", t.name, t.id))
+        .collect();
+    super::confirm(&format!("Delete tags {}?", names.join(", ")), yes)?;
+
+    for tag in &tags {
+        client.delete_tag(tag.id)?;
+        eprintln!("Deleted tag {} (#{})", tag.name, tag.id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn tag(id: u64, name: &str, parent: Option<u64>) -> Tag {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": name,
+            "slug": name.to_lowercase(),
+            "parent": parent,
+        }))
+        .unwrap()
+    }
+
+    /// The tree as `(id, children)` pairs, for comparing shapes.
+    fn shape(nodes: &[TagNode]) -> Vec<(u64, Vec<u64>)> {
+        let mut out = Vec::new();
+        for node in nodes {
+            out.push((
+                node.tag.id,
+                node.children.iter().map(|child| child.tag.id).collect(),
+            ));
+            out.extend(shape(&node.children));
+        }
+        out
+    }
+
+    #[test]
+    fn test_build_tree_nests_in_list_order() {
+        let roots = build_tree(vec![
+            tag(4, "2024", Some(2)),
+            tag(1, "Finance", None),
+            tag(2, "Tax", Some(1)),
+            tag(5, "Inbox", None),
+            tag(3, "Insurance", Some(1)),
+        ]);
+        assert_eq!(
+            shape(&roots),
+            [
+                (1, vec![2, 3]),
+                (2, vec![4]),
+                (4, vec![]),
+                (3, vec![]),
+                (5, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_tree_keeps_orphans_as_roots() {
+        // The parent is missing, e.g. filtered out or deleted, or the tag is
+        // its own parent.
+        let roots = build_tree(vec![
+            tag(1, "Finance", None),
+            tag(2, "Tax", Some(99)),
+            tag(3, "Self", Some(3)),
+        ]);
+        assert_eq!(shape(&roots), [(1, vec![]), (2, vec![]), (3, vec![])]);
+    }
+
+    #[test]
+    fn test_build_tree_breaks_cycles() {
+        let roots = build_tree(vec![
+            tag(1, "Finance", None),
+            tag(9, "B", Some(8)),
+            tag(8, "A", Some(9)),
+            tag(2, "Tax", Some(1)),
+        ]);
+        // Tags only reachable through the cycle come last, by ID, without
+        // children, so none of them is lost or repeated.
+        assert_eq!(
+            shape(&roots),
+            [(1, vec![2]), (2, vec![]), (8, vec![]), (9, vec![])]
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_indents_children() {
+        let mut finance = tag(1, "Finance", None);
+        finance.document_count = Some(1);
+        let mut tax = tag(2, "Tax", Some(1));
+        tax.document_count = Some(3);
+        let roots = build_tree(vec![finance, tax, tag(3, "2024", Some(2))]);
+
+        let mut out = String::new();
+        for node in &roots {
+            render_markdown(node, 0, &mut out);
+        }
+        assert_eq!(
+            out,
+            "- Finance (#1), 1 document\n  - Tax (#2), 3 documents\n    - 2024 (#3)\n"
+        );
+    }
+
+    #[test]
+    fn test_node_to_json_nests_filtered_children() {
+        let roots = build_tree(vec![tag(1, "Finance", None), tag(2, "Tax", Some(1))]);
+        let fields = FieldFilter::parse::<Tag>("name").unwrap();
+
+        let value = node_to_json(&roots[0], Some(&fields)).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "name": "Finance",
+                "children": [{"name": "Tax", "children": []}],
+            })
+        );
+    }
+}
@@ -0,0 +1,829 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, IsTerminal};
+
+use anyhow::{Result, bail};
+use pngx_client::{Client, Document, DocumentRequest, Suggestions};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use url::Url;
+
+/// Key bindings shown at the bottom of the screen.
+const HELP: &str = "↑↓ select  c correspondent  y type  t tags  a accept  d done  o open  u undo  r reload  q quit";
+
+/// Lines the content preview moves per page key.
+const SCROLL_STEP: u16 = 10;
+
+/// Shows the inbox in a terminal UI to assign correspondents, document
+/// types, and tags, and to mark documents as done.
+pub fn run(client: &Client, url: &Url) -> Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        bail!("--interactive needs a terminal");
+    }
+    let mut app = App::load(client, url)?;
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+/// Names of the taxonomy by ID, for display and the pickers.
+struct Names {
+    correspondents: BTreeMap<u64, String>,
+    document_types: BTreeMap<u64, String>,
+    tags: BTreeMap<u64, String>,
+    inbox_tags: Vec<u64>,
+}
+
+impl Names {
+    fn fetch(client: &Client) -> Result<Self> {
+        let (tags, _) = client.collect_tags(None)?;
+        let (correspondents, _) = client.collect_correspondents(None)?;
+        let (document_types, _) = client.collect_document_types(None)?;
+        Ok(Self {
+            inbox_tags: tags
+                .iter()
+                .filter(|tag| tag.is_inbox_tag == Some(true))
+                .map(|tag| tag.id)
+                .collect(),
+            tags: tags.into_iter().map(|tag| (tag.id, tag.name)).collect(),
+            correspondents: correspondents
+                .into_iter()
+                .map(|correspondent| (correspondent.id, correspondent.name))
+                .collect(),
+            document_types: document_types
+                .into_iter()
+                .map(|document_type| (document_type.id, document_type.name))
+                .collect(),
+        })
+    }
+
+    fn of(&self, field: Field) -> &BTreeMap<u64, String> {
+        match field {
+            Field::Correspondent => &self.correspondents,
+            Field::DocumentType => &self.document_types,
+            Field::Tags => &self.tags,
+        }
+    }
+
+    /// The name of an object, `-` for none, or its ID if it is unknown.
+    fn name(&self, field: Field, id: Option<u64>) -> String {
+        match id {
+            Some(id) => self
+                .of(field)
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| format!("#{id}")),
+            None => "-".to_string(),
+        }
+    }
+
+    fn names(&self, field: Field, ids: &[u64]) -> String {
+        if ids.is_empty() {
+            return "-".to_string();
+        }
+        ids.iter()
+            .map(|&id| self.name(field, Some(id)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Correspondent,
+    DocumentType,
+    Tags,
+}
+
+impl Field {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Correspondent => "Correspondent",
+            Self::DocumentType => "Document type",
+            Self::Tags => "Tags",
+        }
+    }
+}
+
+/// Content and suggestions of a document, fetched when it is selected.
+struct Details {
+    content: String,
+    suggestions: Option<Suggestions>,
+}
+
+/// A fuzzy-completing list of the objects a field can be set to.
+struct Picker {
+    field: Field,
+    query: String,
+    selected: ListState,
+    /// Matching objects, best first. `None` clears the field.
+    choices: Vec<(Option<u64>, String)>,
+}
+
+impl Picker {
+    fn new(field: Field, names: &Names) -> Self {
+        let mut picker = Self {
+            field,
+            query: String::new(),
+            selected: ListState::default(),
+            choices: Vec::new(),
+        };
+        picker.update(names);
+        picker
+    }
+
+    /// Ranks the objects against the query.
+    fn update(&mut self, names: &Names) {
+        let mut scored: Vec<(i64, &u64, &String)> = names
+            .of(self.field)
+            .iter()
+            .filter_map(|(id, name)| Some((fuzzy_score(&self.query, name)?, id, name)))
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(a.2.len().cmp(&b.2.len()))
+                .then(a.2.cmp(b.2))
+        });
+        self.choices = scored
+            .into_iter()
+            .map(|(_, id, name)| (Some(*id), name.clone()))
+            .collect();
+        if self.query.is_empty() && self.field != Field::Tags {
+            self.choices.insert(0, (None, "(none)".to_string()));
+        }
+        self.selected
+            .select((!self.choices.is_empty()).then_some(0));
+    }
+
+    fn choice(&self) -> Option<&(Option<u64>, String)> {
+        self.choices.get(self.selected.selected()?)
+    }
+}
+
+/// The document as it was before an action, to restore it on undo.
+struct Undo {
+    label: String,
+    before: Document,
+    /// Position in the list, to put back documents marked as done.
+    index: usize,
+    done: bool,
+}
+
+struct App<'a> {
+    client: &'a Client,
+    url: &'a Url,
+    names: Names,
+    docs: Vec<Document>,
+    list: ListState,
+    details: HashMap<u64, Details>,
+    scroll: u16,
+    picker: Option<Picker>,
+    undo: Option<Undo>,
+    status: String,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    fn load(client: &'a Client, url: &'a Url) -> Result<Self> {
+        let names = Names::fetch(client)?;
+        let (docs, _) = client.collect_inbox_documents(None)?;
+        let mut list = ListState::default();
+        list.select((!docs.is_empty()).then_some(0));
+        Ok(Self {
+            client,
+            url,
+            names,
+            docs,
+            list,
+            details: HashMap::new(),
+            scroll: 0,
+            picker: None,
+            undo: None,
+            status: String::new(),
+            quit: false,
+        })
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            self.load_details();
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                if self.picker.is_some() {
+                    self.picker_key(key);
+                } else {
+                    self.key(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&Document> {
+        self.docs.get(self.list.selected()?)
+    }
+
+    /// Fetches the content and suggestions of the selected document once.
+    fn load_details(&mut self) {
+        let Some(id) = self.selected().map(|doc| doc.id) else {
+            return;
+        };
+        if self.details.contains_key(&id) {
+            return;
+        }
+        let content = self
+            .client
+            .document_content(id)
+            .unwrap_or_else(|err| format!("Could not load the content: {err}"));
+        let suggestions = self.client.suggestions(id).ok();
+        self.details.insert(
+            id,
+            Details {
+                content,
+                suggestions,
+            },
+        );
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        self.status.clear();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.quit = true;
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.select(1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(SCROLL_STEP),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(SCROLL_STEP),
+            KeyCode::Char('c') => self.pick(Field::Correspondent),
+            KeyCode::Char('y') => self.pick(Field::DocumentType),
+            KeyCode::Char('t') => self.pick(Field::Tags),
+            KeyCode::Char('a') => self.accept_suggestions(),
+            KeyCode::Char('d') | KeyCode::Enter => self.done(),
+            KeyCode::Char('o') => self.open(),
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('r') => self.reload(),
+            _ => {}
+        }
+    }
+
+    fn picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = self.picker.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.picker = None,
+            KeyCode::Enter => {
+                let field = picker.field;
+                let choice = picker.choice().cloned();
+                self.picker = None;
+                if let Some((id, name)) = choice {
+                    self.set(field, id, &name);
+                }
+            }
+            KeyCode::Down => picker.selected.select_next(),
+            KeyCode::Up => picker.selected.select_previous(),
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.update(&self.names);
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                picker.query.push(c);
+                picker.update(&self.names);
+            }
+            _ => {}
+        }
+    }
+
+    fn select(&mut self, step: isize) {
+        let Some(index) = self.list.selected() else {
+            return;
+        };
+        let index = index
+            .saturating_add_signed(step)
+            .min(self.docs.len().saturating_sub(1));
+        self.list.select(Some(index));
+        self.scroll = 0;
+    }
+
+    fn pick(&mut self, field: Field) {
+        if self.selected().is_some() {
+            self.picker = Some(Picker::new(field, &self.names));
+        }
+    }
+
+    /// Sets a correspondent or document type, or toggles a tag.
+    fn set(&mut self, field: Field, id: Option<u64>, name: &str) {
+        let Some(doc) = self.selected() else {
+            return;
+        };
+        let mut request = DocumentRequest::default();
+        let label = match (field, id) {
+            (Field::Correspondent, _) => {
+                request.correspondent = Some(id);
+                format!("set correspondent to {name}")
+            }
+            (Field::DocumentType, _) => {
+                request.document_type = Some(id);
+                format!("set document type to {name}")
+            }
+            (Field::Tags, Some(id)) if doc.tags.contains(&id) => {
+                request.tags = Some(doc.tags.iter().copied().filter(|&t| t != id).collect());
+                format!("removed tag {name}")
+            }
+            (Field::Tags, Some(id)) => {
+                request.tags = Some([doc.tags.as_slice(), &[id]].concat());
+                format!("added tag {name}")
+            }
+            (Field::Tags, None) => return,
+        };
+        self.apply(label, &request, false);
+    }
+
+    /// Applies the first suggested correspondent and document type, and
+    /// adds all suggested tags.
+    fn accept_suggestions(&mut self) {
+        let Some(doc) = self.selected() else {
+            return;
+        };
+        let Some(suggestions) = self
+            .details
+            .get(&doc.id)
+            .and_then(|details| details.suggestions.as_ref())
+        else {
+            self.status = "No suggestions for this document".to_string();
+            return;
+        };
+        let mut request = DocumentRequest::default();
+        if let Some(&id) = suggestions.correspondents.first()
+            && doc.correspondent != Some(id)
+        {
+            request.correspondent = Some(Some(id));
+        }
+        if let Some(&id) = suggestions.document_types.first()
+            && doc.document_type != Some(id)
+        {
+            request.document_type = Some(Some(id));
+        }
+        let new_tags: Vec<u64> = suggestions
+            .tags
+            .iter()
+            .copied()
+            .filter(|id| !doc.tags.contains(id))
+            .collect();
+        if !new_tags.is_empty() {
+            request.tags = Some([doc.tags.as_slice(), &new_tags].concat());
+        }
+        if request == DocumentRequest::default() {
+            self.status = "Suggestions are already applied".to_string();
+            return;
+        }
+        self.apply("accepted suggestions".to_string(), &request, false);
+    }
+
+    /// Removes the inbox tags, which takes the document off the list.
+    fn done(&mut self) {
+        let Some(doc) = self.selected() else {
+            return;
+        };
+        let tags = doc
+            .tags
+            .iter()
+            .copied()
+            .filter(|id| !self.names.inbox_tags.contains(id))
+            .collect();
+        let request = DocumentRequest {
+            tags: Some(tags),
+            ..DocumentRequest::default()
+        };
+        self.apply("marked as done".to_string(), &request, true);
+    }
+
+    /// Patches the selected document and remembers how to undo it.
+    fn apply(&mut self, label: String, request: &DocumentRequest, done: bool) {
+        let Some(index) = self.list.selected() else {
+            return;
+        };
+        let before = self.docs[index].clone();
+        match self.client.update_document(before.id, request) {
+            Ok(doc) => {
+                if done {
+                    self.docs.remove(index);
+                    let last = self.docs.len().checked_sub(1);
+                    self.list.select(last.map(|last| index.min(last)));
+                    self.scroll = 0;
+                } else {
+                    self.docs[index] = doc;
+                }
+                self.status = format!("#{}: {label} (u to undo)", before.id);
+                self.undo = Some(Undo {
+                    label,
+                    before,
+                    index,
+                    done,
+                });
+            }
+            Err(err) => self.status = format!("Could not update #{}: {err}", before.id),
+        }
+    }
+
+    /// Restores the correspondent, document type, and tags the last
+    /// action changed.
+    fn undo(&mut self) {
+        let Some(undo) = self.undo.take() else {
+            self.status = "Nothing to undo".to_string();
+            return;
+        };
+        let request = DocumentRequest {
+            correspondent: Some(undo.before.correspondent),
+            document_type: Some(undo.before.document_type),
+            tags: Some(undo.before.tags.clone()),
+            ..DocumentRequest::default()
+        };
+        let id = undo.before.id;
+        match self.client.update_document(id, &request) {
+            Ok(doc) => {
+                let index = if undo.done {
+                    let index = undo.index.min(self.docs.len());
+                    self.docs.insert(index, doc);
+                    Some(index)
+                } else {
+                    let index = self.docs.iter().position(|doc| doc.id == id);
+                    if let Some(index) = index {
+                        self.docs[index] = doc;
+                    }
+                    index
+                };
+                if index.is_some() {
+                    self.list.select(index);
+                }
+                self.status = format!("#{id}: undid {}", undo.label);
+            }
+            Err(err) => {
+                self.status = format!("Could not undo: {err}");
+                self.undo = Some(undo);
+            }
+        }
+    }
+
+    fn open(&mut self) {
+        let Some(doc) = self.selected() else {
+            return;
+        };
+        let url = super::documents::document_url(self.url, doc.id);
+        self.status = match open::that_detached(&url) {
+            Ok(()) => format!("Opened {url}"),
+            Err(err) => format!("Could not open {url}: {err}"),
+        };
+    }
+
+    /// Fetches the inbox and taxonomy again, keeping the selection.
+    fn reload(&mut self) {
+        let selected = self.selected().map(|doc| doc.id);
+        let loaded = Names::fetch(self.client).and_then(|names| {
+            let (docs, _) = self.client.collect_inbox_documents(None)?;
+            Ok((names, docs))
+        });
+        match loaded {
+            Ok((names, docs)) => {
+                self.names = names;
+                self.docs = docs;
+                self.details.clear();
+                let index = self
+                    .docs
+                    .iter()
+                    .position(|doc| Some(doc.id) == selected)
+                    .or((!self.docs.is_empty()).then_some(0));
+                self.list.select(index);
+                self.status = format!("{} documents in the inbox", self.docs.len());
+            }
+            Err(err) => self.status = format!("Could not reload: {err}"),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+        let [info, preview] =
+            Layout::vertical([Constraint::Length(15), Constraint::Min(3)]).areas(right);
+
+        self.draw_list(frame, left);
+        self.draw_info(frame, info);
+        let content = self
+            .selected()
+            .and_then(|doc| self.details.get(&doc.id))
+            .map_or("", |details| details.content.as_str());
+        frame.render_widget(
+            Paragraph::new(content)
+                .wrap(Wrap { trim: false })
+                .scroll((self.scroll, 0))
+                .block(Block::bordered().title("Content")),
+            preview,
+        );
+        let footer_text = if self.status.is_empty() {
+            Line::from(HELP).dim()
+        } else {
+            Line::from(self.status.as_str())
+        };
+        frame.render_widget(footer_text, footer);
+
+        if let Some(picker) = self.picker.as_mut() {
+            draw_picker(
+                frame,
+                picker,
+                self.docs.get(self.list.selected().unwrap_or(0)),
+            );
+        }
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) {
+        let title = format!("Inbox ({})", self.docs.len());
+        let block = Block::bordered().title(title);
+        if self.docs.is_empty() {
+            frame.render_widget(Paragraph::new("Inbox is empty").block(block), area);
+            return;
+        }
+        let items: Vec<ListItem> = self
+            .docs
+            .iter()
+            .map(|doc| {
+                let meta = format!(
+                    "  {} · {} · {}",
+                    self.names.name(Field::Correspondent, doc.correspondent),
+                    self.names.name(Field::DocumentType, doc.document_type),
+                    self.names.names(Field::Tags, &doc.tags)
+                );
+                ListItem::new(Text::from(vec![
+                    Line::from(format!("#{} {}", doc.id, doc.title)),
+                    Line::from(meta).dim(),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_info(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title("Document");
+        let Some(doc) = self.selected() else {
+            frame.render_widget(block, area);
+            return;
+        };
+        let names = &self.names;
+        let field =
+            |label: &'static str, value: String| Line::from(vec![label.bold(), value.into()]);
+        let mut lines = vec![
+            field("Title: ", doc.title.clone()),
+            field(
+                "Correspondent: ",
+                names.name(Field::Correspondent, doc.correspondent),
+            ),
+            field(
+                "Document type: ",
+                names.name(Field::DocumentType, doc.document_type),
+            ),
+            field("Tags: ", names.names(Field::Tags, &doc.tags)),
+            field(
+                "Created: ",
+                doc.created
+                    .map_or_else(|| "-".to_string(), |date| date.to_string()),
+            ),
+            field(
+                "File: ",
+                doc.original_file_name
+                    .clone()
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            Line::default(),
+        ];
+        match self
+            .details
+            .get(&doc.id)
+            .and_then(|details| details.suggestions.as_ref())
+        {
+            Some(suggestions) => {
+                lines.push(Line::from("Suggestions").bold().underlined());
+                lines.push(field(
+                    "Correspondents: ",
+                    names.names(Field::Correspondent, &suggestions.correspondents),
+                ));
+                lines.push(field(
+                    "Document types: ",
+                    names.names(Field::DocumentType, &suggestions.document_types),
+                ));
+                lines.push(field("Tags: ", names.names(Field::Tags, &suggestions.tags)));
+                let dates: Vec<String> =
+                    suggestions.dates.iter().map(ToString::to_string).collect();
+                let dates = if dates.is_empty() {
+                    "-".to_string()
+                } else {
+                    dates.join(", ")
+                };
+                lines.push(field("Dates: ", dates));
+            }
+            None => lines.push(Line::from("No suggestions").dim()),
+        }
+        frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(block),
+            area,
+        );
+    }
+}
+
+fn draw_picker(frame: &mut Frame, picker: &mut Picker, doc: Option<&Document>) {
+    let area = centered(frame.area(), 50, 60);
+    frame.render_widget(Clear, area);
+    let block = Block::bordered().title(format!(
+        "{} (Enter to choose, Esc to cancel)",
+        picker.field.label()
+    ));
+    let [input, choices] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(block.inner(area));
+    frame.render_widget(block, area);
+    frame.render_widget(Line::from(format!("> {}", picker.query)), input);
+
+    let items: Vec<ListItem> = picker
+        .choices
+        .iter()
+        .map(|(id, name)| {
+            let set = doc.is_some_and(|doc| match picker.field {
+                Field::Correspondent => doc.correspondent == *id,
+                Field::DocumentType => doc.document_type == *id,
+                Field::Tags => id.is_some_and(|id| doc.tags.contains(&id)),
+            });
+            let mark = if set { "✓ " } else { "  " };
+            ListItem::new(format!("{mark}{name}"))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, choices, &mut picker.selected);
+}
+
+/// A rectangle of the given percentages of `area`, centered in it.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Percentage(width)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Percentage(height)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(area);
+    area
+}
+
+/// Scores `name` for a query whose characters must all appear in it in
+/// order, ignoring case. Consecutive characters and word starts score
+/// higher, gaps lower. Returns `None` if the name doesn't match.
+fn fuzzy_score(query: &str, name: &str) -> Option<i64> {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let mut score: i64 = 0;
+    let mut gaps: usize = 0;
+    let mut next = 0;
+    let mut last = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let offset = name.get(next..)?.iter().position(|&n| n == c)?;
+        let index = next + offset;
+        score += 1;
+        if last.is_some_and(|last| last + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !name[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        gaps += offset;
+        last = Some(index);
+        next = index + 1;
+    }
+    Some(score - i64::try_from(gaps).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use pngx_testing::{FakePaperless, seed};
+
+    use super::*;
+
+    fn names() -> Names {
+        let map = |names: &[&str]| {
+            (1..)
+                .zip(names)
+                .map(|(id, name)| (id, (*name).to_string()))
+                .collect()
+        };
+        Names {
+            correspondents: map(&["ACME", "Tax office"]),
+            document_types: map(&["Invoice"]),
+            tags: map(&["Taxes", "Contract text", "Tax", "Inbox"]),
+            inbox_tags: vec![4],
+        }
+    }
+
+    fn choices(picker: &Picker) -> Vec<&str> {
+        picker
+            .choices
+            .iter()
+            .map(|(_, name)| name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_fuzzy_score_matches_in_order_ignoring_case() {
+        assert_eq!(fuzzy_score("", "Invoice"), Some(0));
+        assert!(fuzzy_score("INV", "invoice").is_some());
+        assert!(fuzzy_score("t o", "Tax office").is_some());
+        assert_eq!(fuzzy_score("vni", "Invoice"), None);
+        assert_eq!(fuzzy_score("invoices", "Invoice"), None);
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_runs_and_word_starts() {
+        let score = |query| fuzzy_score(query, "Tax office").unwrap();
+        // A run beats the same letters spread out.
+        assert!(score("tax") > score("tae"));
+        // The start of a word beats a letter inside one.
+        assert!(score("o") > score("f"));
+        // Gaps cost.
+        assert!(fuzzy_score("te", "Tee").unwrap() > fuzzy_score("te", "Tax office").unwrap());
+    }
+
+    #[test]
+    fn test_picker_ranks_choices() {
+        let names = names();
+        let mut picker = Picker::new(Field::Tags, &names);
+        assert_eq!(choices(&picker), ["Tax", "Inbox", "Taxes", "Contract text"]);
+        assert_eq!(picker.choice().unwrap().0, Some(3));
+
+        picker.query = "tax".to_string();
+        picker.update(&names);
+        // Scattered matches rank after the runs.
+        assert_eq!(choices(&picker), ["Tax", "Taxes", "Contract text"]);
+
+        picker.query = "text".to_string();
+        picker.selected.select(Some(1));
+        picker.update(&names);
+        assert_eq!(choices(&picker), ["Contract text"]);
+        assert_eq!(picker.selected.selected(), Some(0));
+
+        picker.query = "zzz".to_string();
+        picker.update(&names);
+        assert!(picker.choices.is_empty());
+        assert!(picker.choice().is_none());
+    }
+
+    #[test]
+    fn test_picker_offers_none_for_single_fields() {
+        let names = names();
+        let mut picker = Picker::new(Field::Correspondent, &names);
+        assert_eq!(choices(&picker), ["(none)", "ACME", "Tax office"]);
+        assert_eq!(picker.choice().unwrap().0, None);
+
+        picker.query = "t".to_string();
+        picker.update(&names);
+        assert_eq!(choices(&picker), ["Tax office"]);
+    }
+
+    #[tokio::test]
+    async fn test_undo_done_restores_tags() {
+        let server = FakePaperless::builder()
+            .tag(seed::Tag::new("Inbox").inbox())
+            .tag(seed::Tag::new("Tax"))
+            .document(seed::Document::new("Letter").tag(1))
+            .document(seed::Document::new("Tax return").tag(1).tag(2))
+            .start()
+            .await;
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let url = Url::parse(&server.uri()).unwrap();
+        let mut app = App::load(&client, &url).unwrap();
+        assert_eq!(app.docs.len(), 2);
+
+        app.select(1);
+        app.done();
+        assert_eq!(server.document(2).unwrap().tags, [2]);
+        assert_eq!(app.docs.len(), 1);
+        assert_eq!(app.list.selected(), Some(0));
+
+        app.undo();
+        assert_eq!(server.document(2).unwrap().tags, [1, 2]);
+        let titles: Vec<_> = app.docs.iter().map(|doc| doc.title.as_str()).collect();
+        assert_eq!(titles, ["Letter", "Tax return"]);
+        assert_eq!(app.selected().unwrap().id, 2);
+        assert_eq!(app.status, "#2: undid marked as done");
+
+        app.undo();
+        assert_eq!(app.status, "Nothing to undo");
+    }
+}
@@ -1,26 +1,27 @@
-use std::time::Duration;
-
 use anyhow::Result;
-use pngx_client::Client;
 
-use crate::config::RawConfig;
+use crate::config::{Overrides, RawConfig};
 
-pub fn print(url: Option<&str>, token: Option<&str>) -> Result<()> {
+pub fn print(overrides: &Overrides) -> Result<()> {
     println!("pngx {}", env!("CARGO_PKG_VERSION"));
 
-    let Ok(raw) = RawConfig::load(url, token) else {
+    let Ok(raw) = RawConfig::load(overrides) else {
         return Ok(());
     };
     let Ok(config) = raw.validate() else {
         return Ok(());
     };
 
-    let client = Client::builder(config.url.as_str(), &config.token)
-        .timeout(Duration::from_secs(config.timeout))
-        .page_size(config.page_size)
-        .build()?;
+    let client = crate::client_builder(&config).build()?;
 
     let version = client.server_version()?;
     println!("paperless-ngx {version}");
+    match client.server_api_version() {
+        Some(server) => println!(
+            "api version {} (server supports {server})",
+            client.api_version()
+        ),
+        None => println!("api version {}", client.api_version()),
+    }
     Ok(())
 }
@@ -0,0 +1,666 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result, bail};
+use figment::Figment;
+use figment::providers::{Format, Toml};
+use jiff::{SignedDuration, Timestamp};
+use notify::event::{AccessKind, AccessMode, ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher as _};
+use pngx_client::{ApiError, Client, DocumentUpload, TagRequest, Task, TaskStatus};
+use serde::{Deserialize, Serialize};
+
+use super::sync::{create_parent, write_file};
+
+/// Uploads whose consumption hasn't finished, kept in the watched
+/// directory so a restart resumes waiting instead of uploading again.
+const STATE_FILE: &str = ".pngx-watch.json";
+
+/// Where files are moved once the server consumed them, or failed to.
+const DONE_DIR: &str = "done";
+const FAILED_DIR: &str = "failed";
+
+/// How long to wait for file events before checking tasks and signals.
+const TICK: Duration = Duration::from_millis(200);
+
+/// How often the tasks of pending uploads are checked.
+const TASK_POLL: Duration = Duration::from_secs(1);
+
+/// How long to wait before uploading a file again after a network or
+/// server error.
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Files that never report a close, e.g. on some network file systems,
+/// count as written once unchanged for this long.
+const UNCLOSED_SETTLE: Duration = Duration::from_mins(1);
+
+pub struct WatchOptions {
+    /// TOML file with metadata rules per folder.
+    pub rules: Option<PathBuf>,
+    /// Tag files with the names of the folders they are in.
+    pub subdirs_as_tags: bool,
+    /// How long a file must stay unchanged before it is uploaded.
+    pub settle: Duration,
+    /// How long to wait for the server to consume an upload.
+    pub task_timeout: Duration,
+    /// Process the files already in the directory, then exit.
+    pub once: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct WatchState {
+    /// Uploads by path relative to the watched directory.
+    pending: BTreeMap<String, PendingUpload>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct PendingUpload {
+    task_id: String,
+    uploaded: Timestamp,
+}
+
+/// The rules file: metadata for the files in a folder and its subfolders,
+/// keyed by the folder's path relative to the watched directory. `.` is
+/// the watched directory itself.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rules {
+    #[serde(default)]
+    folders: BTreeMap<String, FolderRule>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FolderRule {
+    #[serde(default)]
+    tags: Vec<String>,
+    correspondent: Option<String>,
+    document_type: Option<String>,
+    storage_path: Option<String>,
+}
+
+/// IDs by lowercase name.
+#[derive(Default)]
+struct Names {
+    tags: HashMap<String, u64>,
+    correspondents: HashMap<String, u64>,
+    document_types: HashMap<String, u64>,
+    storage_paths: HashMap<String, u64>,
+}
+
+/// A file that was created or changed and will be uploaded once it is
+/// completely written.
+struct Candidate {
+    /// The writer closed the file, or it was moved into place.
+    closed: bool,
+    /// When the file last changed.
+    changed: Instant,
+    size: u64,
+    modified: Option<SystemTime>,
+    /// Earliest time to try again after a failed upload.
+    retry_at: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Summary {
+    done: usize,
+    failed: usize,
+}
+
+/// What to do with a file whose upload failed.
+enum UploadError {
+    /// The server rejected the file.
+    Rejected(String),
+    /// A network or server error; the file is uploaded again later.
+    Retry(String),
+    /// An error that stops watching, such as an invalid token.
+    Fatal(ApiError),
+}
+
+struct Watch<'a> {
+    client: &'a Client,
+    dir: PathBuf,
+    options: &'a WatchOptions,
+    rules: Rules,
+    names: Names,
+    state: WatchState,
+    candidates: HashMap<String, Candidate>,
+    summary: Summary,
+}
+
+pub fn watch(client: &Client, dir: &Path, options: &WatchOptions) -> Result<()> {
+    if !dir.is_dir() {
+        bail!("not a directory: {}", dir.display());
+    }
+    let rules = match &options.rules {
+        Some(path) => load_rules(path)?,
+        None => Rules::default(),
+    };
+    let mut watch = Watch {
+        client,
+        dir: dir.to_path_buf(),
+        options,
+        names: Names::fetch(client)?,
+        rules,
+        state: load_state(&dir.join(STATE_FILE))?,
+        candidates: HashMap::new(),
+        summary: Summary::default(),
+    };
+    watch.check_rules()?;
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT] {
+        signal_hook::flag::register(signal, Arc::clone(&shutdown))?;
+    }
+    // Watch before scanning, so no file written in between is missed.
+    let (tx, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    if !options.once {
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .with_context(|| format!("failed to watch {}", dir.display()))?;
+        eprintln!("Watching {}", dir.display());
+    }
+    watch.scan(dir)?;
+    if !watch.state.pending.is_empty() {
+        eprintln!(
+            "Resuming {} uploads from an earlier run",
+            watch.state.pending.len()
+        );
+    }
+
+    watch.run(&events, &shutdown)?;
+    drop(watcher);
+    eprintln!(
+        "{} files consumed, {} failed",
+        watch.summary.done, watch.summary.failed
+    );
+    if !watch.state.pending.is_empty() {
+        eprintln!(
+            "{} uploads still pending; they are resumed on the next start",
+            watch.state.pending.len()
+        );
+    }
+    Ok(())
+}
+
+impl Watch<'_> {
+    fn run(
+        &mut self,
+        events: &Receiver<notify::Result<Event>>,
+        shutdown: &AtomicBool,
+    ) -> Result<()> {
+        let mut last_poll: Option<Instant> = None;
+        while !shutdown.load(Ordering::Relaxed) {
+            match events.recv_timeout(TICK) {
+                Ok(Ok(event)) => self.handle_event(&event),
+                Ok(Err(err)) => tracing::warn!("file watch error: {err}"),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => bail!("the file watcher stopped"),
+            }
+            for path in self.ready() {
+                self.upload(&path)?;
+            }
+            if last_poll.is_none_or(|last| last.elapsed() >= TASK_POLL) {
+                self.poll_tasks()?;
+                last_poll = Some(Instant::now());
+            }
+            if self.options.once && self.candidates.is_empty() && self.state.pending.is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the files already in the directory, which appeared while
+    /// nothing was watching.
+    fn scan(&mut self, dir: &Path) -> Result<()> {
+        let entries =
+            fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let Some(relative) = self.relative(&path) else {
+                continue;
+            };
+            if path.is_dir() {
+                self.scan(&path)?;
+            } else if !self.state.pending.contains_key(&relative) {
+                // Old files are ready right away, recent ones may still be
+                // written to.
+                let age = fs::metadata(&path)
+                    .and_then(|meta| meta.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .unwrap_or_default();
+                let candidate = self.touch(relative, true);
+                if let Some(changed) = Instant::now().checked_sub(age) {
+                    candidate.changed = changed;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: &Event) {
+        let (path, closed) = match event.kind {
+            EventKind::Access(AccessKind::Close(AccessMode::Write))
+            | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => (event.paths.first(), true),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => (event.paths.get(1), true),
+            EventKind::Create(_) | EventKind::Modify(_) => (event.paths.first(), false),
+            _ => return,
+        };
+        let Some(relative) = path.and_then(|path| self.relative(path)) else {
+            return;
+        };
+        if !self.state.pending.contains_key(&relative) {
+            self.touch(relative, closed);
+        }
+    }
+
+    /// Records that a file changed, or was closed after writing.
+    fn touch(&mut self, relative: String, closed: bool) -> &mut Candidate {
+        let meta = fs::metadata(self.dir.join(&relative)).ok();
+        let (size, modified) = meta
+            .as_ref()
+            .map_or((0, None), |meta| (meta.len(), meta.modified().ok()));
+        let candidate = self.candidates.entry(relative).or_insert(Candidate {
+            closed: false,
+            changed: Instant::now(),
+            size,
+            modified,
+            retry_at: None,
+        });
+        candidate.closed |= closed;
+        candidate.changed = Instant::now();
+        candidate.size = size;
+        candidate.modified = modified;
+        candidate
+    }
+
+    /// The candidates that haven't changed for the settle time since they
+    /// were closed, in path order. Files that disappeared are dropped.
+    fn ready(&mut self) -> Vec<String> {
+        let settle = self.options.settle;
+        let mut ready = Vec::new();
+        self.candidates.retain(|relative, candidate| {
+            let Ok(meta) = fs::metadata(self.dir.join(relative)) else {
+                return false;
+            };
+            if !meta.is_file() {
+                return false;
+            }
+            let modified = meta.modified().ok();
+            if meta.len() != candidate.size || modified != candidate.modified {
+                candidate.size = meta.len();
+                candidate.modified = modified;
+                candidate.changed = Instant::now();
+                return true;
+            }
+            let settled = candidate.changed.elapsed();
+            let waiting = candidate.retry_at.is_some_and(|at| at > Instant::now());
+            if !waiting && settled >= settle && (candidate.closed || settled >= UNCLOSED_SETTLE) {
+                ready.push(relative.clone());
+            }
+            true
+        });
+        ready.sort();
+        ready
+    }
+
+    fn upload(&mut self, relative: &str) -> Result<()> {
+        match self.try_upload(relative) {
+            Ok(task_id) => {
+                self.candidates.remove(relative);
+                eprintln!("Uploaded {relative}");
+                self.state.pending.insert(
+                    relative.to_string(),
+                    PendingUpload {
+                        task_id,
+                        uploaded: Timestamp::now(),
+                    },
+                );
+                self.save_state()?;
+            }
+            Err(UploadError::Rejected(message)) => {
+                self.candidates.remove(relative);
+                self.fail(relative, &message)?;
+            }
+            Err(UploadError::Fatal(err)) => return Err(err.into()),
+            Err(UploadError::Retry(message)) if self.options.once => {
+                self.candidates.remove(relative);
+                eprintln!("Failed to upload {relative}, leaving it in place: {message}");
+            }
+            Err(UploadError::Retry(message)) => {
+                eprintln!(
+                    "Failed to upload {relative}, retrying in {}s: {message}",
+                    RETRY_DELAY.as_secs()
+                );
+                if let Some(candidate) = self.candidates.get_mut(relative) {
+                    candidate.retry_at = Some(Instant::now() + RETRY_DELAY);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn try_upload(&mut self, relative: &str) -> Result<String, UploadError> {
+        let data = fs::read(self.dir.join(relative))
+            .map_err(|err| UploadError::Retry(format!("failed to read the file: {err}")))?;
+        let metadata = self.metadata(relative)?;
+        let file_name = relative.rsplit('/').next().unwrap_or(relative);
+        self.client
+            .upload_document(file_name, &data, &metadata)
+            .map_err(upload_error)
+    }
+
+    /// Metadata from the rules of the file's folder and its parents, and
+    /// the folder names with `--subdirs-as-tags`. Tags accumulate; the
+    /// innermost folder's correspondent, document type, and storage path
+    /// win.
+    fn metadata(&mut self, relative: &str) -> Result<DocumentUpload, UploadError> {
+        let folders: Vec<&str> = relative.split('/').collect();
+        let folders = &folders[..folders.len() - 1];
+        let mut upload = DocumentUpload::default();
+        let mut tags = Vec::new();
+        for depth in 0..=folders.len() {
+            let key = if depth == 0 {
+                ".".to_string()
+            } else {
+                folders[..depth].join("/")
+            };
+            let Some(rule) = self.rules.folders.get(&key) else {
+                continue;
+            };
+            tags.extend(rule.tags.iter().cloned());
+            let lookup = |names: &HashMap<String, u64>, name: &Option<String>| {
+                name.as_ref()
+                    .and_then(|name| names.get(&name.to_lowercase()).copied())
+            };
+            upload.correspondent =
+                lookup(&self.names.correspondents, &rule.correspondent).or(upload.correspondent);
+            upload.document_type =
+                lookup(&self.names.document_types, &rule.document_type).or(upload.document_type);
+            upload.storage_path =
+                lookup(&self.names.storage_paths, &rule.storage_path).or(upload.storage_path);
+        }
+        if self.options.subdirs_as_tags {
+            tags.extend(folders.iter().map(|folder| (*folder).to_string()));
+        }
+        for name in tags {
+            let id = self.tag_id(&name).map_err(upload_error)?;
+            if !upload.tags.contains(&id) {
+                upload.tags.push(id);
+            }
+        }
+        Ok(upload)
+    }
+
+    /// The ID of the tag called `name`, created if it doesn't exist.
+    fn tag_id(&mut self, name: &str) -> Result<u64, ApiError> {
+        if let Some(id) = self.names.tags.get(&name.to_lowercase()) {
+            return Ok(*id);
+        }
+        let request = TagRequest {
+            name: Some(name.to_string()),
+            ..TagRequest::default()
+        };
+        let tag = self.client.create_tag(&request)?;
+        eprintln!("Created tag {name}");
+        self.names.tags.insert(name.to_lowercase(), tag.id);
+        Ok(tag.id)
+    }
+
+    /// Moves the files whose consumption finished, and fails those the
+    /// server didn't consume in time.
+    fn poll_tasks(&mut self) -> Result<()> {
+        let pending: Vec<(String, PendingUpload)> = self
+            .state
+            .pending
+            .iter()
+            .map(|(relative, upload)| (relative.clone(), upload.clone()))
+            .collect();
+        for (relative, upload) in pending {
+            let task = match self.client.task(&upload.task_id) {
+                Ok(task) => task,
+                Err(err @ ApiError::Unauthorized { .. }) => return Err(err.into()),
+                Err(err) => {
+                    tracing::warn!("failed to check the task of {relative}: {err}");
+                    continue;
+                }
+            };
+            match task {
+                Some(task) if task.status.is_done() => self.finish(&relative, &task)?,
+                _ if self.timed_out(&upload) => {
+                    let message = format!(
+                        "the server did not consume the file within {}s (task {})",
+                        self.options.task_timeout.as_secs(),
+                        upload.task_id
+                    );
+                    self.fail(&relative, &message)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn timed_out(&self, upload: &PendingUpload) -> bool {
+        let timeout =
+            SignedDuration::try_from(self.options.task_timeout).unwrap_or(SignedDuration::MAX);
+        upload
+            .uploaded
+            .checked_add(timeout)
+            .is_ok_and(|deadline| Timestamp::now() > deadline)
+    }
+
+    fn finish(&mut self, relative: &str, task: &Task) -> Result<()> {
+        if let (TaskStatus::Success, Some(id)) = (task.status, task.related_document) {
+            self.move_to(relative, DONE_DIR)?;
+            self.state.pending.remove(relative);
+            self.save_state()?;
+            self.summary.done += 1;
+            eprintln!("Consumed {relative} as document {id}");
+            Ok(())
+        } else {
+            let message = task
+                .result
+                .clone()
+                .unwrap_or_else(|| format!("the task ended with status {:?}", task.status));
+            self.fail(relative, &message)
+        }
+    }
+
+    /// Moves a file to `failed/` next to a `.error.txt` file with the
+    /// reason.
+    fn fail(&mut self, relative: &str, message: &str) -> Result<()> {
+        if let Some(target) = self.move_to(relative, FAILED_DIR)? {
+            let mut error = target.into_os_string();
+            error.push(".error.txt");
+            write_file(Path::new(&error), format!("{message}\n").as_bytes())?;
+        }
+        self.state.pending.remove(relative);
+        self.save_state()?;
+        self.summary.failed += 1;
+        eprintln!("Failed {relative}: {message}");
+        Ok(())
+    }
+
+    /// Moves a file into `folder`, keeping its relative path and adding a
+    /// counter if the name is taken. Returns the new path, or `None` if the
+    /// file is gone.
+    fn move_to(&self, relative: &str, folder: &str) -> Result<Option<PathBuf>> {
+        let source = self.dir.join(relative);
+        if !source.exists() {
+            return Ok(None);
+        }
+        let mut target = self.dir.join(folder).join(relative);
+        let stem = target
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let extension = target
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        let mut counter = 0;
+        while target.exists() {
+            counter += 1;
+            target.set_file_name(format!("{stem}_{counter:02}{extension}"));
+        }
+        create_parent(&target)?;
+        fs::rename(&source, &target).with_context(|| {
+            format!(
+                "failed to move {} to {}",
+                source.display(),
+                target.display()
+            )
+        })?;
+        Ok(Some(target))
+    }
+
+    /// Checks that every name in the rules exists on the server. Tags are
+    /// created when first used.
+    fn check_rules(&self) -> Result<()> {
+        let mut unknown = Vec::new();
+        for (folder, rule) in &self.rules.folders {
+            let references = [
+                (
+                    "correspondent",
+                    &rule.correspondent,
+                    &self.names.correspondents,
+                ),
+                (
+                    "document type",
+                    &rule.document_type,
+                    &self.names.document_types,
+                ),
+                (
+                    "storage path",
+                    &rule.storage_path,
+                    &self.names.storage_paths,
+                ),
+            ];
+            for (kind, name, names) in references {
+                if let Some(name) = name
+                    && !names.contains_key(&name.to_lowercase())
+                {
+                    unknown.push(format!("{kind} {name:?} (folder {folder:?})"));
+                }
+            }
+        }
+        if !unknown.is_empty() {
+            bail!("unknown names in the rules: {}", unknown.join(", "));
+        }
+        Ok(())
+    }
+
+    /// The path of a file relative to the watched directory with `/`
+    /// separators, or `None` for files that are never uploaded: hidden and
+    /// partial files and those in `done/` and `failed/`.
+    fn relative(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        let mut parts = Vec::new();
+        for component in relative.components() {
+            let Component::Normal(part) = component else {
+                return None;
+            };
+            let part = part.to_str()?;
+            // Hidden files include the partial files of `pngx sync` and
+            // many scanners; editors leave backups ending in `~`.
+            if part.starts_with('.') || part.ends_with('~') {
+                return None;
+            }
+            parts.push(part);
+        }
+        match parts.first() {
+            None | Some(&(DONE_DIR | FAILED_DIR)) => None,
+            Some(_) => Some(parts.join("/")),
+        }
+    }
+
+    fn save_state(&self) -> Result<()> {
+        write_file(
+            &self.dir.join(STATE_FILE),
+            &serde_json::to_vec_pretty(&self.state)?,
+        )
+    }
+}
+
+impl Names {
+    fn fetch(client: &Client) -> Result<Self> {
+        let by_name = |objects: Vec<(u64, String)>| {
+            objects
+                .into_iter()
+                .map(|(id, name)| (name.to_lowercase(), id))
+                .collect()
+        };
+        Ok(Self {
+            tags: by_name(
+                client
+                    .collect_tags(None)?
+                    .0
+                    .into_iter()
+                    .map(|t| (t.id, t.name))
+                    .collect(),
+            ),
+            correspondents: by_name(
+                client
+                    .collect_correspondents(None)?
+                    .0
+                    .into_iter()
+                    .map(|c| (c.id, c.name))
+                    .collect(),
+            ),
+            document_types: by_name(
+                client
+                    .collect_document_types(None)?
+                    .0
+                    .into_iter()
+                    .map(|t| (t.id, t.name))
+                    .collect(),
+            ),
+            storage_paths: by_name(
+                client
+                    .collect_storage_paths(None)?
+                    .0
+                    .into_iter()
+                    .map(|p| (p.id, p.name))
+                    .collect(),
+            ),
+        })
+    }
+}
+
+/// Whether an upload error means the file is bad, or the upload should be
+/// tried again.
+fn upload_error(err: ApiError) -> UploadError {
+    match err {
+        ApiError::Unauthorized { .. } => UploadError::Fatal(err),
+        ApiError::Validation { .. } => UploadError::Rejected(err.to_string()),
+        ApiError::Server { status, .. } if status < 500 && status != 429 => {
+            UploadError::Rejected(err.to_string())
+        }
+        err => UploadError::Retry(err.to_string()),
+    }
+}
+
+fn load_rules(path: &Path) -> Result<Rules> {
+    Figment::from(Toml::file_exact(path))
+        .extract()
+        .with_context(|| format!("invalid rules file: {}", path.display()))
+}
+
+fn load_state(path: &Path) -> Result<WatchState> {
+    match fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data)
+            .with_context(|| format!("malformed watch state: {}", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(WatchState::default()),
+        Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
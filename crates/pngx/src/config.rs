@@ -1,12 +1,19 @@
+use std::collections::BTreeMap;
 use std::fmt;
-use std::path::PathBuf;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
+use anyhow::Context;
 use figment::Figment;
 use figment::providers::{Env, Format, Serialized, Toml};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use toml_edit::{DocumentMut, Item, Table};
 use url::Url;
 
-use crate::output::OutputFormat;
+use crate::output::{DEFAULT_LIST_SEPARATOR, OutputFormat};
 
 /// Configuration validation error. Uses a distinct exit code (5) so agents
 /// can detect missing config and suggest running `pngx auth login`.
@@ -21,6 +28,73 @@ pub enum ConfigError {
         url: String,
         source: url::ParseError,
     },
+    #[error("token command `{command}` failed: {message}")]
+    TokenCommand { command: String, message: String },
+    #[error("failed to read token file {}", path.display())]
+    TokenFile { path: PathBuf, source: io::Error },
+    #[error("failed to read token from stdin: {0}")]
+    TokenStdin(io::Error),
+    #[error("failed to read {} {}", kind, path.display())]
+    TlsFile {
+        kind: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    #[error("client_cert and client_key must be set together")]
+    IncompleteClientCert,
+    #[error("profile '{0}' not found. Run `pngx profile list` to see configured profiles")]
+    UnknownProfile(String),
+}
+
+/// Keys a profile never inherits from the top level of the config file, so
+/// one server's credentials are never sent to another.
+const CREDENTIAL_KEYS: &[&str] = &[
+    "url",
+    "token",
+    "token_command",
+    "token_file",
+    "client_cert",
+    "client_key",
+    "headers",
+];
+
+/// Settings given on the command line, which take precedence over the
+/// environment and the config file.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Overrides<'a> {
+    pub profile: Option<&'a str>,
+    pub url: Option<&'a str>,
+    pub token: Option<&'a str>,
+    /// Read the token from stdin.
+    pub token_stdin: bool,
+}
+
+/// Where the API token comes from. Sources other than a literal token are
+/// only read when the config is validated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TokenSource {
+    #[default]
+    Missing,
+    Flag,
+    Stdin,
+    Env,
+    ConfigFile(PathBuf),
+    Command(String),
+    File(PathBuf),
+}
+
+impl fmt::Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "not configured"),
+            Self::Flag => write!(f, "--token flag"),
+            Self::Stdin => write!(f, "stdin (--token-stdin)"),
+            Self::Env => write!(f, "PNGX_TOKEN environment variable"),
+            Self::ConfigFile(path) => write!(f, "config file {}", path.display()),
+            Self::Command(command) => write!(f, "command `{command}`"),
+            Self::File(path) => write!(f, "file {}", path.display()),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -29,12 +103,56 @@ pub struct RawConfig {
     pub url: String,
     #[serde(default)]
     pub token: String,
+    /// Shell command whose first line of output is the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_command: Option<String>,
+    /// File whose first line is the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_file: Option<PathBuf>,
+    #[serde(skip)]
+    token_source: TokenSource,
+    #[serde(skip)]
+    profile: Option<String>,
     #[serde(default)]
     pub output_format: OutputFormat,
+    #[serde(default = "default_list_separator")]
+    pub list_separator: String,
     #[serde(default = "default_page_size")]
     pub page_size: u32,
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl: u64,
+    /// Pins the REST API version instead of negotiating it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<u32>,
+    /// Follow pagination links on the configured URL instead of the one the
+    /// server reports.
+    #[serde(default)]
+    pub rewrite_next_urls: bool,
+    /// PEM files with CA certificates trusted in addition to the bundled
+    /// roots.
+    #[serde(default)]
+    pub ca_certs: Vec<PathBuf>,
+    /// PEM file with the client certificate for mutual TLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<PathBuf>,
+    /// PEM file with the private key of `client_cert`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<PathBuf>,
+    /// Headers sent with every request, e.g. for an authenticating proxy.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// HTTP(S) proxy for all requests, instead of the `HTTPS_PROXY`
+    /// environment variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+fn default_list_separator() -> String {
+    DEFAULT_LIST_SEPARATOR.to_string()
 }
 
 fn default_page_size() -> u32 {
@@ -45,14 +163,36 @@ fn default_timeout() -> u64 {
     30
 }
 
+fn default_retries() -> u32 {
+    2
+}
+
+fn default_cache_ttl() -> u64 {
+    300
+}
+
 impl Default for RawConfig {
     fn default() -> Self {
         Self {
             url: String::new(),
             token: String::new(),
+            token_command: None,
+            token_file: None,
+            token_source: TokenSource::Missing,
+            profile: None,
             output_format: OutputFormat::Markdown,
+            list_separator: default_list_separator(),
             page_size: default_page_size(),
             timeout: default_timeout(),
+            retries: default_retries(),
+            cache_ttl: default_cache_ttl(),
+            api_version: None,
+            rewrite_next_urls: false,
+            ca_certs: Vec::new(),
+            client_cert: None,
+            client_key: None,
+            headers: BTreeMap::new(),
+            proxy: None,
         }
     }
 }
@@ -62,27 +202,107 @@ impl fmt::Debug for RawConfig {
         f.debug_struct("RawConfig")
             .field("url", &self.url)
             .field("token", &"[REDACTED]")
+            .field("token_command", &self.token_command)
+            .field("token_file", &self.token_file)
+            .field("token_source", &self.token_source)
+            .field("profile", &self.profile)
             .field("output_format", &self.output_format)
+            .field("list_separator", &self.list_separator)
             .field("page_size", &self.page_size)
             .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .field("cache_ttl", &self.cache_ttl)
+            .field("api_version", &self.api_version)
+            .field("rewrite_next_urls", &self.rewrite_next_urls)
+            .field("ca_certs", &self.ca_certs)
+            .field("client_cert", &self.client_cert)
+            .field("client_key", &self.client_key)
+            .field("headers", &self.headers.keys())
+            .field("proxy", &self.proxy)
             .finish()
     }
 }
 
 impl RawConfig {
-    pub fn load(url_override: Option<&str>, token_override: Option<&str>) -> anyhow::Result<Self> {
+    /// Loads the settings of the selected profile: `--profile`, else
+    /// `PNGX_PROFILE`, else `default_profile`, else the top level of the
+    /// config file.
+    pub fn load(overrides: &Overrides) -> anyhow::Result<Self> {
+        let file = ConfigFile::load()?;
+        let profile = overrides
+            .profile
+            .or_else(|| file.default_profile())
+            .map(str::to_string);
+        Self::from_file(file, profile, overrides, true)
+    }
+
+    /// Loads the settings of `profile`, or of the top level of the config
+    /// file for `None`, ignoring `default_profile` and `overrides.profile`.
+    pub fn load_profile(profile: Option<&str>, overrides: &Overrides) -> anyhow::Result<Self> {
+        Self::from_file(
+            ConfigFile::load()?,
+            profile.map(str::to_string),
+            overrides,
+            true,
+        )
+    }
+
+    /// Loads the settings for logging in to `profile`, which need not be
+    /// configured yet.
+    pub fn load_for_login(profile: Option<&str>, overrides: &Overrides) -> anyhow::Result<Self> {
+        Self::from_file(
+            ConfigFile::load()?,
+            profile.map(str::to_string),
+            overrides,
+            false,
+        )
+    }
+
+    fn from_file(
+        file: ConfigFile,
+        profile: Option<String>,
+        overrides: &Overrides,
+        require_profile: bool,
+    ) -> anyhow::Result<Self> {
         let mut figment = Figment::from(Serialized::defaults(RawConfig::default()))
-            .merge(Toml::file(config_file_path()))
-            .merge(Env::prefixed("PNGX_"));
+            .merge(Toml::string(&file.base_layer(profile.is_some())));
+        if let Some(name) = &profile {
+            match file.profile_layer(name) {
+                Some(layer) => figment = figment.merge(Toml::string(&layer)),
+                None if require_profile => {
+                    return Err(ConfigError::UnknownProfile(name.clone()).into());
+                }
+                None => {}
+            }
+        }
+        figment = figment.merge(Env::prefixed("PNGX_").ignore(&["profile"]));
 
-        if let Some(url) = url_override {
+        if let Some(url) = overrides.url {
             figment = figment.merge(Serialized::default("url", url));
         }
-        if let Some(token) = token_override {
+        if let Some(token) = overrides.token {
             figment = figment.merge(Serialized::default("token", token));
         }
 
-        let config: RawConfig = figment.extract()?;
+        let mut config: RawConfig = figment.extract()?;
+        config.profile = profile;
+        config.token_source = if overrides.token_stdin {
+            TokenSource::Stdin
+        } else if overrides.token.is_some() {
+            TokenSource::Flag
+        } else if !config.token.is_empty() {
+            if std::env::var_os("PNGX_TOKEN").is_some_and(|v| !v.is_empty()) {
+                TokenSource::Env
+            } else {
+                TokenSource::ConfigFile(file.path)
+            }
+        } else if let Some(command) = &config.token_command {
+            TokenSource::Command(command.clone())
+        } else if let Some(path) = &config.token_file {
+            TokenSource::File(path.clone())
+        } else {
+            TokenSource::Missing
+        };
 
         if !config.url.is_empty() && config.url.starts_with("http://") {
             tracing::warn!("using insecure HTTP connection to {}", config.url);
@@ -91,25 +311,89 @@ impl RawConfig {
         Ok(config)
     }
 
-    pub fn validate(self) -> Result<ValidConfig, ConfigError> {
+    pub fn token_source(&self) -> &TokenSource {
+        &self.token_source
+    }
+
+    /// The selected profile, if any.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Checks the config and reads the token from its source.
+    pub fn validate(mut self) -> Result<ValidConfig, ConfigError> {
         if self.url.is_empty() {
             return Err(ConfigError::MissingUrl);
         }
-        if self.token.is_empty() {
+        if self.token_source == TokenSource::Missing {
             return Err(ConfigError::MissingToken);
         }
 
-        let url = Url::parse(&self.url).map_err(|e| ConfigError::InvalidUrl {
+        let url = self.parse_url()?;
+
+        let token = match &self.token_source {
+            TokenSource::Missing
+            | TokenSource::Flag
+            | TokenSource::Env
+            | TokenSource::ConfigFile(_) => std::mem::take(&mut self.token),
+            TokenSource::Stdin => read_token_stdin()?,
+            TokenSource::Command(command) => run_token_command(command)?,
+            TokenSource::File(path) => read_token_file(path)?,
+        };
+        if token.is_empty() {
+            return Err(ConfigError::MissingToken);
+        }
+        self.into_valid(url, token)
+    }
+
+    /// Checks the config without a token, for logging in to obtain one.
+    pub fn validate_without_token(self) -> Result<ValidConfig, ConfigError> {
+        if self.url.is_empty() {
+            return Err(ConfigError::MissingUrl);
+        }
+        let url = self.parse_url()?;
+        self.into_valid(url, String::new())
+    }
+
+    fn parse_url(&self) -> Result<Url, ConfigError> {
+        Url::parse(&self.url).map_err(|e| ConfigError::InvalidUrl {
             url: self.url.clone(),
             source: e,
-        })?;
+        })
+    }
+
+    /// Reads the certificates and assembles the checked config.
+    fn into_valid(self, url: Url, token: String) -> Result<ValidConfig, ConfigError> {
+        let ca_certs = self
+            .ca_certs
+            .iter()
+            .map(|path| read_tls_file("CA certificate", path))
+            .collect::<Result<_, _>>()?;
+        let client_cert = match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => Some((
+                read_tls_file("client certificate", cert)?,
+                read_tls_file("client key", key)?,
+            )),
+            (None, None) => None,
+            _ => return Err(ConfigError::IncompleteClientCert),
+        };
 
         Ok(ValidConfig {
             url,
-            token: self.token,
+            token,
+            token_source: self.token_source,
             output_format: self.output_format,
+            list_separator: self.list_separator,
             page_size: self.page_size,
             timeout: self.timeout,
+            retries: self.retries,
+            cache_ttl: self.cache_ttl,
+            api_version: self.api_version,
+            rewrite_next_urls: self.rewrite_next_urls,
+            ca_certs,
+            client_cert,
+            headers: self.headers,
+            proxy: self.proxy,
         })
     }
 }
@@ -117,9 +401,48 @@ impl RawConfig {
 pub struct ValidConfig {
     pub url: Url,
     pub token: String,
+    pub token_source: TokenSource,
     pub output_format: OutputFormat,
+    pub list_separator: String,
     pub page_size: u32,
     pub timeout: u64,
+    pub retries: u32,
+    pub cache_ttl: u64,
+    pub api_version: Option<u32>,
+    pub rewrite_next_urls: bool,
+    /// PEM-encoded CA certificates.
+    pub ca_certs: Vec<Vec<u8>>,
+    /// PEM-encoded client certificate and key.
+    pub client_cert: Option<(Vec<u8>, Vec<u8>)>,
+    pub headers: BTreeMap<String, String>,
+    pub proxy: Option<String>,
+}
+
+impl ValidConfig {
+    /// A key identifying the server and the user, for files stored per
+    /// server. The token identifies the user. It is hashed so it never ends
+    /// up on disk.
+    pub fn server_key(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.url.as_str());
+        hasher.update([0]);
+        hasher.update(&self.token);
+        short_hash(hasher)
+    }
+
+    /// A key identifying the server alone, for files that must outlive a
+    /// change of token.
+    pub fn url_key(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.url.as_str());
+        short_hash(hasher)
+    }
+}
+
+fn short_hash(hasher: Sha256) -> String {
+    let mut key = [0u8; 8];
+    key.copy_from_slice(&hasher.finalize()[..8]);
+    format!("{:016x}", u64::from_be_bytes(key))
 }
 
 impl fmt::Debug for ValidConfig {
@@ -127,13 +450,274 @@ impl fmt::Debug for ValidConfig {
         f.debug_struct("ValidConfig")
             .field("url", &self.url)
             .field("token", &"[REDACTED]")
+            .field("token_source", &self.token_source)
             .field("output_format", &self.output_format)
+            .field("list_separator", &self.list_separator)
             .field("page_size", &self.page_size)
             .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .field("cache_ttl", &self.cache_ttl)
+            .field("api_version", &self.api_version)
+            .field("rewrite_next_urls", &self.rewrite_next_urls)
+            .field("ca_certs", &self.ca_certs.len())
+            .field("client_cert", &self.client_cert.is_some())
+            .field("headers", &self.headers.keys())
+            .field("proxy", &self.proxy)
             .finish()
     }
 }
 
+/// The config file, edited in place so comments and other profiles survive.
+pub struct ConfigFile {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+impl ConfigFile {
+    /// Reads the config file. A missing file reads as empty.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = config_file_path();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(anyhow::Error::new(err)
+                    .context(format!("failed to read config file: {}", path.display())));
+            }
+        };
+        let doc = content.parse().map_err(|err| {
+            anyhow::anyhow!("failed to parse config file {}: {err}", path.display())
+        })?;
+        Ok(Self { path, doc })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn default_profile(&self) -> Option<&str> {
+        self.doc.get("default_profile").and_then(Item::as_str)
+    }
+
+    /// Names of the configured profiles, in file order.
+    pub fn profiles(&self) -> Vec<&str> {
+        self.doc
+            .get("profiles")
+            .and_then(Item::as_table_like)
+            .map(|profiles| profiles.iter().map(|(name, _)| name).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.profiles().contains(&name)
+    }
+
+    /// Whether the top level of the file configures a server.
+    pub fn has_top_level_url(&self) -> bool {
+        self.doc.contains_key("url")
+    }
+
+    /// The server URL of a profile, or of the top level for `None`.
+    pub fn url(&self, profile: Option<&str>) -> Option<&str> {
+        match profile {
+            Some(name) => self.profile_table(name)?.get("url")?.as_str(),
+            None => self.doc.get("url")?.as_str(),
+        }
+    }
+
+    pub fn set_default_profile(&mut self, name: &str) {
+        self.doc["default_profile"] = toml_edit::value(name);
+    }
+
+    /// Saves a URL and token at the top level, or in `profile`. Other token
+    /// sources in the same table are removed, since the token would take
+    /// precedence over them anyway.
+    pub fn set_credentials(
+        &mut self,
+        profile: Option<&str>,
+        url: &str,
+        token: &str,
+    ) -> anyhow::Result<()> {
+        let table = match profile {
+            None => self.doc.as_table_mut(),
+            Some(name) => {
+                let profiles = self
+                    .doc
+                    .entry("profiles")
+                    .or_insert_with(|| {
+                        let mut table = Table::new();
+                        table.set_implicit(true);
+                        Item::Table(table)
+                    })
+                    .as_table_mut()
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("`profiles` in the config file is not a table")
+                    })?;
+                profiles
+                    .entry(name)
+                    .or_insert_with(toml_edit::table)
+                    .as_table_mut()
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("profile '{name}' in the config file is not a table")
+                    })?
+            }
+        };
+        table["url"] = toml_edit::value(url);
+        table["token"] = toml_edit::value(token);
+        table.remove("token_command");
+        table.remove("token_file");
+        Ok(())
+    }
+
+    /// Removes a profile, and the default if it pointed to it. Returns
+    /// whether the profile existed.
+    pub fn remove_profile(&mut self, name: &str) -> bool {
+        let removed = self
+            .doc
+            .get_mut("profiles")
+            .and_then(Item::as_table_like_mut)
+            .and_then(|profiles| profiles.remove(name))
+            .is_some();
+        if self.default_profile() == Some(name) {
+            self.doc.remove("default_profile");
+        }
+        removed
+    }
+
+    /// Removes the top-level URL and token sources, keeping profiles and
+    /// other settings. Returns whether any of them was set.
+    pub fn remove_credentials(&mut self) -> bool {
+        let table = self.doc.as_table_mut();
+        let mut removed = false;
+        for key in ["url", "token", "token_command", "token_file"] {
+            removed |= table.remove(key).is_some();
+        }
+        removed
+    }
+
+    /// Writes the file, readable only by the owner.
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create config directory: {}", dir.display()))?;
+        }
+        write_config_file(&self.path, self.doc.to_string().as_bytes())
+            .with_context(|| format!("failed to write config file: {}", self.path.display()))
+    }
+
+    fn profile_table(&self, name: &str) -> Option<&dyn toml_edit::TableLike> {
+        self.doc
+            .get("profiles")?
+            .as_table_like()?
+            .get(name)?
+            .as_table_like()
+    }
+
+    /// The top-level settings as TOML. Credentials are left out when a
+    /// profile is selected.
+    fn base_layer(&self, for_profile: bool) -> String {
+        let mut table = self.doc.as_table().clone();
+        table.remove("profiles");
+        table.remove("default_profile");
+        if for_profile {
+            for key in CREDENTIAL_KEYS {
+                table.remove(key);
+            }
+        }
+        DocumentMut::from(table).to_string()
+    }
+
+    /// The settings of a profile as TOML.
+    fn profile_layer(&self, name: &str) -> Option<String> {
+        let item = self
+            .doc
+            .get("profiles")?
+            .as_table_like()?
+            .get(name)?
+            .clone();
+        let table = item.into_table().ok()?;
+        Some(DocumentMut::from(table).to_string())
+    }
+}
+
+#[cfg(unix)]
+fn write_config_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(content)
+}
+
+#[cfg(not(unix))]
+fn write_config_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+/// Returns the first line of `text`, which holds the token in the output of
+/// password managers like `pass`.
+fn first_line(text: &str) -> String {
+    text.lines().next().unwrap_or_default().trim().to_string()
+}
+
+fn read_token_stdin() -> Result<String, ConfigError> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(ConfigError::TokenStdin)?;
+    Ok(first_line(&input))
+}
+
+fn read_token_file(path: &Path) -> Result<String, ConfigError> {
+    let content = std::fs::read_to_string(path).map_err(|source| ConfigError::TokenFile {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(first_line(&content))
+}
+
+fn read_tls_file(kind: &'static str, path: &Path) -> Result<Vec<u8>, ConfigError> {
+    fs::read(path).map_err(|source| ConfigError::TlsFile {
+        kind,
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Runs `command` with the platform shell. Its stdin and stderr stay
+/// attached to the terminal so password managers can prompt.
+fn run_token_command(command: &str) -> Result<String, ConfigError> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let failed = |message: String| ConfigError::TokenCommand {
+        command: command.to_string(),
+        message,
+    };
+    let output = shell
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| failed(err.to_string()))?;
+    if !output.status.success() {
+        return Err(failed(output.status.to_string()));
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| failed("output is not valid UTF-8".to_string()))?;
+    Ok(first_line(&stdout))
+}
+
 pub fn config_dir() -> PathBuf {
     etcetera::choose_base_strategy().ok().map_or_else(
         || PathBuf::from("."),
@@ -141,6 +725,358 @@ pub fn config_dir() -> PathBuf {
     )
 }
 
+pub fn cache_dir() -> PathBuf {
+    etcetera::choose_base_strategy().ok().map_or_else(
+        || PathBuf::from("."),
+        |s| etcetera::BaseStrategy::cache_dir(&s).join("pngx"),
+    )
+}
+
 pub fn config_file_path() -> PathBuf {
     config_dir().join("config.toml")
 }
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::panic,
+    // `Jail` closures return figment's large error type.
+    clippy::result_large_err
+)]
+mod tests {
+    use figment::Jail;
+
+    use super::*;
+
+    /// Points the config directory into the jail and writes `content` as the
+    /// config file.
+    fn config_file(jail: &mut Jail, content: &str) {
+        jail.clear_env();
+        let dir = jail.directory().to_path_buf();
+        jail.set_env("HOME", dir.display());
+        jail.set_env("XDG_CONFIG_HOME", dir.display());
+        jail.create_dir("pngx").unwrap();
+        jail.create_file("pngx/config.toml", content).unwrap();
+    }
+
+    fn load(overrides: &Overrides) -> RawConfig {
+        RawConfig::load(overrides).expect("config should load")
+    }
+
+    #[test]
+    fn test_token_source_precedence() {
+        Jail::expect_with(|jail| {
+            config_file(
+                jail,
+                r#"
+                url = "https://paperless.example.com"
+                token_file = "token.txt"
+                "#,
+            );
+            jail.create_file("token.txt", "from-file\nsecond line\n")?;
+            let config = load(&Overrides::default());
+            assert_eq!(
+                config.token_source(),
+                &TokenSource::File(PathBuf::from("token.txt"))
+            );
+            assert_eq!(config.validate().unwrap().token, "from-file");
+
+            jail.set_env("PNGX_TOKEN_COMMAND", "echo from-command");
+            let config = load(&Overrides::default());
+            assert_eq!(
+                config.token_source(),
+                &TokenSource::Command("echo from-command".to_string())
+            );
+            assert_eq!(config.validate().unwrap().token, "from-command");
+
+            jail.set_env("PNGX_TOKEN", "from-env");
+            let config = load(&Overrides::default());
+            assert_eq!(config.token_source(), &TokenSource::Env);
+            assert_eq!(config.validate().unwrap().token, "from-env");
+
+            let overrides = Overrides {
+                token: Some("from-flag"),
+                ..Overrides::default()
+            };
+            let config = load(&overrides);
+            assert_eq!(config.token_source(), &TokenSource::Flag);
+            assert_eq!(config.validate().unwrap().token, "from-flag");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_token_from_config_file() {
+        Jail::expect_with(|jail| {
+            config_file(
+                jail,
+                r#"
+                url = "https://paperless.example.com"
+                token = "from-config"
+                token_command = "echo from-command"
+                "#,
+            );
+            let config = load(&Overrides::default());
+            assert!(
+                matches!(config.token_source(), TokenSource::ConfigFile(path) if path.ends_with("pngx/config.toml"))
+            );
+            assert_eq!(config.validate().unwrap().token, "from-config");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_missing_token_and_url() {
+        Jail::expect_with(|jail| {
+            config_file(jail, r#"url = "https://paperless.example.com""#);
+            let config = load(&Overrides::default());
+            assert_eq!(config.token_source(), &TokenSource::Missing);
+            assert!(matches!(config.validate(), Err(ConfigError::MissingToken)));
+
+            config_file(jail, "");
+            let overrides = Overrides {
+                token: Some("abc"),
+                ..Overrides::default()
+            };
+            assert!(matches!(
+                load(&overrides).validate(),
+                Err(ConfigError::MissingUrl)
+            ));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_profile_layers_over_top_level() {
+        Jail::expect_with(|jail| {
+            config_file(
+                jail,
+                r#"
+                page_size = 50
+                output_format = "csv"
+
+                [profiles.work]
+                url = "https://work.example.com"
+                token = "work-token"
+                output_format = "json"
+                "#,
+            );
+            let overrides = Overrides {
+                profile: Some("work"),
+                ..Overrides::default()
+            };
+            let config = load(&overrides);
+            assert_eq!(config.profile(), Some("work"));
+            assert_eq!(config.page_size, 50);
+            assert!(matches!(config.output_format, OutputFormat::Json));
+            let config = config.validate().unwrap();
+            assert_eq!(config.url.as_str(), "https://work.example.com/");
+            assert_eq!(config.token, "work-token");
+
+            // Environment variables override the profile.
+            jail.set_env("PNGX_PAGE_SIZE", 10);
+            jail.set_env("PNGX_OUTPUT_FORMAT", "tsv");
+            let config = load(&overrides);
+            assert_eq!(config.page_size, 10);
+            assert!(matches!(config.output_format, OutputFormat::Tsv));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_profile_does_not_inherit_credentials() {
+        Jail::expect_with(|jail| {
+            config_file(
+                jail,
+                r#"
+                url = "https://home.example.com"
+                token = "home-token"
+                token_command = "echo home-command"
+                token_file = "home.txt"
+                client_cert = "home.pem"
+                client_key = "home.key"
+                headers = { X-Proxy-User = "home" }
+                timeout = 5
+
+                [profiles.work]
+                output_format = "json"
+                "#,
+            );
+            let overrides = Overrides {
+                profile: Some("work"),
+                ..Overrides::default()
+            };
+            let config = load(&overrides);
+            assert_eq!(config.timeout, 5);
+            for key in CREDENTIAL_KEYS {
+                let inherited = match *key {
+                    "url" => !config.url.is_empty(),
+                    "token" => !config.token.is_empty(),
+                    "token_command" => config.token_command.is_some(),
+                    "token_file" => config.token_file.is_some(),
+                    "client_cert" => config.client_cert.is_some(),
+                    "client_key" => config.client_key.is_some(),
+                    "headers" => !config.headers.is_empty(),
+                    other => panic!("untested credential key {other}"),
+                };
+                assert!(!inherited, "profile inherited `{key}`");
+            }
+            assert_eq!(config.token_source(), &TokenSource::Missing);
+            assert!(matches!(config.validate(), Err(ConfigError::MissingUrl)));
+
+            // The top level keeps its credentials.
+            jail.create_file("home.pem", "certificate")?;
+            jail.create_file("home.key", "key")?;
+            let config = load(&Overrides::default());
+            assert_eq!(config.profile(), None);
+            assert_eq!(config.validate().unwrap().token, "home-token");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_profile_selection() {
+        Jail::expect_with(|jail| {
+            config_file(
+                jail,
+                r#"
+                default_profile = "home"
+
+                [profiles.home]
+                url = "https://home.example.com"
+                token = "home-token"
+
+                [profiles.work]
+                url = "https://work.example.com"
+                token = "work-token"
+                "#,
+            );
+            assert_eq!(load(&Overrides::default()).profile(), Some("home"));
+
+            // `PNGX_PROFILE` arrives as `--profile`; figment must not read it
+            // as a setting.
+            jail.set_env("PNGX_PROFILE", "work");
+            let overrides = Overrides {
+                profile: Some("work"),
+                ..Overrides::default()
+            };
+            let config = load(&overrides);
+            assert_eq!(config.profile(), Some("work"));
+            assert_eq!(config.url, "https://work.example.com");
+
+            let config = RawConfig::load_profile(None, &overrides).unwrap();
+            assert_eq!(config.profile(), None);
+            assert!(config.url.is_empty());
+
+            let overrides = Overrides {
+                profile: Some("missing"),
+                ..Overrides::default()
+            };
+            let err = RawConfig::load(&overrides).expect_err("should fail");
+            assert!(matches!(
+                err.downcast_ref::<ConfigError>(),
+                Some(ConfigError::UnknownProfile(name)) if name == "missing"
+            ));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_load_for_login_allows_new_profile() {
+        Jail::expect_with(|jail| {
+            config_file(
+                jail,
+                r#"
+                url = "https://home.example.com"
+                headers = { X-Proxy-User = "home" }
+                timeout = 5
+                "#,
+            );
+            let overrides = Overrides {
+                url: Some("https://new.example.com"),
+                ..Overrides::default()
+            };
+            let config = RawConfig::load_for_login(Some("new"), &overrides)
+                .unwrap()
+                .validate_without_token()
+                .unwrap();
+            assert_eq!(config.url.as_str(), "https://new.example.com/");
+            assert_eq!(config.timeout, 5);
+            assert!(config.headers.is_empty());
+            assert!(config.token.is_empty());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_set_credentials_and_default_profile() {
+        Jail::expect_with(|jail| {
+            config_file(
+                jail,
+                "# my servers\n[profiles.work]\ntoken_command = \"pass work\"\n",
+            );
+            let mut file = ConfigFile::load().unwrap();
+            assert!(!file.has_top_level_url());
+            file.set_credentials(Some("work"), "https://work.example.com", "abc")
+                .unwrap();
+            file.set_credentials(Some("home"), "https://home.example.com", "def")
+                .unwrap();
+            file.set_default_profile("work");
+            file.save().unwrap();
+
+            let file = ConfigFile::load().unwrap();
+            assert_eq!(file.profiles(), ["work", "home"]);
+            assert_eq!(file.default_profile(), Some("work"));
+            assert_eq!(file.url(Some("home")), Some("https://home.example.com"));
+            let content = fs::read_to_string(file.path()).unwrap();
+            assert!(content.contains("# my servers"));
+            assert!(!content.contains("token_command"));
+
+            let mut file = file;
+            assert!(file.remove_profile("work"));
+            assert_eq!(file.default_profile(), None);
+            assert!(!file.remove_profile("work"));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_transport_settings_from_env() {
+        Jail::expect_with(|jail| {
+            config_file(
+                jail,
+                r#"
+                url = "https://paperless.example.com"
+                token = "abc"
+                "#,
+            );
+            jail.create_file("internal-ca.pem", "first CA")?;
+            jail.create_file("other-ca.pem", "second CA")?;
+            jail.set_env("PNGX_CA_CERTS", r#"["internal-ca.pem", "other-ca.pem"]"#);
+            jail.set_env(
+                "PNGX_HEADERS",
+                r#"{CF-Access-Client-Id="client-id", X-Proxy-User="alice"}"#,
+            );
+            let config = load(&Overrides::default()).validate().unwrap();
+            assert_eq!(
+                config.ca_certs,
+                [b"first CA".to_vec(), b"second CA".to_vec()]
+            );
+            assert_eq!(
+                config.headers,
+                BTreeMap::from([
+                    ("CF-Access-Client-Id".to_string(), "client-id".to_string()),
+                    ("X-Proxy-User".to_string(), "alice".to_string()),
+                ])
+            );
+
+            jail.set_env("PNGX_CA_CERTS", r#"["missing.pem"]"#);
+            assert!(matches!(
+                load(&Overrides::default()).validate(),
+                Err(ConfigError::TlsFile { .. })
+            ));
+            Ok(())
+        });
+    }
+}
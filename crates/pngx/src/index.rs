@@ -0,0 +1,449 @@
+//! Local full-text index of document content and resolved metadata, for
+//! searching without the server.
+//!
+//! The index lives next to the config, one per server and user. Each entry
+//! keeps the document as the server returned it, without its content, so
+//! results are resolved and printed like those of server search. The names
+//! used for resolving are kept in the index state.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use jiff::Timestamp;
+use jiff::tz::TimeZone;
+use pngx_client::Document;
+use serde::{Deserialize, Serialize};
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::QueryParser;
+use tantivy::schema::{
+    DateOptions, DateTimePrecision, Field, INDEXED, STORED, Schema, TEXT, Value,
+};
+use tantivy::snippet::{Snippet, SnippetGenerator};
+use tantivy::{DateTime, DocAddress, Index, IndexWriter, TantivyDocument, Term};
+
+use crate::config::{ValidConfig, config_dir};
+use crate::resolve::NameResolver;
+
+/// Index state, kept next to the index files.
+const STATE_FILE: &str = "pngx-index.json";
+
+/// Memory used by the index writer.
+const WRITER_MEMORY: usize = 50_000_000;
+
+/// Longest snippet of matching content, in characters.
+const SNIPPET_LEN: usize = 150;
+
+/// What the index holds and when it was last brought up to date.
+#[derive(Default, Serialize, Deserialize)]
+pub struct IndexState {
+    pub server: String,
+    pub built: Option<Timestamp>,
+    pub updated: Option<Timestamp>,
+    /// Newest modification time of the indexed documents, as reported by the
+    /// server.
+    pub last_modified: Option<Timestamp>,
+    pub documents: BTreeSet<u64>,
+    /// Names of the tags, correspondents and document types referenced by
+    /// the indexed documents.
+    pub names: NameResolver,
+}
+
+/// A document matching a local search.
+pub struct Hit {
+    pub document: Document,
+    pub score: f32,
+    /// Matching content with the query terms in `**bold**`.
+    pub snippet: String,
+}
+
+pub struct LocalIndex {
+    dir: PathBuf,
+    index: Index,
+    fields: Fields,
+    pub state: IndexState,
+}
+
+/// Fields of the index schema. Their names double as the field names of
+/// search queries, matching those of Paperless-ngx.
+#[derive(Clone, Copy)]
+struct Fields {
+    id: Field,
+    title: Field,
+    content: Field,
+    correspondent: Field,
+    document_type: Field,
+    tag: Field,
+    notes: Field,
+    asn: Field,
+    created: Field,
+    added: Field,
+    /// The document as JSON, without its content.
+    document: Field,
+}
+
+impl Fields {
+    fn schema() -> (Schema, Self) {
+        let date = DateOptions::from(INDEXED).set_precision(DateTimePrecision::Seconds);
+        let mut builder = Schema::builder();
+        let fields = Self {
+            id: builder.add_u64_field("id", INDEXED | STORED),
+            title: builder.add_text_field("title", TEXT),
+            content: builder.add_text_field("content", TEXT | STORED),
+            correspondent: builder.add_text_field("correspondent", TEXT),
+            document_type: builder.add_text_field("type", TEXT),
+            tag: builder.add_text_field("tag", TEXT),
+            notes: builder.add_text_field("notes", TEXT),
+            asn: builder.add_u64_field("asn", INDEXED),
+            created: builder.add_date_field("created", date.clone()),
+            added: builder.add_date_field("added", date),
+            document: builder.add_text_field("document", STORED),
+        };
+        (builder.build(), fields)
+    }
+
+    fn from_schema(schema: &Schema) -> Result<Self> {
+        let field = |name: &str| {
+            schema
+                .get_field(name)
+                .with_context(|| format!("local index has no field '{name}', rebuild it"))
+        };
+        Ok(Self {
+            id: field("id")?,
+            title: field("title")?,
+            content: field("content")?,
+            correspondent: field("correspondent")?,
+            document_type: field("type")?,
+            tag: field("tag")?,
+            notes: field("notes")?,
+            asn: field("asn")?,
+            created: field("created")?,
+            added: field("added")?,
+            document: field("document")?,
+        })
+    }
+
+    fn entry(&self, doc: &Document, names: &NameResolver) -> Result<TantivyDocument> {
+        let mut entry = TantivyDocument::new();
+        entry.add_u64(self.id, doc.id);
+        entry.add_text(self.title, &doc.title);
+        if let Some(content) = &doc.content {
+            entry.add_text(self.content, content);
+        }
+        if let Some(name) = doc
+            .correspondent
+            .and_then(|id| names.correspondent_name(id))
+        {
+            entry.add_text(self.correspondent, name);
+        }
+        if let Some(name) = doc
+            .document_type
+            .and_then(|id| names.document_type_name(id))
+        {
+            entry.add_text(self.document_type, name);
+        }
+        for &id in &doc.tags {
+            entry.add_text(self.tag, names.tag_name(id));
+        }
+        for note in &doc.notes {
+            entry.add_text(self.notes, &note.note);
+        }
+        if let Some(asn) = doc.archive_serial_number {
+            entry.add_u64(self.asn, asn);
+        }
+        let created = doc
+            .created
+            .and_then(|date| date.to_zoned(TimeZone::UTC).ok())
+            .map(|zoned| zoned.timestamp());
+        if let Some(created) = created {
+            entry.add_date(self.created, date_time(created));
+        }
+        if let Some(added) = doc.added {
+            entry.add_date(self.added, date_time(added));
+        }
+
+        // The content is indexed on its own; keep one copy.
+        let mut stored = doc.clone();
+        stored.content = None;
+        entry.add_text(self.document, serde_json::to_string(&stored)?);
+        Ok(entry)
+    }
+
+    /// The document stored in an index entry.
+    fn document(&self, entry: &TantivyDocument) -> Result<Document> {
+        let json = entry
+            .get_first(self.document)
+            .and_then(|value| value.as_str())
+            .context("local index entry has no document, rebuild it")?;
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl LocalIndex {
+    /// Directory of the index for the configured server. It is keyed by
+    /// the URL alone so that a new token finds the same index.
+    pub fn path(config: &ValidConfig) -> PathBuf {
+        config_dir().join("index").join(config.url_key())
+    }
+
+    /// Creates an empty index in `dir`, replacing any existing one.
+    pub fn create(dir: &Path, server: &str) -> Result<Self> {
+        match fs::remove_dir_all(dir) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to remove {}", dir.display()));
+            }
+        }
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create directory: {}", dir.display()))?;
+        let (schema, fields) = Fields::schema();
+        let index = Index::create_in_dir(dir, schema)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            index,
+            fields,
+            state: IndexState {
+                server: server.to_string(),
+                built: Some(Timestamp::now()),
+                ..IndexState::default()
+            },
+        })
+    }
+
+    /// Opens the index in `dir`, or returns `None` if it hasn't been built.
+    pub fn open(dir: &Path) -> Result<Option<Self>> {
+        let state_path = dir.join(STATE_FILE);
+        let data = match fs::read(&state_path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to read {}", state_path.display()));
+            }
+        };
+        let state = serde_json::from_slice(&data)
+            .with_context(|| format!("malformed index state: {}", state_path.display()))?;
+        let index = Index::open_in_dir(dir)
+            .with_context(|| format!("failed to open index in {}", dir.display()))?;
+        let fields = Fields::from_schema(&index.schema())?;
+        Ok(Some(Self {
+            dir: dir.to_path_buf(),
+            index,
+            fields,
+            state,
+        }))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Adds or replaces `docs` and removes the documents with the IDs in
+    /// `removed`. `names` must know the names referenced by `docs`.
+    pub fn apply(
+        &mut self,
+        docs: &[Document],
+        names: &NameResolver,
+        removed: &[u64],
+    ) -> Result<()> {
+        let mut writer: IndexWriter = self.index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        for &id in removed.iter().chain(docs.iter().map(|doc| &doc.id)) {
+            writer.delete_term(Term::from_field_u64(self.fields.id, id));
+        }
+        for doc in docs {
+            writer.add_document(self.fields.entry(doc, names)?)?;
+        }
+        writer.commit()?;
+
+        for id in removed {
+            self.state.documents.remove(id);
+        }
+        for doc in docs {
+            self.state.documents.insert(doc.id);
+            self.state.last_modified = self.state.last_modified.max(doc.modified);
+        }
+        self.state.updated = Some(Timestamp::now());
+        self.save()
+    }
+
+    /// Records the names the indexed documents were resolved with, once all
+    /// of them are up to date.
+    pub fn set_names(&mut self, names: NameResolver) -> Result<()> {
+        self.state.names = names;
+        self.save()
+    }
+
+    /// IDs of the indexed documents for which `matches` returns true.
+    pub fn find(&self, mut matches: impl FnMut(&Document) -> bool) -> Result<Vec<u64>> {
+        let searcher = self.index.reader()?.searcher();
+        let mut ids = Vec::new();
+        for (ord, segment) in searcher.segment_readers().iter().enumerate() {
+            let ord = u32::try_from(ord)?;
+            for doc_id in segment.doc_ids_alive() {
+                let entry: TantivyDocument = searcher.doc(DocAddress::new(ord, doc_id))?;
+                let document = self.fields.document(&entry)?;
+                if matches(&document) {
+                    ids.push(document.id);
+                }
+            }
+        }
+        Ok(ids)
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = self.dir.join(STATE_FILE);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(&self.state)?)
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Returns the total number of matches and up to `limit` of them, best
+    /// first.
+    pub fn search(&self, query: &str, limit: Option<usize>) -> Result<(u64, Vec<Hit>)> {
+        let f = self.fields;
+        let mut parser = QueryParser::for_index(
+            &self.index,
+            vec![
+                f.title,
+                f.content,
+                f.correspondent,
+                f.document_type,
+                f.tag,
+                f.notes,
+            ],
+        );
+        parser.set_conjunction_by_default();
+        parser.set_field_boost(f.title, 2.0);
+        let query = parser
+            .parse_query(&expand_dates(query))
+            .with_context(|| format!("invalid query: {query}"))?;
+
+        let searcher = self.index.reader()?.searcher();
+        let all = usize::try_from(searcher.num_docs()).unwrap_or(usize::MAX);
+        let limit = limit.unwrap_or(all).clamp(1, all.max(1));
+        let (top, total) = searcher.search(&query, &(TopDocs::with_limit(limit), Count))?;
+
+        let mut snippets = SnippetGenerator::create(&searcher, &query, f.content)?;
+        snippets.set_max_num_chars(SNIPPET_LEN);
+        let mut hits = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let entry: TantivyDocument = searcher.doc(address)?;
+            hits.push(Hit {
+                document: f.document(&entry)?,
+                score,
+                snippet: highlight(&snippets.snippet_from_doc(&entry)),
+            });
+        }
+        Ok((total as u64, hits))
+    }
+
+    /// Number of indexed documents.
+    pub fn num_docs(&self) -> Result<u64> {
+        Ok(self.index.reader()?.searcher().num_docs())
+    }
+
+    /// Size of the index files in bytes.
+    pub fn size(&self) -> Result<u64> {
+        let mut size = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let metadata = entry?.metadata()?;
+            if metadata.is_file() {
+                size += metadata.len();
+            }
+        }
+        Ok(size)
+    }
+}
+
+fn date_time(ts: Timestamp) -> DateTime {
+    DateTime::from_timestamp_secs(ts.as_second())
+}
+
+/// Turns plain dates such as `2024-01-31` into the RFC 3339 timestamps the
+/// query parser expects, so `created:[2024-01-01 TO 2024-12-31]` works.
+fn expand_dates(query: &str) -> String {
+    let bytes = query.as_bytes();
+    let mut out = String::with_capacity(query.len());
+    let mut last = 0;
+    let mut i = 0;
+    while i + 10 <= bytes.len() {
+        let candidate = &bytes[i..i + 10];
+        let is_date = candidate.iter().enumerate().all(|(j, b)| match j {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        });
+        let starts_token = i == 0 || !bytes[i - 1].is_ascii_alphanumeric();
+        let ends_token = bytes
+            .get(i + 10)
+            .is_none_or(|b| !b.is_ascii_alphanumeric() && *b != b':');
+        if is_date && starts_token && ends_token {
+            out.push_str(&query[last..i + 10]);
+            out.push_str("T00:00:00Z");
+            last = i + 10;
+            i += 10;
+        } else {
+            i += 1;
+        }
+    }
+    out.push_str(&query[last..]);
+    out
+}
+
+/// Renders a snippet on one line with the matched terms in bold.
+fn highlight(snippet: &Snippet) -> String {
+    let fragment = snippet.fragment();
+    let mut out = String::with_capacity(fragment.len() + 16);
+    let mut last = 0;
+    for range in snippet.highlighted() {
+        out.push_str(&fragment[last..range.start]);
+        out.push_str("**");
+        out.push_str(&fragment[range.clone()]);
+        out.push_str("**");
+        last = range.end;
+    }
+    out.push_str(&fragment[last..]);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_dates() {
+        assert_eq!(
+            expand_dates("created:2024-01-01"),
+            "created:2024-01-01T00:00:00Z"
+        );
+        assert_eq!(
+            expand_dates("created:[2024-01-01 TO 2024-12-31]"),
+            "created:[2024-01-01T00:00:00Z TO 2024-12-31T00:00:00Z]"
+        );
+        assert_eq!(
+            expand_dates("added:{2024-01-01 TO *} café"),
+            "added:{2024-01-01T00:00:00Z TO *} café"
+        );
+        assert_eq!(expand_dates("2024-05-02"), "2024-05-02T00:00:00Z");
+    }
+
+    #[test]
+    fn test_expand_dates_leaves_other_text() {
+        for query in [
+            "invoice",
+            "x2024-01-01",
+            "2024-01-01x",
+            "ref2024-01-01-a",
+            "created:2024-01-01T10:00:00Z",
+            "2024-01-0",
+            "20240101",
+            "2024/01/01",
+        ] {
+            assert_eq!(expand_dates(query), query);
+        }
+    }
+}
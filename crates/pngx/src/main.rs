@@ -1,5 +1,6 @@
 mod commands;
 mod config;
+mod index;
 mod output;
 mod resolve;
 
@@ -8,11 +9,14 @@ use std::process::ExitCode;
 use std::time::Duration;
 
 use clap::{ArgAction, Args, Parser, Subcommand};
-use pngx_client::ApiError;
+use pngx_client::{
+    ApiError, CorrespondentRequest, DocumentTypeRequest, MatchingAlgorithm, TagRequest,
+};
 use tracing_subscriber::EnvFilter;
 
-use config::{ConfigError, RawConfig};
-use output::OutputFormat;
+use config::{ConfigError, Overrides, RawConfig};
+use output::{OutputFormat, OutputOptions};
+use resolve::{CacheMode, NameCache};
 
 #[derive(Parser)]
 #[command(
@@ -22,14 +26,24 @@ use output::OutputFormat;
         transforms physical documents into a searchable online archive.",
     after_long_help = "GETTING STARTED:\n  \
         pngx auth login              Save server URL and API token\n  \
-        pngx auth status             Show config and verify connection\n\n\
+        pngx auth login --username U Log in with a password instead\n  \
+        pngx auth status             Show config and verify connection\n  \
+        pngx profile use work        Switch the default server profile\n\n\
         COMMON WORKFLOWS:\n  \
         pngx inbox                   List unprocessed inbox documents\n  \
+        pngx inbox -i                Triage the inbox in a terminal UI\n  \
+        pngx inbox process --dry-run Preview what the inbox rules change\n  \
         pngx search \"invoice 2024\"   Find documents matching a query\n  \
         pngx documents get 42 43     View document details\n  \
         pngx documents content 42    Read document text\n  \
         pngx documents open 42 43    Open in the web UI\n  \
-        pngx tags                    List all tags\n\n\
+        pngx tags                    List all tags\n  \
+        pngx tags --tree             Show the tag hierarchy\n\n\
+        CACHING:\n  \
+        Tag, correspondent, and document type names are cached for cache_ttl\n  \
+        seconds (default 300).\n  \
+        Use --refresh to refetch them, --no-cache to bypass the cache, or\n  \
+        `pngx cache clear` to remove it.\n\n\
         OUTPUT:\n  \
         Default output is markdown tables. Use -o json for structured output.\n  \
         Use -F to select specific fields (e.g., -F id,title).\n\n\
@@ -39,10 +53,16 @@ use output::OutputFormat;
         2  Usage error or unauthorized\n  \
         3  Not found\n  \
         4  I/O, network, timeout, or URL error\n  \
-        5  Configuration error",
+        5  Configuration error\n  \
+        6  Input rejected by the server (validation error)",
     version
 )]
+#[allow(clippy::struct_excessive_bools)]
 struct Cli {
+    /// Configuration profile to use
+    #[arg(long, global = true, env = "PNGX_PROFILE")]
+    profile: Option<String>,
+
     /// Paperless NGX server URL
     #[arg(long, global = true)]
     url: Option<String>,
@@ -51,14 +71,30 @@ struct Cli {
     #[arg(long, global = true)]
     token: Option<String>,
 
+    /// Read the API token from stdin
+    #[arg(long, global = true, conflicts_with = "token")]
+    token_stdin: bool,
+
     /// Increase verbosity (-v, -vv, -vvv)
     #[arg(short, long, global = true, action = ArgAction::Count)]
     verbose: u8,
 
+    /// Log HTTP requests and truncated response bodies to stderr
+    #[arg(long, global = true, env = "PNGX_DEBUG_HTTP")]
+    debug_http: bool,
+
     /// Emit errors as JSON to stderr
     #[arg(long, global = true, env = "PNGX_JSON_ERRORS")]
     json_errors: bool,
 
+    /// Don't read or write the name cache
+    #[arg(long, global = true, env = "PNGX_NO_CACHE", conflicts_with = "refresh")]
+    no_cache: bool,
+
+    /// Refetch names from the server and update the cache
+    #[arg(long, global = true)]
+    refresh: bool,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -72,6 +108,22 @@ struct OutputArgs {
     /// Comma-separated list of fields to include (e.g., id,title,correspondent)
     #[arg(short = 'F', long)]
     fields: Option<String>,
+
+    /// Separator for multi-valued fields such as tags in CSV and TSV output
+    /// [default: ", "]
+    #[arg(long, value_name = "SEP")]
+    list_separator: Option<String>,
+}
+
+#[derive(Args)]
+struct DocumentOutputArgs {
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Show documents as the server returns them: IDs instead of names, and
+    /// with fields unknown to pngx in JSON output
+    #[arg(long)]
+    raw: bool,
 }
 
 #[derive(Subcommand)]
@@ -88,44 +140,63 @@ enum Command {
         action: DocumentCommand,
     },
     /// List inbox documents
-    Inbox {
-        /// Maximum number of results (0 for unlimited)
-        #[arg(short = 'n', long, default_value = "25")]
-        limit: usize,
-        /// Fetch all results
-        #[arg(short, long)]
-        all: bool,
-        #[command(flatten)]
-        output: OutputArgs,
-    },
+    #[command(args_conflicts_with_subcommands = true)]
+    Inbox(InboxArgs),
     /// Search documents
-    Search {
-        /// Search query
-        query: String,
-        /// Maximum number of results (0 for unlimited)
-        #[arg(short = 'n', long, default_value = "25")]
-        limit: usize,
-        /// Fetch all results
-        #[arg(short, long)]
-        all: bool,
-        #[command(flatten)]
-        output: OutputArgs,
+    Search(SearchArgs),
+    /// Manage the local search index
+    Index {
+        #[command(subcommand)]
+        action: IndexCommand,
     },
-    /// List tags
+    /// Mirror documents and their metadata into a local directory
+    Sync(SyncArgs),
+    /// Export documents and metadata in the format of the Paperless-ngx
+    /// document exporter
+    Export(ExportArgs),
+    /// Import an export directory or zip archive, matching existing metadata
+    /// by name
+    Import(ImportArgs),
+    /// Upload files dropped into a directory, then move them to done/ or
+    /// failed/
+    Watch(WatchArgs),
+    /// List and manage tags
+    #[command(args_conflicts_with_subcommands = true)]
     Tags {
+        #[command(subcommand)]
+        action: Option<TagCommand>,
+        /// Show tags as a parent/child hierarchy
+        #[arg(long)]
+        tree: bool,
         #[command(flatten)]
         output: OutputArgs,
     },
-    /// List correspondents
+    /// List and manage correspondents
+    #[command(args_conflicts_with_subcommands = true)]
     Correspondents {
+        #[command(subcommand)]
+        action: Option<MetadataCommand>,
         #[command(flatten)]
         output: OutputArgs,
     },
-    /// List document types
+    /// List and manage document types
+    #[command(args_conflicts_with_subcommands = true)]
     DocumentTypes {
+        #[command(subcommand)]
+        action: Option<MetadataCommand>,
         #[command(flatten)]
         output: OutputArgs,
     },
+    /// List and switch server profiles
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommand,
+    },
+    /// Manage the local name cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
     /// MCP (Model Context Protocol) server
     Mcp {
         #[command(subcommand)]
@@ -135,6 +206,287 @@ enum Command {
     Version,
 }
 
+#[derive(Args)]
+struct InboxArgs {
+    #[command(subcommand)]
+    action: Option<InboxCommand>,
+    /// Maximum number of results (0 for unlimited)
+    #[arg(short = 'n', long, default_value = "25")]
+    limit: usize,
+    /// Fetch all results
+    #[arg(short, long)]
+    all: bool,
+    /// Triage the inbox in a terminal UI
+    #[arg(short, long, conflicts_with_all = ["limit", "all"])]
+    interactive: bool,
+    #[command(flatten)]
+    output: DocumentOutputArgs,
+}
+
+#[derive(Subcommand)]
+enum InboxCommand {
+    /// Apply the rules from rules.toml to inbox documents
+    Process {
+        /// Rules file [default: rules.toml in the config directory]
+        #[arg(long, value_name = "FILE")]
+        rules: Option<PathBuf>,
+        /// Print the changes without making them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    /// Search query
+    query: String,
+    /// Maximum number of results (0 for unlimited)
+    #[arg(short = 'n', long, default_value = "25")]
+    limit: usize,
+    /// Fetch all results
+    #[arg(short, long)]
+    all: bool,
+    /// Search the local index instead of the server
+    #[arg(long)]
+    local: bool,
+    #[command(flatten)]
+    output: DocumentOutputArgs,
+}
+
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)]
+struct SyncArgs {
+    /// Directory to sync into
+    dir: PathBuf,
+    /// Download original files
+    #[arg(long)]
+    original: bool,
+    /// Download archived files (the default unless --original is given)
+    #[arg(long)]
+    archived: bool,
+    /// File name template; slashes create directories
+    #[arg(
+        long,
+        default_value = commands::sync::DEFAULT_TEMPLATE,
+        value_parser = commands::sync::Template::parse,
+    )]
+    template: commands::sync::Template,
+    /// Check all documents instead of only those modified since the last sync
+    #[arg(long)]
+    full: bool,
+    /// Keep local copies of documents deleted on the server
+    #[arg(long)]
+    no_prune: bool,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Directory to export into
+    dir: PathBuf,
+    /// Write a zip archive into the directory instead of loose files
+    #[arg(long, conflicts_with = "compare_checksums")]
+    zip: bool,
+    /// Compare checksums instead of size and modification time to skip
+    /// files exported by an earlier run
+    #[arg(long)]
+    compare_checksums: bool,
+    /// Only export documents matching this full-text query
+    #[arg(short, long)]
+    query: Option<String>,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// Export directory or zip archive to import
+    source: PathBuf,
+    /// Where to write the report mapping exported IDs to server IDs
+    #[arg(long, default_value = "import-report.json")]
+    report: PathBuf,
+    /// Seconds to wait for the server to consume each document
+    #[arg(long, value_name = "SECS", default_value_t = 300)]
+    task_timeout: u64,
+}
+
+#[derive(Args)]
+struct WatchArgs {
+    /// Directory to watch
+    dir: PathBuf,
+    /// TOML file with tags, correspondent, document type, and storage path
+    /// per folder
+    #[arg(long, value_name = "FILE")]
+    rules: Option<PathBuf>,
+    /// Tag files with the names of the folders they are in
+    #[arg(long)]
+    subdirs_as_tags: bool,
+    /// Seconds a file must stay unchanged before it is uploaded
+    #[arg(long, value_name = "SECS", default_value_t = 2)]
+    settle: u64,
+    /// Seconds to wait for the server to consume each file
+    #[arg(long, value_name = "SECS", default_value_t = 600)]
+    task_timeout: u64,
+    /// Upload the files already in the directory, then exit
+    #[arg(long)]
+    once: bool,
+}
+
+#[derive(Args)]
+struct MatchingArgs {
+    /// Pattern used by the matching algorithm
+    #[arg(long = "match", value_name = "PATTERN")]
+    pattern: Option<String>,
+    /// Algorithm used to assign new documents automatically
+    #[arg(long, value_enum)]
+    matching_algorithm: Option<MatchingAlgorithmArg>,
+    /// Ignore case when matching
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    insensitive: Option<bool>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum MatchingAlgorithmArg {
+    None,
+    Any,
+    All,
+    Literal,
+    Regex,
+    Fuzzy,
+    Auto,
+}
+
+impl From<MatchingAlgorithmArg> for MatchingAlgorithm {
+    fn from(arg: MatchingAlgorithmArg) -> Self {
+        match arg {
+            MatchingAlgorithmArg::None => Self::None,
+            MatchingAlgorithmArg::Any => Self::Any,
+            MatchingAlgorithmArg::All => Self::All,
+            MatchingAlgorithmArg::Literal => Self::Literal,
+            MatchingAlgorithmArg::Regex => Self::Regex,
+            MatchingAlgorithmArg::Fuzzy => Self::Fuzzy,
+            MatchingAlgorithmArg::Auto => Self::Auto,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum TagCommand {
+    /// Create a tag
+    Create {
+        /// Tag name
+        name: String,
+        /// Hex color code (e.g., #ff0000)
+        #[arg(long)]
+        color: Option<String>,
+        /// Mark documents with this tag as inbox items
+        #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+        inbox: Option<bool>,
+        /// ID of the parent tag
+        #[arg(long)]
+        parent: Option<u64>,
+        #[command(flatten)]
+        matching: MatchingArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Change an existing tag
+    Edit {
+        /// Tag ID
+        id: u64,
+        /// New tag name
+        #[arg(long)]
+        name: Option<String>,
+        /// Hex color code (e.g., #ff0000)
+        #[arg(long)]
+        color: Option<String>,
+        /// Mark documents with this tag as inbox items
+        #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+        inbox: Option<bool>,
+        /// ID of the parent tag
+        #[arg(long, conflicts_with = "no_parent")]
+        parent: Option<u64>,
+        /// Move the tag to the top level
+        #[arg(long)]
+        no_parent: bool,
+        #[command(flatten)]
+        matching: MatchingArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Delete tags
+    Delete {
+        /// Tag IDs
+        #[arg(required = true)]
+        ids: Vec<u64>,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Subcommands shared by correspondents and document types.
+#[derive(Subcommand)]
+enum MetadataCommand {
+    /// Create a new entry
+    Create {
+        /// Name
+        name: String,
+        #[command(flatten)]
+        matching: MatchingArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Change the name or matching rule of an entry
+    Edit {
+        /// ID
+        id: u64,
+        /// New name
+        #[arg(long)]
+        name: Option<String>,
+        #[command(flatten)]
+        matching: MatchingArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Delete entries
+    Delete {
+        /// IDs
+        #[arg(required = true)]
+        ids: Vec<u64>,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Reassign all documents of the sources to a target, then delete the
+    /// sources
+    Merge {
+        /// IDs to merge away
+        #[arg(required = true)]
+        sources: Vec<u64>,
+        /// ID to merge into
+        #[arg(long, value_name = "ID")]
+        into: u64,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Remove all cached names
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum IndexCommand {
+    /// Index all documents, replacing the existing index
+    Build,
+    /// Index documents changed since the last build or update, including
+    /// those whose tags, correspondent, or document type were renamed
+    Update,
+    /// Show the size and age of the index
+    Stats,
+}
+
 #[derive(Subcommand)]
 enum McpCommand {
     /// Start the MCP server over stdio
@@ -149,13 +501,32 @@ enum AuthCommand {
         #[arg(long)]
         url: Option<String>,
         /// API token (skip interactive prompt)
-        #[arg(long)]
+        #[arg(long, conflicts_with = "username")]
         token: Option<String>,
+        /// Log in with this user's password instead of an API token
+        #[arg(long)]
+        username: Option<String>,
     },
-    /// Remove saved credentials
+    /// Remove the saved credentials of --profile, the default profile, or
+    /// the top level
     Logout,
     /// Show current configuration
-    Status,
+    Status {
+        /// Verify every configured profile
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// List configured profiles
+    List,
+    /// Set the default profile
+    Use {
+        /// Profile name
+        name: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -169,7 +540,7 @@ enum DocumentCommand {
         #[arg(short, long)]
         all: bool,
         #[command(flatten)]
-        output: OutputArgs,
+        output: DocumentOutputArgs,
     },
     /// Get documents by ID
     Get {
@@ -177,7 +548,7 @@ enum DocumentCommand {
         #[arg(required = true)]
         ids: Vec<u64>,
         #[command(flatten)]
-        output: OutputArgs,
+        output: DocumentOutputArgs,
     },
     /// Open documents in the Paperless-ngx web UI
     Open {
@@ -205,18 +576,29 @@ enum DocumentCommand {
     },
 }
 
-fn init_tracing(verbosity: u8) {
-    let filter = match verbosity {
+fn init_tracing(verbosity: u8, debug_http: bool) {
+    let level = match verbosity {
         0 => "warn",
         1 => "info",
         2 => "debug",
         _ => "trace",
     };
+    // Response bodies are only logged when asked for, even with -vvv.
+    let http = if debug_http {
+        "pngx_client::http=trace"
+    } else {
+        "pngx_client::http::body=off"
+    };
 
+    // `--debug-http` applies on top of `RUST_LOG`.
+    let directives = match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(env) if !env.is_empty() && debug_http => format!("{env},{http}"),
+        Ok(env) if !env.is_empty() => env,
+        _ => format!("{level},{http}"),
+    };
+    let filter = EnvFilter::new(directives);
     tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter)),
-        )
+        .with_env_filter(filter)
         .with_writer(std::io::stderr)
         .init();
 }
@@ -225,21 +607,54 @@ fn resolve_limit(limit: usize, all: bool) -> Option<usize> {
     if all || limit == 0 { None } else { Some(limit) }
 }
 
-fn build_client(
-    url: Option<&str>,
-    token: Option<&str>,
-) -> anyhow::Result<(pngx_client::Client, config::ValidConfig)> {
-    let raw = RawConfig::load(url, token)?;
-    let config = raw.validate()?;
-    let client = pngx_client::Client::builder(config.url.as_str(), &config.token)
+fn load_config(overrides: &Overrides) -> anyhow::Result<config::ValidConfig> {
+    let raw = RawConfig::load(overrides)?;
+    Ok(raw.validate()?)
+}
+
+fn client_builder(config: &config::ValidConfig) -> pngx_client::ClientBuilder {
+    let mut builder = pngx_client::Client::builder(config.url.as_str(), &config.token)
         .timeout(Duration::from_secs(config.timeout))
         .page_size(config.page_size)
-        .build()?;
+        .retry_policy(pngx_client::RetryPolicy::new(config.retries))
+        .rewrite_next_urls(config.rewrite_next_urls);
+    if let Some(version) = config.api_version {
+        builder = builder.api_version(version);
+    }
+    for pem in &config.ca_certs {
+        builder = builder.add_root_certificate(pem.clone());
+    }
+    if let Some((cert, key)) = &config.client_cert {
+        builder = builder.client_certificate(cert.clone(), key.clone());
+    }
+    for (name, value) in &config.headers {
+        builder = builder.header(name, value);
+    }
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(proxy);
+    }
+    builder
+}
+
+fn build_client(
+    overrides: &Overrides,
+) -> anyhow::Result<(pngx_client::Client, config::ValidConfig)> {
+    let config = load_config(overrides)?;
+    let client = client_builder(&config).build()?;
     Ok((client, config))
 }
 
-fn resolve_output(output: &OutputArgs, config: &config::ValidConfig) -> OutputFormat {
-    output.output.unwrap_or(config.output_format)
+fn resolve_output<'a>(
+    output: &'a OutputArgs,
+    config: &'a config::ValidConfig,
+) -> OutputOptions<'a> {
+    OutputOptions {
+        format: output.output.unwrap_or(config.output_format),
+        list_separator: output
+            .list_separator
+            .as_deref()
+            .unwrap_or(&config.list_separator),
+    }
 }
 
 /// Parse and validate the `--fields` flag for a specific entity type.
@@ -255,39 +670,422 @@ fn resolve_fields<T: output::FieldNames>(
     }
 }
 
+fn run_inbox(overrides: &Overrides, cache_mode: CacheMode, args: &InboxArgs) -> anyhow::Result<()> {
+    let (client, config) = build_client(overrides)?;
+    if let Some(InboxCommand::Process { rules, dry_run }) = &args.action {
+        let options = commands::rules::ProcessOptions {
+            rules: rules
+                .clone()
+                .unwrap_or_else(|| config::config_dir().join(commands::rules::RULES_FILE)),
+            dry_run: *dry_run,
+        };
+        return commands::rules::process(&client, &options);
+    }
+    if args.interactive {
+        return commands::triage::run(&client, &config.url);
+    }
+    let cache = NameCache::new(&config, cache_mode);
+    let output = &args.output;
+    let options = resolve_output(&output.output, &config);
+    let fields = resolve_fields::<output::ResolvedDocument>(&output.output)?;
+    commands::inbox::list(
+        &client,
+        &cache,
+        options,
+        resolve_limit(args.limit, args.all),
+        fields.as_ref(),
+        output.raw,
+    )
+}
+
+fn run_search(
+    overrides: &Overrides,
+    cache_mode: CacheMode,
+    args: &SearchArgs,
+) -> anyhow::Result<()> {
+    let limit = resolve_limit(args.limit, args.all);
+    let output = &args.output;
+    if args.local {
+        let config = load_config(overrides)?;
+        let options = resolve_output(&output.output, &config);
+        let fields = resolve_fields::<output::SearchHit>(&output.output)?;
+        return commands::search::search_local(
+            &config,
+            &args.query,
+            options,
+            limit,
+            fields.as_ref(),
+            output.raw,
+        );
+    }
+    let (client, config) = build_client(overrides)?;
+    let cache = NameCache::new(&config, cache_mode);
+    let options = resolve_output(&output.output, &config);
+    let fields = resolve_fields::<output::ResolvedDocument>(&output.output)?;
+    commands::search::search(
+        &client,
+        &cache,
+        &args.query,
+        options,
+        limit,
+        fields.as_ref(),
+        output.raw,
+    )
+}
+
+fn run_index(
+    client: &pngx_client::Client,
+    config: &config::ValidConfig,
+    cache: &NameCache,
+    action: &IndexCommand,
+) -> anyhow::Result<()> {
+    match action {
+        IndexCommand::Build => commands::index::build(client, cache, config),
+        IndexCommand::Update => commands::index::update(client, cache, config),
+        IndexCommand::Stats => commands::index::stats(config),
+    }
+}
+
+fn run_sync(
+    client: &pngx_client::Client,
+    config: &config::ValidConfig,
+    cache: &NameCache,
+    args: SyncArgs,
+) -> anyhow::Result<()> {
+    let options = commands::sync::SyncOptions {
+        files: commands::sync::Files {
+            original: args.original,
+            archived: args.archived || !args.original,
+        },
+        template: args.template,
+        full: args.full,
+        prune: !args.no_prune,
+    };
+    commands::sync::sync(client, cache, config.url.as_str(), &args.dir, &options)
+}
+
+fn run_export(overrides: &Overrides, args: ExportArgs) -> anyhow::Result<()> {
+    let (client, _) = build_client(overrides)?;
+    let options = commands::export::ExportOptions {
+        zip: args.zip,
+        compare_checksums: args.compare_checksums,
+        query: args.query,
+    };
+    commands::export::export(&client, &args.dir, &options)
+}
+
+fn run_import(
+    overrides: &Overrides,
+    cache_mode: CacheMode,
+    args: ImportArgs,
+) -> anyhow::Result<()> {
+    let (client, config) = build_client(overrides)?;
+    let options = commands::import::ImportOptions {
+        report: args.report,
+        timeout: Duration::from_secs(args.task_timeout),
+    };
+    let result = commands::import::import(&client, &args.source, &options);
+    // Taxonomy may have been created even if some documents failed.
+    NameCache::new(&config, cache_mode).invalidate();
+    result
+}
+
+fn run_watch(overrides: &Overrides, cache_mode: CacheMode, args: WatchArgs) -> anyhow::Result<()> {
+    let (client, config) = build_client(overrides)?;
+    let options = commands::watch::WatchOptions {
+        rules: args.rules,
+        subdirs_as_tags: args.subdirs_as_tags,
+        settle: Duration::from_secs(args.settle),
+        task_timeout: Duration::from_secs(args.task_timeout),
+        once: args.once,
+    };
+    let result = commands::watch::watch(&client, &args.dir, &options);
+    // Tags may have been created for folders.
+    NameCache::new(&config, cache_mode).invalidate();
+    result
+}
+
+fn run_auth(action: AuthCommand, overrides: &Overrides) -> anyhow::Result<()> {
+    match action {
+        AuthCommand::Login {
+            url,
+            token,
+            username,
+        } => commands::auth::login(
+            overrides.profile,
+            url.as_deref(),
+            token.as_deref(),
+            username.as_deref(),
+        ),
+        AuthCommand::Logout => commands::auth::logout(overrides.profile),
+        AuthCommand::Status { all } => commands::auth::status(overrides, all),
+    }
+}
+
+fn run_tags(
+    client: &pngx_client::Client,
+    config: &config::ValidConfig,
+    cache: &NameCache,
+    action: Option<TagCommand>,
+    tree: bool,
+    output: &OutputArgs,
+) -> anyhow::Result<()> {
+    if action.is_some() {
+        cache.invalidate();
+    }
+    match action {
+        None => {
+            let options = resolve_output(output, config);
+            let fields = resolve_fields::<pngx_client::Tag>(output)?;
+            if tree {
+                commands::tags::tree(client, options, fields.as_ref())?;
+            } else {
+                commands::tags::list(client, options, fields.as_ref())?;
+            }
+        }
+        Some(TagCommand::Create {
+            name,
+            color,
+            inbox,
+            parent,
+            matching,
+            output,
+        }) => {
+            let options = resolve_output(&output, config);
+            let fields = resolve_fields::<pngx_client::Tag>(&output)?;
+            let request = TagRequest {
+                name: Some(name),
+                color,
+                is_inbox_tag: inbox,
+                r#match: matching.pattern,
+                matching_algorithm: matching.matching_algorithm.map(Into::into),
+                is_insensitive: matching.insensitive,
+                parent: parent.map(Some),
+            };
+            commands::tags::create(client, &request, options, fields.as_ref())?;
+        }
+        Some(TagCommand::Edit {
+            id,
+            name,
+            color,
+            inbox,
+            parent,
+            no_parent,
+            matching,
+            output,
+        }) => {
+            let options = resolve_output(&output, config);
+            let fields = resolve_fields::<pngx_client::Tag>(&output)?;
+            let request = TagRequest {
+                name,
+                color,
+                is_inbox_tag: inbox,
+                r#match: matching.pattern,
+                matching_algorithm: matching.matching_algorithm.map(Into::into),
+                is_insensitive: matching.insensitive,
+                parent: if no_parent {
+                    Some(None)
+                } else {
+                    parent.map(Some)
+                },
+            };
+            commands::tags::edit(client, id, &request, options, fields.as_ref())?;
+        }
+        Some(TagCommand::Delete { ids, yes }) => {
+            commands::tags::delete(client, &ids, yes)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_correspondents(
+    client: &pngx_client::Client,
+    config: &config::ValidConfig,
+    cache: &NameCache,
+    action: Option<MetadataCommand>,
+    output: &OutputArgs,
+) -> anyhow::Result<()> {
+    if action.is_some() {
+        cache.invalidate();
+    }
+    match action {
+        None => {
+            let options = resolve_output(output, config);
+            let fields = resolve_fields::<pngx_client::Correspondent>(output)?;
+            commands::correspondents::list(client, options, fields.as_ref())?;
+        }
+        Some(MetadataCommand::Create {
+            name,
+            matching,
+            output,
+        }) => {
+            let options = resolve_output(&output, config);
+            let fields = resolve_fields::<pngx_client::Correspondent>(&output)?;
+            let request = CorrespondentRequest {
+                name: Some(name),
+                r#match: matching.pattern,
+                matching_algorithm: matching.matching_algorithm.map(Into::into),
+                is_insensitive: matching.insensitive,
+            };
+            commands::correspondents::create(client, &request, options, fields.as_ref())?;
+        }
+        Some(MetadataCommand::Edit {
+            id,
+            name,
+            matching,
+            output,
+        }) => {
+            let options = resolve_output(&output, config);
+            let fields = resolve_fields::<pngx_client::Correspondent>(&output)?;
+            let request = CorrespondentRequest {
+                name,
+                r#match: matching.pattern,
+                matching_algorithm: matching.matching_algorithm.map(Into::into),
+                is_insensitive: matching.insensitive,
+            };
+            commands::correspondents::edit(client, id, &request, options, fields.as_ref())?;
+        }
+        Some(MetadataCommand::Delete { ids, yes }) => {
+            commands::correspondents::delete(client, &ids, yes)?;
+        }
+        Some(MetadataCommand::Merge { sources, into, yes }) => {
+            commands::correspondents::merge(client, &sources, into, yes)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_document_types(
+    client: &pngx_client::Client,
+    config: &config::ValidConfig,
+    cache: &NameCache,
+    action: Option<MetadataCommand>,
+    output: &OutputArgs,
+) -> anyhow::Result<()> {
+    if action.is_some() {
+        cache.invalidate();
+    }
+    match action {
+        None => {
+            let options = resolve_output(output, config);
+            let fields = resolve_fields::<pngx_client::DocumentType>(output)?;
+            commands::document_types::list(client, options, fields.as_ref())?;
+        }
+        Some(MetadataCommand::Create {
+            name,
+            matching,
+            output,
+        }) => {
+            let options = resolve_output(&output, config);
+            let fields = resolve_fields::<pngx_client::DocumentType>(&output)?;
+            let request = DocumentTypeRequest {
+                name: Some(name),
+                r#match: matching.pattern,
+                matching_algorithm: matching.matching_algorithm.map(Into::into),
+                is_insensitive: matching.insensitive,
+            };
+            commands::document_types::create(client, &request, options, fields.as_ref())?;
+        }
+        Some(MetadataCommand::Edit {
+            id,
+            name,
+            matching,
+            output,
+        }) => {
+            let options = resolve_output(&output, config);
+            let fields = resolve_fields::<pngx_client::DocumentType>(&output)?;
+            let request = DocumentTypeRequest {
+                name,
+                r#match: matching.pattern,
+                matching_algorithm: matching.matching_algorithm.map(Into::into),
+                is_insensitive: matching.insensitive,
+            };
+            commands::document_types::edit(client, id, &request, options, fields.as_ref())?;
+        }
+        Some(MetadataCommand::Delete { ids, yes }) => {
+            commands::document_types::delete(client, &ids, yes)?;
+        }
+        Some(MetadataCommand::Merge { sources, into, yes }) => {
+            commands::document_types::merge(client, &sources, into, yes)?;
+        }
+    }
+    Ok(())
+}
+
+fn cache_mode(cli: &Cli) -> CacheMode {
+    if cli.no_cache {
+        CacheMode::Off
+    } else if cli.refresh {
+        CacheMode::Refresh
+    } else {
+        CacheMode::Use
+    }
+}
+
+fn run_mcp(
+    overrides: &Overrides,
+    cache_mode: CacheMode,
+    action: &McpCommand,
+) -> anyhow::Result<()> {
+    match action {
+        McpCommand::Serve => {
+            let config = load_config(overrides)?;
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            let client = client_builder(&config).build_async()?;
+            let cache = NameCache::new(&config, cache_mode);
+            runtime.block_on(commands::mcp::serve(client, cache))
+        }
+    }
+}
+
 fn run(cli: Cli) -> anyhow::Result<()> {
-    init_tracing(cli.verbose);
+    init_tracing(cli.verbose, cli.debug_http);
+    let cache_mode = cache_mode(&cli);
+    let overrides = Overrides {
+        profile: cli.profile.as_deref(),
+        url: cli.url.as_deref(),
+        token: cli.token.as_deref(),
+        token_stdin: cli.token_stdin,
+    };
 
     match cli.command {
-        Command::Auth { action } => match action {
-            AuthCommand::Login { url, token } => {
-                commands::auth::login(url.as_deref(), token.as_deref())?;
-            }
-            AuthCommand::Logout => commands::auth::logout()?,
-            AuthCommand::Status => {
-                commands::auth::status(cli.url.as_deref(), cli.token.as_deref())?;
-            }
+        Command::Auth { action } => run_auth(action, &overrides)?,
+        Command::Profile { action } => match action {
+            ProfileCommand::List => commands::profile::list()?,
+            ProfileCommand::Use { name } => commands::profile::use_profile(&name)?,
         },
         Command::Version => {
-            commands::version::print(cli.url.as_deref(), cli.token.as_deref())?;
+            commands::version::print(&overrides)?;
         }
         Command::Documents { action } => {
-            let (client, config) = build_client(cli.url.as_deref(), cli.token.as_deref())?;
+            let (client, config) = build_client(&overrides)?;
+            let cache = NameCache::new(&config, cache_mode);
             match action {
                 DocumentCommand::List { limit, all, output } => {
-                    let format = resolve_output(&output, &config);
-                    let fields = resolve_fields::<output::ResolvedDocument>(&output)?;
+                    let options = resolve_output(&output.output, &config);
+                    let fields = resolve_fields::<output::ResolvedDocument>(&output.output)?;
                     commands::documents::list(
                         &client,
-                        format,
+                        &cache,
+                        options,
                         resolve_limit(limit, all),
                         fields.as_ref(),
+                        output.raw,
                     )?;
                 }
                 DocumentCommand::Get { ids, output } => {
-                    let format = resolve_output(&output, &config);
-                    let fields = resolve_fields::<output::ResolvedDocument>(&output)?;
-                    commands::documents::get(&client, &ids, format, fields.as_ref())?;
+                    let options = resolve_output(&output.output, &config);
+                    let fields = resolve_fields::<output::ResolvedDocument>(&output.output)?;
+                    commands::documents::get(
+                        &client,
+                        &cache,
+                        &ids,
+                        options,
+                        fields.as_ref(),
+                        output.raw,
+                    )?;
                 }
                 DocumentCommand::Open { ids } => {
                     commands::documents::open(&config.url, &ids)?;
@@ -304,56 +1102,44 @@ fn run(cli: Cli) -> anyhow::Result<()> {
                 }
             }
         }
-        Command::Inbox { limit, all, output } => {
-            let (client, config) = build_client(cli.url.as_deref(), cli.token.as_deref())?;
-            let format = resolve_output(&output, &config);
-            let fields = resolve_fields::<output::ResolvedDocument>(&output)?;
-            commands::inbox::list(&client, format, resolve_limit(limit, all), fields.as_ref())?;
+        Command::Inbox(args) => run_inbox(&overrides, cache_mode, &args)?,
+        Command::Search(args) => run_search(&overrides, cache_mode, &args)?,
+        Command::Index { action } => {
+            let (client, config) = build_client(&overrides)?;
+            let cache = NameCache::new(&config, cache_mode);
+            run_index(&client, &config, &cache, &action)?;
         }
-        Command::Search {
-            query,
-            limit,
-            all,
+        Command::Sync(args) => {
+            let (client, config) = build_client(&overrides)?;
+            let cache = NameCache::new(&config, cache_mode);
+            run_sync(&client, &config, &cache, args)?;
+        }
+        Command::Export(args) => run_export(&overrides, args)?,
+        Command::Import(args) => run_import(&overrides, cache_mode, args)?,
+        Command::Watch(args) => run_watch(&overrides, cache_mode, args)?,
+        Command::Tags {
+            action,
+            tree,
             output,
         } => {
-            let (client, config) = build_client(cli.url.as_deref(), cli.token.as_deref())?;
-            let format = resolve_output(&output, &config);
-            let fields = resolve_fields::<output::ResolvedDocument>(&output)?;
-            commands::search::search(
-                &client,
-                &query,
-                format,
-                resolve_limit(limit, all),
-                fields.as_ref(),
-            )?;
+            let (client, config) = build_client(&overrides)?;
+            let cache = NameCache::new(&config, cache_mode);
+            run_tags(&client, &config, &cache, action, tree, &output)?;
         }
-        Command::Tags { output } => {
-            let (client, config) = build_client(cli.url.as_deref(), cli.token.as_deref())?;
-            let format = resolve_output(&output, &config);
-            let fields = resolve_fields::<pngx_client::Tag>(&output)?;
-            commands::tags::list(&client, format, fields.as_ref())?;
+        Command::Correspondents { action, output } => {
+            let (client, config) = build_client(&overrides)?;
+            let cache = NameCache::new(&config, cache_mode);
+            run_correspondents(&client, &config, &cache, action, &output)?;
         }
-        Command::Correspondents { output } => {
-            let (client, config) = build_client(cli.url.as_deref(), cli.token.as_deref())?;
-            let format = resolve_output(&output, &config);
-            let fields = resolve_fields::<pngx_client::Correspondent>(&output)?;
-            commands::correspondents::list(&client, format, fields.as_ref())?;
+        Command::DocumentTypes { action, output } => {
+            let (client, config) = build_client(&overrides)?;
+            let cache = NameCache::new(&config, cache_mode);
+            run_document_types(&client, &config, &cache, action, &output)?;
         }
-        Command::DocumentTypes { output } => {
-            let (client, config) = build_client(cli.url.as_deref(), cli.token.as_deref())?;
-            let format = resolve_output(&output, &config);
-            let fields = resolve_fields::<pngx_client::DocumentType>(&output)?;
-            commands::document_types::list(&client, format, fields.as_ref())?;
-        }
-        Command::Mcp { action } => match action {
-            McpCommand::Serve => {
-                let (client, _config) = build_client(cli.url.as_deref(), cli.token.as_deref())?;
-                tokio::runtime::Builder::new_current_thread()
-                    .enable_all()
-                    .build()?
-                    .block_on(commands::mcp::serve(client))?;
-            }
+        Command::Cache { action } => match action {
+            CacheCommand::Clear => commands::cache::clear()?,
         },
+        Command::Mcp { action } => run_mcp(&overrides, cache_mode, &action)?,
     }
 
     Ok(())
@@ -363,8 +1149,8 @@ fn run(cli: Cli) -> anyhow::Result<()> {
 fn error_code(err: &anyhow::Error) -> &'static str {
     if let Some(api_err) = err.downcast_ref::<ApiError>() {
         match api_err {
-            ApiError::Unauthorized => "unauthorized",
-            ApiError::NotFound => "not_found",
+            ApiError::Unauthorized { .. } => "unauthorized",
+            ApiError::NotFound { .. } => "not_found",
             ApiError::InvalidUrl(_) => "invalid_url",
             ApiError::Io(_) => "io_error",
             ApiError::Network(_) => "network_error",
@@ -372,6 +1158,9 @@ fn error_code(err: &anyhow::Error) -> &'static str {
             ApiError::SchemeMismatch { .. } => "scheme_mismatch",
             ApiError::Server { .. } => "server_error",
             ApiError::Deserialization(_) => "deserialization_error",
+            ApiError::Validation { .. } => "validation_error",
+            ApiError::UnsupportedApiVersion { .. } => "unsupported_api_version",
+            ApiError::InvalidConfig(_) => "config_error",
         }
     } else if err.downcast_ref::<ConfigError>().is_some() {
         "config_error"
@@ -385,13 +1174,15 @@ fn error_code(err: &anyhow::Error) -> &'static str {
 fn exit_code_for_error(err: &anyhow::Error) -> ExitCode {
     if let Some(api_err) = err.downcast_ref::<ApiError>() {
         match api_err {
-            ApiError::Unauthorized => ExitCode::from(2),
-            ApiError::NotFound => ExitCode::from(3),
+            ApiError::Unauthorized { .. } => ExitCode::from(2),
+            ApiError::NotFound { .. } => ExitCode::from(3),
             ApiError::InvalidUrl(_)
             | ApiError::Io(_)
             | ApiError::Network(_)
             | ApiError::Timeout
             | ApiError::SchemeMismatch { .. } => ExitCode::from(4),
+            ApiError::InvalidConfig(_) => ExitCode::from(5),
+            ApiError::Validation { .. } => ExitCode::from(6),
             _ => ExitCode::from(1),
         }
     } else if err.downcast_ref::<ConfigError>().is_some() {
@@ -412,10 +1203,18 @@ fn main() -> ExitCode {
         Err(err) => {
             let code = exit_code_for_error(&err);
             if json_errors {
-                let json_err = serde_json::json!({
+                let mut json_err = serde_json::json!({
                     "error": format!("{err:#}"),
                     "code": error_code(&err),
                 });
+                if let Some(ApiError::Validation {
+                    field_errors,
+                    non_field_errors,
+                }) = err.downcast_ref::<ApiError>()
+                {
+                    json_err["field_errors"] = serde_json::json!(field_errors);
+                    json_err["non_field_errors"] = serde_json::json!(non_field_errors);
+                }
                 eprintln!("{json_err}");
             } else {
                 eprintln!("Error: {err:#}");
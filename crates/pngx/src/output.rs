@@ -4,9 +4,11 @@ use comfy_table::presets::ASCII_MARKDOWN;
 use comfy_table::{ContentArrangement, Table};
 use serde::{Deserialize, Serialize};
 
-use pngx_client::{Correspondent, Document, DocumentType, Tag};
+use pngx_client::{
+    Client, Correspondent, CustomFieldInstance, Document, DocumentType, Note, Pages, Tag,
+};
 
-use crate::resolve::NameResolver;
+use crate::resolve::{NameCache, NameResolver};
 
 #[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
@@ -15,16 +17,29 @@ pub enum OutputFormat {
     Markdown,
     Json,
     Ndjson,
+    Csv,
+    Tsv,
 }
 
-impl OutputFormat {
+/// Default separator for multi-valued fields such as `tags` in CSV and TSV.
+pub const DEFAULT_LIST_SEPARATOR: &str = ", ";
+
+/// How to print the output of a command.
+#[derive(Debug, Clone, Copy)]
+pub struct OutputOptions<'a> {
+    pub format: OutputFormat,
+    /// Separator for multi-valued fields in CSV and TSV output.
+    pub list_separator: &'a str,
+}
+
+impl OutputOptions<'_> {
     pub fn format_list<T: Tabular + Serialize>(
         self,
         items: &[T],
         fields: Option<&FieldFilter>,
     ) -> Result<String, serde_json::Error> {
-        match self {
-            Self::Json => {
+        match self.format {
+            OutputFormat::Json => {
                 let value = serde_json::to_value(items)?;
                 if let Some(filter) = fields {
                     serde_json::to_string_pretty(&filter.filter_json_array(value))
@@ -32,7 +47,7 @@ impl OutputFormat {
                     serde_json::to_string_pretty(&value)
                 }
             }
-            Self::Ndjson => {
+            OutputFormat::Ndjson => {
                 let mut lines = Vec::new();
                 for item in items {
                     let value = serde_json::to_value(item)?;
@@ -44,36 +59,41 @@ impl OutputFormat {
                 }
                 Ok(lines.join("\n"))
             }
-            Self::Markdown => {
+            OutputFormat::Markdown => {
                 let all_headers = T::headers();
-                let indices = fields.map(|f| f.column_indices(all_headers));
-
-                let visible_headers: Vec<&str> = match &indices {
-                    Some(idx) => idx.iter().map(|&i| all_headers[i]).collect(),
-                    None => all_headers.to_vec(),
+                let indices = match fields {
+                    Some(f) => f.column_indices(all_headers),
+                    None => default_column_indices::<T>(),
                 };
 
+                let visible_headers: Vec<&str> = indices.iter().map(|&i| all_headers[i]).collect();
+
                 let mut table = new_markdown_table(&visible_headers);
                 for item in items {
                     let all_cols = item.row();
-                    let row: Vec<String> = match &indices {
-                        Some(idx) => idx.iter().map(|&i| all_cols[i].clone()).collect(),
-                        None => all_cols,
-                    };
-                    table.add_row(row);
+                    table.add_row(indices.iter().map(|&i| all_cols[i].clone()));
                 }
                 Ok(table.to_string())
             }
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let headers = default_column_indices::<T>()
+                    .into_iter()
+                    .map(|i| T::headers()[i]);
+                let columns = delimited_columns(fields, headers);
+                self.format_delimited(items, &columns)
+            }
         }
     }
 
-    pub fn format_detail<T: DetailView + Serialize>(
+    /// Formats one item as a field/value view. CSV and TSV use the list
+    /// columns instead, so every item gets the same header.
+    pub fn format_detail<T: DetailView + Tabular + Serialize>(
         self,
         item: &T,
         fields: Option<&FieldFilter>,
     ) -> Result<String, serde_json::Error> {
-        match self {
-            Self::Json => {
+        match self.format {
+            OutputFormat::Json => {
                 let value = serde_json::to_value(item)?;
                 if let Some(filter) = fields {
                     serde_json::to_string_pretty(&filter.filter_json_object(value))
@@ -81,7 +101,7 @@ impl OutputFormat {
                     serde_json::to_string_pretty(&value)
                 }
             }
-            Self::Ndjson => {
+            OutputFormat::Ndjson => {
                 let value = serde_json::to_value(item)?;
                 let line = match fields {
                     Some(f) => f.filter_json_object(value),
@@ -89,7 +109,7 @@ impl OutputFormat {
                 };
                 serde_json::to_string(&line)
             }
-            Self::Markdown => {
+            OutputFormat::Markdown => {
                 let mut table = new_markdown_table(&["Field", "Value"]);
                 for (field, value) in item.fields() {
                     if let Some(filter) = fields
@@ -101,10 +121,97 @@ impl OutputFormat {
                 }
                 Ok(table.to_string())
             }
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                self.format_list(std::slice::from_ref(item), fields)
+            }
+        }
+    }
+
+    /// Writes a header row and a record per item, taking each cell from the
+    /// item's JSON. Cells are quoted as RFC 4180 describes.
+    fn format_delimited<T: Serialize>(
+        self,
+        items: &[T],
+        columns: &[(String, String)],
+    ) -> Result<String, serde_json::Error> {
+        let delimiter = if let OutputFormat::Tsv = self.format {
+            '\t'
+        } else {
+            ','
+        };
+        let separator = self.list_separator;
+        let mut records = vec![record(
+            columns.iter().map(|(header, _)| header.clone()),
+            delimiter,
+        )];
+        for item in items {
+            let value = serde_json::to_value(item)?;
+            records.push(record(
+                columns.iter().map(|(_, key)| {
+                    value
+                        .get(key)
+                        .map_or_else(String::new, |value| cell(value, separator))
+                }),
+                delimiter,
+            ));
         }
+        Ok(records.join("\n"))
+    }
+}
+
+/// Columns of CSV and TSV output as `(header, JSON key)` pairs: the
+/// selected fields in their order, else the field names of `headers`.
+fn delimited_columns<'a>(
+    fields: Option<&FieldFilter>,
+    headers: impl Iterator<Item = &'a str>,
+) -> Vec<(String, String)> {
+    if let Some(filter) = fields {
+        return filter
+            .fields
+            .iter()
+            .map(|field| (field.clone(), filter.resolve_json_key(field).to_string()))
+            .collect();
+    }
+    let mut columns: Vec<(String, String)> = Vec::new();
+    for header in headers {
+        let name = field_name(header);
+        if !columns.iter().any(|(column, _)| *column == name) {
+            columns.push((name.clone(), name));
+        }
+    }
+    columns
+}
+
+/// The text of a JSON value in a CSV or TSV cell. Lists are joined with
+/// `separator`; objects, such as notes, are written as JSON.
+fn cell(value: &serde_json::Value, separator: &str) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(|value| cell(value, separator))
+            .collect::<Vec<_>>()
+            .join(separator),
+        other => other.to_string(),
     }
 }
 
+/// Joins cells into a record, quoting those that contain the delimiter, a
+/// quote, or a line break, with quotes inside doubled.
+fn record(cells: impl Iterator<Item = String>, delimiter: char) -> String {
+    cells
+        .map(|cell| {
+            if cell.contains([delimiter, '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&delimiter.to_string())
+}
+
 // --- FieldFilter ---
 
 /// Maps between user-facing field names, JSON keys, and display headers.
@@ -217,29 +324,32 @@ impl FieldFilter {
 
     /// Check if a display header name is included in the filter.
     fn includes_display_name(&self, display_name: &str) -> bool {
-        self.fields.iter().any(|f| {
-            f.eq_ignore_ascii_case(display_name)
-                || match display_name {
-                    "ID" => f == "id",
-                    "Title" => f == "title",
-                    "Correspondent" => f == "correspondent",
-                    "Type" | "Document Type" => f == "document_type",
-                    "Tags" => f == "tags",
-                    "Created" => f == "created",
-                    "Added" => f == "added",
-                    "Name" => f == "name",
-                    "Color" => f == "color",
-                    "Documents" => f == "document_count",
-                    "Slug" => f == "slug",
-                    "Inbox Tag" => f == "is_inbox_tag",
-                    "Original File" => f == "original_file_name",
-                    "ASN" => f == "archive_serial_number",
-                    _ => false,
-                }
-        })
+        let name = field_name(display_name);
+        self.fields
+            .iter()
+            .any(|f| f.eq_ignore_ascii_case(display_name) || *f == name)
     }
 }
 
+/// The field name shown as a display header, e.g. `document_type` for
+/// `Type`.
+fn field_name(display_name: &str) -> String {
+    let name = match display_name {
+        "ID" => "id",
+        "Type" | "Document Type" => "document_type",
+        "Documents" => "document_count",
+        "Inbox Tag" => "is_inbox_tag",
+        "Case Insensitive" => "is_insensitive",
+        "Original File" => "original_file_name",
+        "ASN" => "archive_serial_number",
+        "Pages" => "page_count",
+        "Archived File" => "archived_file_name",
+        "Shared" => "is_shared_by_requester",
+        other => return other.to_lowercase().replace(' ', "_"),
+    };
+    name.to_string()
+}
+
 #[derive(Debug)]
 pub struct FieldFilterError {
     pub invalid: String,
@@ -262,12 +372,28 @@ impl std::error::Error for FieldFilterError {}
 pub trait Tabular {
     fn headers() -> &'static [&'static str];
     fn row(&self) -> Vec<String>;
+
+    /// Headers shown when no fields are selected.
+    fn default_headers() -> &'static [&'static str] {
+        Self::headers()
+    }
 }
 
 pub trait DetailView {
     fn fields(&self) -> Vec<(&'static str, String)>;
 }
 
+/// Indices of `T`'s default headers among all of its headers.
+fn default_column_indices<T: Tabular>() -> Vec<usize> {
+    let defaults = T::default_headers();
+    T::headers()
+        .iter()
+        .enumerate()
+        .filter(|(_, h)| defaults.contains(h))
+        .map(|(i, _)| i)
+        .collect()
+}
+
 fn display_opt<T: std::fmt::Display>(opt: Option<&T>, default: &str) -> String {
     opt.map_or_else(|| default.to_string(), std::string::ToString::to_string)
 }
@@ -294,6 +420,15 @@ impl FieldNames for ResolvedDocument {
             "added",
             "archive_serial_number",
             "original_file_name",
+            "modified",
+            "owner",
+            "notes",
+            "storage_path",
+            "custom_fields",
+            "page_count",
+            "mime_type",
+            "archived_file_name",
+            "is_shared_by_requester",
         ]
     }
 }
@@ -319,10 +454,60 @@ pub struct ResolvedDocument {
     pub added: Option<jiff::Timestamp>,
     pub archive_serial_number: Option<u64>,
     pub original_file_name: Option<String>,
+    pub modified: Option<jiff::Timestamp>,
+    pub owner: Option<u64>,
+    pub notes: Vec<Note>,
+    pub storage_path: Option<u64>,
+    pub custom_fields: Vec<CustomFieldInstance>,
+    pub page_count: Option<u64>,
+    pub mime_type: Option<String>,
+    pub archived_file_name: Option<String>,
+    pub is_shared_by_requester: Option<bool>,
+}
+
+impl ResolvedDocument {
+    /// Custom field values as `#<field>: <value>`. Field names aren't
+    /// resolved.
+    fn custom_field_summary(&self) -> String {
+        self.custom_fields
+            .iter()
+            .map(|cf| {
+                let value = cf
+                    .value
+                    .as_str()
+                    .map_or_else(|| cf.value.to_string(), str::to_string);
+                format!("#{}: {value}", cf.field)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 impl Tabular for ResolvedDocument {
     fn headers() -> &'static [&'static str] {
+        &[
+            "ID",
+            "Title",
+            "Correspondent",
+            "Type",
+            "Created",
+            "Tags",
+            "Added",
+            "Modified",
+            "ASN",
+            "Original File",
+            "Archived File",
+            "Owner",
+            "Notes",
+            "Storage Path",
+            "Custom Fields",
+            "Pages",
+            "MIME Type",
+            "Shared",
+        ]
+    }
+
+    fn default_headers() -> &'static [&'static str] {
         &["ID", "Title", "Correspondent", "Type", "Created", "Tags"]
     }
 
@@ -334,6 +519,20 @@ impl Tabular for ResolvedDocument {
             self.document_type_name.clone().unwrap_or_default(),
             display_opt(self.created.as_ref(), ""),
             self.tag_names.join(", "),
+            display_opt(self.added.as_ref(), ""),
+            display_opt(self.modified.as_ref(), ""),
+            display_opt(self.archive_serial_number.as_ref(), ""),
+            self.original_file_name.clone().unwrap_or_default(),
+            self.archived_file_name.clone().unwrap_or_default(),
+            self.owner.map(|id| format!("#{id}")).unwrap_or_default(),
+            self.notes.len().to_string(),
+            self.storage_path
+                .map(|id| format!("#{id}"))
+                .unwrap_or_default(),
+            self.custom_field_summary(),
+            display_opt(self.page_count.as_ref(), ""),
+            self.mime_type.clone().unwrap_or_default(),
+            display_opt(self.is_shared_by_requester.as_ref(), ""),
         ]
     }
 }
@@ -365,10 +564,162 @@ impl DetailView for ResolvedDocument {
         if let Some(asn) = self.archive_serial_number {
             fields.push(("ASN", asn.to_string()));
         }
+        if let Some(modified) = self.modified {
+            fields.push(("Modified", modified.to_string()));
+        }
+        if let Some(ref name) = self.archived_file_name {
+            fields.push(("Archived File", name.clone()));
+        }
+        if let Some(ref mime_type) = self.mime_type {
+            fields.push(("MIME Type", mime_type.clone()));
+        }
+        if let Some(pages) = self.page_count {
+            fields.push(("Pages", pages.to_string()));
+        }
+        if let Some(id) = self.storage_path {
+            fields.push(("Storage Path", format!("#{id}")));
+        }
+        if let Some(owner) = self.owner {
+            fields.push(("Owner", format!("#{owner}")));
+        }
+        if let Some(shared) = self.is_shared_by_requester {
+            fields.push(("Shared", shared.to_string()));
+        }
+        if !self.custom_fields.is_empty() {
+            fields.push(("Custom Fields", self.custom_field_summary()));
+        }
+        for note in &self.notes {
+            fields.push(("Notes", note.note.clone()));
+        }
         fields
     }
 }
 
+// --- SearchHit ---
+
+/// A document found in the local index. Shown like a document from server
+/// search, with its score and a snippet of matching content on request.
+#[derive(Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub document: ResolvedDocument,
+    pub score: f32,
+    pub snippet: String,
+}
+
+impl FieldNames for SearchHit {
+    fn valid_fields() -> &'static [&'static str] {
+        &[
+            "id",
+            "title",
+            "correspondent",
+            "document_type",
+            "tags",
+            "created",
+            "added",
+            "archive_serial_number",
+            "original_file_name",
+            "modified",
+            "owner",
+            "notes",
+            "storage_path",
+            "custom_fields",
+            "page_count",
+            "mime_type",
+            "archived_file_name",
+            "is_shared_by_requester",
+            "score",
+            "snippet",
+        ]
+    }
+}
+
+impl Tabular for SearchHit {
+    fn headers() -> &'static [&'static str] {
+        &[
+            "ID",
+            "Title",
+            "Correspondent",
+            "Type",
+            "Created",
+            "Tags",
+            "Added",
+            "Modified",
+            "ASN",
+            "Original File",
+            "Archived File",
+            "Owner",
+            "Notes",
+            "Storage Path",
+            "Custom Fields",
+            "Pages",
+            "MIME Type",
+            "Shared",
+            "Score",
+            "Snippet",
+        ]
+    }
+
+    fn default_headers() -> &'static [&'static str] {
+        ResolvedDocument::default_headers()
+    }
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.document.row();
+        row.push(format!("{:.2}", self.score));
+        row.push(self.snippet.clone());
+        row
+    }
+}
+
+// --- Document ---
+
+/// Documents as the server returns them, for `--raw`. Tables show IDs in
+/// place of names.
+impl Tabular for Document {
+    fn headers() -> &'static [&'static str] {
+        &["ID", "Title", "Correspondent", "Type", "Created", "Tags"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.title.clone(),
+            display_opt(self.correspondent.as_ref(), ""),
+            display_opt(self.document_type.as_ref(), ""),
+            display_opt(self.created.as_ref(), ""),
+            join_ids(&self.tags),
+        ]
+    }
+}
+
+impl DetailView for Document {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("ID", self.id.to_string()),
+            ("Title", self.title.clone()),
+            ("Created", display_opt(self.created.as_ref(), "N/A")),
+            ("Added", display_opt(self.added.as_ref(), "N/A")),
+            (
+                "Correspondent",
+                display_opt(self.correspondent.as_ref(), "N/A"),
+            ),
+            (
+                "Document Type",
+                display_opt(self.document_type.as_ref(), "N/A"),
+            ),
+            ("Tags", join_ids(&self.tags)),
+        ]
+    }
+}
+
+fn join_ids(ids: &[u64]) -> String {
+    ids.iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 pub fn resolve_documents(docs: &[Document], resolver: &NameResolver) -> Vec<ResolvedDocument> {
     docs.iter()
         .map(|doc| ResolvedDocument {
@@ -388,10 +739,45 @@ pub fn resolve_documents(docs: &[Document], resolver: &NameResolver) -> Vec<Reso
             added: doc.added,
             archive_serial_number: doc.archive_serial_number,
             original_file_name: doc.original_file_name.clone(),
+            modified: doc.modified,
+            owner: doc.owner,
+            notes: doc.notes.clone(),
+            storage_path: doc.storage_path,
+            custom_fields: doc.custom_fields.clone(),
+            page_count: doc.page_count,
+            mime_type: doc.mime_type.clone(),
+            archived_file_name: doc.archived_file_name.clone(),
+            is_shared_by_requester: doc.is_shared_by_requester,
         })
         .collect()
 }
 
+/// Resolve names on each page of documents as it is fetched, producing the
+/// `(total_count, items)` pages expected by `print_results`. Names missing
+/// from `names` are fetched per page.
+pub fn resolve_pages<'a>(
+    client: &'a Client,
+    pages: Pages<'a, Document>,
+    mut names: NameResolver,
+    cache: &'a NameCache,
+) -> impl Iterator<Item = anyhow::Result<(u64, Vec<ResolvedDocument>)>> + 'a {
+    pages.map(move |page| {
+        let page = page?;
+        names.fill(client, &page.results, cache)?;
+        Ok((page.count, resolve_documents(&page.results, &names)))
+    })
+}
+
+/// Pass pages of documents through without resolving names, for `--raw`.
+pub fn raw_pages(
+    pages: Pages<'_, Document>,
+) -> impl Iterator<Item = anyhow::Result<(u64, Vec<Document>)>> + '_ {
+    pages.map(|page| {
+        let page = page?;
+        Ok((page.count, page.results))
+    })
+}
+
 // --- FieldNames impls for metadata types ---
 
 impl FieldNames for Tag {
@@ -401,7 +787,12 @@ impl FieldNames for Tag {
             "name",
             "slug",
             "color",
+            "text_color",
             "is_inbox_tag",
+            "match",
+            "matching_algorithm",
+            "is_insensitive",
+            "parent",
             "document_count",
         ]
     }
@@ -409,13 +800,29 @@ impl FieldNames for Tag {
 
 impl FieldNames for Correspondent {
     fn valid_fields() -> &'static [&'static str] {
-        &["id", "name", "slug", "document_count"]
+        &[
+            "id",
+            "name",
+            "slug",
+            "match",
+            "matching_algorithm",
+            "is_insensitive",
+            "document_count",
+        ]
     }
 }
 
 impl FieldNames for DocumentType {
     fn valid_fields() -> &'static [&'static str] {
-        &["id", "name", "slug", "document_count"]
+        &[
+            "id",
+            "name",
+            "slug",
+            "match",
+            "matching_algorithm",
+            "is_insensitive",
+            "document_count",
+        ]
     }
 }
 
@@ -469,3 +876,78 @@ impl Tabular for DocumentType {
         ]
     }
 }
+
+// --- DetailView impls ---
+
+impl DetailView for Tag {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("ID", self.id.to_string()),
+            ("Name", self.name.clone()),
+            ("Slug", self.slug.clone()),
+            ("Color", display_opt(self.color.as_ref(), "N/A")),
+            ("Text Color", display_opt(self.text_color.as_ref(), "N/A")),
+            ("Inbox Tag", display_opt(self.is_inbox_tag.as_ref(), "N/A")),
+            ("Parent", display_opt(self.parent.as_ref(), "N/A")),
+            ("Match", display_opt(self.r#match.as_ref(), "")),
+            (
+                "Matching Algorithm",
+                display_opt(self.matching_algorithm.as_ref(), "N/A"),
+            ),
+            (
+                "Case Insensitive",
+                display_opt(self.is_insensitive.as_ref(), "N/A"),
+            ),
+            (
+                "Documents",
+                display_opt(self.document_count.as_ref(), "N/A"),
+            ),
+        ]
+    }
+}
+
+impl DetailView for Correspondent {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("ID", self.id.to_string()),
+            ("Name", self.name.clone()),
+            ("Slug", self.slug.clone()),
+            ("Match", display_opt(self.r#match.as_ref(), "")),
+            (
+                "Matching Algorithm",
+                display_opt(self.matching_algorithm.as_ref(), "N/A"),
+            ),
+            (
+                "Case Insensitive",
+                display_opt(self.is_insensitive.as_ref(), "N/A"),
+            ),
+            (
+                "Documents",
+                display_opt(self.document_count.as_ref(), "N/A"),
+            ),
+        ]
+    }
+}
+
+impl DetailView for DocumentType {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("ID", self.id.to_string()),
+            ("Name", self.name.clone()),
+            ("Slug", self.slug.clone()),
+            ("Match", display_opt(self.r#match.as_ref(), "")),
+            (
+                "Matching Algorithm",
+                display_opt(self.matching_algorithm.as_ref(), "N/A"),
+            ),
+            (
+                "Case Insensitive",
+                display_opt(self.is_insensitive.as_ref(), "N/A"),
+            ),
+            (
+                "Documents",
+                display_opt(self.document_count.as_ref(), "N/A"),
+            ),
+        ]
+    }
+}
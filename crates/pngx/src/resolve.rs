@@ -1,44 +1,314 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::Result;
-use pngx_client::Client;
+use jiff::Timestamp;
+use pngx_client::{ApiError, AsyncClient, Client, Correspondent, Document, DocumentType, Tag};
+use serde::{Deserialize, Serialize};
 
+use crate::config::{ValidConfig, cache_dir};
 use crate::output::FieldFilter;
 
 const RESOLVED_FIELDS: &[&str] = &["correspondent", "document_type", "tags"];
 
+/// Above this many unknown IDs, a resolver without cached names fetches the
+/// full lists instead of filtering by ID.
+const FULL_FETCH_THRESHOLD: usize = 200;
+
+/// Maps tag, correspondent and document type IDs to their names.
+///
+/// Only names referenced by the documents being shown are fetched, unless the
+/// cache is cold and many IDs are unknown, in which case all names are
+/// fetched at once.
+#[derive(Default, Serialize, Deserialize)]
 pub struct NameResolver {
+    fetched_at: Option<Timestamp>,
     tags: HashMap<u64, String>,
     correspondents: HashMap<u64, String>,
     document_types: HashMap<u64, String>,
+    /// IDs the server returned no name for, e.g. of deleted objects or ones
+    /// the user may not see. They expire with the names.
+    #[serde(default)]
+    absent: AbsentIds,
+    /// Set when the field filter needs no names.
+    #[serde(skip)]
+    disabled: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct AbsentIds {
+    tags: HashSet<u64>,
+    correspondents: HashSet<u64>,
+    document_types: HashSet<u64>,
+}
+
+/// IDs referenced by documents whose names a resolver doesn't know yet.
+#[derive(Default)]
+struct MissingNames {
+    tags: Vec<u64>,
+    correspondents: Vec<u64>,
+    document_types: Vec<u64>,
+}
+
+impl MissingNames {
+    fn len(&self) -> usize {
+        self.tags.len() + self.correspondents.len() + self.document_types.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The names a resolver lacks for some documents, fetched either by ID or as
+/// full lists. Created by [`NameResolver::request`]; the sync and async
+/// clients fetch it, and [`NameResolver::apply`] adds the result.
+pub struct NameRequest {
+    missing: MissingNames,
+    full: bool,
+}
+
+/// Names fetched for a [`NameRequest`].
+pub struct FetchedNames {
+    tags: Vec<Tag>,
+    correspondents: Vec<Correspondent>,
+    document_types: Vec<DocumentType>,
+}
+
+impl NameRequest {
+    pub fn fetch(&self, client: &Client) -> Result<FetchedNames, ApiError> {
+        if self.full {
+            let (tags, _) = client.collect_tags(None)?;
+            let (correspondents, _) = client.collect_correspondents(None)?;
+            let (document_types, _) = client.collect_document_types(None)?;
+            return Ok(FetchedNames {
+                tags,
+                correspondents,
+                document_types,
+            });
+        }
+        Ok(FetchedNames {
+            tags: client.tags_by_id(&self.missing.tags)?,
+            correspondents: client.correspondents_by_id(&self.missing.correspondents)?,
+            document_types: client.document_types_by_id(&self.missing.document_types)?,
+        })
+    }
+
+    /// Like [`fetch`](Self::fetch), with the three lists fetched
+    /// concurrently.
+    pub async fn fetch_async(&self, client: &AsyncClient) -> Result<FetchedNames, ApiError> {
+        if self.full {
+            let ((tags, _), (correspondents, _), (document_types, _)) = tokio::try_join!(
+                client.collect_tags(None),
+                client.collect_correspondents(None),
+                client.collect_document_types(None),
+            )?;
+            return Ok(FetchedNames {
+                tags,
+                correspondents,
+                document_types,
+            });
+        }
+        let (tags, correspondents, document_types) = tokio::try_join!(
+            client.tags_by_id(&self.missing.tags),
+            client.correspondents_by_id(&self.missing.correspondents),
+            client.document_types_by_id(&self.missing.document_types),
+        )?;
+        Ok(FetchedNames {
+            tags,
+            correspondents,
+            document_types,
+        })
+    }
 }
 
 impl NameResolver {
-    pub fn fetch(client: &Client, fields: Option<&FieldFilter>) -> Result<Self> {
+    /// Start from the cached names, if any. Returns a resolver that never
+    /// fetches when the field filter needs no names.
+    pub fn cached(fields: Option<&FieldFilter>, cache: &NameCache) -> Self {
         if let Some(f) = fields
             && !f.needs_any(RESOLVED_FIELDS)
         {
-            return Ok(Self {
-                tags: HashMap::new(),
-                correspondents: HashMap::new(),
-                document_types: HashMap::new(),
-            });
+            return Self {
+                disabled: true,
+                ..Self::default()
+            };
+        }
+        cache.load().unwrap_or_default()
+    }
+
+    /// Resolve the names referenced by `docs`, using the cache where
+    /// possible.
+    pub fn fetch(
+        client: &Client,
+        docs: &[Document],
+        fields: Option<&FieldFilter>,
+        cache: &NameCache,
+    ) -> Result<Self> {
+        let mut names = Self::cached(fields, cache);
+        names.fill(client, docs, cache)?;
+        Ok(names)
+    }
+
+    /// Fetch the names referenced by `docs` that aren't known yet and update
+    /// the cache.
+    pub fn fill(&mut self, client: &Client, docs: &[Document], cache: &NameCache) -> Result<()> {
+        let Some(request) = self.request(docs) else {
+            return Ok(());
+        };
+        let fetched = request.fetch(client)?;
+        self.apply(request, fetched);
+        cache.store(self);
+        Ok(())
+    }
+
+    /// What to fetch to name everything `docs` reference, or `None` if all
+    /// names are known.
+    pub fn request(&self, docs: &[Document]) -> Option<NameRequest> {
+        let missing = self.missing(docs);
+        if missing.is_empty() {
+            return None;
         }
+        let full = self.wants_full_fetch(&missing);
+        Some(NameRequest { missing, full })
+    }
 
-        let (tags, _) = client.collect_tags(None)?;
-        let (correspondents, _) = client.collect_correspondents(None)?;
-        let (document_types, _) = client.collect_document_types(None)?;
+    /// Add the names fetched for `request`.
+    pub fn apply(&mut self, request: NameRequest, fetched: FetchedNames) {
+        let FetchedNames {
+            tags,
+            correspondents,
+            document_types,
+        } = fetched;
+        if request.full {
+            *self = Self::from_lists(tags, correspondents, document_types);
+        } else {
+            self.extend(tags, correspondents, document_types);
+        }
 
-        Ok(Self {
-            tags: tags.into_iter().map(|t| (t.id, t.name)).collect(),
-            correspondents: correspondents.into_iter().map(|c| (c.id, c.name)).collect(),
-            document_types: document_types
+        let MissingNames {
+            tags,
+            correspondents,
+            document_types,
+        } = request.missing;
+        self.absent
+            .tags
+            .extend(tags.into_iter().filter(|id| !self.tags.contains_key(id)));
+        self.absent.correspondents.extend(
+            correspondents
+                .into_iter()
+                .filter(|id| !self.correspondents.contains_key(id)),
+        );
+        self.absent.document_types.extend(
+            document_types
                 .into_iter()
-                .map(|dt| (dt.id, dt.name))
-                .collect(),
+                .filter(|id| !self.document_types.contains_key(id)),
+        );
+    }
+
+    /// IDs referenced by `docs` that have no known name.
+    fn missing(&self, docs: &[Document]) -> MissingNames {
+        let mut missing = MissingNames::default();
+        if self.disabled {
+            return missing;
+        }
+        for doc in docs {
+            missing.tags.extend(
+                doc.tags
+                    .iter()
+                    .filter(|id| !self.tags.contains_key(id) && !self.absent.tags.contains(id)),
+            );
+            missing
+                .correspondents
+                .extend(doc.correspondent.filter(|id| {
+                    !self.correspondents.contains_key(id)
+                        && !self.absent.correspondents.contains(id)
+                }));
+            missing
+                .document_types
+                .extend(doc.document_type.filter(|id| {
+                    !self.document_types.contains_key(id)
+                        && !self.absent.document_types.contains(id)
+                }));
+        }
+        for ids in [
+            &mut missing.tags,
+            &mut missing.correspondents,
+            &mut missing.document_types,
+        ] {
+            ids.sort_unstable();
+            ids.dedup();
+        }
+        missing
+    }
+
+    /// Whether fetching all names is preferable to fetching `missing` by ID:
+    /// true when nothing was cached and many IDs are unknown.
+    fn wants_full_fetch(&self, missing: &MissingNames) -> bool {
+        self.fetched_at.is_none() && missing.len() > FULL_FETCH_THRESHOLD
+    }
+
+    pub fn from_lists(
+        tags: Vec<Tag>,
+        correspondents: Vec<Correspondent>,
+        document_types: Vec<DocumentType>,
+    ) -> Self {
+        let mut names = Self::default();
+        names.extend(tags, correspondents, document_types);
+        names
+    }
+
+    /// Add fetched names. The resolver keeps the time of its first fetch, so
+    /// the TTL bounds the age of every name it holds.
+    fn extend(
+        &mut self,
+        tags: Vec<Tag>,
+        correspondents: Vec<Correspondent>,
+        document_types: Vec<DocumentType>,
+    ) {
+        self.fetched_at.get_or_insert_with(Timestamp::now);
+        self.tags.extend(tags.into_iter().map(|t| (t.id, t.name)));
+        self.correspondents
+            .extend(correspondents.into_iter().map(|c| (c.id, c.name)));
+        self.document_types
+            .extend(document_types.into_iter().map(|dt| (dt.id, dt.name)));
+    }
+
+    /// Whether the names were fetched less than `ttl` ago.
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        self.fetched_at.is_some_and(|at| {
+            Timestamp::now()
+                .duration_since(at)
+                .try_into()
+                .is_ok_and(|age: Duration| age < ttl)
         })
     }
 
+    /// Whether `other` knows the same names, ignoring when they were
+    /// fetched.
+    pub fn same_names(&self, other: &Self) -> bool {
+        self.tags == other.tags
+            && self.correspondents == other.correspondents
+            && self.document_types == other.document_types
+    }
+
+    /// Whether `doc` references a name that `current` resolves differently,
+    /// e.g. because the tag was renamed or deleted since.
+    pub fn is_stale(&self, doc: &Document, current: &Self) -> bool {
+        doc.tags
+            .iter()
+            .any(|&id| self.tag_name(id) != current.tag_name(id))
+            || doc
+                .correspondent
+                .is_some_and(|id| self.correspondent_name(id) != current.correspondent_name(id))
+            || doc
+                .document_type
+                .is_some_and(|id| self.document_type_name(id) != current.document_type_name(id))
+    }
+
     pub fn tag_name(&self, id: u64) -> String {
         self.tags
             .get(&id)
@@ -54,3 +324,394 @@ impl NameResolver {
         self.document_types.get(&id).cloned()
     }
 }
+
+/// How the on-disk name cache is used for this invocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Read fresh entries and write new ones.
+    Use,
+    /// Ignore existing entries but write new ones.
+    Refresh,
+    /// Neither read nor write.
+    Off,
+}
+
+/// On-disk cache of resolved names for one server URL and API token.
+///
+/// Failures to read or write the cache are logged and otherwise ignored;
+/// callers fall back to fetching from the server.
+pub struct NameCache {
+    path: PathBuf,
+    ttl: Duration,
+    mode: CacheMode,
+}
+
+impl NameCache {
+    pub fn new(config: &ValidConfig, mode: CacheMode) -> Self {
+        Self::in_dir(&cache_dir(), config, mode)
+    }
+
+    fn in_dir(dir: &Path, config: &ValidConfig, mode: CacheMode) -> Self {
+        Self {
+            path: dir.join(format!("names-{}.json", config.server_key())),
+            ttl: Duration::from_secs(config.cache_ttl),
+            mode,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the cached names if caching is enabled and the entry is
+    /// younger than the TTL.
+    pub fn load(&self) -> Option<NameResolver> {
+        if self.mode != CacheMode::Use {
+            return None;
+        }
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                tracing::warn!("failed to read name cache {}: {err}", self.path.display());
+                return None;
+            }
+        };
+        match serde_json::from_slice::<NameResolver>(&data) {
+            Ok(names) if names.is_fresh(self.ttl) => {
+                tracing::debug!("using cached names from {}", self.path.display());
+                Some(names)
+            }
+            Ok(_) => None,
+            Err(err) => {
+                tracing::debug!(
+                    "ignoring malformed name cache {}: {err}",
+                    self.path.display()
+                );
+                None
+            }
+        }
+    }
+
+    pub fn store(&self, names: &NameResolver) {
+        if self.mode == CacheMode::Off {
+            return;
+        }
+        if let Err(err) = write_atomic(&self.path, names) {
+            tracing::warn!("failed to write name cache {}: {err}", self.path.display());
+        }
+    }
+
+    /// Drops the entry after names changed on the server.
+    pub fn invalidate(&self) {
+        match fs::remove_file(&self.path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                tracing::warn!("failed to remove name cache {}: {err}", self.path.display());
+            }
+        }
+    }
+}
+
+fn write_atomic(path: &Path, names: &NameResolver) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec(names)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Removes all cache entries in `dir`. Returns the number of files removed.
+pub fn clear_cache(dir: &Path) -> io::Result<usize> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err),
+    };
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry?.path();
+        let is_entry = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("names-"));
+        if is_entry {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use pngx_testing::{FakePaperless, seed};
+
+    use super::*;
+    use crate::output::OutputFormat;
+
+    /// An empty scratch directory for one test.
+    fn scratch(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pngx-resolve-{}-{test}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("should create scratch directory");
+        dir
+    }
+
+    fn config(url: &str, token: &str) -> ValidConfig {
+        ValidConfig {
+            url: url.parse().unwrap(),
+            token: token.to_string(),
+            token_source: crate::config::TokenSource::Flag,
+            output_format: OutputFormat::Markdown,
+            list_separator: ", ".to_string(),
+            page_size: 100,
+            timeout: 30,
+            retries: 0,
+            cache_ttl: 3600,
+            api_version: None,
+            rewrite_next_urls: false,
+            ca_certs: Vec::new(),
+            client_cert: None,
+            headers: std::collections::BTreeMap::new(),
+            proxy: None,
+        }
+    }
+
+    fn cache(dir: &Path, mode: CacheMode) -> NameCache {
+        NameCache::in_dir(dir, &config("https://paperless.example", "secret"), mode)
+    }
+
+    fn inbox_names(fetched_at: Timestamp) -> NameResolver {
+        NameResolver {
+            fetched_at: Some(fetched_at),
+            tags: HashMap::from([(1, "Inbox".to_string())]),
+            ..NameResolver::default()
+        }
+    }
+
+    #[test]
+    fn test_cache_round_trip_and_ttl() {
+        let dir = scratch("ttl");
+        let cache = cache(&dir, CacheMode::Use);
+        assert!(cache.load().is_none());
+
+        cache.store(&inbox_names(Timestamp::now()));
+        let names = cache.load().expect("fresh entry should load");
+        assert_eq!(names.tag_name(1), "Inbox");
+        assert_eq!(names.tag_name(2), "#2");
+
+        let expired = Timestamp::now() - jiff::SignedDuration::from_secs(3601);
+        cache.store(&inbox_names(expired));
+        assert!(cache.load().is_none());
+    }
+
+    #[test]
+    fn test_refresh_and_no_cache_modes() {
+        let dir = scratch("modes");
+        cache(&dir, CacheMode::Use).store(&inbox_names(Timestamp::now()));
+
+        let refresh = cache(&dir, CacheMode::Refresh);
+        assert!(refresh.load().is_none());
+        refresh.store(&NameResolver::default());
+        assert!(
+            cache(&dir, CacheMode::Use).load().is_none(),
+            "--refresh should overwrite the entry"
+        );
+
+        let off = cache(&dir, CacheMode::Off);
+        off.store(&inbox_names(Timestamp::now()));
+        assert!(off.load().is_none());
+        assert!(
+            cache(&dir, CacheMode::Use).load().is_none(),
+            "--no-cache should not write"
+        );
+    }
+
+    #[test]
+    fn test_cache_entry_per_server_and_user() {
+        let dir = Path::new("/cache");
+        let path = |url, token| {
+            NameCache::in_dir(dir, &config(url, token), CacheMode::Use)
+                .path
+                .clone()
+        };
+        let work = path("https://paperless.example", "secret");
+        assert_eq!(work, path("https://paperless.example", "secret"));
+        assert_ne!(work, path("https://paperless.example", "other"));
+        assert_ne!(work, path("https://home.example", "secret"));
+        assert!(!work.to_string_lossy().contains("secret"));
+    }
+
+    #[test]
+    fn test_corrupt_cache_is_ignored_and_replaced() {
+        let dir = scratch("corrupt");
+        let cache = cache(&dir, CacheMode::Use);
+        fs::write(&cache.path, b"{not json").unwrap();
+        assert!(cache.load().is_none());
+
+        cache.store(&inbox_names(Timestamp::now()));
+        assert!(cache.load().is_some());
+    }
+
+    #[test]
+    fn test_invalidate_and_clear() {
+        let dir = scratch("clear");
+        let cache = cache(&dir, CacheMode::Use);
+        cache.store(&inbox_names(Timestamp::now()));
+        cache.invalidate();
+        assert!(!cache.path.exists());
+        cache.invalidate();
+
+        cache.store(&inbox_names(Timestamp::now()));
+        fs::write(dir.join("names-other.json"), b"{}").unwrap();
+        fs::write(dir.join("index.json"), b"{}").unwrap();
+        assert_eq!(clear_cache(&dir).unwrap(), 2);
+        assert!(dir.join("index.json").exists());
+        assert_eq!(clear_cache(&dir.join("missing")).unwrap(), 0);
+    }
+
+    /// Paths and queries of the taxonomy requests the server received.
+    async fn taxonomy_requests(server: &FakePaperless) -> Vec<String> {
+        let requests = server.mock_server().received_requests().await.unwrap();
+        requests
+            .iter()
+            .filter(|r| !r.url.path().starts_with("/api/documents/"))
+            .map(|r| format!("{}?{}", r.url.path(), r.url.query().unwrap_or_default()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_fill_fetches_only_missing_names() {
+        let server = FakePaperless::builder()
+            .tag(seed::Tag::new("Inbox"))
+            .tag(seed::Tag::new("Tax"))
+            .tag(seed::Tag::new("Unused"))
+            .correspondent(seed::Correspondent::new("ACME"))
+            .document_type(seed::DocumentType::new("Invoice"))
+            .document(
+                seed::Document::new("Invoice")
+                    .tag(1)
+                    .tag(2)
+                    .correspondent(1)
+                    .document_type(1),
+            )
+            .start()
+            .await;
+        let dir = scratch("fill");
+        let cache = cache(&dir, CacheMode::Use);
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let (docs, _) = client.collect_documents(None).unwrap();
+
+        let mut names = inbox_names(Timestamp::now());
+        names.fill(&client, &docs, &cache).unwrap();
+        assert_eq!(names.tag_name(2), "Tax");
+        assert_eq!(names.correspondent_name(1).as_deref(), Some("ACME"));
+        assert_eq!(names.document_type_name(1).as_deref(), Some("Invoice"));
+
+        let requests = taxonomy_requests(&server).await;
+        assert_eq!(requests.len(), 3, "{requests:?}");
+        assert!(
+            requests.iter().all(|r| r.contains("id__in=")),
+            "{requests:?}"
+        );
+        assert!(requests[0].contains("id__in=2&"), "{requests:?}");
+
+        names.fill(&client, &docs, &cache).unwrap();
+        assert_eq!(taxonomy_requests(&server).await.len(), 3);
+        assert_eq!(cache.load().unwrap().tag_name(2), "Tax");
+    }
+
+    #[tokio::test]
+    async fn test_names_the_server_lacks_are_not_requested_again() {
+        let server = FakePaperless::builder()
+            .tag(seed::Tag::new("Inbox"))
+            .document(
+                seed::Document::new("Invoice")
+                    .tag(1)
+                    .tag(9)
+                    .correspondent(4),
+            )
+            .start()
+            .await;
+        let dir = scratch("absent");
+        let cache = cache(&dir, CacheMode::Use);
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let (docs, _) = client.collect_documents(None).unwrap();
+
+        let mut names = NameResolver::default();
+        names.fill(&client, &docs, &cache).unwrap();
+        assert_eq!(names.tag_name(1), "Inbox");
+        assert_eq!(names.tag_name(9), "#9");
+        assert_eq!(names.correspondent_name(4), None);
+        assert_eq!(taxonomy_requests(&server).await.len(), 2);
+
+        names.fill(&client, &docs, &cache).unwrap();
+        let mut cached = cache.load().unwrap();
+        cached.fill(&client, &docs, &cache).unwrap();
+        assert_eq!(taxonomy_requests(&server).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cold_resolver_fetches_full_lists_for_many_ids() {
+        let mut builder = FakePaperless::builder();
+        let mut document = seed::Document::new("Tagged");
+        for id in 1..=201 {
+            builder = builder.tag(seed::Tag::new(format!("Tag {id}")));
+            document = document.tag(id);
+        }
+        let server = builder.document(document).start().await;
+        let dir = scratch("full-fetch");
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let (docs, _) = client.collect_documents(None).unwrap();
+
+        let mut names = NameResolver::default();
+        let request = names.request(&docs).expect("names should be missing");
+        assert!(request.full);
+        names
+            .fill(&client, &docs, &cache(&dir, CacheMode::Off))
+            .unwrap();
+        assert_eq!(names.tag_name(201), "Tag 201");
+        let requests = taxonomy_requests(&server).await;
+        assert!(
+            requests.iter().all(|r| !r.contains("id__in=")),
+            "{requests:?}"
+        );
+
+        let warm = inbox_names(Timestamp::now());
+        assert!(!warm.request(&docs).expect("names should be missing").full);
+    }
+    #[tokio::test]
+    async fn test_renamed_names_mark_documents_stale() {
+        let server = FakePaperless::builder()
+            .tag(seed::Tag::new("Inbox"))
+            .tag(seed::Tag::new("Tax"))
+            .document(seed::Document::new("Invoice").tag(1))
+            .document(seed::Document::new("Return").tag(2))
+            .start()
+            .await;
+        let client = Client::new(&server.uri(), server.token()).unwrap();
+        let (docs, _) = client.collect_documents(None).unwrap();
+
+        let names = || NameResolver {
+            tags: HashMap::from([(1, "Inbox".to_string()), (2, "Tax".to_string())]),
+            ..NameResolver::default()
+        };
+        let (indexed, mut current) = (names(), names());
+        assert!(indexed.same_names(&current));
+        assert!(docs.iter().all(|doc| !indexed.is_stale(doc, &current)));
+
+        current.tags.insert(2, "Taxes".to_string());
+        assert!(!indexed.same_names(&current));
+        let stale: Vec<_> = docs
+            .iter()
+            .filter(|doc| indexed.is_stale(doc, &current))
+            .map(|doc| doc.title.as_str())
+            .collect();
+        assert_eq!(stale, ["Return"]);
+    }
+}
@@ -0,0 +1,1226 @@
+//! End-to-end tests running the `pngx` binary against a fake server.
+
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use pngx_testing::{FakePaperless, seed};
+
+async fn server() -> FakePaperless {
+    FakePaperless::builder()
+        .page_size(2)
+        .tag(seed::Tag::new("Inbox").inbox())
+        .tag(seed::Tag::new("Tax"))
+        .correspondent(seed::Correspondent::new("ACME"))
+        .correspondent(seed::Correspondent::new("ACME Inc."))
+        .document(
+            seed::Document::new("Invoice 42")
+                .content("Total amount: 42 EUR")
+                .correspondent(1)
+                .tag(1)
+                .original_file_name("invoice-42.pdf")
+                .file(b"%PDF-original".to_vec()),
+        )
+        .document(
+            seed::Document::new("Tax return 2024")
+                .tag(2)
+                .created(jiff::civil::date(2024, 4, 30)),
+        )
+        .document(
+            seed::Document::new("Letter")
+                .content("Dear customer")
+                .correspondent(2),
+        )
+        .start()
+        .await
+}
+
+/// A scratch directory for one test's config, cache, and downloads.
+fn scratch(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pngx-cli-{}-{test}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("should create scratch directory");
+    dir
+}
+
+fn pngx(server: &FakePaperless, dir: &Path, args: &[&str]) -> Output {
+    command(server, dir, args)
+        .output()
+        .expect("should run pngx")
+}
+
+fn command(server: &FakePaperless, dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_pngx"));
+    command
+        .args(args)
+        .env("PNGX_URL", server.uri())
+        .env("PNGX_TOKEN", server.token())
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env("XDG_CACHE_HOME", dir.join("cache"))
+        .env_remove("PNGX_PROFILE")
+        // Keeps the plain-HTTP warning out of stderr.
+        .env("RUST_LOG", "error");
+    command
+}
+
+fn json(output: &Output) -> serde_json::Value {
+    assert!(
+        output.status.success(),
+        "pngx failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).expect("output should be JSON")
+}
+
+fn read_json(path: &Path) -> serde_json::Value {
+    let data = std::fs::read(path).expect("file should exist");
+    serde_json::from_slice(&data).expect("file should be JSON")
+}
+
+fn titles(value: &serde_json::Value) -> Vec<&str> {
+    value["results"]
+        .as_array()
+        .expect("results should be an array")
+        .iter()
+        .map(|doc| doc["title"].as_str().expect("title should be a string"))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_documents_list_spans_pages() {
+    let server = server().await;
+    let dir = scratch("documents-list");
+
+    let output = pngx(&server, &dir, &["documents", "list", "--all", "-o", "json"]);
+    let documents = json(&output);
+    assert_eq!(
+        titles(&documents),
+        ["Invoice 42", "Tax return 2024", "Letter"]
+    );
+    assert_eq!(documents["results"][0]["correspondent"], "ACME");
+
+    let output = pngx(
+        &server,
+        &dir,
+        &["documents", "list", "-o", "json", "-F", "id,mime_type"],
+    );
+    assert_eq!(
+        json(&output)["results"][0],
+        serde_json::json!({"id": 1, "mime_type": "application/pdf"})
+    );
+}
+
+#[tokio::test]
+async fn test_documents_get_fields_and_raw() {
+    let server = server().await;
+    let dir = scratch("documents-get");
+
+    let output = pngx(
+        &server,
+        &dir,
+        &[
+            "documents",
+            "get",
+            "1",
+            "-o",
+            "json",
+            "-F",
+            "title,mime_type,notes",
+        ],
+    );
+    assert_eq!(
+        json(&output),
+        serde_json::json!({
+            "title": "Invoice 42",
+            "mime_type": "application/pdf",
+            "notes": [],
+        })
+    );
+
+    let output = pngx(
+        &server,
+        &dir,
+        &["documents", "get", "1", "-o", "json", "--raw"],
+    );
+    let document = json(&output);
+    assert_eq!(document["correspondent"], 1);
+    assert_eq!(document["user_can_change"], true);
+}
+
+#[tokio::test]
+async fn test_raw_lists_keep_unknown_fields() {
+    let server = FakePaperless::builder()
+        .page_size(1)
+        .tag(seed::Tag::new("Inbox").inbox())
+        .document(
+            seed::Document::new("Invoice 42")
+                .tag(1)
+                .field("future_field", "kept"),
+        )
+        .document(seed::Document::new("Letter").field("future_field", 7))
+        .start()
+        .await;
+    let dir = scratch("raw-lists");
+
+    let output = pngx(&server, &dir, &["documents", "list", "--raw", "-o", "json"]);
+    let documents = json(&output);
+    assert_eq!(documents["results"][0]["future_field"], "kept");
+    assert_eq!(documents["results"][1]["future_field"], 7);
+
+    let output = pngx(&server, &dir, &["inbox", "--raw", "-o", "json"]);
+    assert_eq!(json(&output)["results"][0]["future_field"], "kept");
+
+    let output = pngx(&server, &dir, &["documents", "list", "-o", "json"]);
+    assert!(json(&output)["results"][0].get("future_field").is_none());
+}
+
+#[tokio::test]
+async fn test_search_and_inbox() {
+    let server = server().await;
+    let dir = scratch("search");
+
+    let output = pngx(&server, &dir, &["search", "dear", "-o", "json"]);
+    assert_eq!(titles(&json(&output)), ["Letter"]);
+
+    let output = pngx(&server, &dir, &["inbox", "-o", "json"]);
+    assert_eq!(titles(&json(&output)), ["Invoice 42"]);
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "pngx failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).expect("output should be UTF-8")
+}
+
+#[tokio::test]
+async fn test_csv_and_tsv_output() {
+    let server = FakePaperless::builder()
+        .tag(seed::Tag::new("Inbox"))
+        .tag(seed::Tag::new("Tax"))
+        .document(seed::Document::new(r#"Invoice "42", paid"#).tag(1).tag(2))
+        .document(seed::Document::new("Letter"))
+        .start()
+        .await;
+    let dir = scratch("csv");
+
+    let output = pngx(
+        &server,
+        &dir,
+        &["documents", "list", "-o", "csv", "-F", "tags,title,id"],
+    );
+    assert_eq!(
+        stdout(&output),
+        "tags,title,id\n\"Inbox, Tax\",\"Invoice \"\"42\"\", paid\",1\n,Letter,2\n"
+    );
+
+    let output = pngx(
+        &server,
+        &dir,
+        &[
+            "documents",
+            "list",
+            "-o",
+            "tsv",
+            "-F",
+            "title,tags",
+            "--list-separator",
+            "|",
+        ],
+    );
+    assert_eq!(
+        stdout(&output),
+        "title\ttags\n\"Invoice \"\"42\"\", paid\"\tInbox|Tax\nLetter\t\n"
+    );
+
+    let output = pngx(&server, &dir, &["search", "nothing", "-o", "csv"]);
+    assert_eq!(
+        stdout(&output),
+        "id,title,correspondent,document_type,created,tags\n"
+    );
+
+    let config = dir.join("config").join("pngx");
+    std::fs::create_dir_all(&config).unwrap();
+    std::fs::write(
+        config.join("config.toml"),
+        "output_format = \"csv\"\nlist_separator = \";\"\n",
+    )
+    .unwrap();
+    let output = pngx(&server, &dir, &["documents", "get", "1", "-F", "id,tags"]);
+    assert_eq!(stdout(&output), "id,tags\n1,Inbox;Tax\n");
+}
+
+#[tokio::test]
+async fn test_csv_columns_do_not_depend_on_the_document() {
+    let server = FakePaperless::builder()
+        .tag(seed::Tag::new("Inbox"))
+        .correspondent(seed::Correspondent::new("ACME"))
+        .document(
+            seed::Document::new("Full")
+                .tag(1)
+                .correspondent(1)
+                .archive_serial_number(7)
+                .original_file_name("full.pdf")
+                .archived_file(b"%PDF-archived".to_vec()),
+        )
+        .document(seed::Document::new("Sparse"))
+        .start()
+        .await;
+    let dir = scratch("csv-columns");
+    let header = |args: &[&str]| {
+        let output = pngx(&server, &dir, args);
+        assert!(output.status.success());
+        stdout(&output)
+            .lines()
+            .next()
+            .expect("output should have a header")
+            .to_string()
+    };
+
+    let full = header(&["documents", "get", "1", "-o", "csv"]);
+    assert_eq!(full, "id,title,correspondent,document_type,created,tags");
+    assert_eq!(header(&["documents", "get", "2", "-o", "csv"]), full);
+    assert_eq!(header(&["documents", "get", "1", "2", "-o", "csv"]), full);
+    assert_eq!(header(&["documents", "list", "-o", "csv"]), full);
+    assert_eq!(
+        header(&["documents", "get", "2", "-o", "tsv", "-F", "tags,id"]),
+        "tags\tid"
+    );
+}
+
+#[tokio::test]
+async fn test_download() {
+    let server = server().await;
+    let dir = scratch("download");
+    let file = dir.join("invoice.pdf");
+
+    let output = pngx(
+        &server,
+        &dir,
+        &[
+            "documents",
+            "download",
+            "1",
+            "--original",
+            "--file",
+            file.to_str().expect("path should be UTF-8"),
+        ],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read(&file).expect("file should exist"),
+        b"%PDF-original"
+    );
+}
+
+#[tokio::test]
+async fn test_local_search() {
+    let server = server().await;
+    let dir = scratch("local-search");
+
+    let output = pngx(&server, &dir, &["search", "--local", "dear"]);
+    assert!(!output.status.success());
+
+    // Documents are indexed page by page.
+    let output = command(&server, &dir, &["index", "build"])
+        .env("PNGX_PAGE_SIZE", "1")
+        .output()
+        .expect("should run pngx");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let search = |query: &str| {
+        let output = pngx(
+            &server,
+            &dir,
+            &[
+                "search",
+                "--local",
+                query,
+                "-o",
+                "json",
+                "-F",
+                "title,snippet",
+            ],
+        );
+        json(&output)
+    };
+
+    let results = search("dear");
+    assert_eq!(titles(&results), ["Letter"]);
+    assert_eq!(results["results"][0]["snippet"], "**Dear** customer");
+    assert_eq!(titles(&search("tag:tax")), ["Tax return 2024"]);
+    assert_eq!(titles(&search("correspondent:\"acme inc\"")), ["Letter"]);
+    assert_eq!(
+        titles(&search("created:[2024-01-01 TO 2024-12-31]")),
+        ["Tax return 2024"]
+    );
+
+    let output = pngx(
+        &server,
+        &dir,
+        &["search", "--local", "invoice", "-o", "json"],
+    );
+    let results = json(&output);
+    assert_eq!(results["results"][0]["correspondent"], "ACME");
+    assert_eq!(results["results"][0]["tags"], serde_json::json!(["Inbox"]));
+
+    let id = server.add_document(seed::Document::new("Receipt").content("Coffee beans"));
+    assert!(server.remove_document(3));
+    let output = pngx(&server, &dir, &["index", "update"]);
+    assert!(output.status.success());
+    assert_eq!(titles(&search("coffee")), ["Receipt"]);
+    assert!(titles(&search("dear")).is_empty());
+
+    let output = pngx(
+        &server,
+        &dir,
+        &[
+            "search", "--local", "coffee", "--raw", "-o", "json", "-F", "id",
+        ],
+    );
+    assert_eq!(json(&output)["results"][0]["id"], id);
+
+    // Documents with a renamed tag are re-indexed.
+    let client =
+        pngx_client::Client::new(&server.uri(), server.token()).expect("should build client");
+    client
+        .update_tag(
+            2,
+            &pngx_client::TagRequest {
+                name: Some("Taxes".to_string()),
+                ..pngx_client::TagRequest::default()
+            },
+        )
+        .expect("should rename tag");
+    let output = pngx(&server, &dir, &["index", "update"]);
+    assert!(output.status.success());
+    assert_eq!(titles(&search("tag:taxes")), ["Tax return 2024"]);
+    assert!(titles(&search("tag:tax")).is_empty());
+
+    let output = pngx(&server, &dir, &["index", "stats"]);
+    let stats = String::from_utf8_lossy(&output.stdout);
+    assert!(stats.contains("Documents: 3"), "{stats}");
+
+    // A new token finds the same index.
+    let output = command(&server, &dir, &["search", "--local", "coffee"])
+        .env("PNGX_TOKEN", "rotated")
+        .output()
+        .expect("should run pngx");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Receipt"));
+}
+
+#[tokio::test]
+async fn test_tag_lifecycle() {
+    let server = server().await;
+    let dir = scratch("tags");
+
+    let output = pngx(&server, &dir, &["tags", "create", "Receipts"]);
+    assert!(output.status.success());
+    assert!(server.tags().iter().any(|tag| tag.name == "Receipts"));
+
+    let output = pngx(&server, &dir, &["tags", "delete", "2", "--yes"]);
+    assert!(output.status.success());
+    assert!(server.tags().iter().all(|tag| tag.name != "Tax"));
+    assert!(
+        server
+            .document(2)
+            .expect("document should exist")
+            .tags
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_merge_correspondents() {
+    let server = server().await;
+    let dir = scratch("merge");
+
+    let output = pngx(
+        &server,
+        &dir,
+        &["correspondents", "merge", "2", "2", "--into", "1", "--yes"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(server.correspondents().len(), 1);
+    assert_eq!(
+        server
+            .document(3)
+            .expect("document should exist")
+            .correspondent,
+        Some(1)
+    );
+}
+
+#[tokio::test]
+async fn test_debug_http_with_rust_log() {
+    let server = server().await;
+    let dir = scratch("debug-http");
+
+    // `command` sets `RUST_LOG=error`.
+    let output = pngx(&server, &dir, &["--debug-http", "tags"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("http_request"), "{stderr}");
+    assert!(stderr.contains("[REDACTED]"), "{stderr}");
+    assert!(!stderr.contains(server.token()), "{stderr}");
+
+    let output = pngx(&server, &dir, &["tags"]);
+    assert!(output.stderr.is_empty());
+}
+
+#[tokio::test]
+async fn test_errors() {
+    let server = server().await;
+    let dir = scratch("errors");
+
+    let output = pngx(&server, &dir, &["--json-errors", "documents", "get", "99"]);
+    assert_eq!(
+        output.status.code(),
+        Some(3),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let error: serde_json::Value =
+        serde_json::from_slice(&output.stderr).expect("error should be JSON");
+    assert_eq!(error["code"], "not_found");
+
+    let output = pngx(
+        &server,
+        &dir,
+        &["--json-errors", "--token", "wrong", "tags"],
+    );
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = pngx(
+        &server,
+        &dir,
+        &["--json-errors", "tags", "edit", "1", "--parent", "1"],
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(6),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let error: serde_json::Value =
+        serde_json::from_slice(&output.stderr).expect("error should be JSON");
+    assert_eq!(error["code"], "validation_error");
+
+    let output = pngx(
+        &server,
+        &dir,
+        &["--token", "abc", "auth", "login", "--username", "alice"],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--username cannot be used with --token"),
+        "{stderr}"
+    );
+}
+
+#[tokio::test]
+async fn test_login_profiles() {
+    let server = server().await;
+    let dir = scratch("login-profiles");
+    let _ = std::fs::remove_dir_all(dir.join("config"));
+    let login = |profile: &str, url: &str| {
+        let output = command(&server, &dir, &["--profile", profile, "auth", "login"])
+            .args(["--url", url, "--token", server.token()])
+            .env_remove("PNGX_URL")
+            .env_remove("PNGX_TOKEN")
+            .output()
+            .expect("should run pngx");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+    let config_file = dir.join("config/pngx/config.toml");
+    let default_profile = || {
+        let content = std::fs::read_to_string(&config_file).expect("config should exist");
+        let config: toml_edit::DocumentMut = content.parse().expect("config should be TOML");
+        config
+            .get("default_profile")
+            .and_then(toml_edit::Item::as_str)
+            .map(str::to_string)
+    };
+
+    // The first profile becomes the default, later ones do not.
+    login("work", &server.uri());
+    assert_eq!(default_profile().as_deref(), Some("work"));
+    login("other", "http://127.0.0.1:9");
+    assert_eq!(default_profile().as_deref(), Some("work"));
+
+    let tags = |profile: Option<&str>| {
+        let mut command = command(&server, &dir, &["tags", "-o", "json"]);
+        command.env_remove("PNGX_URL").env_remove("PNGX_TOKEN");
+        if let Some(profile) = profile {
+            command.env("PNGX_PROFILE", profile);
+        }
+        command.output().expect("should run pngx")
+    };
+    assert!(tags(None).status.success());
+    assert!(tags(Some("work")).status.success());
+    assert_eq!(tags(Some("other")).status.code(), Some(4));
+
+    // No default is chosen when the top level configures a server.
+    std::fs::write(&config_file, format!("url = \"{}\"\n", server.uri())).unwrap();
+    login("work", &server.uri());
+    assert_eq!(default_profile(), None);
+}
+
+#[tokio::test]
+async fn test_logout_keeps_other_profiles() {
+    let server = server().await;
+    let dir = scratch("logout");
+    let config_file = dir.join("config/pngx/config.toml");
+    std::fs::create_dir_all(config_file.parent().unwrap()).unwrap();
+    let logout = || {
+        let output = command(&server, &dir, &["auth", "logout"])
+            .env_remove("PNGX_URL")
+            .env_remove("PNGX_TOKEN")
+            .output()
+            .expect("should run pngx");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let content = std::fs::read_to_string(&config_file).expect("config should exist");
+        content
+            .parse::<toml_edit::DocumentMut>()
+            .expect("config should be TOML")
+    };
+
+    // Only the default profile is removed.
+    std::fs::write(
+        &config_file,
+        "default_profile = \"work\"\noutput_format = \"json\"\n\n\
+         [profiles.work]\nurl = \"http://work\"\ntoken = \"a\"\n\n\
+         [profiles.home]\nurl = \"http://home\"\ntoken = \"b\"\n",
+    )
+    .unwrap();
+    let config = logout();
+    assert!(config.get("default_profile").is_none());
+    assert!(config["profiles"].get("work").is_none());
+    assert_eq!(config["profiles"]["home"]["token"].as_str(), Some("b"));
+    assert_eq!(config["output_format"].as_str(), Some("json"));
+
+    // Without a default, only the top-level credentials are removed.
+    std::fs::write(
+        &config_file,
+        "url = \"http://top\"\ntoken_command = \"pass paperless\"\noutput_format = \"json\"\n\n\
+         [profiles.home]\nurl = \"http://home\"\ntoken = \"b\"\n",
+    )
+    .unwrap();
+    let config = logout();
+    assert!(config.get("url").is_none());
+    assert!(config.get("token_command").is_none());
+    assert_eq!(config["output_format"].as_str(), Some("json"));
+    assert_eq!(config["profiles"]["home"]["token"].as_str(), Some("b"));
+}
+
+#[tokio::test]
+async fn test_sync() {
+    let server = FakePaperless::builder()
+        .correspondent(seed::Correspondent::new("ACME"))
+        .document(
+            seed::Document::new("Invoice 42")
+                .content("Total amount: 42 EUR")
+                .correspondent(1)
+                .created(jiff::civil::date(2024, 3, 1))
+                .original_file_name("invoice-42.pdf")
+                .file(b"%PDF-original".to_vec())
+                .archived_file(b"%PDF-archived".to_vec()),
+        )
+        .document(
+            seed::Document::new("Letter")
+                .created(jiff::civil::date(2024, 5, 2))
+                .original_file_name("letter.txt")
+                .file(b"Dear customer".to_vec()),
+        )
+        .document(
+            seed::Document::new("Receipt")
+                .correspondent(1)
+                .created(jiff::civil::date(2024, 6, 3))
+                .original_file_name("receipt.pdf"),
+        )
+        .start()
+        .await;
+    let dir = scratch("sync");
+    let mirror = dir.join("mirror");
+    let _ = std::fs::remove_dir_all(&mirror);
+    let sync = |args: &[&str]| {
+        let mut all = vec!["sync", mirror.to_str().expect("path should be UTF-8")];
+        all.extend_from_slice(args);
+        // Documents are synced page by page.
+        let output = command(&server, &dir, &all)
+            .env("PNGX_PAGE_SIZE", "2")
+            .output()
+            .expect("should run pngx");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+
+    sync(&[]);
+    assert_eq!(
+        std::fs::read(mirror.join("ACME/2024-03-01 Invoice 42.pdf")).expect("file should exist"),
+        b"%PDF-archived"
+    );
+    assert!(mirror.join("none/2024-05-02 Letter.txt").is_file());
+    assert!(mirror.join("ACME/2024-06-03 Receipt.pdf").is_file());
+    let sidecar: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(mirror.join("ACME/2024-03-01 Invoice 42.json"))
+            .expect("sidecar should exist"),
+    )
+    .expect("sidecar should be JSON");
+    assert_eq!(sidecar["correspondent"], "ACME");
+    assert_eq!(sidecar["content"], "Total amount: 42 EUR");
+    assert!(mirror.join(".pngx-sync.json").is_file());
+    let pages = server
+        .mock_server()
+        .received_requests()
+        .await
+        .expect("requests should be recorded")
+        .iter()
+        .filter(|request| request.url.path() == "/api/documents/")
+        .count();
+    assert_eq!(pages, 2);
+
+    // Changed documents move with their metadata.
+    let client = pngx_client::Client::builder(&server.uri(), server.token())
+        .build()
+        .expect("should build client");
+    client
+        .bulk_edit(&[2], &pngx_client::BulkEdit::SetCorrespondent(Some(1)))
+        .expect("should edit document");
+    sync(&[]);
+    assert!(mirror.join("ACME/2024-05-02 Letter.txt").is_file());
+    assert!(!mirror.join("none").exists());
+
+    // Deleted documents are pruned.
+    assert!(server.remove_document(3));
+    sync(&[]);
+    assert!(!mirror.join("ACME/2024-06-03 Receipt.pdf").exists());
+    assert!(!mirror.join("ACME/2024-06-03 Receipt.json").exists());
+    assert!(mirror.join("ACME/2024-03-01 Invoice 42.pdf").is_file());
+
+    let output = pngx(&server, &dir, &["sync", "x", "--template", "{nope}"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+/// A server with one document using every kind of metadata the exporter
+/// writes, and a plain one.
+async fn export_server() -> FakePaperless {
+    FakePaperless::builder()
+        .tag(seed::Tag::new("Tax"))
+        .correspondent(seed::Correspondent::new("ACME"))
+        .storage_path(seed::StoragePath::new("Archive", "{{ title }}"))
+        .custom_field(seed::CustomField::new("Amount", "monetary"))
+        .document(
+            seed::Document::new("Invoice 42")
+                .content("Total amount: 42 EUR")
+                .correspondent(1)
+                .tag(1)
+                .storage_path(1)
+                .custom_field(1, "EUR42.00")
+                .note("Paid")
+                .created(jiff::civil::date(2024, 3, 1))
+                .original_file_name("invoice-42.pdf")
+                .file(b"%PDF-original".to_vec())
+                .archived_file(b"%PDF-archived".to_vec())
+                .thumbnail(b"thumb".to_vec()),
+        )
+        .document(
+            seed::Document::new("Letter")
+                .content("Dear customer")
+                .created(jiff::civil::date(2024, 5, 2))
+                .original_file_name("letter.txt")
+                .file(b"Dear customer".to_vec()),
+        )
+        .start()
+        .await
+}
+
+#[tokio::test]
+async fn test_export() {
+    let server = export_server().await;
+    let dir = scratch("export");
+    let target = dir.join("export");
+    let _ = std::fs::remove_dir_all(&target);
+    let export = |args: &[&str]| {
+        let mut all = vec!["export", target.to_str().expect("path should be UTF-8")];
+        all.extend_from_slice(args);
+        let output = pngx(&server, &dir, &all);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    export(&[]);
+    let manifest = read_json(&target.join("manifest.json"));
+    let models: Vec<&str> = manifest
+        .as_array()
+        .expect("manifest should be an array")
+        .iter()
+        .map(|record| record["model"].as_str().expect("model should be a string"))
+        .collect();
+    assert_eq!(
+        models,
+        [
+            "documents.correspondent",
+            "documents.tag",
+            "documents.storagepath",
+            "documents.customfield",
+            "documents.document",
+            "documents.document",
+            "documents.note",
+            "documents.customfieldinstance",
+        ]
+    );
+    let invoice = &manifest[4];
+    assert_eq!(invoice["fields"]["title"], "Invoice 42");
+    assert_eq!(invoice["fields"]["tags"], serde_json::json!([1]));
+    assert_eq!(
+        invoice["fields"]["checksum"],
+        "0497c4bd8fc29f86da69f6716b6bd72f"
+    );
+    assert_eq!(
+        invoice["__exported_file_name__"],
+        "2024-03-01 ACME Invoice 42.pdf"
+    );
+    assert_eq!(
+        std::fs::read(target.join("2024-03-01 ACME Invoice 42-archive.pdf"))
+            .expect("archive should exist"),
+        b"%PDF-archived"
+    );
+    assert_eq!(
+        std::fs::read(target.join("2024-03-01 ACME Invoice 42-thumbnail.webp"))
+            .expect("thumbnail should exist"),
+        b"thumb"
+    );
+    assert!(manifest[5].get("__exported_archive_name__").is_none());
+    assert_eq!(manifest[6]["fields"]["note"], "Paid");
+    assert_eq!(manifest[7]["fields"]["value_monetary"], "EUR42.00");
+    assert_eq!(
+        read_json(&target.join("metadata.json"))["version"],
+        "2.15.0"
+    );
+
+    // Unchanged files are skipped, and files of documents no longer
+    // exported are removed.
+    let stderr = export(&[]);
+    assert!(
+        stderr.contains("0 files downloaded, 5 unchanged"),
+        "{stderr}"
+    );
+    let stderr = export(&["--compare-checksums", "--query", "dear"]);
+    assert!(stderr.contains("Exported 1 documents"), "{stderr}");
+    assert!(
+        stderr.contains("0 files downloaded, 2 unchanged, 3 removed"),
+        "{stderr}"
+    );
+    assert!(!target.join("2024-03-01 ACME Invoice 42.pdf").exists());
+
+    export(&["--zip"]);
+    let zip = std::fs::read_dir(&target)
+        .expect("target should exist")
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "zip"))
+        .expect("zip should exist");
+    let archive = zip::ZipArchive::new(std::fs::File::open(zip).expect("zip should open"))
+        .expect("zip should be readable");
+    let mut names: Vec<&str> = archive.file_names().collect();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        [
+            "2024-03-01 ACME Invoice 42-archive.pdf",
+            "2024-03-01 ACME Invoice 42-thumbnail.webp",
+            "2024-03-01 ACME Invoice 42.pdf",
+            "2024-05-02 Letter-thumbnail.webp",
+            "2024-05-02 Letter.txt",
+            "manifest.json",
+            "metadata.json",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_import() {
+    let source = export_server().await;
+    let dir = scratch("import");
+    let export = dir.join("export");
+    let _ = std::fs::remove_dir_all(&export);
+    let export_arg = export.to_str().expect("path should be UTF-8");
+    let output = pngx(&source, &dir, &["export", export_arg]);
+    assert!(output.status.success());
+
+    // The tag exists under another ID and the letter is already there.
+    let target = FakePaperless::builder()
+        .tag(seed::Tag::new("Inbox").inbox())
+        .tag(seed::Tag::new("tax"))
+        .document(seed::Document::new("Letter").file(b"Dear customer".to_vec()))
+        .start()
+        .await;
+    let report = dir.join("report.json");
+    let report_arg = report.to_str().expect("path should be UTF-8");
+    let output = pngx(
+        &target,
+        &dir,
+        &["import", export_arg, "--report", report_arg],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(
+        stderr.contains("Imported 1 documents, 1 already existed, 0 failed"),
+        "{stderr}"
+    );
+
+    assert_eq!(target.tags().len(), 2);
+    assert_eq!(target.correspondents()[0].name, "ACME");
+    assert_eq!(target.storage_paths()[0].path, "{{ title }}");
+    assert_eq!(target.custom_fields()[0].data_type, "monetary");
+    let invoice = target.document(2).expect("invoice should be imported");
+    assert_eq!(invoice.title, "Invoice 42");
+    assert_eq!(invoice.tags, [2]);
+    assert_eq!(invoice.correspondent, Some(1));
+    assert_eq!(invoice.storage_path, Some(1));
+    assert_eq!(invoice.created, Some(jiff::civil::date(2024, 3, 1)));
+    assert_eq!(
+        invoice.original_file_name.as_deref(),
+        Some("invoice-42.pdf")
+    );
+    assert_eq!(invoice.notes[0].note, "Paid");
+    assert_eq!(invoice.custom_fields[0].value, "EUR42.00");
+
+    let report = read_json(&report);
+    assert_eq!(report["tags"][0]["id"], 2);
+    assert_eq!(report["tags"][0]["status"], "existing");
+    assert_eq!(report["correspondents"][0]["status"], "created");
+    assert_eq!(report["documents"][0]["id"], 2);
+    assert_eq!(report["documents"][0]["status"], "created");
+    assert_eq!(report["documents"][1]["id"], 1);
+    assert_eq!(report["documents"][1]["status"], "existing");
+
+    // Everything already exists on a second run.
+    let output = pngx(
+        &target,
+        &dir,
+        &["import", export_arg, "--report", report_arg],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Imported 0 documents, 2 already existed"),
+        "{stderr}"
+    );
+    assert_eq!(target.documents().len(), 2);
+}
+
+#[tokio::test]
+async fn test_import_reports_failed_notes() {
+    let source = export_server().await;
+    let dir = scratch("import-notes");
+    let export = dir.join("export");
+    let _ = std::fs::remove_dir_all(&export);
+    let export_arg = export.to_str().expect("path should be UTF-8");
+    assert!(
+        pngx(&source, &dir, &["export", export_arg])
+            .status
+            .success()
+    );
+
+    let target = FakePaperless::start().await;
+    wiremock::Mock::given(wiremock::matchers::method("POST"))
+        .and(wiremock::matchers::path_regex(
+            r"^/api/documents/\d+/notes/$",
+        ))
+        .respond_with(wiremock::ResponseTemplate::new(500))
+        .with_priority(1)
+        .mount(target.mock_server())
+        .await;
+    let report = dir.join("report.json");
+    let report_arg = report.to_str().expect("path should be UTF-8");
+    let output = pngx(
+        &target,
+        &dir,
+        &["import", export_arg, "--report", report_arg],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "{stderr}");
+    assert!(
+        stderr.contains("Imported 2 documents, 0 already existed, 0 failed"),
+        "{stderr}"
+    );
+
+    // The report maps every document, and the custom field is still set.
+    let report = read_json(&report);
+    let invoice = &report["documents"][0];
+    assert_eq!(invoice["status"], "created");
+    assert!(
+        invoice["error"]
+            .as_str()
+            .is_some_and(|error| error.contains("failed to add a note")),
+        "{invoice}"
+    );
+    assert_eq!(report["documents"][1]["status"], "created");
+    assert!(report["documents"][1].get("error").is_none());
+    let id = invoice["id"].as_u64().expect("invoice should be mapped");
+    let invoice = target.document(id).expect("invoice should be imported");
+    assert!(invoice.notes.is_empty());
+    assert_eq!(invoice.custom_fields[0].value, "EUR42.00");
+}
+
+/// A directory to watch, with a scan for a folder rule and a duplicate of
+/// an existing document.
+fn watch_dir(test: &str) -> (PathBuf, PathBuf) {
+    let dir = scratch(test);
+    let watched = dir.join("scans");
+    let _ = std::fs::remove_dir_all(&watched);
+    std::fs::create_dir_all(watched.join("invoices")).expect("should create folders");
+    std::fs::write(watched.join("invoices/scan.pdf"), b"%PDF-scan").expect("should write");
+    std::fs::write(watched.join("letter.pdf"), b"%PDF-original").expect("should write");
+    (dir, watched)
+}
+
+#[tokio::test]
+async fn test_watch_once() {
+    let server = server().await;
+    let (dir, watched) = watch_dir("watch-once");
+    let rules = dir.join("rules.toml");
+    std::fs::write(
+        &rules,
+        "[folders.invoices]\ntags = [\"Tax\"]\ncorrespondent = \"acme\"\n",
+    )
+    .expect("should write rules");
+    let watched_arg = watched.to_str().expect("path should be UTF-8");
+    let rules_arg = rules.to_str().expect("path should be UTF-8");
+    let args = [
+        "watch",
+        watched_arg,
+        "--once",
+        "--settle",
+        "0",
+        "--subdirs-as-tags",
+        "--rules",
+        rules_arg,
+    ];
+    let output = pngx(&server, &dir, &args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("1 files consumed, 1 failed"), "{stderr}");
+
+    assert!(watched.join("done/invoices/scan.pdf").exists());
+    assert!(watched.join("failed/letter.pdf").exists());
+    let error = std::fs::read_to_string(watched.join("failed/letter.pdf.error.txt"))
+        .expect("error file should exist");
+    assert!(error.contains("It is a duplicate of Invoice 42"), "{error}");
+
+    let scan = server
+        .documents()
+        .into_iter()
+        .find(|doc| doc.original_file_name.as_deref() == Some("scan.pdf"))
+        .expect("scan should be uploaded");
+    let invoices = server
+        .tags()
+        .into_iter()
+        .find(|tag| tag.name == "invoices")
+        .expect("folder tag should be created");
+    assert_eq!(scan.tags, [2, invoices.id]);
+    assert_eq!(scan.correspondent, Some(1));
+    let state = read_json(&watched.join(".pngx-watch.json"));
+    assert_eq!(state["pending"], serde_json::json!({}));
+
+    // Unknown names in the rules are reported before anything is uploaded.
+    std::fs::write(&rules, "[folders.\".\"]\ndocument_type = \"Receipt\"\n")
+        .expect("should write rules");
+    let output = pngx(&server, &dir, &args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("document type \"Receipt\""), "{stderr}");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_watch_stops_on_sigterm() {
+    let server = server().await;
+    let (dir, watched) = watch_dir("watch-sigterm");
+    std::fs::remove_file(watched.join("letter.pdf")).expect("should remove");
+    let watched_arg = watched.to_str().expect("path should be UTF-8");
+    let child = command(&server, &dir, &["watch", watched_arg, "--settle", "0"])
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("should start pngx");
+
+    // Written while watching, in a new folder.
+    std::fs::create_dir_all(watched.join("new")).expect("should create folder");
+    std::fs::write(watched.join("new/later.pdf"), b"%PDF-later").expect("should write");
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(20);
+    while !(watched.join("done/invoices/scan.pdf").exists()
+        && watched.join("done/new/later.pdf").exists())
+    {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "files should be uploaded"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+
+    let status = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .expect("should run kill");
+    assert!(status.success());
+    let output = child.wait_with_output().expect("pngx should exit");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("2 files consumed, 0 failed"), "{stderr}");
+}
+
+#[tokio::test]
+async fn test_inbox_interactive_needs_terminal() {
+    let server = server().await;
+    let dir = scratch("inbox-interactive");
+
+    let output = pngx(&server, &dir, &["inbox", "--interactive"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("--interactive needs a terminal"),
+        "{stderr}"
+    );
+
+    let output = pngx(&server, &dir, &["inbox", "-i", "--all"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
+
+const INBOX_RULES: &str = r#"
+[[rule]]
+name = "ACME invoices"
+[rule.when]
+content = '(?i)invoice no\. \d+'
+suggested_tags = ["vat"]
+[rule.then]
+correspondent = "acme"
+document_type = "Invoice"
+tags = ["Finance"]
+title = "{correspondent} {document_type} {created}"
+remove_inbox_tag = true
+
+[[rule]]
+name = "Paid receipts"
+[rule.when]
+asn = true
+original_file_name = '\.jpg$'
+custom_fields = { Amount = '^EUR' }
+[rule.then]
+tags = ["Finance"]
+"#;
+
+#[tokio::test]
+async fn test_inbox_process() {
+    use pngx_client::MatchingAlgorithm;
+
+    let server = FakePaperless::builder()
+        .tag(seed::Tag::new("Inbox").inbox())
+        .tag(seed::Tag::new("Finance"))
+        .tag(seed::Tag::new("VAT").matching(MatchingAlgorithm::Any, "vat"))
+        .correspondent(seed::Correspondent::new("ACME"))
+        .document_type(seed::DocumentType::new("Invoice"))
+        .custom_field(seed::CustomField::new("Amount", "monetary"))
+        .document(
+            seed::Document::new("Scan 1")
+                .content("ACME GmbH, Invoice no. 4711, incl. VAT")
+                .created(jiff::civil::date(2024, 5, 1))
+                .tag(1),
+        )
+        .document(
+            seed::Document::new("Receipt")
+                .original_file_name("receipt.jpg")
+                .archive_serial_number(7)
+                .custom_field(1, "EUR12.00")
+                .tag(1),
+        )
+        .document(
+            seed::Document::new("Letter")
+                .content("Invoice no. 1")
+                .tag(1),
+        )
+        .document(seed::Document::new("Filed").content("Invoice no. 2, VAT"))
+        .start()
+        .await;
+    let dir = scratch("inbox-process");
+    let rules = dir.join("rules.toml");
+    std::fs::write(&rules, INBOX_RULES).expect("should write rules");
+    let rules_arg = rules.to_str().expect("path should be UTF-8");
+
+    let output = pngx(
+        &server,
+        &dir,
+        &["inbox", "process", "--rules", rules_arg, "--dry-run"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert_eq!(
+        stdout,
+        "#1 Scan 1\n  rules: ACME invoices\n  correspondent: none -> ACME\n  \
+         document type: none -> Invoice\n  tags: +Finance -Inbox\n  \
+         title: Scan 1 -> ACME Invoice 2024-05-01\n\
+         #2 Receipt\n  rules: Paid receipts\n  tags: +Finance\n"
+    );
+    assert!(
+        stderr.contains("2 of 3 inbox documents would change"),
+        "{stderr}"
+    );
+    assert_eq!(server.document(1).map(|doc| doc.tags), Some(vec![1]));
+
+    let output = pngx(&server, &dir, &["inbox", "process", "--rules", rules_arg]);
+    assert!(output.status.success());
+    let scan = server.document(1).expect("document should exist");
+    assert_eq!(scan.title, "ACME Invoice 2024-05-01");
+    assert_eq!(scan.correspondent, Some(1));
+    assert_eq!(scan.document_type, Some(1));
+    assert_eq!(scan.tags, [2]);
+    assert_eq!(server.document(2).map(|doc| doc.tags), Some(vec![1, 2]));
+    assert_eq!(server.document(3).map(|doc| doc.tags), Some(vec![1]));
+    assert_eq!(
+        server.document(4).map(|doc| doc.title),
+        Some("Filed".into())
+    );
+
+    // Names are checked before any document is looked at.
+    std::fs::write(&rules, INBOX_RULES.replace("\"acme\"", "\"Umbrella\""))
+        .expect("should write rules");
+    let output = pngx(&server, &dir, &["inbox", "process", "--rules", rules_arg]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("rule \"ACME invoices\"") && stderr.contains("unknown correspondent"),
+        "{stderr}"
+    );
+}